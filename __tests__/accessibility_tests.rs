@@ -0,0 +1,54 @@
+use super::*;
+
+// WCAG AA for normal text, the usual value to opt into
+const WCAG_AA_CONTRAST: f32 = 4.5;
+
+fn rgb(hex: &str) -> Color {
+    let value = u32::from_str_radix(hex.trim_start_matches('#'), 16).expect("hex colour");
+    Color::from_rgb8((value >> 16) as u8, (value >> 8) as u8, value as u8)
+}
+
+#[test]
+fn contrast_ratio_matches_wcag_reference_values() {
+    assert!((contrast_ratio(Color::BLACK, Color::WHITE) - 21.0).abs() < 0.01);
+    assert!((contrast_ratio(Color::WHITE, Color::WHITE) - 1.0).abs() < 0.01);
+    assert!((contrast_ratio(rgb("#767676"), Color::WHITE) - 4.54).abs() < 0.02);
+}
+
+#[test]
+fn ensure_contrast_fixes_solarized_light_on_ansi_7() {
+    let theme = crate::themes::preset_theme("solarized_light");
+    let to_color = |key: &str| {
+        let [r, g, b] = theme.colors[key];
+        Color::from_rgb(r, g, b)
+    };
+    let (fg, bg) = (to_color("foreground"), to_color("ansi_7"));
+    let adjusted = ensure_contrast(fg, bg, WCAG_AA_CONTRAST);
+    assert!(contrast_ratio(adjusted, bg) >= WCAG_AA_CONTRAST);
+}
+
+#[test]
+fn ensure_contrast_leaves_readable_colours_alone() {
+    let fg = rgb("#d4d4d4");
+    let bg = rgb("#1e1e1e");
+    assert_eq!(ensure_contrast(fg, bg, WCAG_AA_CONTRAST), fg);
+    assert_eq!(ensure_contrast(bg, bg, DEFAULT_MIN_CONTRAST), bg);
+}
+
+#[test]
+fn ensure_contrast_darkens_on_light_backgrounds() {
+    let adjusted = ensure_contrast(rgb("#ffff00"), Color::WHITE, 3.0);
+    assert!(relative_luminance(adjusted) < relative_luminance(rgb("#ffff00")));
+    assert!(contrast_ratio(adjusted, Color::WHITE) >= 3.0);
+}
+
+#[test]
+fn colour_blind_palettes_avoid_red_green_pairs() {
+    for palette in [StatusColors::Deuteranopia, StatusColors::Protanopia] {
+        let (success, failure) = (palette.success(), palette.failure());
+        assert!(success.b > success.g * 0.9, "{:?} success should lean blue", palette);
+        assert!(failure.r > failure.b, "{:?} failure should lean orange", palette);
+        assert!(contrast_ratio(text_color_on(success), success) >= 3.0);
+        assert!(contrast_ratio(text_color_on(failure), failure) >= 3.0);
+    }
+}
@@ -0,0 +1,92 @@
+use super::*;
+use crate::parser::{ParserEvent, TerminalParser};
+
+fn time(value: &str) -> NaiveTime {
+    parse_schedule_time(value).expect("valid time")
+}
+
+#[test]
+fn parses_gdbus_and_busctl_output() {
+    assert_eq!(parse_portal_output("(<<uint32 1>>,)\n"), Some(1));
+    assert_eq!(parse_portal_output("(<uint32 2>,)\n"), Some(2));
+    assert_eq!(parse_portal_output("v v u 0\n"), Some(0));
+    assert_eq!(parse_portal_output(""), None);
+}
+
+#[test]
+fn parses_the_setting_changed_signal() {
+    let signal = "/org/freedesktop/portal/desktop: org.freedesktop.portal.Settings.SettingChanged ('org.freedesktop.appearance', 'color-scheme', <uint32 2>)";
+    assert_eq!(parse_setting_changed(signal), Some(2));
+    let other = "/org/freedesktop/portal/desktop: org.freedesktop.portal.Settings.SettingChanged ('org.gnome.desktop.interface', 'gtk-theme', <'Adwaita'>)";
+    assert_eq!(parse_setting_changed(other), None);
+    assert_eq!(parse_setting_changed("(<<uint32 1>>,)"), None);
+}
+
+#[test]
+fn portal_values_map_to_schemes() {
+    assert_eq!(scheme_from_portal_value(1), Some(ColorScheme::Dark));
+    assert_eq!(scheme_from_portal_value(2), Some(ColorScheme::Light));
+    assert_eq!(scheme_from_portal_value(0), None);
+}
+
+#[test]
+fn schedule_switches_at_boundaries() {
+    let (light, dark) = (time("07:00"), time("19:00"));
+    assert_eq!(scheduled_color_scheme(time("06:59"), light, dark), ColorScheme::Dark);
+    assert_eq!(scheduled_color_scheme(time("07:00"), light, dark), ColorScheme::Light);
+    assert_eq!(scheduled_color_scheme(time("19:00"), light, dark), ColorScheme::Dark);
+}
+
+#[test]
+fn schedule_handles_light_period_across_midnight() {
+    let (light, dark) = (time("22:00"), time("06:00"));
+    assert_eq!(scheduled_color_scheme(time("23:30"), light, dark), ColorScheme::Light);
+    assert_eq!(scheduled_color_scheme(time("12:00"), light, dark), ColorScheme::Dark);
+}
+
+#[test]
+fn presets_classify_by_background() {
+    assert_eq!(ColorScheme::of_theme(&crate::themes::preset_theme("light")), ColorScheme::Light);
+    assert_eq!(ColorScheme::of_theme(&crate::themes::preset_theme("one_dark")), ColorScheme::Dark);
+}
+
+#[test]
+fn parser_tracks_mode_2031_and_queries() {
+    let mut parser = TerminalParser::new(24, 80);
+    assert!(!parser.color_scheme_updates_enabled());
+    parser.process(b"\x1b[?2031h");
+    assert!(parser.color_scheme_updates_enabled());
+    parser.process(b"\x1b[?996n");
+    let queries = parser
+        .take_events()
+        .into_iter()
+        .filter(|event| matches!(event, ParserEvent::ColorSchemeQuery))
+        .count();
+    assert_eq!(queries, 1);
+    parser.process(b"\x1b[?2031l");
+    assert!(!parser.color_scheme_updates_enabled());
+}
+
+#[test]
+fn parser_reads_mode_lists_and_split_sequences() {
+    let mut parser = TerminalParser::new(24, 80);
+    parser.process(b"\x1b[?2004;2031h");
+    assert!(parser.color_scheme_updates_enabled());
+    parser.process(b"\x1b[?20");
+    parser.process(b"31l");
+    assert!(!parser.color_scheme_updates_enabled());
+    parser.process(b"\x1b[?12031h\x1b[?2031;1049h\x1b[?99");
+    parser.process(b"6n");
+    assert!(parser.color_scheme_updates_enabled());
+    let queries = parser.take_events().into_iter().filter(|event| matches!(event, ParserEvent::ColorSchemeQuery)).count();
+    assert_eq!(queries, 1);
+}
+
+#[test]
+fn the_portal_is_only_polled_once_asked_for() {
+    let watcher = AppearanceWatcher::new(true);
+    assert!(watcher.receiver.is_none());
+    let mut fixed = AppearanceWatcher::new(false);
+    fixed.current("07:00", "19:00");
+    assert!(fixed.receiver.is_none());
+}
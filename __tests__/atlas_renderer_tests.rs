@@ -0,0 +1,203 @@
+use super::*;
+use std::time::{Duration, Instant};
+
+const BENCH_ROWS: u16 = 100;
+const BENCH_COLS: u16 = 300;
+
+fn palette() -> TerminalPalette {
+    TerminalPalette::from_theme(&crate::themes::preset_theme("one_dark"), crate::accessibility::DEFAULT_MIN_CONTRAST)
+}
+
+fn fonts() -> Arc<FontSet> {
+    Arc::new(FontSet::load("monospace", &[]))
+}
+
+fn cell_size(fonts: &FontSet, px: f32) -> (f32, f32) {
+    (fonts.metrics.advance * px, fonts.metrics.line_height.max(1.2) * px)
+}
+
+/// A pane filled with 256-colour text, one SGR change every eight cells.
+fn coloured_screen(rows: u16, cols: u16, seed: usize) -> vt100::Parser {
+    let mut parser = vt100::Parser::new(rows, cols, 0);
+    let alphabet: Vec<char> = ('!'..='~').collect();
+    for row in 0..rows as usize {
+        let mut line = format!("\x1b[{};1H", row + 1);
+        for col in 0..cols as usize {
+            if col % 8 == 0 {
+                line.push_str(&format!("\x1b[38;5;{}m\x1b[48;5;{}m", (row + col + seed) % 256, (row * 3 + col) % 16));
+            }
+            line.push(alphabet[(row * 7 + col + seed) % alphabet.len()]);
+        }
+        parser.process(line.as_bytes());
+    }
+    parser
+}
+
+#[test]
+fn first_update_damages_every_row_then_nothing() {
+    let fonts = fonts();
+    let mut atlas = GlyphAtlas::new(fonts.clone(), 14.0);
+    let (cell_w, cell_h) = cell_size(&fonts, 14.0);
+    let parser = coloured_screen(24, 80, 0);
+    let mut raster = GridRaster::new();
+    assert_eq!(raster.update(parser.screen(), &palette(), &mut atlas, cell_w, cell_h, GridOverlay::default()).len(), 24);
+    assert!(raster.handle().is_some());
+    assert!(raster.update(parser.screen(), &palette(), &mut atlas, cell_w, cell_h, GridOverlay::default()).is_empty());
+}
+
+#[test]
+fn only_changed_rows_are_composited() {
+    let fonts = fonts();
+    let mut atlas = GlyphAtlas::new(fonts.clone(), 14.0);
+    let (cell_w, cell_h) = cell_size(&fonts, 14.0);
+    let mut parser = coloured_screen(24, 80, 0);
+    let mut raster = GridRaster::new();
+    raster.update(parser.screen(), &palette(), &mut atlas, cell_w, cell_h, GridOverlay::default());
+    parser.process(b"\x1b[5;10Hchanged\x1b[20;1Hx");
+    assert_eq!(raster.update(parser.screen(), &palette(), &mut atlas, cell_w, cell_h, GridOverlay::default()), &[4, 19]);
+}
+
+#[test]
+fn resizing_the_grid_redraws_everything() {
+    let fonts = fonts();
+    let mut atlas = GlyphAtlas::new(fonts.clone(), 14.0);
+    let (cell_w, cell_h) = cell_size(&fonts, 14.0);
+    let mut parser = coloured_screen(24, 80, 0);
+    let mut raster = GridRaster::new();
+    raster.update(parser.screen(), &palette(), &mut atlas, cell_w, cell_h, GridOverlay::default());
+    parser.screen_mut().set_size(30, 100);
+    assert_eq!(raster.update(parser.screen(), &palette(), &mut atlas, cell_w, cell_h, GridOverlay::default()).len(), 30);
+    let (width, height) = raster.size();
+    assert_eq!(raster.pixels().len(), width as usize * height as usize * 4);
+}
+
+#[test]
+fn backgrounds_are_painted_per_cell() {
+    let fonts = fonts();
+    let mut atlas = GlyphAtlas::new(fonts.clone(), 14.0);
+    let (cell_w, cell_h) = (10.0, 20.0);
+    let mut parser = vt100::Parser::new(2, 4, 0);
+    parser.process(b"\x1b[41m \x1b[0m");
+    let mut raster = GridRaster::new();
+    raster.update(parser.screen(), &palette(), &mut atlas, cell_w, cell_h, GridOverlay::default());
+    let pixel = |x: usize, y: usize| {
+        let index = (y * raster.size().0 as usize + x) * 4;
+        raster.pixels()[index..index + 4].to_vec()
+    };
+    assert_eq!(pixel(2, 2), palette().ansi[1].into_rgba8().to_vec());
+    assert_eq!(pixel(12, 2), palette().background.into_rgba8().to_vec());
+}
+
+#[test]
+fn selection_repaints_only_selected_rows() {
+    let fonts = fonts();
+    let mut atlas = GlyphAtlas::new(fonts.clone(), 14.0);
+    let (cell_w, cell_h) = (10.0, 20.0);
+    let mut parser = vt100::Parser::new(4, 8, 0);
+    let mut raster = GridRaster::new();
+    raster.update(parser.screen(), &palette(), &mut atlas, cell_w, cell_h, GridOverlay::default());
+    let mut selection = crate::selection::Selection::new(crate::selection::SelectionMode::Block, crate::selection::GridPoint::new(1, 2));
+    selection.extend_to(crate::selection::GridPoint::new(2, 3));
+    selection.expand(&crate::selection::GridLines::read(&mut parser, selection.endpoint_lines()), "");
+    assert_eq!(raster.update(parser.screen(), &palette(), &mut atlas, cell_w, cell_h, GridOverlay { selection: Some(&selection), find: None }), &[1, 2]);
+    let pixel = |x: usize, y: usize| {
+        let index = (y * raster.size().0 as usize + x) * 4;
+        raster.pixels()[index..index + 4].to_vec()
+    };
+    assert_eq!(pixel(25, 25), palette().selection.into_rgba8().to_vec());
+    assert_eq!(pixel(45, 25), palette().background.into_rgba8().to_vec());
+}
+
+#[test]
+fn glyphs_are_rasterised_once() {
+    let fonts = fonts();
+    if fonts.face_count() == 0 {
+        // No system fonts in this environment; nothing to rasterise
+        return;
+    }
+    let mut atlas = GlyphAtlas::new(fonts.clone(), 14.0);
+    let (cell_w, cell_h) = cell_size(&fonts, 14.0);
+    let mut raster = GridRaster::new();
+    raster.update(coloured_screen(24, 80, 0).screen(), &palette(), &mut atlas, cell_w, cell_h, GridOverlay::default());
+    let glyphs = atlas.glyph_count();
+    assert!(glyphs > 0 && glyphs <= 94);
+    raster.update(coloured_screen(24, 80, 5).screen(), &palette(), &mut atlas, cell_w, cell_h, GridOverlay::default());
+    assert_eq!(atlas.glyph_count(), glyphs);
+}
+
+fn per_frame(total: Duration, frames: u32) -> f64 {
+    total.as_secs_f64() * 1000.0 / frames as f64
+}
+
+/// Headless frame-time benchmark on a 300x100 pane filled with coloured text.
+/// Run with `cargo test --release bench_atlas_frame_time -- --ignored --nocapture`.
+#[test]
+#[ignore]
+fn bench_atlas_frame_time() {
+    let fonts = fonts();
+    let mut atlas = GlyphAtlas::new(fonts.clone(), 14.0);
+    let (cell_w, cell_h) = cell_size(&fonts, 14.0);
+    let palette = palette();
+    let frames = 60;
+
+    let screens: Vec<vt100::Parser> = (0..frames).map(|seed| coloured_screen(BENCH_ROWS, BENCH_COLS, seed as usize + 1)).collect();
+    let mut raster = GridRaster::new();
+
+    let started = Instant::now();
+    raster.update(screens[0].screen(), &palette, &mut atlas, cell_w, cell_h, GridOverlay::default());
+    let cold = started.elapsed();
+
+    let started = Instant::now();
+    for parser in &screens {
+        raster.update(parser.screen(), &palette, &mut atlas, cell_w, cell_h, GridOverlay::default());
+    }
+    let full = started.elapsed();
+
+    let mut parser = coloured_screen(BENCH_ROWS, BENCH_COLS, 0);
+    raster.update(parser.screen(), &palette, &mut atlas, cell_w, cell_h, GridOverlay::default());
+    let started = Instant::now();
+    for frame in 0..frames {
+        parser.process(format!("\x1b[{};1H\x1b[3{}mtick {}", frame % BENCH_ROWS as u32 + 1, frame % 8, frame).as_bytes());
+        raster.update(parser.screen(), &palette, &mut atlas, cell_w, cell_h, GridOverlay::default());
+    }
+    let one_row = started.elapsed();
+
+    let started = Instant::now();
+    for _ in 0..frames {
+        raster.update(parser.screen(), &palette, &mut atlas, cell_w, cell_h, GridOverlay::default());
+    }
+    let idle = started.elapsed();
+
+    let (width, height) = raster.size();
+    println!("atlas renderer, {}x{} cells ({}x{} px), {} glyphs cached", BENCH_COLS, BENCH_ROWS, width, height, atlas.glyph_count());
+    println!("  cold frame:          {:>8.3} ms", per_frame(cold, 1));
+    println!("  full redraw:         {:>8.3} ms/frame", per_frame(full, frames));
+    println!("  one damaged row:     {:>8.3} ms/frame", per_frame(one_row, frames));
+    println!("  no damage:           {:>8.3} ms/frame", per_frame(idle, frames));
+}
+
+#[test]
+fn wide_characters_keep_the_half_drawn_over_their_continuation_cell() {
+    let fonts = fonts();
+    if fonts.face_count() == 0 {
+        // No system fonts in this environment; nothing to rasterise
+        return;
+    }
+    let mut atlas = GlyphAtlas::new(fonts.clone(), 14.0);
+    let glyph = atlas.glyph('漢').expect("a glyph or the font's missing-glyph box");
+    // Cells narrower than the glyph, so its right edge lands in the continuation cell
+    let cell_w = (glyph.width as f32 / 1.5).floor();
+    let mut parser = vt100::Parser::new(1, 4, 0);
+    parser.process("\x1b[41m漢\x1b[0m".as_bytes());
+    assert!(parser.screen().cell(0, 1).unwrap().is_wide_continuation());
+    let mut raster = GridRaster::new();
+    raster.update(parser.screen(), &palette(), &mut atlas, cell_w, 20.0, GridOverlay::default());
+    let (width, height) = raster.size();
+    let pixels = raster.pixels();
+    // The top row is above the glyph, so it shows the continuation cell's background
+    let pixel = |x: usize, y: usize| &pixels[(y * width as usize + x) * 4..(y * width as usize + x) * 4 + 4];
+    let background = pixel(cell_w as usize, 0);
+    let continuation = cell_w as usize..(glyph.left as usize + glyph.width).min(2 * cell_w as usize);
+    let inked = (0..height as usize).any(|y| continuation.clone().any(|x| pixel(x, y) != background));
+    assert!(inked, "glyph pixels in the continuation cell were painted over");
+}
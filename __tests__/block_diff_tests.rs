@@ -0,0 +1,111 @@
+use super::*;
+
+fn block(command: &str, cwd: &str) -> Block {
+    Block { cwd: Some(std::path::PathBuf::from(cwd)), ..crate::test_block(command, "") }
+}
+
+fn kinds(lines: &[DiffLine]) -> Vec<DiffKind> {
+    lines.iter().map(|line| line.kind).collect()
+}
+
+#[test]
+fn changed_line_is_removed_then_added() {
+    let lines = diff_lines("a\nb\nc", "a\nB\nc", &[]);
+    assert_eq!(kinds(&lines), vec![DiffKind::Same, DiffKind::Removed, DiffKind::Added, DiffKind::Same]);
+    assert_eq!(lines[1], DiffLine { kind: DiffKind::Removed, old: Some(2), new: None, text: "b".to_string() });
+    assert_eq!(lines[2], DiffLine { kind: DiffKind::Added, old: None, new: Some(2), text: "B".to_string() });
+    assert_eq!(lines[3].old, Some(3));
+    assert_eq!(lines[3].new, Some(3));
+    assert_eq!(diff_stats(&lines), (1, 1));
+}
+
+#[test]
+fn insertions_keep_common_lines_aligned() {
+    let lines = diff_lines("test a ... ok\ntest c ... ok", "test a ... ok\ntest b ... ok\ntest c ... ok\ndone", &[]);
+    assert_eq!(kinds(&lines), vec![DiffKind::Same, DiffKind::Added, DiffKind::Same, DiffKind::Added]);
+    assert_eq!(lines[2].old, Some(2));
+    assert_eq!(lines[2].new, Some(3));
+}
+
+#[test]
+fn normalisers_ignore_volatile_text_but_keep_it_visible() {
+    let old = "started 2026-10-01 12:00:01\ntest result: ok. finished in 1.52s";
+    let new = "started 2026-10-02 09:14:45\ntest result: ok. finished in 0.98s";
+    let (normalisers, errors) = DiffSettings::default().compile();
+    assert!(errors.is_empty());
+    let lines = diff_lines(old, new, &normalisers);
+    assert!(lines.iter().all(|line| line.kind == DiffKind::Same));
+    assert_eq!(lines[1].text, "test result: ok. finished in 0.98s");
+    assert_eq!(diff_stats(&diff_lines(old, new, &[])), (2, 2));
+}
+
+#[test]
+fn invalid_normalisers_are_skipped_and_reported() {
+    let settings = DiffSettings { normalisers: vec!["(".to_string(), r"\d+".to_string()], ..DiffSettings::default() };
+    let (normalisers, errors) = settings.compile();
+    assert_eq!(normalisers.len(), 1);
+    assert_eq!(errors.len(), 1);
+    assert!(errors[0].starts_with("'('"));
+}
+
+#[test]
+fn side_by_side_pairs_removals_with_additions() {
+    let lines = diff_lines("a\nb\nc\nd", "a\nB\nd\ne", &[]);
+    let rows = side_by_side(&lines);
+    let texts: Vec<(Option<&str>, Option<&str>)> = rows
+        .iter()
+        .map(|row| (row.left.as_ref().map(|line| line.text.as_str()), row.right.as_ref().map(|line| line.text.as_str())))
+        .collect();
+    assert_eq!(texts, vec![
+        (Some("a"), Some("a")),
+        (Some("b"), Some("B")),
+        (Some("c"), None),
+        (Some("d"), Some("d")),
+        (None, Some("e")),
+    ]);
+}
+
+#[test]
+fn previous_run_matches_command_and_directory() {
+    let history = vec![
+        block("cargo test", "/work/app"),
+        block("cargo test", "/work/lib"),
+        block("ls", "/work/app"),
+        block("cargo test ", "/work/app"),
+    ];
+    assert_eq!(previous_run(&history, 3), Some(0));
+    assert_eq!(previous_run(&history, 0), None);
+    assert_eq!(previous_run(&history, 2), None);
+    assert_eq!(previous_run(&history, 9), None);
+}
+
+#[test]
+fn context_ranges_merge_nearby_changes() {
+    let mut changed = vec![false; 30];
+    changed[5] = true;
+    changed[10] = true;
+    changed[25] = true;
+    assert_eq!(context_ranges(&changed, 3), vec![2..14, 22..29]);
+    assert_eq!(context_ranges(&[true, false], 3), vec![0..2]);
+    assert!(context_ranges(&[false; 4], 3).is_empty());
+}
+
+#[test]
+fn view_puts_the_earlier_block_on_the_left() {
+    let mut first = block("cargo test", "/work/app");
+    first.output = "ok 1\nok 2".to_string();
+    first.exit_code = Some(1);
+    let mut second = block("cargo test", "/work/app");
+    second.output = "ok 1\nok 3".to_string();
+    let history = vec![first, second];
+    let settings = DiffSettings { normalise: true, ..DiffSettings::default() };
+    let mut view = BlockDiffView::new(&history, 1, 0, &settings).unwrap();
+    assert_eq!(view.old_label, "#1 · exit 1");
+    assert_eq!(view.new_label, "#2 · exit 0");
+    assert_eq!(diff_stats(&view.lines), (0, 0));
+    assert!(view.errors.is_empty());
+    view.normalise = false;
+    view.refresh(&settings);
+    assert_eq!(diff_stats(&view.lines), (1, 1));
+    assert!(BlockDiffView::new(&history, 0, 5, &settings).is_none());
+}
@@ -0,0 +1,48 @@
+use super::*;
+use chrono::TimeZone;
+
+fn block(command: &str, minute: u32, pinned: bool, tags: &[&str]) -> Block {
+    Block {
+        started_at: Some(Utc.with_ymd_and_hms(2026, 10, 1, 12, minute, 0).unwrap()),
+        pinned,
+        tags: tags.iter().map(|tag| tag.to_string()).collect(),
+        ..crate::test_block(command, "")
+    }
+}
+
+#[test]
+fn tags_are_split_on_commas_and_deduplicated() {
+    assert_eq!(parse_tags(" deploy, incident 42 ,,rollback "), vec!["deploy", "incident-42", "rollback"]);
+    let mut tags = vec!["Deploy".to_string()];
+    assert_eq!(add_tags(&mut tags, parse_tags("deploy, db")), 1);
+    assert_eq!(tags, vec!["Deploy", "db"]);
+}
+
+#[test]
+fn tag_queries_quote_when_needed() {
+    assert_eq!(tag_query("deploy"), "tag:deploy");
+    assert_eq!(tag_query("say \"hi\""), "tag:\"say \\\"hi\\\"\"");
+}
+
+#[test]
+fn bookmarks_span_tabs_in_timeline_order() {
+    let mut noted = block("kubectl rollout undo", 5, false, &["incident"]);
+    noted.note = "\n  Rolled back after 5xx spike\nsecond line".to_string();
+    let first_tab = vec![block("ls", 1, false, &[]), noted];
+    let second_tab = vec![block("tail -f app.log", 2, true, &[]), block("date", 3, false, &[])];
+    let bookmarks = collect_bookmarks([(0, 0, first_tab.as_slice()), (1, 2, second_tab.as_slice())]);
+    assert_eq!(bookmarks.len(), 2);
+    assert_eq!((bookmarks[0].tab, bookmarks[0].pane, bookmarks[0].block), (1, 2, 0));
+    assert!(bookmarks[0].pinned);
+    assert_eq!(bookmarks[1].command, "kubectl rollout undo");
+    assert_eq!(bookmarks[1].note.as_deref(), Some("Rolled back after 5xx spike"));
+}
+
+#[test]
+fn annotation_note_drops_the_editor_newline() {
+    let mut source = block("make", 0, false, &[]);
+    source.note = "# Cause\n\nBad config".to_string();
+    let annotation = BlockAnnotation::new(3, &source);
+    assert_eq!(annotation.block_index, 3);
+    assert_eq!(annotation.note_text(), "# Cause\n\nBad config");
+}
@@ -0,0 +1,84 @@
+use super::*;
+
+fn type_text(editor: &mut CommandEditor, text: &str) {
+    for c in text.chars() {
+        editor.perform(Action::Edit(Edit::Insert(c)));
+    }
+}
+
+fn kinds(line: &str) -> Vec<(&str, SyntaxKind)> {
+    highlight_shell(line).into_iter().map(|(range, kind)| (&line[range], kind)).collect()
+}
+
+#[test]
+fn typed_words_undo_one_at_a_time() {
+    let mut editor = CommandEditor::default();
+    type_text(&mut editor, "git status");
+    assert_eq!(editor.text(), "git status");
+    assert!(editor.undo());
+    assert_eq!(editor.text(), "git ");
+    assert!(editor.undo());
+    assert_eq!(editor.text(), "git");
+    assert!(editor.redo());
+    assert_eq!(editor.text(), "git ");
+    type_text(&mut editor, "log");
+    assert!(!editor.redo());
+    assert_eq!(editor.text(), "git log");
+}
+
+#[test]
+fn newlines_and_replacements_are_separate_steps() {
+    let mut editor = CommandEditor::default();
+    type_text(&mut editor, "for f in *");
+    editor.perform(Action::Edit(Edit::Enter));
+    type_text(&mut editor, "do echo");
+    assert_eq!(editor.text(), "for f in *\ndo echo");
+    assert!(editor.cursor_at_end());
+
+    editor.set_text("ls -la");
+    assert_eq!(editor.text(), "ls -la");
+    assert!(editor.cursor_at_end());
+    assert!(editor.undo());
+    assert_eq!(editor.text(), "for f in *\ndo echo");
+
+    editor.reset();
+    assert_eq!(editor.text(), "");
+    assert!(!editor.undo());
+}
+
+#[test]
+fn command_words_and_reserved_words_are_keywords() {
+    assert_eq!(kinds("git log | grep fix && make"), vec![
+        ("git", SyntaxKind::Keyword),
+        ("grep", SyntaxKind::Keyword),
+        ("make", SyntaxKind::Keyword),
+    ]);
+    assert_eq!(kinds("if true; then RUST_LOG=debug cargo run; fi"), vec![
+        ("if", SyntaxKind::Keyword),
+        ("true", SyntaxKind::Keyword),
+        ("then", SyntaxKind::Keyword),
+        ("cargo", SyntaxKind::Keyword),
+        ("fi", SyntaxKind::Keyword),
+    ]);
+}
+
+#[test]
+fn strings_numbers_and_comments() {
+    assert_eq!(kinds(r#"echo "a \"b\"" 'c' 42 -1.5 v2 # done"#), vec![
+        ("echo", SyntaxKind::Keyword),
+        (r#""a \"b\"""#, SyntaxKind::String),
+        ("'c'", SyntaxKind::String),
+        ("42", SyntaxKind::Number),
+        ("-1.5", SyntaxKind::Number),
+        ("# done", SyntaxKind::Comment),
+    ]);
+    assert_eq!(kinds("grep 'unterminated"), vec![("grep", SyntaxKind::Keyword), ("'unterminated", SyntaxKind::String)]);
+    assert_eq!(kinds("git log --format=\"%h\""), vec![("git", SyntaxKind::Keyword), ("\"%h\"", SyntaxKind::String)]);
+}
+
+#[test]
+fn multi_line_commands_are_sent_as_one_paste() {
+    assert_eq!(submission("ls", true), "ls\r");
+    assert_eq!(submission("for f in *\ndo echo $f\ndone", true), "\x1b[200~for f in *\ndo echo $f\ndone\x1b[201~\r");
+    assert_eq!(submission("a\nb", false), "a\nb\r");
+}
@@ -0,0 +1,84 @@
+use super::*;
+use crate::test_dir;
+
+fn builtin() -> Completer {
+    Completer::new(BUILTIN_SPECS.iter().map(|json| serde_json::from_str(json).unwrap()).collect())
+}
+
+fn history(commands: &[&str]) -> Vec<String> {
+    commands.iter().map(|command| command.to_string()).collect()
+}
+
+#[test]
+fn history_prefix_wins_and_extends_the_input() {
+    let completer = builtin();
+    let history = history(&["git status", "git stash pop"]);
+    let suggestion = completer.suggest("git sta", None, &history).unwrap();
+    assert_eq!(suggestion, Suggestion { text: "git status".to_string(), source: SuggestionSource::History });
+    assert_eq!(suggestion.ghost("git sta"), Some("tus"));
+    assert_eq!(completer.suggest("git status", None, &history), None);
+    assert_eq!(completer.suggest("   ", None, &history), None);
+}
+
+#[test]
+fn specs_complete_subcommands_and_options() {
+    let completer = builtin();
+    assert_eq!(completer.complete_from_specs("kubec"), Some("kubectl ".to_string()));
+    assert_eq!(completer.complete_from_specs("git chec"), Some("git checkout ".to_string()));
+    assert_eq!(completer.complete_from_specs("docker compose u"), Some("docker compose up ".to_string()));
+    assert_eq!(completer.complete_from_specs("cargo t --work"), Some("cargo t --workspace ".to_string()));
+    assert_eq!(completer.complete_from_specs("kubectl get pods --all-n"), Some("kubectl get pods --all-namespaces ".to_string()));
+    assert_eq!(completer.complete_from_specs("git commit "), None);
+    assert_eq!(completer.complete_from_specs("unknown sub"), None);
+}
+
+#[test]
+fn paths_complete_relative_to_the_working_directory() {
+    let dir = test_dir("completion", "paths");
+    std::fs::create_dir_all(dir.join("src/nested")).unwrap();
+    std::fs::write(dir.join("src/main.rs"), "").unwrap();
+    std::fs::write(dir.join("src/mapping.rs"), "").unwrap();
+    std::fs::write(dir.join("my notes.txt"), "").unwrap();
+    std::fs::write(dir.join(".env"), "").unwrap();
+
+    assert_eq!(complete_path("cd sr", &dir), Some("cd src/".to_string()));
+    assert_eq!(complete_path("vim src/ma", &dir), None);
+    assert_eq!(complete_path("vim src/m", &dir), Some("vim src/ma".to_string()));
+    assert_eq!(complete_path("vim src/n", &dir), Some("vim src/nested/".to_string()));
+    assert_eq!(complete_path("cat my", &dir), Some("cat my\\ notes.txt ".to_string()));
+    assert_eq!(complete_path("cat .e", &dir), Some("cat .env ".to_string()));
+    assert_eq!(complete_path("cat e", &dir), None);
+    assert_eq!(complete_path("sr", &dir), None);
+}
+
+#[test]
+fn fuzzy_history_is_the_last_resort() {
+    let completer = builtin();
+    let history = history(&["docker compose logs -f api"]);
+    let suggestion = completer.suggest("dclogs", None, &history).unwrap();
+    assert_eq!(suggestion.source, SuggestionSource::FuzzyHistory);
+    assert_eq!(suggestion.ghost("dclogs"), None);
+    assert_eq!(completer.suggest("dc", None, &history), None);
+    assert!(is_subsequence("GCM", "git commit -m"));
+    assert!(!is_subsequence("gmc", "git commit"));
+}
+
+#[test]
+fn user_specs_load_from_a_directory() {
+    let dir = test_dir("completion", "specs");
+    std::fs::write(dir.join("just.json"), r#"{ "name": "just", "subcommands": [{ "name": "deploy" }] }"#).unwrap();
+    std::fs::write(dir.join("broken.json"), "{").unwrap();
+    std::fs::write(dir.join("README.md"), "not a spec").unwrap();
+    let (specs, errors) = load_dir(&dir);
+    assert_eq!(specs.len(), 1);
+    assert_eq!(errors.len(), 1);
+    assert!(errors[0].contains("broken.json"));
+    assert_eq!(Completer::new(specs).complete_from_specs("just de"), Some("just deploy ".to_string()));
+}
+
+#[test]
+fn words_split_after_multibyte_whitespace() {
+    assert_eq!(split_last_word("git\u{a0}che"), ("git\u{a0}", "che"));
+    assert_eq!(split_last_word("cd\u{3000}"), ("cd\u{3000}", ""));
+    assert_eq!(split_last_word("ls"), ("", "ls"));
+}
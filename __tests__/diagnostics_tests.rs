@@ -0,0 +1,65 @@
+use super::*;
+
+fn builtin() -> Vec<Matcher> {
+    DiagnosticSettings::default().compile()
+}
+
+fn locations(diagnostics: &[Diagnostic]) -> Vec<String> {
+    diagnostics.iter().map(|diagnostic| format!("{} {}", diagnostic.severity.label(), diagnostic.location())).collect()
+}
+
+#[test]
+fn rustc_errors_take_the_location_from_the_arrow_line() {
+    let output = "   Compiling tant v0.1.0\nerror[E0425]: cannot find value `x` in this scope\n  --> src/main.rs:10:5\n   |\nwarning: unused variable: `y`\n --> src/lib.rs:3:9\n";
+    let diagnostics = extract(output, &builtin());
+    assert_eq!(locations(&diagnostics), vec!["error src/main.rs:10:5", "warning src/lib.rs:3:9"]);
+    assert_eq!(diagnostics[0].message, "cannot find value `x` in this scope");
+}
+
+#[test]
+fn gcc_and_tsc_lines() {
+    let output = "main.c:3:5: error: unknown type name 'foo'\nmain.c:7:1: warning: control reaches end\nsrc/app.ts(4,7): error TS2322: Type 'string' is not assignable\nsrc/util.ts:9:2 - error TS2304: Cannot find name 'y'.\n";
+    let diagnostics = extract(output, &builtin());
+    assert_eq!(locations(&diagnostics), vec!["error main.c:3:5", "warning main.c:7:1", "error src/app.ts:4:7", "error src/util.ts:9:2"]);
+    assert_eq!(diagnostics[2].message, "TS2322: Type 'string' is not assignable");
+}
+
+#[test]
+fn eslint_takes_the_file_from_the_header_above() {
+    let output = "\n/repo/src/app.js\n  3:7   error    'x' is assigned a value but never used  no-unused-vars\n  9:1   warning  Unexpected console statement            no-console\n\n/repo/src/b.tsx\n  1:10  error  Missing semicolon  semi\n\n✖ 3 problems\n";
+    let diagnostics = extract(output, &builtin());
+    assert_eq!(locations(&diagnostics), vec!["error /repo/src/app.js:3:7", "warning /repo/src/app.js:9:1", "error /repo/src/b.tsx:1:10"]);
+    assert_eq!(diagnostics[0].message, "'x' is assigned a value but never used");
+}
+
+#[test]
+fn pytest_and_go_failures() {
+    let output = "tests/test_app.py:12: AssertionError\nFAILED tests/test_app.py::test_add - assert 1 == 2\n./main.go:12:5: undefined: foo\n    main_test.go:9: got 1, want 2\n";
+    let diagnostics = extract(output, &builtin());
+    assert_eq!(locations(&diagnostics), vec!["error tests/test_app.py:12", "error tests/test_app.py", "error ./main.go:12:5", "error main_test.go:9"]);
+    assert_eq!(diagnostics[1].message, "test_add - assert 1 == 2");
+}
+
+#[test]
+fn user_matchers_run_after_the_builtins_and_bad_ones_are_skipped() {
+    let settings = DiagnosticSettings {
+        matchers: vec![
+            MatcherConfig { name: "lint".to_string(), pattern: r"(?m)^LINT (?P<file>\S+) line (?P<line>\d+): (?P<message>.+)$".to_string(), file_pattern: None, severity: Severity::Warning },
+            MatcherConfig { name: "broken".to_string(), pattern: "(".to_string(), file_pattern: None, severity: Severity::Error },
+        ],
+    };
+    let matchers = settings.compile();
+    assert_eq!(matchers.len(), builtin_matchers().len() + 1);
+    let diagnostics = extract("LINT app.rb line 4: trailing whitespace\n", &matchers);
+    assert_eq!(locations(&diagnostics), vec!["warning app.rb:4"]);
+    assert!(extract("all good\n", &matchers).is_empty());
+}
+
+#[test]
+fn summaries_and_paths() {
+    let diagnostic = Diagnostic { file: "src/main.rs".to_string(), line: Some(10), column: Some(5), severity: Severity::Error, message: "boom".to_string() };
+    assert_eq!(summary(std::slice::from_ref(&diagnostic)), "error src/main.rs:10:5: boom");
+    assert_eq!(diagnostic.path(Some(Path::new("/work"))), PathBuf::from("/work/src/main.rs"));
+    let no_line = Diagnostic { line: None, column: None, ..diagnostic };
+    assert_eq!(no_line.location(), "src/main.rs");
+}
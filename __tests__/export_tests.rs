@@ -5,22 +5,12 @@ use chrono::Utc;
 
 fn sample_block() -> Block {
     Block {
-        command: "echo hello".to_string(),
         started_at: Some(Utc::now()),
         ended_at: Some(Utc::now()),
         duration_ms: Some(12),
-        exit_code: Some(0),
-        cwd: None,
-        output_range: None,
-        pinned: false,
-        tags: vec![],
-        selected: false,
-        output: "hello".to_string(),
         git_branch: Some("main".to_string()),
         git_status: Some(GitStatus::Clean),
-        host: "localhost".to_string(),
-        is_remote: false,
-        collapsed: false,
+        ..crate::test_block("echo hello", "hello")
     }
 }
 
@@ -179,3 +169,40 @@ fn export_json_empty_blocks_is_empty_array() {
     let parsed: serde_json::Value = serde_json::from_str(&result.content).expect("valid json");
     assert_eq!(parsed, serde_json::json!([]));
 }
+
+#[test]
+fn exports_include_tags_and_notes() {
+    let mut block = sample_block();
+    block.tags = vec!["incident".to_string(), "db".to_string()];
+    block.note = "Failover at **14:02**\n".to_string();
+    let markdown = format_blocks(&[block.clone()], ExportFormat::Markdown).unwrap().content;
+    assert!(markdown.contains("Tags: incident, db"));
+    assert!(markdown.contains("### Note\n\nFailover at **14:02**\n"));
+    let text = format_blocks(&[block.clone()], ExportFormat::Text).unwrap().content;
+    assert!(text.contains("Note:\nFailover at **14:02**\n"));
+    let json: serde_json::Value = serde_json::from_str(&format_blocks(&[block.clone()], ExportFormat::Json).unwrap().content).unwrap();
+    assert_eq!(json[0]["note"], "Failover at **14:02**\n");
+    assert_eq!(json[0]["tags"][1], "db");
+    block.note = "<b>".to_string();
+    let html = format_blocks(&[block], ExportFormat::Html).unwrap().content;
+    assert!(html.contains("<pre class=\"note\">&lt;b&gt;</pre>"));
+}
+
+#[test]
+fn export_shows_diagnostics_instead_of_output() {
+    let mut block = sample_block();
+    block.exit_code = Some(101);
+    block.output = "   Compiling tant\nerror: boom\n".to_string();
+    block.diagnostics = vec![crate::diagnostics::Diagnostic {
+        file: "src/main.rs".to_string(),
+        line: Some(3),
+        column: Some(1),
+        severity: crate::diagnostics::Severity::Error,
+        message: "boom".to_string(),
+    }];
+    let text = format_blocks(&[block.clone()], ExportFormat::Text).expect("text export");
+    assert!(text.content.contains("Diagnostics:\nerror src/main.rs:3:1: boom"));
+    assert!(!text.content.contains("Compiling"));
+    let markdown = format_blocks(&[block], ExportFormat::Markdown).expect("markdown export");
+    assert!(markdown.content.contains("### Diagnostics"));
+}
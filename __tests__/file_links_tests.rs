@@ -0,0 +1,41 @@
+use super::*;
+use crate::{test_dir, TestDir};
+
+fn project_dir(name: &str) -> TestDir {
+    let dir = test_dir("file-links", name);
+    std::fs::create_dir_all(dir.join("src")).unwrap();
+    std::fs::write(dir.join("src/main.rs"), "fn main() {}\n").unwrap();
+    std::fs::write(dir.join("Cargo.toml"), "").unwrap();
+    dir
+}
+
+#[test]
+fn references_to_existing_files_become_links() {
+    let dir = project_dir("existing");
+    let output = "error: boom\n  --> src/main.rs:1747:13\nsee Cargo.toml, and src/missing.rs:3\n";
+    let links = find_links(output, Some(&dir));
+    assert_eq!(links.len(), 2);
+    assert_eq!(links[0], FileLink { output_line: 1, start: 6, end: 25, path: dir.join("src/main.rs"), line: Some(1747), column: Some(13) });
+    assert_eq!((links[1].output_line, links[1].line, &links[1].path), (2, None, &dir.join("Cargo.toml")));
+    assert_eq!(&output.lines().nth(2).unwrap()[links[1].start..links[1].end], "Cargo.toml");
+}
+
+#[test]
+fn words_without_a_slash_or_extension_are_not_checked() {
+    let dir = project_dir("words");
+    std::fs::write(dir.join("README"), "").unwrap();
+    assert!(find_links("README src https://example.com/src/main.rs --src/main.rs", Some(&dir)).is_empty());
+    assert_eq!(find_links("'src/main.rs:2:'", Some(&dir))[0].line, Some(2));
+}
+
+#[test]
+fn templates_fill_in_the_location() {
+    let path = Path::new("/work/it's.rs");
+    assert_eq!(fill_template("code -g {file}:{line}:{col}", path, Some(10), None), r"code -g '/work/it'\''s.rs':10:1");
+    let settings = FileLinkSettings { editor_command: Some("nvim +{line} {file}".to_string()), run_in_pane: true };
+    assert_eq!(settings.open_action(Path::new("a.rs"), Some(3), Some(2)), OpenAction::Pane("nvim +3 'a.rs'".to_string()));
+    let launched = FileLinkSettings { run_in_pane: false, ..settings };
+    assert_eq!(launched.open_action(Path::new("a.rs"), None, None), OpenAction::Launch("nvim +1 'a.rs'".to_string()));
+    assert_eq!(FileLinkSettings::default().open_action(Path::new("a.rs"), Some(4), None), OpenAction::Pane("${EDITOR:-vi} +4 'a.rs'".to_string()));
+    assert_eq!(default_editor_command(Path::new("a.rs"), None), "${EDITOR:-vi} 'a.rs'");
+}
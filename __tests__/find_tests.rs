@@ -0,0 +1,117 @@
+use super::*;
+
+fn parser(rows: u16, cols: u16, scrollback: usize, input: &str) -> vt100::Parser {
+    let mut parser = vt100::Parser::new(rows, cols, scrollback);
+    parser.process(input.as_bytes());
+    parser
+}
+
+fn find(parser: &mut vt100::Parser, query: &str, mode: FindMode) -> FindState {
+    let first = -(GridLines::scrollback_len(parser) as isize);
+    let last = parser.screen().size().0 as isize - 1;
+    let lines = GridLines::read(parser, first..=last);
+    let mut state = FindState::new();
+    state.query = query.to_string();
+    state.mode = mode;
+    state.search(&lines, first, last);
+    state
+}
+
+fn starts(state: &FindState) -> Vec<(isize, u16)> {
+    state.matches().iter().map(|found| (found.start.line, found.start.col)).collect()
+}
+
+#[test]
+fn literal_search_ignores_case_and_covers_scrollback() {
+    let input: String = (1..=6).map(|n| format!("Error {}\r\n", n)).collect();
+    let mut parser = parser(3, 20, 100, &input);
+    let state = find(&mut parser, "error", FindMode::Literal);
+    assert_eq!(starts(&state), vec![(-4, 0), (-3, 0), (-2, 0), (-1, 0), (0, 0), (1, 0)]);
+    // Starts on the match closest to the prompt
+    assert_eq!(state.status(), "6 of 6");
+}
+
+#[test]
+fn case_sensitive_search_matches_exact_case() {
+    let mut parser = parser(3, 30, 0, "Warn warn WARN");
+    assert_eq!(starts(&find(&mut parser, "warn", FindMode::CaseSensitive)), vec![(0, 5)]);
+    assert_eq!(find(&mut parser, "warn", FindMode::Literal).matches().len(), 3);
+    // Literal mode does not interpret regex syntax
+    assert!(find(&mut parser, "w.rn", FindMode::Literal).matches().is_empty());
+}
+
+#[test]
+fn regex_search_and_invalid_patterns() {
+    let mut parser = parser(3, 40, 0, "port 8080 and port 443");
+    let state = find(&mut parser, r"port \d+", FindMode::Regex);
+    assert_eq!(state.matches(), &[
+        FindMatch { start: GridPoint::new(0, 0), end: GridPoint::new(0, 9) },
+        FindMatch { start: GridPoint::new(0, 14), end: GridPoint::new(0, 22) },
+    ]);
+    let invalid = find(&mut parser, "port (", FindMode::Regex);
+    assert!(invalid.matches().is_empty());
+    assert!(invalid.error.is_some());
+}
+
+#[test]
+fn matches_map_back_to_cells_after_wide_characters() {
+    let mut parser = parser(2, 20, 0, "日本 needle");
+    let state = find(&mut parser, "needle", FindMode::Literal);
+    assert_eq!(state.matches(), &[FindMatch { start: GridPoint::new(0, 5), end: GridPoint::new(0, 11) }]);
+}
+
+#[test]
+fn matches_can_cross_a_soft_wrap() {
+    let mut parser = parser(3, 10, 0, "xxxxxxxneedle");
+    let mut state = find(&mut parser, "needle", FindMode::Literal);
+    assert_eq!(state.matches(), &[FindMatch { start: GridPoint::new(0, 7), end: GridPoint::new(1, 3) }]);
+    assert_eq!(state.columns_in_line(0, 10), vec![(7, 10, true)]);
+    assert_eq!(state.columns_in_line(1, 10), vec![(0, 3, true)]);
+    state.step(true);
+    assert_eq!(state.columns_in_line(2, 10), vec![]);
+}
+
+#[test]
+fn stepping_wraps_in_both_directions() {
+    let mut parser = parser(3, 30, 0, "a b a b a");
+    let mut state = find(&mut parser, "a", FindMode::Literal);
+    assert_eq!(state.current().map(|found| found.start.col), Some(8));
+    assert_eq!(state.step(true).map(|found| found.start.col), Some(0));
+    assert_eq!(state.step(false).map(|found| found.start.col), Some(8));
+    assert_eq!(state.step(false).map(|found| found.start.col), Some(4));
+    assert_eq!(state.columns_in_line(0, 30), vec![(0, 1, false), (4, 5, true), (8, 9, false)]);
+}
+
+#[test]
+fn refresh_is_throttled_while_output_streams() {
+    let mut parser = parser(3, 30, 0, "abc");
+    let mut state = find(&mut parser, "b", FindMode::Literal);
+    let now = Instant::now();
+    assert!(!state.refresh_due(false, now));
+    assert!(!state.refresh_due(true, now));
+    assert!(state.refresh_due(false, now + Duration::from_millis(250)));
+}
+
+#[test]
+fn scrolling_centres_off_screen_matches() {
+    assert_eq!(scroll_offset_for(1, 24, 0, 500), 0);
+    assert_eq!(scroll_offset_for(-100, 24, 0, 500), 112);
+    assert_eq!(scroll_offset_for(-490, 24, 0, 500), 500);
+    assert_eq!(scroll_offset_for(-100, 24, 110, 500), 110);
+}
+
+#[test]
+fn incremental_refresh_matches_a_full_search_as_output_scrolls() {
+    let input: String = (1..=4).map(|n| format!("error {}\r\n", n)).collect();
+    let mut streamed = parser(3, 10, 8, &input);
+    let mut state = FindState::new();
+    state.query = "error".to_string();
+    state.refresh(&mut streamed, false);
+    // A match wrapped across the old top row and more lines than fit in scrollback
+    for chunk in ["xx error 5 error 6\r\n", "error 7\r\nok\r\n", "error 8\r\nerror 9\r\nerror 10\r\nok\r\n"] {
+        streamed.process(chunk.as_bytes());
+        state.refresh(&mut streamed, true);
+        let full = find(&mut streamed, "error", FindMode::Literal);
+        assert_eq!(state.matches(), full.matches());
+    }
+}
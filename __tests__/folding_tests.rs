@@ -0,0 +1,46 @@
+use super::*;
+
+#[test]
+fn fold_keeps_head_and_tail() {
+    let settings = FoldSettings { head_lines: 3, tail_lines: 2, max_visible_lines: 10 };
+    let fold = settings.fold(100);
+    assert_eq!(fold, Fold { head: 0..3, hidden: 3..98, tail: 98..100 });
+    assert_eq!(hidden_label(fold.hidden.len()), "… 95 lines hidden");
+}
+
+#[test]
+fn short_output_folds_entirely() {
+    let settings = FoldSettings { head_lines: 3, tail_lines: 2, max_visible_lines: 10 };
+    assert_eq!(settings.fold(5), Fold { head: 0..0, hidden: 0..5, tail: 5..5 });
+    assert_eq!(settings.fold(6).hidden, 3..4);
+    assert_eq!(hidden_label(1), "… 1 line hidden");
+}
+
+#[test]
+fn hidden_counts_are_grouped() {
+    assert_eq!(hidden_label(12_345), "… 12,345 lines hidden");
+    assert_eq!(hidden_label(1_000_000), "… 1,000,000 lines hidden");
+    assert_eq!(hidden_label(999), "… 999 lines hidden");
+}
+
+#[test]
+fn window_covers_viewport_plus_overscan() {
+    assert_eq!(visible_window(10_000, 0.0, 20.0, 25), 0..33);
+    // Scrolled to line 500
+    assert_eq!(visible_window(10_000, 10_000.0, 20.0, 25), 492..533);
+    assert_eq!(visible_window(510, 10_000.0, 20.0, 25), 492..510);
+    assert_eq!(visible_window(3, 0.0, 20.0, 25), 0..3);
+    assert_eq!(visible_window(0, 50.0, 20.0, 25), 0..0);
+}
+
+#[test]
+fn viewport_never_exceeds_output() {
+    let settings = FoldSettings::default();
+    assert_eq!(settings.viewport_lines(4), 4);
+    assert_eq!(settings.viewport_lines(1_000), settings.max_visible_lines);
+}
+
+#[test]
+fn gutter_numbers_are_right_aligned() {
+    assert_eq!(gutter(8..11, 120), "  9\n 10\n 11");
+}
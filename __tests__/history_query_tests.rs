@@ -0,0 +1,151 @@
+use super::*;
+
+fn sample_block() -> Block {
+    Block {
+        started_at: Some(Local.with_ymd_and_hms(2026, 10, 5, 14, 30, 0).unwrap().with_timezone(&Utc)),
+        duration_ms: Some(45_000),
+        exit_code: Some(101),
+        cwd: Some(PathBuf::from("/srv/svc/api")),
+        tags: vec!["Deploy".to_string()],
+        git_branch: Some("main".to_string()),
+        git_status: Some(GitStatus::Dirty),
+        host: "build-01".to_string(),
+        is_remote: true,
+        ..crate::test_block("cargo test --workspace", "thread 'main' panicked at src/lib.rs")
+    }
+}
+
+fn matches(query: &str) -> bool {
+    HistoryQuery::parse(query).expect("valid query").matches(&sample_block())
+}
+
+fn parse_error(query: &str) -> QueryError {
+    HistoryQuery::parse(query).expect_err("invalid query")
+}
+
+#[test]
+fn empty_query_matches_everything() {
+    let query = HistoryQuery::parse("   ").unwrap();
+    assert!(query.is_empty());
+    assert!(query.matches(&sample_block()));
+}
+
+#[test]
+fn full_example_query_matches() {
+    assert!(matches("exit:!0 cwd:/srv/svc dur:>30s branch:main tag:deploy after:2026-10-01 \"panic\""));
+    assert!(!matches("exit:!0 cwd:/srv/svc dur:>30s branch:main tag:deploy after:2026-10-06 \"panic\""));
+}
+
+#[test]
+fn exit_code_comparisons() {
+    assert!(matches("exit:101"));
+    assert!(matches("exit:=101"));
+    assert!(matches("exit:!=0"));
+    assert!(matches("exit:>=101"));
+    assert!(!matches("exit:<101"));
+    assert!(!matches("exit:0"));
+    let mut running = sample_block();
+    running.exit_code = None;
+    assert!(!HistoryQuery::parse("exit:!0").unwrap().matches(&running));
+    assert!(HistoryQuery::parse("is:running").unwrap().matches(&running));
+}
+
+#[test]
+fn durations_accept_units() {
+    assert!(matches("dur:>30s"));
+    assert!(matches("dur:45"));
+    assert!(matches("dur:<1m"));
+    assert!(matches("duration:>=45000ms"));
+    assert!(!matches("dur:>0.5h"));
+    assert_eq!(parse_error("dur:>30x").position, 4);
+}
+
+#[test]
+fn cwd_matches_whole_path_components() {
+    assert!(matches("cwd:/srv/svc"));
+    assert!(!matches("cwd:/srv/sv"));
+    assert_eq!(expand_home("~/svc", Some("/home/me")), PathBuf::from("/home/me/svc"));
+    assert_eq!(expand_home("~", Some("/home/me")), PathBuf::from("/home/me"));
+    assert_eq!(expand_home("~other/svc", Some("/home/me")), PathBuf::from("~other/svc"));
+}
+
+#[test]
+fn text_fields_are_case_insensitive() {
+    assert!(matches("PANIC"));
+    assert!(matches("branch:MAIN host:Build-01 tag:DEPLOY status:dirty"));
+    assert!(matches("cmd:cargo output:panicked"));
+    assert!(!matches("cmd:panicked"));
+    assert!(matches("is:remote is:failed"));
+    assert!(!matches("is:pinned"));
+}
+
+#[test]
+fn notes_and_tags_are_searchable() {
+    let mut block = sample_block();
+    block.note = "Root cause: expired **TLS** cert".to_string();
+    assert!(HistoryQuery::parse("note:tls is:tagged").unwrap().matches(&block));
+    block.tags.clear();
+    assert!(!HistoryQuery::parse("is:tagged").unwrap().matches(&block));
+    // Free text only looks at the command and output
+    assert!(!HistoryQuery::parse("expired").unwrap().matches(&block));
+}
+
+#[test]
+fn negation_and_quoting() {
+    assert!(matches("-exit:0"));
+    assert!(!matches("-\"panic\""));
+    assert!(!matches("-output:panic"));
+    assert!(matches("\"thread 'main'\""));
+    assert!(matches("output:\"src/lib.rs\""));
+    assert!(!matches("\"main panicked\""));
+    // A lone dash or a dash before a plain word is searched as text, not a negation
+    assert!(matches("test -"));
+    assert!(matches("--workspace"));
+    assert!(!matches("-panic"));
+    let query = HistoryQuery::parse(r#""say \"hi\"""#).unwrap();
+    let mut block = sample_block();
+    block.command = "echo say \"hi\"".to_string();
+    assert!(query.matches(&block));
+}
+
+#[test]
+fn flags_are_searched_as_typed() {
+    let mut block = sample_block();
+    block.command = "git push --force".to_string();
+    assert!(HistoryQuery::parse("--force").unwrap().matches(&block));
+    assert!(!HistoryQuery::parse("-rf").unwrap().matches(&block));
+    block.command = "rm -rf build".to_string();
+    assert!(HistoryQuery::parse("-rf").unwrap().matches(&block));
+}
+
+#[test]
+fn dates_bound_the_start_time() {
+    assert!(matches("after:2026-10-05"));
+    assert!(matches("before:2026-10-06"));
+    assert!(matches("after:2026-10-05T14:00 before:2026-10-05T15:00"));
+    assert!(!matches("before:2026-10-05"));
+    assert_eq!(parse_error("after:yesterday").message, "Expected a date like 2026-10-01, found 'yesterday'");
+}
+
+#[test]
+fn errors_point_at_the_offending_term() {
+    let unknown = parse_error("exit:1 colour:red");
+    assert_eq!(unknown.position, 7);
+    assert!(unknown.message.contains("quote the term"));
+    assert!(HistoryQuery::parse("\"colour:red\"").is_ok());
+    assert_eq!(parse_error("panic \"unterminated").position, 6);
+    assert_eq!(parse_error("exit:abc").position, 5);
+    assert_eq!(parse_error("tag:").message, "Missing value for 'tag:'");
+    assert_eq!(parse_error("status:weird").to_string(), "Unknown git status 'weird' (clean, dirty or conflicts) (column 8)");
+}
+
+#[test]
+fn text_matches_report_highlighted_parts() {
+    let block = sample_block();
+    let both = HistoryQuery::parse("cargo panicked").unwrap().text_matches(&block);
+    assert_eq!(both, TextMatches { command: true, output: true });
+    let negated = HistoryQuery::parse("-\"foo\" exit:101").unwrap().text_matches(&block);
+    assert_eq!(negated, TextMatches::default());
+    let output_only = HistoryQuery::parse("output:cargo").unwrap().text_matches(&block);
+    assert!(!output_only.command);
+}
@@ -0,0 +1,149 @@
+use super::*;
+use crate::{test_dir, TestDir};
+use chrono::Duration;
+
+fn block(command: &str, cwd: &str, hours_ago: i64, exit_code: i32) -> Block {
+    Block {
+        started_at: Some(Utc::now() - Duration::hours(hours_ago)),
+        duration_ms: Some(10),
+        exit_code: Some(exit_code),
+        cwd: Some(PathBuf::from(cwd)),
+        git_branch: Some("main".to_string()),
+        git_status: Some(GitStatus::Clean),
+        ..crate::test_block(command, "output is not recorded")
+    }
+}
+
+fn commands(matches: Vec<HistoryMatch>) -> Vec<String> {
+    matches.into_iter().map(|found| found.command).collect()
+}
+
+fn store_with(name: &str, blocks: &[Block]) -> (TestDir, HistoryStore) {
+    let dir = test_dir("history", name);
+    let path = dir.join("history.jsonl");
+    let store = HistoryStore::open(path.clone());
+    for block in blocks {
+        store.append_task(block)().unwrap();
+    }
+    (dir, HistoryStore::open(path))
+}
+
+#[test]
+fn records_survive_reopening_without_output() {
+    let (_dir, store) = store_with("reopen", &[block("make", "/src/a", 1, 0), block("   ", "/src/a", 1, 0)]);
+    assert_eq!(store.records.len(), 1);
+    let record = &store.records[0];
+    assert_eq!(record.command, "make");
+    assert_eq!(record.cwd.as_deref(), Some(Path::new("/src/a")));
+    assert_eq!(record.git_branch.as_deref(), Some("main"));
+    let raw = std::fs::read_to_string(&store.path).unwrap();
+    assert!(!raw.contains("output is not recorded"));
+}
+
+#[test]
+fn refresh_picks_up_appends_from_other_windows() {
+    let dir = test_dir("history", "refresh");
+    let path = dir.join("history.jsonl");
+    let mut mine = HistoryStore::open(path.clone());
+    let other = HistoryStore::open(path.clone());
+    other.append_task(&block("ls", "/", 0, 0))().unwrap();
+    // Half-written line from a window that is still appending
+    std::fs::OpenOptions::new().append(true).open(&path).unwrap().write_all(b"{\"command\":\"gi").unwrap();
+    mine.refresh();
+    assert_eq!(mine.records.len(), 1);
+    std::fs::OpenOptions::new().append(true).open(&path).unwrap().write_all(b"t\",\"started_at\":null,\"duration_ms\":null,\"exit_code\":null,\"cwd\":null,\"git_branch\":null,\"git_status\":null,\"host\":\"h\"}\nnot json\n").unwrap();
+    mine.refresh();
+    let commands: Vec<&str> = mine.records.iter().map(|record| record.command.as_str()).collect();
+    assert_eq!(commands, vec!["ls", "git"]);
+}
+
+#[test]
+fn frequent_recent_commands_rank_first() {
+    let (_dir, store) = store_with(
+        "frecency",
+        &[
+            block("cargo build", "/a", 2000, 0),
+            block("cargo build", "/a", 2000, 0),
+            block("cargo build", "/a", 2000, 0),
+            block("cargo test", "/a", 1, 0),
+            block("cargo test", "/a", 2, 0),
+            block("cargo fmt", "/a", 100, 0),
+        ],
+    );
+    let results = commands(store.search("CARGO", None, Utc::now(), 10));
+    assert_eq!(results, vec!["cargo test", "cargo fmt", "cargo build"]);
+    assert_eq!(commands(store.search("cargo", None, Utc::now(), 1)), vec!["cargo test"]);
+    assert!(store.search("npm", None, Utc::now(), 10).is_empty());
+}
+
+#[test]
+fn current_directory_and_success_are_boosted() {
+    let (_dir, store) = store_with(
+        "boost",
+        &[
+            block("make deploy", "/srv/web", 5, 0),
+            block("make check", "/srv/api", 1, 0),
+            block("make tset", "/srv/api", 1, 2),
+            block("make test", "/srv/api", 3, 0),
+        ],
+    );
+    let anywhere = commands(store.search("make", None, Utc::now(), 10));
+    assert_eq!(anywhere[0], "make check");
+    assert_eq!(anywhere.last().map(String::as_str), Some("make tset"));
+    let in_web = commands(store.search("make", Some(Path::new("/srv/web")), Utc::now(), 10));
+    assert_eq!(in_web[0], "make deploy");
+}
+
+#[test]
+fn import_skips_commands_already_recorded() {
+    let mut imported = block("git status", "/", 10, 0);
+    imported.tags = vec![IMPORTED_TAG.to_string()];
+    let pushed = block("git push", "/", 10, 0);
+    let (_dir, mut store) = store_with("import", std::slice::from_ref(&pushed));
+    let batch = vec![imported.clone(), imported, pushed];
+    assert_eq!(store.import(&batch).unwrap(), 1);
+    assert_eq!(store.import(&batch).unwrap(), 0);
+    let results = store.search("git status", None, Utc::now(), 10);
+    assert_eq!(results, vec![HistoryMatch { command: "git status".to_string(), imported: true }]);
+    store.append_task(&block("git status", "/", 0, 0))().unwrap();
+    store.refresh();
+    assert!(!store.search("git status", None, Utc::now(), 10)[0].imported);
+}
+
+#[test]
+fn history_files_start_with_a_version_header() {
+    let dir = test_dir("history", "header");
+    let path = dir.join("history.jsonl");
+    let store = HistoryStore::open(path.clone());
+    store.append_task(&block("ls", "/", 0, 0))().unwrap();
+    store.append_task(&block("pwd", "/", 0, 0))().unwrap();
+    let raw = std::fs::read_to_string(&path).unwrap();
+    let lines: Vec<&str> = raw.lines().collect();
+    assert_eq!(lines.len(), 3);
+    assert_eq!(lines[0], versioning::header(&HISTORY));
+    assert_eq!(HistoryStore::open(path).records.len(), 2);
+}
+
+#[test]
+fn headerless_files_are_read_as_version_0_and_marked_before_appending() {
+    let dir = test_dir("history", "legacy");
+    let path = dir.join("history.jsonl");
+    let legacy = serde_json::to_string(&HistoryRecord::from_block(&block("make", "/", 0, 0))).unwrap();
+    std::fs::write(&path, format!("{}\n", legacy)).unwrap();
+    let store = HistoryStore::open(path.clone());
+    assert_eq!(store.records.len(), 1);
+    store.append_task(&block("ls", "/", 0, 0))().unwrap();
+    let raw = std::fs::read_to_string(&path).unwrap();
+    assert_eq!(raw.lines().nth(1), Some(versioning::header(&HISTORY).as_str()));
+    let commands: Vec<String> = HistoryStore::open(path).records.into_iter().map(|record| record.command).collect();
+    assert_eq!(commands, vec!["make", "ls"]);
+}
+
+#[test]
+fn records_after_a_newer_header_are_skipped() {
+    let dir = test_dir("history", "newer");
+    let path = dir.join("history.jsonl");
+    let record = serde_json::to_string(&HistoryRecord::from_block(&block("make", "/", 0, 0))).unwrap();
+    std::fs::write(&path, format!("{}\n{}\n{{\"version\": {}}}\n{}\n", versioning::header(&HISTORY), record, HISTORY.version() + 1, record)).unwrap();
+    assert_eq!(HistoryStore::open(path).records.len(), 1);
+}
@@ -0,0 +1,77 @@
+use super::*;
+use crate::Axis;
+
+fn fixture(name: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("__tests__/fixtures/layouts").join(name)
+}
+
+fn leaf(pane_id: usize) -> Box<LayoutNode> {
+    Box::new(LayoutNode::Leaf { pane_id })
+}
+
+fn tab(root: LayoutNode, panes: usize) -> String {
+    let file = LayoutFile { tabs: vec![TabTemplate { title: String::new(), root, panes: vec![PaneTemplate::default(); panes], active_pane: 0 }], active_tab: 0, dir: PathBuf::new(), name: String::new() };
+    file.to_toml().unwrap()
+}
+
+#[test]
+fn toml_and_yaml_describe_the_same_layout() {
+    let toml = LayoutFile::load(&fixture("dev.toml")).unwrap();
+    let yaml = LayoutFile::load(&fixture("dev.yaml")).unwrap();
+    assert_eq!(toml, yaml);
+
+    let dev = &toml.tabs[0];
+    let right = LayoutNode::Split { axis: Axis::Vertical, ratio: 0.5, left: leaf(1), right: leaf(2) };
+    assert_eq!(dev.root, LayoutNode::Split { axis: Axis::Horizontal, ratio: 0.6, left: leaf(0), right: Box::new(right) });
+    assert_eq!(dev.panes[1].profile.as_deref(), Some("prod"));
+    assert_eq!(dev.panes[1].commands, vec!["npm install", "npm run dev"]);
+    assert_eq!(toml.tabs[1].root, LayoutNode::Leaf { pane_id: 0 });
+    assert_eq!(toml.tabs[1].panes[0].commands, vec!["tail -f /var/log/syslog"]);
+}
+
+#[test]
+fn pane_directories_resolve_against_the_file_and_home() {
+    let file = LayoutFile::load(&fixture("dev.toml")).unwrap();
+    let panes = &file.tabs[0].panes;
+    assert_eq!(file.pane_directory(&panes[0]), Some(fixture("app")));
+    assert_eq!(file.pane_directory(&panes[2]), Some(paths::home_dir().join("src/app")));
+    assert_eq!(file.pane_directory(&PaneTemplate::default()), None);
+}
+
+#[test]
+fn split_trees_must_number_every_pane_once() {
+    let path = Path::new("bad.toml");
+    let duplicate = tab(LayoutNode::Split { axis: Axis::Horizontal, ratio: 0.5, left: leaf(0), right: leaf(0) }, 0);
+    assert!(LayoutFile::parse(path, &duplicate).unwrap_err().contains("each used once"));
+    let too_many = tab(LayoutNode::Leaf { pane_id: 0 }, 2);
+    assert!(LayoutFile::parse(path, &too_many).is_err());
+    let bad_ratio = tab(LayoutNode::Split { axis: Axis::Vertical, ratio: 1.5, left: leaf(0), right: leaf(1) }, 0);
+    assert!(LayoutFile::parse(path, &bad_ratio).unwrap_err().contains("ratio"));
+    assert!(LayoutFile::parse(path, "tabs = []").is_err());
+    assert!(LayoutFile::parse(Path::new("dev.json"), "{}").is_err());
+}
+
+#[test]
+fn missing_panes_are_filled_in_and_ratios_clamped() {
+    let file = LayoutFile::parse(Path::new("x.toml"), &tab(LayoutNode::Split { axis: Axis::Horizontal, ratio: 0.02, left: leaf(1), right: leaf(0) }, 1)).unwrap();
+    assert_eq!(file.tabs[0].panes.len(), 2);
+    assert!(matches!(file.tabs[0].root, LayoutNode::Split { ratio, .. } if ratio == MIN_RATIO));
+}
+
+#[test]
+fn templates_round_trip_through_toml() {
+    let file = LayoutFile::load(&fixture("dev.toml")).unwrap();
+    let reparsed = LayoutFile::parse(&fixture("dev.toml"), &file.to_toml().unwrap()).unwrap();
+    assert_eq!(reparsed, file);
+}
+
+#[test]
+fn layouts_are_found_by_path_or_name() {
+    let dir = fixture("");
+    assert_eq!(find("dev", &dir), Some(dir.join("dev.toml")));
+    assert_eq!(find("dev.yaml", &dir), Some(dir.join("dev.yaml")));
+    assert_eq!(find("missing", &dir), None);
+    assert_eq!(list(&dir), vec![dir.join("dev.toml"), dir.join("dev.yaml")]);
+    assert_eq!(template_name(" morning "), Ok("morning".to_string()));
+    assert!(template_name("../x").is_err());
+}
@@ -0,0 +1,52 @@
+use super::*;
+
+#[test]
+fn panes_are_offset_by_the_open_bookmarks_sidebar() {
+    let window = Size::new(1000.0, 600.0);
+    let root = LayoutNode::Split { axis: Axis::Horizontal, ratio: 0.5, left: Box::new(LayoutNode::Leaf { pane_id: 0 }), right: Box::new(LayoutNode::Leaf { pane_id: 1 }) };
+
+    let closed = Tant::pane_rect(&root, Tant::layout_area(window, false), 1).unwrap();
+    assert_eq!((closed.x, closed.width), (500.0, 500.0));
+
+    let open = Tant::layout_area(window, true);
+    assert_eq!((open.x, open.width), (BOOKMARKS_WIDTH, 1000.0 - BOOKMARKS_WIDTH));
+    let left = Tant::pane_rect(&root, open, 0).unwrap();
+    let right = Tant::pane_rect(&root, open, 1).unwrap();
+    assert_eq!(left.x, BOOKMARKS_WIDTH);
+    assert_eq!(right.x, BOOKMARKS_WIDTH + (1000.0 - BOOKMARKS_WIDTH) / 2.0);
+}
+
+#[test]
+fn block_mode_clicks_neither_select_nor_copy() {
+    let mut parser = TerminalParser::new(4, 20);
+    parser.process(b"hello world");
+    let rect = Rectangle { x: 100.0, y: 0.0, width: 200.0, height: 80.0 };
+    let cell = (10.0, 20.0);
+    let inside = Point::new(120.0, 10.0);
+
+    let point = Tant::grid_point(&parser, false, rect, cell, inside, false);
+    assert_eq!(point, None);
+    assert_eq!(Tant::grid_point(&parser, false, rect, cell, inside, true), None);
+    let selection = point.map(|point| Selection::new(mode_for_click(2, false), point));
+    assert_eq!(Tant::selection_text(selection.as_ref(), &mut parser), None);
+
+    // On the grid a press must land in the pane, while a drag is clamped to it
+    assert_eq!(Tant::grid_point(&parser, true, rect, cell, Point::new(50.0, 10.0), false), None);
+    assert_eq!(Tant::grid_point(&parser, true, rect, cell, Point::new(50.0, 10.0), true), Some(GridPoint::new(0, 0)));
+    let point = Tant::grid_point(&parser, true, rect, cell, inside, false).unwrap();
+    let mut selection = Selection::new(mode_for_click(2, false), point);
+    selection.expand(&parser.grid_lines(selection.endpoint_lines()), DEFAULT_WORD_CHARS);
+    assert_eq!(Tant::selection_text(Some(&selection), &mut parser), Some("hello".to_string()));
+}
+
+#[test]
+fn tabs_whose_shell_does_not_start_are_kept_for_the_session() {
+    let pane = SerializablePane { history: vec![crate::test_block("make", "ok")], current_command: String::new(), working_directory: "/".to_string(), title: "build".to_string(), scroll_offset: 0, profile: None, screen: None, last_command: None };
+    let tab = SerializableTab { root: LayoutNode::Leaf { pane_id: 0 }, panes: vec![pane], active_pane: 0, title: "api".to_string() };
+    let mut left_out = Vec::new();
+    let restored = Tant::restore_tabs(Layout { tabs: vec![tab], active_tab: 0 }, "/no/such/tant/shell", &[], &mut left_out);
+    assert!(restored.is_none());
+    assert_eq!(left_out.len(), 1);
+    assert_eq!(left_out[0].panes[0].history[0].command, "make");
+    assert!(Tant::left_out_message(&left_out).unwrap().contains("'api'"));
+}
@@ -0,0 +1,20 @@
+use super::*;
+
+#[test]
+fn transcripts_round_trip_and_replay_greyed_out() {
+    let mut parser = TerminalParser::new(4, 20);
+    parser.process(b"one\r\ntwo  \r\nthree\r\nfour\r\nfive\r\n");
+    let transcript = parser.transcript();
+    assert_eq!(transcript, "one\ntwo\nthree\nfour\nfive");
+
+    let mut restored = TerminalParser::new(4, 20);
+    restored.replay_transcript(&transcript);
+    assert_eq!(restored.transcript(), transcript);
+    assert_eq!(restored.screen().cell(3, 0).map(|cell| cell.contents().to_string()), Some(String::new()));
+    // Replayed text is drawn in bright black and the colour is reset afterwards
+    let five = restored.screen().cell(2, 0).expect("replayed cell");
+    assert_eq!(five.contents(), "f");
+    assert_eq!(five.fgcolor(), vt100::Color::Idx(8));
+    restored.process(b"live");
+    assert_eq!(restored.screen().cell(3, 0).map(|cell| cell.fgcolor()), Some(vt100::Color::Default));
+}
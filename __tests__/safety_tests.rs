@@ -0,0 +1,121 @@
+use super::*;
+use crate::test_dir;
+
+fn defaults() -> SafetyGuard {
+    SafetyGuard::from_configs(&SafetyConfig::default(), &[]).0
+}
+
+fn on_branch(branch: &str) -> CommandContext {
+    CommandContext { git_branch: Some(branch.to_string()), kube_context: None }
+}
+
+#[test]
+fn recursive_delete_of_root_or_home_is_flagged() {
+    let guard = defaults();
+    let none = CommandContext::default();
+    for command in ["rm -rf /", "sudo rm -rf /*", "rm -r -f ~", "rm -Rf $HOME/ && ls"] {
+        assert_eq!(guard.check(command, &none), vec!["Recursive delete of / or home"], "{}", command);
+    }
+    for command in ["rm -rf /tmp/build", "rm -f /", "rm -rf ./target", "echo rm -rf"] {
+        assert!(guard.check(command, &none).is_empty(), "{}", command);
+    }
+}
+
+#[test]
+fn force_push_only_matters_on_protected_branches() {
+    let guard = defaults();
+    let label = vec!["Force push to a protected branch".to_string()];
+    assert_eq!(guard.check("git push --force", &on_branch("main")), label);
+    assert!(guard.check("git push --force", &on_branch("feature/x")).is_empty());
+    assert_eq!(guard.check("git push -f origin HEAD:master", &on_branch("feature/x")), label);
+    assert_eq!(guard.check("git push origin +main --force-with-lease", &CommandContext::default()), label);
+    assert!(guard.check("git push origin main", &on_branch("main")).is_empty());
+}
+
+#[test]
+fn kubectl_delete_needs_a_production_context() {
+    let guard = defaults();
+    let label = vec!["kubectl delete in production".to_string()];
+    let prod = CommandContext { git_branch: None, kube_context: Some("eks-prod-eu".to_string()) };
+    assert_eq!(guard.check("kubectl delete pod api-1", &prod), label);
+    assert!(guard.check("kubectl delete pod api-1", &CommandContext::default()).is_empty());
+    assert!(guard.check("kubectl get pods", &prod).is_empty());
+    assert_eq!(guard.check("kubectl --context=prod delete ns x", &CommandContext::default()), label);
+    assert!(guard.check("kubectl --context staging delete ns x", &prod).is_empty());
+}
+
+#[test]
+fn sql_drops_are_flagged_in_any_case() {
+    let guard = defaults();
+    assert_eq!(guard.check("psql -c 'drop table users'", &CommandContext::default()), vec!["Drop table or database"]);
+    assert!(guard.check("echo dropped tables", &CommandContext::default()).is_empty());
+}
+
+#[test]
+fn the_user_config_adds_replaces_and_disables_rules() {
+    let config = SafetyConfig {
+        rules: vec![
+            SafetyRuleConfig { label: "Terraform destroy".to_string(), pattern: r"\bterraform\s+destroy\b".to_string(), branches: Vec::new(), kube_context: None },
+            SafetyRuleConfig { label: "Format a filesystem".to_string(), pattern: r"\bmkfs\.ext4\b".to_string(), branches: Vec::new(), kube_context: None },
+            SafetyRuleConfig { label: "Broken".to_string(), pattern: "(".to_string(), branches: Vec::new(), kube_context: None },
+        ],
+        disabled: vec!["Drop table or database".to_string()],
+    };
+    let (guard, errors) = SafetyGuard::from_configs(&config, &[]);
+    assert_eq!(errors.len(), 1);
+    assert!(errors[0].starts_with("Broken"));
+    let none = CommandContext::default();
+    assert_eq!(guard.check("terraform destroy -auto-approve", &none), vec!["Terraform destroy"]);
+    assert!(guard.check("mkfs.xfs /dev/sdb1", &none).is_empty());
+    assert_eq!(guard.check("mkfs.ext4 /dev/sdb1", &none), vec!["Format a filesystem"]);
+    assert!(guard.check("DROP TABLE users;", &none).is_empty());
+}
+
+#[test]
+fn directory_rules_apply_outermost_first() {
+    let root = test_dir("safety", "dirs");
+    let inner = root.join("services/api");
+    std::fs::create_dir_all(root.join(".tant")).unwrap();
+    std::fs::create_dir_all(inner.join(".tant")).unwrap();
+    std::fs::write(root.join(".tant/safety_rules.json"), r#"{ "rules": [{ "label": "Reset", "pattern": "\\bgit reset --hard\\b" }] }"#).unwrap();
+    std::fs::write(inner.join(".tant/safety_rules.json"), r#"{ "rules": [{ "label": "Push", "pattern": "\\bgit push\\b" }] }"#).unwrap();
+
+    assert_eq!(directory_rule_files(&inner), vec![root.join(".tant/safety_rules.json"), inner.join(".tant/safety_rules.json")]);
+    let none = CommandContext::default();
+    assert_eq!(SafetyGuard::load(&root).0.check("git reset --hard", &none), vec!["Reset"]);
+    assert!(SafetyGuard::load(&root).0.check("git push", &none).is_empty());
+    let (guard, errors) = SafetyGuard::load(&inner);
+    assert!(errors.is_empty());
+    assert_eq!(guard.check("git reset --hard && git push", &none), vec!["Reset", "Push"]);
+}
+
+#[test]
+fn directory_configs_cannot_replace_or_disable_rules() {
+    let directory = SafetyConfig {
+        rules: vec![SafetyRuleConfig { label: "Recursive delete of / or home".to_string(), pattern: "^never$".to_string(), branches: Vec::new(), kube_context: None }],
+        disabled: vec!["Drop table or database".to_string()],
+    };
+    let (guard, errors) = SafetyGuard::from_configs(&SafetyConfig::default(), &[directory]);
+    assert_eq!(errors.len(), 2);
+    let none = CommandContext::default();
+    assert_eq!(guard.check("rm -rf /", &none), vec!["Recursive delete of / or home"]);
+    assert_eq!(guard.check("DROP TABLE users;", &none), vec!["Drop table or database"]);
+}
+
+#[test]
+fn guards_are_cached_until_a_rule_file_changes() {
+    let root = test_dir("safety", "cache");
+    let mut guards = SafetyGuards::default();
+    let none = CommandContext::default();
+    assert!(guards.guard(&root).0.check("make deploy", &none).is_empty());
+
+    std::fs::create_dir_all(root.join(".tant")).unwrap();
+    std::fs::write(root.join(".tant/safety_rules.json"), r#"{ "rules": [{ "label": "Deploy", "pattern": "\\bdeploy\\b" }] }"#).unwrap();
+    assert_eq!(guards.guard(&root).0.check("make deploy", &none), vec!["Deploy"]);
+
+    std::fs::write(root.join(".tant/safety_rules.json"), "{").unwrap();
+    let (guard, errors) = guards.guard(&root);
+    assert!(guard.check("make deploy", &none).is_empty());
+    assert_eq!(errors.len(), 1);
+    assert!(guards.guard(&root).1.is_empty());
+}
@@ -0,0 +1,97 @@
+use super::*;
+
+fn parser(rows: u16, cols: u16, scrollback: usize, input: &str) -> vt100::Parser {
+    let mut parser = vt100::Parser::new(rows, cols, scrollback);
+    parser.process(input.as_bytes());
+    parser
+}
+
+fn select(parser: &mut vt100::Parser, mode: SelectionMode, from: GridPoint, to: GridPoint) -> String {
+    let mut selection = Selection::new(mode, from);
+    selection.extend_to(to);
+    let lines = GridLines::read(parser, selection.endpoint_lines());
+    selection.expand(&lines, DEFAULT_WORD_CHARS);
+    let (first, last) = selection.line_range();
+    selection.text(&GridLines::read(parser, first..=last))
+}
+
+#[test]
+fn simple_selection_handles_multibyte_cells() {
+    let mut parser = parser(4, 20, 0, "héllo wörld\r\nsecond line");
+    let text = select(&mut parser, SelectionMode::Simple, GridPoint::new(0, 1), GridPoint::new(1, 5));
+    assert_eq!(text, "éllo wörld\nsecond");
+}
+
+#[test]
+fn wide_characters_are_copied_once() {
+    let mut parser = parser(2, 20, 0, "日本語 text");
+    let text = select(&mut parser, SelectionMode::Simple, GridPoint::new(0, 0), GridPoint::new(0, 10));
+    assert_eq!(text, "日本語 text");
+}
+
+#[test]
+fn double_click_selects_a_word_including_word_chars() {
+    let mut parser = parser(2, 40, 0, "cat ./src/main.rs | wc");
+    let text = select(&mut parser, SelectionMode::Word, GridPoint::new(0, 8), GridPoint::new(0, 8));
+    assert_eq!(text, "./src/main.rs");
+    let mut custom = Selection::new(SelectionMode::Word, GridPoint::new(0, 8));
+    custom.expand(&GridLines::read(&mut parser, custom.endpoint_lines()), "");
+    assert_eq!(custom.text(&GridLines::read(&mut parser, 0..=0)), "src");
+}
+
+#[test]
+fn triple_click_selects_whole_lines() {
+    let mut parser = parser(3, 20, 0, "first\r\nsecond\r\nthird");
+    let text = select(&mut parser, SelectionMode::Line, GridPoint::new(1, 3), GridPoint::new(2, 0));
+    assert_eq!(text, "second\nthird");
+}
+
+#[test]
+fn block_selection_takes_the_same_columns_from_every_line() {
+    let mut parser = parser(3, 20, 0, "abcdef\r\nghijkl\r\nmnopqr");
+    let text = select(&mut parser, SelectionMode::Block, GridPoint::new(0, 4), GridPoint::new(2, 1));
+    assert_eq!(text, "bcde\nhijk\nnopq");
+    let mut selection = Selection::new(SelectionMode::Block, GridPoint::new(0, 4));
+    selection.extend_to(GridPoint::new(2, 1));
+    selection.expand(&GridLines::read(&mut parser, selection.endpoint_lines()), DEFAULT_WORD_CHARS);
+    assert_eq!(selection.columns_in_line(1, 20), Some((1, 5)));
+    assert_eq!(selection.columns_in_line(3, 20), None);
+}
+
+#[test]
+fn selection_reaches_into_scrollback() {
+    let input: String = (1..=6).map(|n| format!("line {}\r\n", n)).collect();
+    let mut parser = parser(3, 20, 100, &input);
+    // Three rows visible: "line 5", "line 6" and the empty prompt row
+    let text = select(&mut parser, SelectionMode::Line, GridPoint::new(-2, 0), GridPoint::new(0, 0));
+    assert_eq!(text, "line 3\nline 4\nline 5");
+    assert_eq!(parser.screen().scrollback(), 0);
+}
+
+#[test]
+fn soft_wrapped_lines_are_joined() {
+    let mut parser = parser(3, 10, 0, "0123456789abc");
+    let text = select(&mut parser, SelectionMode::Simple, GridPoint::new(0, 0), GridPoint::new(1, 2));
+    assert_eq!(text, "0123456789abc");
+}
+
+#[test]
+fn plain_click_selects_nothing() {
+    let mut parser = parser(2, 20, 0, "hello");
+    assert_eq!(select(&mut parser, SelectionMode::Simple, GridPoint::new(0, 2), GridPoint::new(0, 2)), "");
+}
+
+#[test]
+fn clicks_count_up_to_three_then_restart() {
+    let mut clicks = ClickTracker::default();
+    let start = Instant::now();
+    let at = |ms: u64| start + Duration::from_millis(ms);
+    let position = Point::new(10.0, 10.0);
+    assert_eq!(clicks.register(at(0), position), 1);
+    assert_eq!(clicks.register(at(150), position), 2);
+    assert_eq!(clicks.register(at(300), position), 3);
+    assert_eq!(clicks.register(at(450), position), 1);
+    assert_eq!(clicks.register(at(2000), position), 1);
+    assert_eq!(clicks.register(at(2100), Point::new(40.0, 10.0)), 1);
+    assert_eq!(mode_for_click(2, true), SelectionMode::Block);
+}
@@ -0,0 +1,119 @@
+use super::*;
+use crate::{test_dir, LayoutNode, SerializableTab, TestDir};
+
+fn temp_store(name: &str) -> (TestDir, SessionStore) {
+    let dir = test_dir("sessions", name);
+    let store = SessionStore::new(dir.to_path_buf());
+    (dir, store)
+}
+
+fn layout(titles: &[&str]) -> Layout {
+    let tabs = titles
+        .iter()
+        .map(|title| SerializableTab { root: LayoutNode::Leaf { pane_id: 0 }, panes: Vec::new(), active_pane: 0, title: title.to_string() })
+        .collect();
+    Layout { tabs, active_tab: 0 }
+}
+
+#[test]
+fn sessions_are_saved_listed_and_deleted_by_name() {
+    let (_dir, store) = temp_store("named");
+    assert!(store.list().is_empty());
+    store.save("work", &layout(&["api", "web"])).unwrap();
+    store.save("oncall", &layout(&["logs"])).unwrap();
+    store.set_current("work").unwrap();
+
+    assert_eq!(store.list(), vec!["oncall", "work"]);
+    let titles: Vec<String> = store.load("work").unwrap().unwrap().tabs.into_iter().map(|tab| tab.title).collect();
+    assert_eq!(titles, vec!["api", "web"]);
+    assert_eq!(store.current(), "work");
+
+    store.delete("oncall").unwrap();
+    assert_eq!(store.list(), vec!["work"]);
+    assert!(store.load("oncall").unwrap().is_none());
+}
+
+#[test]
+fn saving_replaces_the_file_without_leaving_temporaries() {
+    let (_dir, store) = temp_store("atomic");
+    store.save(DEFAULT_SESSION, &layout(&["one"])).unwrap();
+    store.save(DEFAULT_SESSION, &layout(&["one", "two"])).unwrap();
+    assert_eq!(store.load(DEFAULT_SESSION).unwrap().unwrap().tabs.len(), 2);
+    let files: Vec<_> = std::fs::read_dir(&store.dir).unwrap().filter_map(|entry| entry.ok()).map(|entry| entry.file_name()).collect();
+    assert_eq!(files, vec![std::ffi::OsString::from("default.json")]);
+}
+
+#[test]
+fn current_falls_back_to_default() {
+    let (_dir, store) = temp_store("current");
+    assert_eq!(store.current(), DEFAULT_SESSION);
+    std::fs::write(store.dir.join("current"), "../escape").unwrap();
+    assert_eq!(store.current(), DEFAULT_SESSION);
+}
+
+#[test]
+fn unused_names_skip_saved_sessions() {
+    let (_dir, store) = temp_store("unused");
+    assert_eq!(store.unused_name("dev"), "dev");
+    store.save("dev", &layout(&["one"])).unwrap();
+    store.save("dev-2", &layout(&["one"])).unwrap();
+    assert_eq!(store.unused_name("dev"), "dev-3");
+    assert_eq!(store.unused_name(".hidden"), "layout");
+}
+
+#[test]
+fn session_names_must_be_plain_file_names() {
+    assert_eq!(session_name("  oncall "), Ok("oncall".to_string()));
+    for bad in ["", "  ", "a/b", ".hidden", "current", "x\ny"] {
+        assert!(session_name(bad).is_err(), "{:?}", bad);
+    }
+}
+
+#[test]
+fn legacy_files_are_copied_once() {
+    let (_dir, store) = temp_store("legacy");
+    let legacy = store.dir.join("session.json");
+    let target = store.dir.join("state/default.json");
+    let old = serde_json::to_string(&layout(&["old"])).unwrap();
+    std::fs::write(&legacy, &old).unwrap();
+    assert!(paths::adopt_legacy_file(&legacy, &target, paths::written_as::<Layout>).unwrap());
+    std::fs::write(&legacy, serde_json::to_string(&layout(&["newer"])).unwrap()).unwrap();
+    assert!(!paths::adopt_legacy_file(&legacy, &target, paths::written_as::<Layout>).unwrap());
+    assert_eq!(std::fs::read_to_string(&target).unwrap(), old);
+    assert!(legacy.exists());
+}
+
+#[test]
+fn only_files_shaped_like_tants_are_adopted() {
+    let (_dir, store) = temp_store("foreign");
+    let legacy = store.dir.join("config.json");
+    let target = store.dir.join("config/config.json");
+    for foreign in [r#"{"name": "my-app", "version": "1.0.0"}"#, "{}", "[]", "not json"] {
+        std::fs::write(&legacy, foreign).unwrap();
+        assert!(!paths::adopt_legacy_file(&legacy, &target, paths::written_as::<crate::AppConfig>).unwrap(), "{}", foreign);
+    }
+    assert!(!target.exists());
+    std::fs::write(&legacy, r#"{"theme": "nord", "ai_onboarding_seen": true}"#).unwrap();
+    assert!(paths::adopt_legacy_file(&legacy, &target, paths::written_as::<crate::AppConfig>).unwrap());
+}
+
+#[test]
+fn only_allow_listed_commands_are_rerun() {
+    let allowed = vec!["npm run dev".to_string(), "cargo watch *".to_string()];
+    assert!(should_rerun("npm run dev", &allowed));
+    assert!(should_rerun("  cargo watch -x test ", &allowed));
+    assert!(!should_rerun("npm run build", &allowed));
+    assert!(!should_rerun("", &["*".to_string()]));
+    assert!(!should_rerun("rm -rf build", &[]));
+}
+
+#[test]
+fn missing_directories_fall_back_to_home() {
+    let dir = std::env::temp_dir();
+    assert_eq!(restore_directory(&dir.to_string_lossy()), dir.to_string_lossy());
+    let fallback = restore_directory("/no/such/tant/dir");
+    assert!(std::path::Path::new(&fallback).is_dir());
+    if paths::home_dir().is_dir() {
+        assert_eq!(fallback, paths::home_dir().to_string_lossy());
+    }
+}
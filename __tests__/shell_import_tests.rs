@@ -0,0 +1,62 @@
+use super::*;
+use std::path::Path;
+
+fn fixture(name: &str) -> Vec<u8> {
+    std::fs::read(Path::new(env!("CARGO_MANIFEST_DIR")).join("__tests__/fixtures/shell_history").join(name)).unwrap()
+}
+
+fn commands(blocks: &[Block]) -> Vec<&str> {
+    blocks.iter().map(|block| block.command.as_str()).collect()
+}
+
+#[test]
+fn bash_history_uses_timestamp_comments() {
+    let blocks = ShellKind::Bash.parse(&fixture("bash_history"));
+    assert_eq!(commands(&blocks), vec!["ls -la", "git status", "cargo build --release", "make"]);
+    assert_eq!(blocks[0].started_at, None);
+    assert_eq!(blocks[1].started_at, timestamp(1_760_000_000));
+    assert_eq!(blocks[2].started_at, timestamp(1_760_000_060));
+    assert_eq!(blocks[3].started_at, None);
+}
+
+#[test]
+fn zsh_extended_history_has_start_and_duration() {
+    let blocks = ShellKind::Zsh.parse(&fixture("zsh_history"));
+    assert_eq!(commands(&blocks), vec!["cargo test", "echo \"one\ntwo\"", "echo ─", "plain command"]);
+    assert_eq!(blocks[0].started_at, timestamp(1_760_000_000));
+    assert_eq!(blocks[0].duration_ms, Some(3_000));
+    assert_eq!(blocks[3].duration_ms, None);
+}
+
+#[test]
+fn fish_history_unescapes_commands() {
+    let blocks = ShellKind::Fish.parse(&fixture("fish_history"));
+    assert_eq!(commands(&blocks), vec!["git commit -m \"wip\"", "echo a\nb\\c", "exit"]);
+    assert_eq!(blocks[1].started_at, timestamp(1_760_000_300));
+    assert_eq!(blocks[2].started_at, None);
+}
+
+#[test]
+fn imported_blocks_are_tagged_and_have_no_output() {
+    for block in parse_bash("ls\n") {
+        assert_eq!(block.tags, vec![IMPORTED_TAG.to_string()]);
+        assert!(block.output.is_empty());
+        assert_eq!(block.exit_code, None);
+    }
+    assert_eq!(ShellKind::from_name(" ZSH"), Some(ShellKind::Zsh));
+    assert_eq!(ShellKind::from_name("tcsh"), None);
+    assert_eq!(ShellKind::from_list("bash, Fish"), Ok(vec![ShellKind::Bash, ShellKind::Fish]));
+    assert!(ShellKind::from_list("bash,tcsh").unwrap_err().contains("'tcsh'"));
+    assert!(ShellKind::from_list("zhs").is_err());
+    assert!(ShellKind::from_list("").is_err());
+}
+
+#[test]
+fn summary_lists_each_shell() {
+    let results = vec![
+        Ok(ImportSummary { shell: ShellKind::Bash, path: PathBuf::from("/home/me/.bash_history"), added: 3 }),
+        Err("Failed to read /home/me/.zsh_history: denied".to_string()),
+    ];
+    assert_eq!(summary_message(&results), "Imported 3 commands from bash (/home/me/.bash_history); Failed to read /home/me/.zsh_history: denied");
+    assert_eq!(summary_message(&[]), "No shell history found to import");
+}
@@ -0,0 +1,131 @@
+use crate::theme_import::{color_to_hex, list_importable_themes, parse_hex_color, ThemeFormat};
+use crate::ThemeConfig;
+use std::path::{Path, PathBuf};
+
+fn fixture(name: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("__tests__/fixtures/themes").join(name)
+}
+
+fn hex(theme: &ThemeConfig, key: &str) -> String {
+    color_to_hex(theme.colors[key])
+}
+
+fn assert_round_trip(name: &str, format: ThemeFormat) -> ThemeConfig {
+    let path = fixture(name);
+    let contents = std::fs::read_to_string(&path).expect("fixture exists");
+    assert_eq!(ThemeFormat::detect(&path, &contents), Some(format));
+    let imported = ThemeConfig::import_file(&path).expect("import fixture");
+    let exported = imported.export_str(format).expect("export theme");
+    let reimported = ThemeConfig::import_str(format, &exported).expect("re-import exported theme");
+    for key in ["foreground", "background", "cursor", "selection"] {
+        assert_eq!(hex(&imported, key), hex(&reimported, key), "{} changed in {}", key, name);
+    }
+    for index in 0..16 {
+        let key = format!("ansi_{}", index);
+        assert_eq!(hex(&imported, &key), hex(&reimported, &key), "{} changed in {}", key, name);
+    }
+    imported
+}
+
+#[test]
+fn itermcolors_round_trip() {
+    let theme = assert_round_trip("tomorrow_night.itermcolors", ThemeFormat::ITerm2);
+    assert_eq!(hex(&theme, "background"), "#1d1f21");
+    assert_eq!(hex(&theme, "foreground"), "#c5c8c6");
+    assert_eq!(hex(&theme, "ansi_1"), "#cc6666");
+    assert_eq!(hex(&theme, "selection"), "#373b41");
+}
+
+#[test]
+fn alacritty_toml_round_trip() {
+    let theme = assert_round_trip("gruvbox.toml", ThemeFormat::AlacrittyToml);
+    assert_eq!(hex(&theme, "background"), "#282828");
+    assert_eq!(hex(&theme, "ansi_4"), "#458588");
+    assert_eq!(hex(&theme, "ansi_12"), "#83a598");
+    assert_eq!(hex(&theme, "selection"), "#504945");
+}
+
+#[test]
+fn alacritty_yaml_round_trip() {
+    let theme = assert_round_trip("gruvbox.yml", ThemeFormat::AlacrittyYaml);
+    assert_eq!(hex(&theme, "foreground"), "#ebdbb2");
+    assert_eq!(hex(&theme, "ansi_9"), "#fb4934");
+}
+
+#[test]
+fn kitty_conf_round_trip() {
+    let theme = assert_round_trip("catppuccin.conf", ThemeFormat::Kitty);
+    assert_eq!(hex(&theme, "background"), "#1e1e2e");
+    assert_eq!(hex(&theme, "selection"), "#f5e0dc");
+    assert_eq!(hex(&theme, "ansi_15"), "#a6adc8");
+}
+
+#[test]
+fn windows_terminal_round_trip() {
+    let theme = assert_round_trip("campbell.json", ThemeFormat::WindowsTerminal);
+    assert_eq!(hex(&theme, "ansi_5"), "#881798");
+    assert_eq!(hex(&theme, "ansi_13"), "#b4009e");
+    assert_eq!(hex(&theme, "cursor"), "#ffffff");
+}
+
+#[test]
+fn base16_round_trip() {
+    let theme = assert_round_trip("ocean.yaml", ThemeFormat::Base16);
+    assert_eq!(hex(&theme, "background"), "#2b303b");
+    assert_eq!(hex(&theme, "ansi_1"), "#bf616a");
+    assert_eq!(hex(&theme, "ansi_8"), "#65737e");
+}
+
+#[test]
+fn tant_json_round_trip() {
+    let theme = crate::themes::preset_theme("nord");
+    let exported = theme.export_str(ThemeFormat::Tant).expect("export theme");
+    assert!(exported.contains(&format!("\"version\": {}", crate::versioning::THEME.version())));
+    let reimported = ThemeConfig::import_str(ThemeFormat::Tant, &exported).expect("import theme");
+    assert_eq!(theme.colors, reimported.colors);
+    assert_eq!(ThemeFormat::detect(Path::new("theme.json"), &exported), Some(ThemeFormat::Tant));
+}
+
+#[test]
+fn imported_themes_fill_ui_and_syntax_colors() {
+    let theme = ThemeConfig::import_file(&fixture("ocean.yaml")).expect("import fixture");
+    for key in ["ui_border", "ui_input_bg", "ui_input_border", "ui_panel_bg", "syntax_keyword", "syntax_comment"] {
+        assert!(theme.colors.contains_key(key), "missing {}", key);
+    }
+    assert_eq!(hex(&theme, "syntax_string"), hex(&theme, "ansi_2"));
+}
+
+#[test]
+fn partial_palette_falls_back_for_missing_ansi() {
+    let theme = ThemeConfig::from_kitty_conf("foreground #ffffff\nbackground #000000\n").expect("import");
+    assert_eq!(theme.colors.len(), crate::themes::preset_theme("one_dark").colors.len());
+    assert_eq!(hex(&theme, "cursor"), "#ffffff");
+}
+
+#[test]
+fn missing_background_is_an_error() {
+    assert!(ThemeConfig::from_kitty_conf("foreground #ffffff\n").is_err());
+}
+
+#[test]
+fn parse_hex_color_accepts_common_notations() {
+    assert_eq!(parse_hex_color("#ff0000"), Some([1.0, 0.0, 0.0]));
+    assert_eq!(parse_hex_color("0x00ff00"), Some([0.0, 1.0, 0.0]));
+    assert_eq!(parse_hex_color("'#00f'"), Some([0.0, 0.0, 1.0]));
+    assert_eq!(parse_hex_color("CellForeground"), None);
+    assert_eq!(parse_hex_color("#ééé"), None);
+    assert_eq!(parse_hex_color("#aé"), None);
+}
+
+#[test]
+fn list_importable_themes_finds_every_fixture() {
+    let themes = list_importable_themes(&fixture(""));
+    let names: Vec<String> = themes
+        .iter()
+        .map(|(path, _)| path.file_name().unwrap().to_string_lossy().to_string())
+        .collect();
+    assert_eq!(
+        names,
+        vec!["campbell.json", "catppuccin.conf", "gruvbox.toml", "gruvbox.yml", "ocean.yaml", "tomorrow_night.itermcolors"]
+    );
+}
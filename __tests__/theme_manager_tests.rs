@@ -0,0 +1,60 @@
+use super::*;
+use crate::test_dir;
+use notify::event::{CreateKind, EventKind, RemoveKind};
+use std::sync::mpsc::Sender;
+use std::time::{Duration, Instant};
+
+fn gruvbox() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("__tests__/fixtures/themes/gruvbox.toml")
+}
+
+// Swaps the real watcher for a channel the test sends events on
+fn inject_events(manager: &mut ThemeManager) -> Sender<notify::Result<notify::Event>> {
+    let (sender, receiver) = channel();
+    manager.watcher = None;
+    manager.events = Some(receiver);
+    sender
+}
+
+#[test]
+fn watcher_events_reload_and_remove_themes() {
+    let root = test_dir("theme-manager", "events");
+    let dir = root.join("themes");
+    let mut manager = ThemeManager::new(dir.clone());
+    assert!(dir.is_dir());
+    let events = inject_events(&mut manager);
+
+    let dropped = dir.join("dropped.toml");
+    std::fs::copy(gruvbox(), &dropped).unwrap();
+    events.send(Ok(notify::Event::new(EventKind::Create(CreateKind::File)).add_path(dropped.clone()))).unwrap();
+    events.send(Ok(notify::Event::new(EventKind::Create(CreateKind::File)).add_path(dropped.clone()))).unwrap();
+    assert_eq!(manager.poll_changes(), vec!["dropped".to_string()]);
+    assert!(manager.names().contains(&"dropped".to_string()));
+
+    std::fs::remove_file(&dropped).unwrap();
+    events.send(Ok(notify::Event::new(EventKind::Remove(RemoveKind::File)).add_path(dropped))).unwrap();
+    assert_eq!(manager.poll_changes(), vec!["dropped".to_string()]);
+    assert!(!manager.names().contains(&"dropped".to_string()));
+    assert!(manager.poll_changes().is_empty());
+}
+
+/// Needs a filesystem that delivers inotify/FSEvents events; timing-sensitive on loaded machines.
+/// Run with `cargo test themes_dropped_into_a_new_directory_are_loaded -- --ignored`.
+#[test]
+#[ignore]
+fn themes_dropped_into_a_new_directory_are_loaded() {
+    let root = test_dir("theme-manager", "fresh");
+    let dir = root.join("themes");
+    let mut manager = ThemeManager::new(dir.clone());
+    assert!(dir.is_dir());
+
+    std::fs::copy(gruvbox(), dir.join("dropped.toml")).unwrap();
+    let deadline = Instant::now() + Duration::from_secs(5);
+    let mut changed = Vec::new();
+    while changed.is_empty() && Instant::now() < deadline {
+        std::thread::sleep(Duration::from_millis(50));
+        changed = manager.poll_changes();
+    }
+    assert_eq!(changed, vec!["dropped".to_string()]);
+    assert!(manager.names().contains(&"dropped".to_string()));
+}
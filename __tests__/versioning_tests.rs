@@ -0,0 +1,125 @@
+use super::*;
+use crate::{test_dir, AppConfig, Layout, PlanTier, ThemeConfig, UsageLedger};
+
+fn fixture(name: &str) -> String {
+    std::fs::read_to_string(Path::new(env!("CARGO_MANIFEST_DIR")).join("__tests__/fixtures/versions").join(name)).unwrap()
+}
+
+#[test]
+fn sessions_load_from_every_version() {
+    let v0: Layout = decode(&SESSION, &fixture("session_v0.json")).unwrap();
+    assert_eq!(v0.tabs[0].panes.len(), 2);
+    let pane = &v0.tabs[0].panes[0];
+    assert_eq!((pane.history[0].command.as_str(), pane.history[0].pinned, pane.profile.as_deref()), ("cargo build", true, None));
+    assert!(pane.history[0].note.is_empty() && pane.history[0].diagnostics.is_empty());
+    assert_eq!(pane.screen, None);
+
+    let v1: Layout = decode(&SESSION, &fixture("session_v1.json")).unwrap();
+    let pane = &v1.tabs[0].panes[0];
+    assert_eq!(pane.last_command.as_deref(), Some("npm run dev"));
+    assert_eq!(pane.history[0].diagnostics[0].line, Some(4));
+}
+
+#[test]
+fn config_ledger_and_themes_load_from_every_version() {
+    let v0: AppConfig = decode(&CONFIG, &fixture("config_v0.json")).unwrap();
+    assert!(v0.ai_onboarding_seen);
+    assert_eq!(v0.autosave_secs, AppConfig::default().autosave_secs);
+    let v1: AppConfig = decode(&CONFIG, &fixture("config_v1.json")).unwrap();
+    assert_eq!((v1.plan_tier, v1.theme.as_str(), v1.autosave_secs), (PlanTier::Pro, "nord", 0));
+    assert_eq!(v1.file_links.editor_command.as_deref(), Some("code -g {file}:{line}:{col}"));
+
+    let ledger: UsageLedger = decode(&USAGE_LEDGER, &fixture("usage_ledger_v0.json")).unwrap();
+    assert_eq!(ledger.entries[0].request_chars, 157);
+    let ledger: UsageLedger = decode(&USAGE_LEDGER, &fixture("usage_ledger_v1.json")).unwrap();
+    assert!(ledger.entries.is_empty());
+
+    let theme: ThemeConfig = decode(&THEME, &fixture("theme_v0.json")).unwrap();
+    assert_eq!(theme.font_family, "JetBrains Mono");
+    let theme: ThemeConfig = decode(&THEME, &fixture("theme_v1.json")).unwrap();
+    assert_eq!(theme.font_family, "Fira Code");
+}
+
+#[test]
+fn saved_files_carry_the_current_version() {
+    let dir = test_dir("versioning", "save");
+    let path = dir.join("ledger.json");
+    let ledger: UsageLedger = decode(&USAGE_LEDGER, &fixture("usage_ledger_v0.json")).unwrap();
+    save(&USAGE_LEDGER, &path, &ledger).unwrap();
+    let written: Value = serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+    assert_eq!(written["version"], json!(USAGE_LEDGER.version()));
+    assert_eq!(written["data"]["entries"][0]["model"], json!("llama3.2:latest"));
+    let reloaded: Option<UsageLedger> = load(&USAGE_LEDGER, &path).unwrap();
+    assert_eq!(reloaded.unwrap().entries.len(), 1);
+}
+
+#[test]
+fn migrations_run_in_order_from_the_file_version() {
+    fn add_a(mut data: Value) -> Result<Value, String> {
+        data["steps"].as_array_mut().ok_or("no steps")?.push(json!("a"));
+        Ok(data)
+    }
+    fn add_b(mut data: Value) -> Result<Value, String> {
+        data["steps"].as_array_mut().ok_or("no steps")?.push(json!("b"));
+        Ok(data)
+    }
+    let schema = Schema { name: "test", migrations: &[add_a, add_b], pretty: false };
+    let steps = |json: &str| decode::<Value>(&schema, json).map(|data| data["steps"].clone());
+    assert_eq!(steps(r#"{"steps": []}"#), Ok(json!(["a", "b"])));
+    assert_eq!(steps(r#"{"version": 1, "data": {"steps": []}}"#), Ok(json!(["b"])));
+    assert_eq!(steps(r#"{"version": 2, "data": {"steps": []}}"#), Ok(json!([])));
+    assert!(steps(r#"{"version": 3, "data": {"steps": []}}"#).unwrap_err().contains("newer"));
+    assert!(steps(r#"{"version": 0, "data": {}}"#).unwrap_err().contains("from version 0"));
+}
+
+#[test]
+fn renamed_fields_migrate_through_the_chain() {
+    #[derive(Debug, PartialEq, serde::Deserialize)]
+    struct Record {
+        command: String,
+        exit_code: i32,
+    }
+    // Version 2 renamed `cmd` to `command`
+    fn rename_cmd(mut data: Value) -> Result<Value, String> {
+        let map = data.as_object_mut().ok_or("not an object")?;
+        let cmd = map.remove("cmd").ok_or("no cmd")?;
+        map.insert("command".to_string(), cmd);
+        Ok(data)
+    }
+    let schema = Schema { name: "test", migrations: &[bare_to_v1, rename_cmd], pretty: false };
+    let expected = Record { command: "make".to_string(), exit_code: 2 };
+    assert_eq!(decode::<Record>(&schema, r#"{"cmd": "make", "exit_code": 2}"#), Ok(expected));
+    assert_eq!(decode::<Record>(&schema, r#"{"version": 1, "data": {"cmd": "make", "exit_code": 2}}"#).unwrap().command, "make");
+    assert_eq!(decode::<Record>(&schema, r#"{"version": 2, "data": {"command": "make", "exit_code": 2}}"#).unwrap().command, "make");
+    // Already renamed data is not migrated again
+    assert!(decode::<Record>(&schema, r#"{"version": 1, "data": {"command": "make", "exit_code": 2}}"#).unwrap_err().contains("no cmd"));
+    let v1 = serde_json::json!({"cmd": "ls", "exit_code": 0});
+    assert_eq!(decode_value::<Record>(&schema, 1, v1).unwrap().command, "ls");
+}
+
+#[test]
+fn unreadable_files_are_backed_up_not_lost() {
+    let dir = test_dir("versioning", "corrupt");
+    let path = dir.join("config.json");
+    std::fs::write(&path, "{ \"theme\": ").unwrap();
+    let err = load::<AppConfig>(&CONFIG, &path).unwrap_err();
+    assert!(err.kept().is_none());
+    let backup = err.backup.expect("backed up");
+    assert!(!path.exists());
+    assert!(backup.file_name().unwrap().to_string_lossy().starts_with("config.json.corrupt-"));
+    assert_eq!(std::fs::read_to_string(&backup).unwrap(), "{ \"theme\": ");
+    assert!(load::<AppConfig>(&CONFIG, &path).unwrap().is_none());
+}
+
+#[test]
+fn files_from_newer_versions_are_left_in_place() {
+    let dir = test_dir("versioning", "newer");
+    let path = dir.join("config.json");
+    let newer = format!(r#"{{"version": {}, "data": {{}}}}"#, CONFIG.version() + 1);
+    std::fs::write(&path, &newer).unwrap();
+    let err = load::<AppConfig>(&CONFIG, &path).unwrap_err();
+    assert!(err.message.contains("newer"));
+    assert_eq!(err.kept(), Some(path.as_path()));
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), newer);
+    assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 1);
+}
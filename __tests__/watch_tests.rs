@@ -0,0 +1,96 @@
+use super::*;
+use crate::test_dir;
+
+fn block(command: &str, cwd: &Path) -> Block {
+    Block { cwd: Some(cwd.to_path_buf()), ..crate::test_block(command, "") }
+}
+
+#[test]
+fn interval_watch_runs_once_per_interval() {
+    let start = Instant::now();
+    let mut watch = Watch::every(1, &block("cargo test", Path::new("/work")), Duration::from_secs(5), 3, start);
+    assert!(!watch.due(start + Duration::from_secs(4)));
+    assert!(watch.due(start + Duration::from_secs(5)));
+    watch.started();
+    assert!(!watch.due(start + Duration::from_secs(20)));
+    assert!(watch.finished(Some(0), start + Duration::from_secs(21)));
+    assert!(!watch.due(start + Duration::from_secs(25)));
+    assert!(watch.due(start + Duration::from_secs(26)));
+}
+
+#[test]
+fn watch_stops_after_failures_in_a_row() {
+    let now = Instant::now();
+    let mut watch = Watch::every(1, &block("make", Path::new("/work")), Duration::from_secs(1), 2, now);
+    assert!(watch.finished(Some(1), now));
+    assert!(watch.finished(Some(0), now));
+    assert_eq!(watch.failures_in_row, 0);
+    assert!(watch.finished(Some(2), now));
+    assert!(!watch.finished(Some(2), now));
+
+    let mut forever = Watch::every(2, &block("make", Path::new("/work")), Duration::from_secs(1), 0, now);
+    for _ in 0..10 {
+        assert!(forever.finished(Some(1), now));
+    }
+}
+
+#[test]
+fn file_watch_matches_glob_relative_to_cwd() {
+    let dir = test_dir("watch", "glob");
+    let watch = Watch::files_changed(1, &block("cargo test", &dir), "**/*.rs", &WatchSettings::default().exclude, 3).unwrap();
+    assert!(watch.matches(&dir.join("src/main.rs")));
+    assert!(watch.matches(&dir.join("lib.rs")));
+    assert!(!watch.matches(&dir.join("Cargo.toml")));
+    assert!(!watch.matches(Path::new("/elsewhere/main.rs")));
+    assert!(!watch.matches(&dir.join("target/debug/build/out.rs")));
+    assert!(!watch.matches(&dir.join("node_modules/pkg/index.rs")));
+    assert_eq!(watch.trigger.label(), "on changes to **/*.rs");
+    assert!(Watch::files_changed(2, &block("ls", &dir), "src/[", &[], 3).is_err());
+    assert!(Watch::files_changed(3, &block("ls", &dir), "**/*", &["[".to_string()], 3).is_err());
+}
+
+#[test]
+fn changes_made_during_a_run_do_not_trigger_another() {
+    let dir = test_dir("watch", "running");
+    let mut watch = Watch::files_changed(1, &block("cargo build", &dir), "**/*", &[], 3).unwrap();
+    let (sender, receiver) = channel();
+    watch.events = Some(receiver);
+    let changed = |name: &str| Ok(notify::Event::new(notify::EventKind::Any).add_path(dir.join(name)));
+    let now = Instant::now();
+
+    watch.started();
+    sender.send(changed("build.log")).unwrap();
+    assert!(!watch.due(now));
+    assert!(watch.finished(Some(0), now));
+    assert!(!watch.due(now + Duration::from_secs(1)));
+
+    sender.send(changed("src/main.rs")).unwrap();
+    assert!(!watch.due(now + Duration::from_secs(1)));
+    assert!(watch.due(now + Duration::from_secs(2)));
+}
+
+#[test]
+fn form_interval_must_be_positive_seconds() {
+    let mut form = WatchForm::new(0, &WatchSettings::default());
+    assert_eq!(form.interval(), Ok(Duration::from_secs(5)));
+    form.interval = "0".to_string();
+    assert!(form.interval().is_err());
+    form.interval = " 12 ".to_string();
+    assert_eq!(form.interval(), Ok(Duration::from_secs(12)));
+}
+
+#[test]
+fn watch_ids_avoid_history_and_active_watches() {
+    let mut history = vec![block("a", Path::new("/")), block("b", Path::new("/"))];
+    assert_eq!(next_watch_id(&history, &[]), 1);
+    history[1].watch_id = Some(4);
+    assert_eq!(next_watch_id(&history, &[]), 5);
+    let active = Watch::every(9, &history[0], Duration::from_secs(1), 3, Instant::now());
+    assert_eq!(next_watch_id(&history, &[active]), 10);
+}
+
+#[test]
+fn sparkline_scales_to_slowest_run() {
+    assert_eq!(sparkline(&[Some(100), Some(700), None, Some(350)]), vec!['▂', '█', '▁', '▅']);
+    assert_eq!(sparkline(&[Some(0), Some(0)]), vec!['▁', '▁']);
+}
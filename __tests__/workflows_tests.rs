@@ -0,0 +1,69 @@
+use super::*;
+use crate::test_dir;
+
+#[test]
+fn placeholders_are_listed_once_in_order() {
+    assert_eq!(placeholders("kubectl -n {{ namespace }} logs {{pod}} | grep {{namespace}}"), vec!["namespace", "pod"]);
+    assert_eq!(placeholders("echo {{}} {{unterminated"), Vec::<String>::new());
+}
+
+#[test]
+fn render_fills_values_and_reports_missing_ones() {
+    let value_of = |name: &str| (name == "env").then(|| "staging".to_string());
+    assert_eq!(render("deploy --env {{ env }}", value_of), Ok("deploy --env staging".to_string()));
+    assert_eq!(render("deploy {{env}} {{region}}", value_of), Err("missing value for 'region'".to_string()));
+    assert_eq!(render("echo '{{literal'", value_of), Ok("echo '{{literal'".to_string()));
+}
+
+#[test]
+fn yaml_and_toml_workflows_parse() {
+    let yaml = "name: Tail logs\ndescription: Follow a pod\ntags: [k8s]\ncommand: kubectl -n {{ns}} logs -f {{pod}}\narguments:\n  - name: ns\n    default: default\n    choices: [default, kube-system]\n";
+    let workflow = Workflow::parse(Path::new("logs.yaml"), yaml).unwrap();
+    assert_eq!(workflow.tags, vec!["k8s"]);
+    assert_eq!(workflow.arguments[0].choices, vec!["default", "kube-system"]);
+
+    let toml = "name = \"Release\"\ncommand = \"git tag v{{version}}\"\n";
+    let workflow = Workflow::parse(Path::new("release.toml"), toml).unwrap();
+    assert_eq!(workflow.source, WorkflowSource::Personal);
+    assert!(workflow.arguments.is_empty());
+
+    assert!(Workflow::parse(Path::new("x.json"), "{}").is_err());
+    let bad_default = "name: x\ncommand: echo {{a}}\narguments:\n  - name: a\n    default: c\n    choices: [a, b]\n";
+    assert!(Workflow::parse(Path::new("x.yml"), bad_default).unwrap_err().contains("not one of its choices"));
+}
+
+#[test]
+fn form_starts_from_defaults_and_first_choice() {
+    let workflow = Workflow::parse(
+        Path::new("w.yaml"),
+        "name: w\ncommand: run {{mode}} {{target}} {{extra}}\narguments:\n  - name: target\n    default: all\n  - name: mode\n    choices: [fast, slow]\n",
+    )
+    .unwrap();
+    let mut form = WorkflowForm::new(workflow);
+    let names: Vec<&str> = form.arguments.iter().map(|arg| arg.name.as_str()).collect();
+    assert_eq!(names, vec!["target", "mode", "extra"]);
+    assert_eq!(form.values, vec!["all", "fast", ""]);
+    assert_eq!(form.command(), Err("missing value for 'extra'".to_string()));
+    form.values[2] = "--verbose".to_string();
+    assert_eq!(form.command(), Ok("run fast all --verbose".to_string()));
+}
+
+#[test]
+fn team_workflows_are_found_above_cwd() {
+    let root = test_dir("workflows", "team");
+    let team = root.join(".tant/workflows");
+    std::fs::create_dir_all(&team).unwrap();
+    std::fs::create_dir_all(root.join("services/api")).unwrap();
+    std::fs::write(team.join("b.yaml"), "name: beta\ncommand: echo b\n").unwrap();
+    std::fs::write(team.join("a.toml"), "name = \"Alpha\"\ncommand = \"echo a\"\n").unwrap();
+    std::fs::write(team.join("broken.yaml"), "command: [\n").unwrap();
+    std::fs::write(team.join("notes.md"), "not a workflow").unwrap();
+
+    assert_eq!(team_dir(&root.join("services/api")), Some(team.clone()));
+    let (workflows, errors) = load_dir(&team, WorkflowSource::Team);
+    let names: Vec<&str> = workflows.iter().map(|workflow| workflow.name.as_str()).collect();
+    assert_eq!(names, vec!["Alpha", "beta"]);
+    assert!(workflows.iter().all(|workflow| workflow.source == WorkflowSource::Team));
+    assert_eq!(errors.len(), 1);
+    assert!(errors[0].contains("broken.yaml"));
+}
@@ -0,0 +1,107 @@
+// Readability helpers: WCAG contrast enforcement and colour-vision-safe status colours
+
+use iced::Color;
+use serde::{Deserialize, Serialize};
+
+/// Themes are drawn as designed unless a minimum contrast is opted into.
+pub const DEFAULT_MIN_CONTRAST: f32 = 1.0;
+
+fn linear_channel(value: f32) -> f32 {
+    if value <= 0.03928 {
+        value / 12.92
+    } else {
+        ((value + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+pub fn relative_luminance(color: Color) -> f32 {
+    0.2126 * linear_channel(color.r) + 0.7152 * linear_channel(color.g) + 0.0722 * linear_channel(color.b)
+}
+
+/// WCAG 2.x contrast ratio, from 1.0 (identical) to 21.0 (black on white).
+pub fn contrast_ratio(a: Color, b: Color) -> f32 {
+    let (la, lb) = (relative_luminance(a), relative_luminance(b));
+    let (lighter, darker) = if la > lb { (la, lb) } else { (lb, la) };
+    (lighter + 0.05) / (darker + 0.05)
+}
+
+fn mix(from: Color, to: Color, amount: f32) -> Color {
+    Color::from_rgb(
+        from.r + (to.r - from.r) * amount,
+        from.g + (to.g - from.g) * amount,
+        from.b + (to.b - from.b) * amount,
+    )
+}
+
+/// Moves `fg` towards white or black, whichever contrasts more with `bg`, by the
+/// smallest amount that reaches `min_ratio`. Colours that already pass are returned as-is.
+pub fn ensure_contrast(fg: Color, bg: Color, min_ratio: f32) -> Color {
+    if min_ratio <= 1.0 || contrast_ratio(fg, bg) >= min_ratio {
+        return fg;
+    }
+    let target = if contrast_ratio(Color::WHITE, bg) >= contrast_ratio(Color::BLACK, bg) {
+        Color::WHITE
+    } else {
+        Color::BLACK
+    };
+    if contrast_ratio(target, bg) < min_ratio {
+        return target;
+    }
+    let (mut low, mut high) = (0.0_f32, 1.0_f32);
+    for _ in 0..12 {
+        let mid = (low + high) / 2.0;
+        if contrast_ratio(mix(fg, target, mid), bg) >= min_ratio {
+            high = mid;
+        } else {
+            low = mid;
+        }
+    }
+    mix(fg, target, high)
+}
+
+/// Black or white, whichever reads better on `bg`.
+pub fn text_color_on(bg: Color) -> Color {
+    if contrast_ratio(Color::WHITE, bg) >= contrast_ratio(Color::BLACK, bg) {
+        Color::WHITE
+    } else {
+        Color::BLACK
+    }
+}
+
+/// Palette for success/failure indicators. The colour-blind variants avoid the
+/// red/green pair and use the Okabe-Ito blue/orange colours instead.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StatusColors {
+    #[default]
+    Default,
+    Deuteranopia,
+    Protanopia,
+}
+
+impl StatusColors {
+    pub fn success(self) -> Color {
+        match self {
+            StatusColors::Default => Color::from_rgb(0.25, 0.8, 0.4),
+            StatusColors::Deuteranopia => Color::from_rgb(0.0, 0.45, 0.7),
+            StatusColors::Protanopia => Color::from_rgb(0.34, 0.71, 0.91),
+        }
+    }
+
+    pub fn failure(self) -> Color {
+        match self {
+            StatusColors::Default => Color::from_rgb(0.9, 0.35, 0.35),
+            StatusColors::Deuteranopia => Color::from_rgb(0.84, 0.37, 0.0),
+            StatusColors::Protanopia => Color::from_rgb(0.9, 0.62, 0.0),
+        }
+    }
+
+    pub fn running(self) -> Color {
+        Color::from_rgb(0.6, 0.6, 0.6)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    include!(concat!(env!("CARGO_MANIFEST_DIR"), "/__tests__/accessibility_tests.rs"));
+}
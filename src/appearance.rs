@@ -0,0 +1,215 @@
+// Desktop colour-scheme tracking for automatic light/dark theme switching
+// The preference is read from the XDG desktop portal and followed through its
+// SettingChanged signal, with a time-of-day schedule as fallback
+
+use std::io::{BufRead, BufReader};
+use std::process::{Command, Stdio};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::time::Duration;
+use chrono::{Local, NaiveTime};
+use log::debug;
+use crate::ThemeConfig;
+
+// Only used when `gdbus` can't follow the portal's signal but `busctl` can read it
+const PORTAL_POLL_INTERVAL: Duration = Duration::from_secs(5);
+const PORTAL_DEST: &str = "org.freedesktop.portal.Desktop";
+const PORTAL_PATH: &str = "/org/freedesktop/portal/desktop";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColorScheme {
+    Light,
+    Dark,
+}
+
+impl ColorScheme {
+    /// Classifies a theme by the relative luminance of its background.
+    pub fn of_theme(theme: &ThemeConfig) -> Self {
+        match theme.colors.get("background") {
+            Some(rgb) if 0.2126 * rgb[0] + 0.7152 * rgb[1] + 0.0722 * rgb[2] > 0.5 => ColorScheme::Light,
+            _ => ColorScheme::Dark,
+        }
+    }
+
+    /// DEC mode 2031 colour-scheme report (`CSI ? 997 ; 1 n` dark, `CSI ? 997 ; 2 n` light).
+    pub fn dsr_report(self) -> &'static [u8] {
+        match self {
+            ColorScheme::Dark => b"\x1b[?997;1n",
+            ColorScheme::Light => b"\x1b[?997;2n",
+        }
+    }
+}
+
+/// Maps the portal `color-scheme` value (0 no preference, 1 dark, 2 light).
+pub fn scheme_from_portal_value(value: u32) -> Option<ColorScheme> {
+    match value {
+        1 => Some(ColorScheme::Dark),
+        2 => Some(ColorScheme::Light),
+        _ => None,
+    }
+}
+
+/// Extracts the `color-scheme` value from `gdbus` (`(<<uint32 1>>,)`) or `busctl` (`v v u 1`) output.
+pub fn parse_portal_output(output: &str) -> Option<u32> {
+    let value = match output.rfind("uint32") {
+        Some(pos) => &output[pos + "uint32".len()..],
+        None => output.split_whitespace().last()?,
+    };
+    let digits: String = value
+        .trim_start()
+        .chars()
+        .take_while(|c| c.is_ascii_digit())
+        .collect();
+    digits.parse().ok()
+}
+
+/// The `color-scheme` value from a `gdbus monitor` line, if it is the portal's
+/// `SettingChanged` signal for it.
+pub fn parse_setting_changed(line: &str) -> Option<u32> {
+    if !line.contains(".SettingChanged") || !line.contains("'org.freedesktop.appearance', 'color-scheme'") {
+        return None;
+    }
+    parse_portal_output(line)
+}
+
+/// Queries `org.freedesktop.appearance color-scheme` through the settings portal.
+/// Errors when neither `gdbus` nor `busctl` could read it.
+pub fn portal_color_scheme() -> Result<Option<ColorScheme>, String> {
+    let gdbus = Command::new("gdbus")
+        .args([
+            "call", "--session",
+            "--dest", PORTAL_DEST,
+            "--object-path", PORTAL_PATH,
+            "--method", "org.freedesktop.portal.Settings.Read",
+            "org.freedesktop.appearance", "color-scheme",
+        ])
+        .output();
+    let output = match gdbus {
+        Ok(output) if output.status.success() => output,
+        _ => Command::new("busctl")
+            .args([
+                "--user", "call",
+                PORTAL_DEST,
+                PORTAL_PATH,
+                "org.freedesktop.portal.Settings", "Read", "ss",
+                "org.freedesktop.appearance", "color-scheme",
+            ])
+            .output()
+            .ok()
+            .filter(|output| output.status.success())
+            .ok_or("neither gdbus nor busctl could read the settings portal")?,
+    };
+    let text = String::from_utf8_lossy(&output.stdout);
+    Ok(parse_portal_output(&text).and_then(scheme_from_portal_value))
+}
+
+// Sends each change of the portal preference from `gdbus monitor`; false if
+// it couldn't be started
+fn follow_portal_signal(sender: &Sender<Option<ColorScheme>>) -> bool {
+    let monitor = Command::new("gdbus")
+        .args(["monitor", "--session", "--dest", PORTAL_DEST, "--object-path", PORTAL_PATH])
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn();
+    let Ok(mut child) = monitor else {
+        return false;
+    };
+    let Some(stdout) = child.stdout.take() else {
+        return false;
+    };
+    for line in BufReader::new(stdout).lines().map_while(Result::ok) {
+        if let Some(value) = parse_setting_changed(&line) {
+            let scheme = scheme_from_portal_value(value);
+            debug!("[Appearance] Portal colour scheme changed: {:?}", scheme);
+            if sender.send(scheme).is_err() {
+                break;
+            }
+        }
+    }
+    child.kill().ok();
+    child.wait().ok();
+    true
+}
+
+/// Light between `light_start` and `dark_start`, dark otherwise.
+pub fn scheduled_color_scheme(now: NaiveTime, light_start: NaiveTime, dark_start: NaiveTime) -> ColorScheme {
+    let is_light = if light_start <= dark_start {
+        now >= light_start && now < dark_start
+    } else {
+        now >= light_start || now < dark_start
+    };
+    if is_light { ColorScheme::Light } else { ColorScheme::Dark }
+}
+
+pub fn parse_schedule_time(value: &str) -> Option<NaiveTime> {
+    NaiveTime::parse_from_str(value.trim(), "%H:%M").ok()
+}
+
+/// Follows the desktop preference on a background thread so the UI never blocks on D-Bus.
+/// The thread starts the first time the scheme is asked for, which only happens
+/// once both a light and a dark theme are configured, and gives up if the
+/// portal can't be read at all.
+pub struct AppearanceWatcher {
+    follow_system: bool,
+    receiver: Option<Receiver<Option<ColorScheme>>>,
+    portal_scheme: Option<ColorScheme>,
+}
+
+impl AppearanceWatcher {
+    pub fn new(follow_system: bool) -> Self {
+        AppearanceWatcher { follow_system, receiver: None, portal_scheme: None }
+    }
+
+    fn start(&mut self) {
+        let (sender, receiver) = channel();
+        std::thread::spawn(move || {
+            let mut last = match portal_color_scheme() {
+                Ok(scheme) => scheme,
+                Err(err) => {
+                    debug!("[Appearance] Using the schedule: {}", err);
+                    return;
+                }
+            };
+            debug!("[Appearance] Portal colour scheme: {:?}", last);
+            if sender.send(last).is_err() || follow_portal_signal(&sender) {
+                return;
+            }
+            loop {
+                std::thread::sleep(PORTAL_POLL_INTERVAL);
+                let Ok(scheme) = portal_color_scheme() else {
+                    return;
+                };
+                if scheme != last {
+                    debug!("[Appearance] Portal colour scheme: {:?}", scheme);
+                    if sender.send(scheme).is_err() {
+                        break;
+                    }
+                    last = scheme;
+                }
+            }
+        });
+        self.receiver = Some(receiver);
+    }
+
+    /// The portal preference when one is set, otherwise the schedule.
+    pub fn current(&mut self, light_start: &str, dark_start: &str) -> ColorScheme {
+        if self.follow_system && self.receiver.is_none() {
+            self.start();
+        }
+        if let Some(receiver) = &self.receiver {
+            while let Ok(scheme) = receiver.try_recv() {
+                self.portal_scheme = scheme;
+            }
+        }
+        if let Some(scheme) = self.portal_scheme {
+            return scheme;
+        }
+        let light_start = parse_schedule_time(light_start).unwrap_or_else(|| NaiveTime::from_hms_opt(7, 0, 0).unwrap());
+        let dark_start = parse_schedule_time(dark_start).unwrap_or_else(|| NaiveTime::from_hms_opt(19, 0, 0).unwrap());
+        scheduled_color_scheme(Local::now().time(), light_start, dark_start)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    include!(concat!(env!("CARGO_MANIFEST_DIR"), "/__tests__/appearance_tests.rs"));
+}
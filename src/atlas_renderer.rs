@@ -0,0 +1,325 @@
+// CPU glyph atlas renderer for the raw terminal grid (vim, top, etc.)
+// Glyphs are rasterised once into a shared coverage atlas. Each pane keeps an RGBA
+// frame buffer and only the rows whose contents changed are composited again;
+// the result is shown through an iced image, so text is never laid out per frame
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use iced::widget::image;
+use iced::Color;
+use log::warn;
+use crate::fonts::FontSet;
+use crate::renderer::{cell_colors, compute_row_hash, highlight_at, GridOverlay, Highlight, TerminalPalette};
+
+const ATLAS_WIDTH: usize = 1024;
+
+#[derive(Debug, Clone, Copy)]
+struct GlyphEntry {
+    x: usize,
+    y: usize,
+    width: usize,
+    height: usize,
+    left: i32,
+    // Offset of the bitmap's top edge from the baseline (negative is above)
+    top: i32,
+}
+
+/// Coverage bitmaps for every glyph drawn so far at one pixel size.
+pub struct GlyphAtlas {
+    fonts: Arc<FontSet>,
+    faces: Vec<Option<fontdue::Font>>,
+    px: f32,
+    ascent: f32,
+    descent: f32,
+    glyphs: HashMap<(usize, char), Option<GlyphEntry>>,
+    coverage: Vec<u8>,
+    height: usize,
+    shelf_x: usize,
+    shelf_y: usize,
+    shelf_height: usize,
+}
+
+impl GlyphAtlas {
+    pub fn new(fonts: Arc<FontSet>, px: f32) -> Self {
+        let faces: Vec<Option<fontdue::Font>> = (0..fonts.face_count())
+            .map(|index| {
+                let (data, collection_index) = fonts.face_data(index)?;
+                let settings = fontdue::FontSettings { collection_index, scale: px, ..fontdue::FontSettings::default() };
+                fontdue::Font::from_bytes(data, settings)
+                    .map_err(|err| warn!("Failed to load font face {} for the glyph atlas: {}", index, err))
+                    .ok()
+            })
+            .collect();
+        let (ascent, descent) = faces
+            .first()
+            .and_then(|face| face.as_ref())
+            .and_then(|face| face.horizontal_line_metrics(px))
+            .map(|metrics| (metrics.ascent, metrics.descent))
+            .unwrap_or((px * 0.8, -px * 0.2));
+        GlyphAtlas {
+            fonts,
+            faces,
+            px,
+            ascent,
+            descent,
+            glyphs: HashMap::new(),
+            coverage: Vec::new(),
+            height: 0,
+            shelf_x: 0,
+            shelf_y: 0,
+            shelf_height: 0,
+        }
+    }
+
+    #[cfg(test)]
+    pub fn glyph_count(&self) -> usize {
+        self.glyphs.values().filter(|entry| entry.is_some()).count()
+    }
+
+    fn glyph(&mut self, c: char) -> Option<GlyphEntry> {
+        let face_index = self.fonts.face_index_for_char(c);
+        if let Some(entry) = self.glyphs.get(&(face_index, c)) {
+            return *entry;
+        }
+        let entry = self.rasterize(face_index, c);
+        self.glyphs.insert((face_index, c), entry);
+        entry
+    }
+
+    fn rasterize(&mut self, face_index: usize, c: char) -> Option<GlyphEntry> {
+        let face = self.faces.get(face_index)?.as_ref()?;
+        let (metrics, bitmap) = face.rasterize(c, self.px);
+        if metrics.width == 0 || metrics.height == 0 || metrics.width > ATLAS_WIDTH {
+            return None;
+        }
+        // Shelf packing: glyphs fill a row left to right, then a new shelf starts below
+        if self.shelf_x + metrics.width > ATLAS_WIDTH {
+            self.shelf_y += self.shelf_height;
+            self.shelf_x = 0;
+            self.shelf_height = 0;
+        }
+        let (x, y) = (self.shelf_x, self.shelf_y);
+        self.shelf_x += metrics.width;
+        self.shelf_height = self.shelf_height.max(metrics.height);
+        if y + metrics.height > self.height {
+            self.height = y + metrics.height;
+            self.coverage.resize(self.height * ATLAS_WIDTH, 0);
+        }
+        for row in 0..metrics.height {
+            let src = &bitmap[row * metrics.width..(row + 1) * metrics.width];
+            let start = (y + row) * ATLAS_WIDTH + x;
+            self.coverage[start..start + metrics.width].copy_from_slice(src);
+        }
+        Some(GlyphEntry {
+            x,
+            y,
+            width: metrics.width,
+            height: metrics.height,
+            left: metrics.xmin,
+            top: -(metrics.ymin + metrics.height as i32),
+        })
+    }
+}
+
+/// Atlases for every pixel size in use, rebuilt when the font set changes.
+pub struct GlyphAtlases {
+    fonts: Arc<FontSet>,
+    by_size: HashMap<u32, GlyphAtlas>,
+}
+
+impl GlyphAtlases {
+    pub fn new(fonts: Arc<FontSet>) -> Self {
+        GlyphAtlases { fonts, by_size: HashMap::new() }
+    }
+
+    pub fn get(&mut self, px: f32) -> &mut GlyphAtlas {
+        let fonts = &self.fonts;
+        self.by_size
+            .entry(px.to_bits())
+            .or_insert_with(|| GlyphAtlas::new(fonts.clone(), px))
+    }
+}
+
+// Lets the image handle share the frame buffer instead of copying it
+#[derive(Clone)]
+struct SharedPixels(Arc<Vec<u8>>);
+
+impl AsRef<[u8]> for SharedPixels {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+/// One pane's frame buffer plus the row hashes used to find damage.
+pub struct GridRaster {
+    cols: u16,
+    rows: u16,
+    cell_width: f32,
+    cell_height: f32,
+    width: u32,
+    height: u32,
+    pixels: SharedPixels,
+    row_hashes: Vec<Option<u64>>,
+    damage: Vec<u16>,
+    handle: Option<image::Handle>,
+}
+
+impl Default for GridRaster {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl GridRaster {
+    pub fn new() -> Self {
+        GridRaster {
+            cols: 0,
+            rows: 0,
+            cell_width: 0.0,
+            cell_height: 0.0,
+            width: 0,
+            height: 0,
+            pixels: SharedPixels(Arc::new(Vec::new())),
+            row_hashes: Vec::new(),
+            damage: Vec::new(),
+            handle: None,
+        }
+    }
+
+    pub fn handle(&self) -> Option<&image::Handle> {
+        self.handle.as_ref()
+    }
+
+    pub fn size(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    #[cfg(test)]
+    pub fn pixels(&self) -> &[u8] {
+        self.pixels.as_ref()
+    }
+
+    /// Composites the rows of `screen` that changed since the last call and
+    /// publishes a new image handle when anything was redrawn.
+    pub fn update(&mut self, screen: &vt100::Screen, palette: &TerminalPalette, atlas: &mut GlyphAtlas, cell_width: f32, cell_height: f32, overlay: GridOverlay) -> &[u16] {
+        let (rows, cols) = (screen.size().0, screen.size().1);
+        if rows != self.rows || cols != self.cols || cell_width != self.cell_width || cell_height != self.cell_height {
+            self.resize(rows, cols, cell_width, cell_height);
+        }
+        self.damage.clear();
+        let mut hashes = Vec::new();
+        for row in 0..rows {
+            let highlights = overlay.row(screen, row);
+            let hash = compute_row_hash(screen, row, &highlights);
+            if self.row_hashes[row as usize] != Some(hash) {
+                self.damage.push(row);
+                hashes.push((hash, highlights));
+            }
+        }
+        if self.damage.is_empty() {
+            return &self.damage;
+        }
+        // Release our handle first so the buffer is only copied if iced still holds the last frame
+        self.handle = None;
+        let damage = std::mem::take(&mut self.damage);
+        for (row, (hash, highlights)) in damage.iter().zip(hashes) {
+            self.composite_row(screen, *row, palette, atlas, &highlights);
+            self.row_hashes[*row as usize] = Some(hash);
+        }
+        self.damage = damage;
+        self.handle = Some(image::Handle::from_pixels(self.width, self.height, self.pixels.clone()));
+        &self.damage
+    }
+
+    fn resize(&mut self, rows: u16, cols: u16, cell_width: f32, cell_height: f32) {
+        self.rows = rows;
+        self.cols = cols;
+        self.cell_width = cell_width;
+        self.cell_height = cell_height;
+        self.width = (cols as f32 * cell_width).ceil().max(1.0) as u32;
+        self.height = (rows as f32 * cell_height).ceil().max(1.0) as u32;
+        self.pixels = SharedPixels(Arc::new(vec![0; self.width as usize * self.height as usize * 4]));
+        self.row_hashes = vec![None; rows as usize];
+    }
+
+    fn composite_row(&mut self, screen: &vt100::Screen, row: u16, palette: &TerminalPalette, atlas: &mut GlyphAtlas, highlights: &[(u16, u16, Highlight)]) {
+        let y0 = (row as f32 * self.cell_height).floor() as usize;
+        let y1 = (((row + 1) as f32 * self.cell_height).floor() as usize).min(self.height as usize);
+        // Centre the font's line box in the cell
+        let line_box = atlas.ascent - atlas.descent;
+        let baseline = y0 as f32 + ((self.cell_height - line_box) / 2.0).max(0.0) + atlas.ascent;
+        // Every background goes down before any glyph, so a wide character's
+        // glyph isn't painted over by its continuation cell
+        for col in 0..self.cols {
+            let Some(cell) = screen.cell(row, col) else { continue };
+            let x0 = (col as f32 * self.cell_width).floor() as usize;
+            let x1 = (((col + 1) as f32 * self.cell_width).floor() as usize).min(self.width as usize);
+            let (_, bg) = cell_colors(cell, palette, highlight_at(highlights, col));
+            self.fill(x0, x1, y0, y1, bg);
+        }
+        for col in 0..self.cols {
+            let Some(cell) = screen.cell(row, col) else { continue };
+            if cell.is_wide_continuation() {
+                continue;
+            }
+            let x0 = (col as f32 * self.cell_width).floor() as usize;
+            let (fg, _) = cell_colors(cell, palette, highlight_at(highlights, col));
+            let Some(c) = cell.contents().chars().next() else { continue };
+            if c == ' ' {
+                continue;
+            }
+            if let Some(glyph) = atlas.glyph(c) {
+                let gx = x0 as i32 + glyph.left;
+                let gy = baseline.round() as i32 + glyph.top;
+                self.blend_glyph(atlas, glyph, gx, gy, y0, y1, fg);
+            }
+        }
+    }
+
+    fn fill(&mut self, x0: usize, x1: usize, y0: usize, y1: usize, color: Color) {
+        let rgba = color.into_rgba8();
+        let stride = self.width as usize * 4;
+        let pixels = Arc::make_mut(&mut self.pixels.0);
+        for y in y0..y1 {
+            for pixel in pixels[y * stride + x0 * 4..y * stride + x1 * 4].chunks_exact_mut(4) {
+                pixel.copy_from_slice(&rgba);
+            }
+        }
+    }
+
+    // Clipped to the row band so undamaged neighbouring rows are never touched
+    #[allow(clippy::too_many_arguments)]
+    fn blend_glyph(&mut self, atlas: &GlyphAtlas, glyph: GlyphEntry, gx: i32, gy: i32, y0: usize, y1: usize, fg: Color) {
+        let [fr, fg_, fb, _] = fg.into_rgba8();
+        let stride = self.width as usize * 4;
+        let width = self.width as i32;
+        let pixels = Arc::make_mut(&mut self.pixels.0);
+        for gy_offset in 0..glyph.height {
+            let y = gy + gy_offset as i32;
+            if y < y0 as i32 || y >= y1 as i32 {
+                continue;
+            }
+            let src_row = (glyph.y + gy_offset) * ATLAS_WIDTH + glyph.x;
+            for gx_offset in 0..glyph.width {
+                let x = gx + gx_offset as i32;
+                if x < 0 || x >= width {
+                    continue;
+                }
+                let alpha = atlas.coverage[src_row + gx_offset] as u32;
+                if alpha == 0 {
+                    continue;
+                }
+                let index = y as usize * stride + x as usize * 4;
+                let pixel = &mut pixels[index..index + 3];
+                for (channel, source) in pixel.iter_mut().zip([fr, fg_, fb]) {
+                    *channel = ((source as u32 * alpha + *channel as u32 * (255 - alpha)) / 255) as u8;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    include!(concat!(env!("CARGO_MANIFEST_DIR"), "/__tests__/atlas_renderer_tests.rs"));
+}
@@ -0,0 +1,268 @@
+// Block diffing: line diff of two blocks' outputs, shown unified or side by side
+// Normalisers blank out volatile text (timestamps, timings, ids) before lines are
+// compared, but the diff still shows the original lines
+
+use std::ops::Range;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use crate::Block;
+
+// Above this many line pairs the changed middle is shown as a single replacement
+// rather than aligned line by line
+const MAX_ALIGNED_CELLS: usize = 4_000_000;
+
+// Unchanged lines kept around each change; longer unchanged runs are folded
+pub const CONTEXT_LINES: usize = 3;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DiffSettings {
+    /// Regexes whose matches are ignored when comparing lines.
+    pub normalisers: Vec<String>,
+    /// Whether a new diff starts with the normalisers applied.
+    pub normalise: bool,
+    /// Whether a new diff starts side by side rather than unified.
+    pub side_by_side: bool,
+}
+
+impl Default for DiffSettings {
+    fn default() -> Self {
+        DiffSettings {
+            normalisers: vec![
+                r"\d{4}-\d{2}-\d{2}[T ]\d{2}:\d{2}:\d{2}(\.\d+)?(Z|[+-]\d{2}:?\d{2})?".to_string(),
+                r"\b\d{2}:\d{2}:\d{2}(\.\d+)?\b".to_string(),
+                r"\d+(\.\d+)?".to_string(),
+            ],
+            normalise: false,
+            side_by_side: false,
+        }
+    }
+}
+
+impl DiffSettings {
+    /// The configured normalisers, plus an error for each pattern that doesn't
+    /// compile and is skipped.
+    pub fn compile(&self) -> (Vec<Regex>, Vec<String>) {
+        let mut errors = Vec::new();
+        let normalisers = self
+            .normalisers
+            .iter()
+            .filter_map(|pattern| Regex::new(pattern).map_err(|err| errors.push(format!("'{}': {}", pattern, err))).ok())
+            .collect();
+        (normalisers, errors)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiffKind {
+    Same,
+    Removed,
+    Added,
+}
+
+/// One line of a unified diff, with its 1-based line number on each side.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiffLine {
+    pub kind: DiffKind,
+    pub old: Option<usize>,
+    pub new: Option<usize>,
+    pub text: String,
+}
+
+/// A side-by-side row; a `None` side is padding opposite an added or removed line.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiffRow {
+    pub left: Option<DiffLine>,
+    pub right: Option<DiffLine>,
+}
+
+pub fn normalise(line: &str, normalisers: &[Regex]) -> String {
+    normalisers.iter().fold(line.to_string(), |line, regex| regex.replace_all(&line, "∗").into_owned())
+}
+
+/// Line diff of `old` against `new`, comparing lines after normalisation.
+pub fn diff_lines(old: &str, new: &str, normalisers: &[Regex]) -> Vec<DiffLine> {
+    let old_lines: Vec<&str> = old.lines().collect();
+    let new_lines: Vec<&str> = new.lines().collect();
+    let old_keys: Vec<String> = old_lines.iter().map(|line| normalise(line, normalisers)).collect();
+    let new_keys: Vec<String> = new_lines.iter().map(|line| normalise(line, normalisers)).collect();
+
+    let prefix = old_keys.iter().zip(&new_keys).take_while(|(a, b)| a == b).count();
+    let suffix = old_keys[prefix..]
+        .iter()
+        .rev()
+        .zip(new_keys[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let old_mid = prefix..old_keys.len() - suffix;
+    let new_mid = prefix..new_keys.len() - suffix;
+
+    let mut ops = vec![DiffKind::Same; prefix];
+    ops.extend(align(&old_keys[old_mid], &new_keys[new_mid]));
+    ops.extend(vec![DiffKind::Same; suffix]);
+
+    let (mut old_index, mut new_index) = (0, 0);
+    ops.into_iter()
+        .map(|kind| {
+            let line = match kind {
+                DiffKind::Same => DiffLine { kind, old: Some(old_index + 1), new: Some(new_index + 1), text: new_lines[new_index].to_string() },
+                DiffKind::Removed => DiffLine { kind, old: Some(old_index + 1), new: None, text: old_lines[old_index].to_string() },
+                DiffKind::Added => DiffLine { kind, old: None, new: Some(new_index + 1), text: new_lines[new_index].to_string() },
+            };
+            if kind != DiffKind::Added {
+                old_index += 1;
+            }
+            if kind != DiffKind::Removed {
+                new_index += 1;
+            }
+            line
+        })
+        .collect()
+}
+
+// Longest-common-subsequence alignment; removals come before additions in each hunk
+fn align(old: &[String], new: &[String]) -> Vec<DiffKind> {
+    if old.len().saturating_mul(new.len()) > MAX_ALIGNED_CELLS {
+        let mut ops = vec![DiffKind::Removed; old.len()];
+        ops.extend(vec![DiffKind::Added; new.len()]);
+        return ops;
+    }
+    // lengths[i][j] is the LCS length of old[i..] and new[j..]
+    let width = new.len() + 1;
+    let mut lengths = vec![0u32; (old.len() + 1) * width];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lengths[i * width + j] = if old[i] == new[j] {
+                lengths[(i + 1) * width + j + 1] + 1
+            } else {
+                lengths[(i + 1) * width + j].max(lengths[i * width + j + 1])
+            };
+        }
+    }
+    let (mut i, mut j) = (0, 0);
+    let mut ops = Vec::with_capacity(old.len() + new.len());
+    while i < old.len() || j < new.len() {
+        if i < old.len() && j < new.len() && old[i] == new[j] {
+            ops.push(DiffKind::Same);
+            i += 1;
+            j += 1;
+        } else if j == new.len() || (i < old.len() && lengths[(i + 1) * width + j] >= lengths[i * width + j + 1]) {
+            ops.push(DiffKind::Removed);
+            i += 1;
+        } else {
+            ops.push(DiffKind::Added);
+            j += 1;
+        }
+    }
+    ops
+}
+
+/// Pairs each run of removed lines with the added lines that follow it.
+pub fn side_by_side(lines: &[DiffLine]) -> Vec<DiffRow> {
+    let mut rows = Vec::new();
+    let mut index = 0;
+    while index < lines.len() {
+        if lines[index].kind == DiffKind::Same {
+            rows.push(DiffRow { left: Some(lines[index].clone()), right: Some(lines[index].clone()) });
+            index += 1;
+            continue;
+        }
+        let removed_end = index + lines[index..].iter().take_while(|line| line.kind == DiffKind::Removed).count();
+        let added_end = removed_end + lines[removed_end..].iter().take_while(|line| line.kind == DiffKind::Added).count();
+        let removed = &lines[index..removed_end];
+        let added = &lines[removed_end..added_end];
+        for row in 0..removed.len().max(added.len()) {
+            rows.push(DiffRow { left: removed.get(row).cloned(), right: added.get(row).cloned() });
+        }
+        index = added_end;
+    }
+    rows
+}
+
+/// Lines added and removed.
+pub fn diff_stats(lines: &[DiffLine]) -> (usize, usize) {
+    lines.iter().fold((0, 0), |(added, removed), line| match line.kind {
+        DiffKind::Added => (added + 1, removed),
+        DiffKind::Removed => (added, removed + 1),
+        DiffKind::Same => (added, removed),
+    })
+}
+
+/// Runs of rows to show: each changed row plus `context` rows either side of it.
+pub fn context_ranges(changed: &[bool], context: usize) -> Vec<Range<usize>> {
+    let mut ranges: Vec<Range<usize>> = Vec::new();
+    for (index, _) in changed.iter().enumerate().filter(|(_, changed)| **changed) {
+        let range = index.saturating_sub(context)..(index + context + 1).min(changed.len());
+        match ranges.last_mut() {
+            Some(last) if range.start <= last.end => last.end = range.end,
+            _ => ranges.push(range),
+        }
+    }
+    ranges
+}
+
+/// Two blocks of one pane being compared; the older block is on the left.
+pub struct BlockDiffView {
+    pub command: String,
+    pub old_label: String,
+    pub new_label: String,
+    pub old_output: String,
+    pub new_output: String,
+    pub side_by_side: bool,
+    pub normalise: bool,
+    pub lines: Vec<DiffLine>,
+    /// Normalisers skipped by the last refresh because they don't compile.
+    pub errors: Vec<String>,
+}
+
+impl BlockDiffView {
+    pub fn new(history: &[Block], first: usize, second: usize, settings: &DiffSettings) -> Option<Self> {
+        let (old, new) = (first.min(second), first.max(second));
+        let (old_block, new_block) = (history.get(old)?, history.get(new)?);
+        let mut view = BlockDiffView {
+            command: new_block.command.clone(),
+            old_label: block_label(old, old_block),
+            new_label: block_label(new, new_block),
+            old_output: old_block.output.clone(),
+            new_output: new_block.output.clone(),
+            side_by_side: settings.side_by_side,
+            normalise: settings.normalise,
+            lines: Vec::new(),
+            errors: Vec::new(),
+        };
+        view.refresh(settings);
+        Some(view)
+    }
+
+    /// Recomputes the diff, e.g. after normalising is toggled.
+    pub fn refresh(&mut self, settings: &DiffSettings) {
+        let (normalisers, errors) = if self.normalise { settings.compile() } else { (Vec::new(), Vec::new()) };
+        self.errors = errors;
+        self.lines = diff_lines(&self.old_output, &self.new_output, &normalisers);
+    }
+}
+
+// "#3 · 14:02:11 · exit 1"
+fn block_label(index: usize, block: &Block) -> String {
+    let mut label = format!("#{}", index + 1);
+    if let Some(started) = block.started_at {
+        label.push_str(&started.with_timezone(&chrono::Local).format(" · %H:%M:%S").to_string());
+    }
+    if let Some(code) = block.exit_code {
+        label.push_str(&format!(" · exit {}", code));
+    }
+    label
+}
+
+/// The most recent block before `index` that ran the same command in the same directory.
+pub fn previous_run(history: &[Block], index: usize) -> Option<usize> {
+    let block = history.get(index)?;
+    history[..index]
+        .iter()
+        .rposition(|earlier| earlier.command.trim() == block.command.trim() && earlier.cwd == block.cwd)
+}
+
+#[cfg(test)]
+mod tests {
+    include!(concat!(env!("CARGO_MANIFEST_DIR"), "/__tests__/block_diff_tests.rs"));
+}
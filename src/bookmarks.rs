@@ -0,0 +1,97 @@
+// Block annotations: tag editing, a markdown note per block and the bookmarks
+// sidebar, which lists pinned and tagged blocks from every tab in the order they ran
+
+use chrono::{DateTime, Utc};
+use iced::widget::text_editor;
+use crate::Block;
+
+/// Inline tag and note editor open on one block of a pane.
+pub struct BlockAnnotation {
+    pub block_index: usize,
+    pub tag_input: String,
+    pub note: text_editor::Content,
+}
+
+impl BlockAnnotation {
+    pub fn new(block_index: usize, block: &Block) -> Self {
+        BlockAnnotation { block_index, tag_input: String::new(), note: text_editor::Content::with_text(&block.note) }
+    }
+
+    /// The note as typed; the editor always reports a trailing newline.
+    pub fn note_text(&self) -> String {
+        self.note.text().trim_end_matches('\n').to_string()
+    }
+}
+
+/// Splits comma-separated input into tags; spaces inside a tag become dashes.
+pub fn parse_tags(input: &str) -> Vec<String> {
+    input
+        .split(',')
+        .map(|tag| tag.split_whitespace().collect::<Vec<_>>().join("-"))
+        .filter(|tag| !tag.is_empty())
+        .collect()
+}
+
+/// Adds the tags a block does not already have (ignoring case); returns how many were added.
+pub fn add_tags(tags: &mut Vec<String>, new: Vec<String>) -> usize {
+    let before = tags.len();
+    for tag in new {
+        if !tags.iter().any(|existing| existing.eq_ignore_ascii_case(&tag)) {
+            tags.push(tag);
+        }
+    }
+    tags.len() - before
+}
+
+/// History search query that shows only blocks with `tag`.
+pub fn tag_query(tag: &str) -> String {
+    if tag.chars().any(|c| c.is_whitespace() || c == '"') {
+        format!("tag:\"{}\"", tag.replace('\\', "\\\\").replace('"', "\\\""))
+    } else {
+        format!("tag:{}", tag)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Bookmark {
+    pub tab: usize,
+    pub pane: usize,
+    pub block: usize,
+    pub command: String,
+    pub pinned: bool,
+    pub tags: Vec<String>,
+    /// First line of the note, if any.
+    pub note: Option<String>,
+    pub started_at: Option<DateTime<Utc>>,
+}
+
+pub fn is_bookmarked(block: &Block) -> bool {
+    block.pinned || !block.tags.is_empty()
+}
+
+/// Pinned and tagged blocks of every `(tab, pane, history)`, oldest first.
+pub fn collect_bookmarks<'a>(panes: impl IntoIterator<Item = (usize, usize, &'a [Block])>) -> Vec<Bookmark> {
+    let mut bookmarks: Vec<Bookmark> = panes
+        .into_iter()
+        .flat_map(|(tab, pane, history)| {
+            history.iter().enumerate().filter(|(_, block)| is_bookmarked(block)).map(move |(index, block)| Bookmark {
+                tab,
+                pane,
+                block: index,
+                command: block.command.clone(),
+                pinned: block.pinned,
+                tags: block.tags.clone(),
+                note: block.note.lines().find(|line| !line.trim().is_empty()).map(|line| line.trim().to_string()),
+                started_at: block.started_at,
+            })
+        })
+        .collect();
+    // Blocks that never started sort last; the sort is stable so they keep pane order
+    bookmarks.sort_by_key(|bookmark| (bookmark.started_at.is_none(), bookmark.started_at));
+    bookmarks
+}
+
+#[cfg(test)]
+mod tests {
+    include!(concat!(env!("CARGO_MANIFEST_DIR"), "/__tests__/bookmarks_tests.rs"));
+}
@@ -0,0 +1,282 @@
+// Command editor: the multi-line input under a pane's blocks, with undo/redo
+// and shell syntax highlighting in the theme's syntax colours. Enter submits and
+// Shift+Enter starts a new line; word motion comes from the text editor widget
+
+use std::ops::Range;
+use iced::widget::text_editor::{self, Action, Edit, Motion};
+use iced::Color;
+use crate::theme_manager::theme_color;
+use crate::ThemeConfig;
+
+// Undo steps kept per command
+const UNDO_LIMIT: usize = 200;
+
+const RESERVED_WORDS: [&str; 19] = [
+    "if", "then", "else", "elif", "fi", "for", "while", "until", "do", "done", "case", "esac", "in", "function",
+    "select", "time", "[[", "]]", "!",
+];
+
+// Reserved words after which a new command starts
+const COMMAND_STARTERS: [&str; 9] = ["if", "then", "else", "elif", "while", "until", "do", "time", "!"];
+
+pub struct CommandEditor {
+    pub content: text_editor::Content,
+    undo: Vec<String>,
+    redo: Vec<String>,
+    // Set while typing a word, so the whole word is undone in one step
+    in_word: bool,
+}
+
+impl Default for CommandEditor {
+    fn default() -> Self {
+        CommandEditor { content: text_editor::Content::new(), undo: Vec::new(), redo: Vec::new(), in_word: false }
+    }
+}
+
+impl CommandEditor {
+    /// The command as typed; the editor always reports a trailing newline.
+    pub fn text(&self) -> String {
+        self.content.text().trim_end_matches('\n').to_string()
+    }
+
+    pub fn perform(&mut self, action: Action) {
+        let Action::Edit(edit) = &action else {
+            self.in_word = false;
+            self.content.perform(action);
+            return;
+        };
+        let typing_word = matches!(edit, Edit::Insert(c) if c.is_alphanumeric() || *c == '_' || *c == '-');
+        let continues_word = typing_word && self.in_word;
+        let before = self.text();
+        self.content.perform(action);
+        if self.text() != before {
+            if !continues_word {
+                self.push_undo(before);
+            }
+            self.redo.clear();
+        }
+        self.in_word = typing_word;
+    }
+
+    /// Replaces the whole command as one undoable step, leaving the cursor at the end.
+    pub fn set_text(&mut self, text: &str) {
+        let before = self.text();
+        if before == text {
+            return;
+        }
+        self.push_undo(before);
+        self.redo.clear();
+        self.replace(text);
+    }
+
+    /// Empties the editor and forgets its undo history, e.g. once the command is sent.
+    pub fn reset(&mut self) {
+        *self = CommandEditor::default();
+    }
+
+    pub fn undo(&mut self) -> bool {
+        let Some(text) = self.undo.pop() else {
+            return false;
+        };
+        self.redo.push(self.text());
+        self.replace(&text);
+        true
+    }
+
+    pub fn redo(&mut self) -> bool {
+        let Some(text) = self.redo.pop() else {
+            return false;
+        };
+        self.undo.push(self.text());
+        self.replace(&text);
+        true
+    }
+
+    pub fn cursor_at_end(&self) -> bool {
+        let (line, index) = self.content.cursor_position();
+        let last = self.content.line_count().saturating_sub(1);
+        line == last && self.content.line(last).map_or(0, |text| text.len()) == index
+    }
+
+    pub fn move_to_end(&mut self) {
+        self.content.perform(Action::Move(Motion::DocumentEnd));
+    }
+
+    fn push_undo(&mut self, text: String) {
+        if self.undo.len() == UNDO_LIMIT {
+            self.undo.remove(0);
+        }
+        self.undo.push(text);
+    }
+
+    fn replace(&mut self, text: &str) {
+        self.content = text_editor::Content::with_text(text);
+        self.in_word = false;
+        self.move_to_end();
+    }
+}
+
+/// Bytes to write to the shell for `command`. A multi-line command goes in as one
+/// bracketed paste when the shell has asked for them, so it runs on the final
+/// Enter rather than line by line.
+pub fn submission(command: &str, bracketed_paste: bool) -> String {
+    if bracketed_paste && command.contains('\n') {
+        format!("\x1b[200~{}\x1b[201~\r", command)
+    } else {
+        format!("{}\r", command)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SyntaxKind {
+    /// Command names and shell reserved words.
+    Keyword,
+    String,
+    Number,
+    Comment,
+}
+
+/// Highlighted byte ranges of one line of shell. Quotes left open run to the end
+/// of the line; constructs spanning lines aren't tracked.
+pub fn highlight_shell(line: &str) -> Vec<(Range<usize>, SyntaxKind)> {
+    let mut spans = Vec::new();
+    let mut command_position = true;
+    let mut chars = line.char_indices().peekable();
+    while let Some(&(start, c)) = chars.peek() {
+        match c {
+            c if c.is_whitespace() => {
+                chars.next();
+            }
+            '#' => {
+                spans.push((start..line.len(), SyntaxKind::Comment));
+                break;
+            }
+            '\'' | '"' => {
+                chars.next();
+                let mut end = line.len();
+                let mut escaped = false;
+                for (index, next) in chars.by_ref() {
+                    if next == c && !escaped {
+                        end = index + 1;
+                        break;
+                    }
+                    escaped = c == '"' && next == '\\' && !escaped;
+                }
+                spans.push((start..end, SyntaxKind::String));
+                command_position = false;
+            }
+            '|' | '&' | ';' | '(' | ')' | '`' => {
+                chars.next();
+                command_position = true;
+            }
+            _ => {
+                let mut end = line.len();
+                while let Some(&(index, next)) = chars.peek() {
+                    if next.is_whitespace() || matches!(next, '|' | '&' | ';' | '(' | ')' | '`' | '\'' | '"') {
+                        end = index;
+                        break;
+                    }
+                    chars.next();
+                }
+                let word = &line[start..end];
+                if is_number(word) {
+                    spans.push((start..end, SyntaxKind::Number));
+                    command_position = false;
+                } else if RESERVED_WORDS.contains(&word) {
+                    spans.push((start..end, SyntaxKind::Keyword));
+                    command_position = COMMAND_STARTERS.contains(&word);
+                } else if command_position && !is_assignment(word) {
+                    spans.push((start..end, SyntaxKind::Keyword));
+                    command_position = false;
+                }
+            }
+        }
+    }
+    spans
+}
+
+fn is_number(word: &str) -> bool {
+    let digits = word.strip_prefix('-').unwrap_or(word);
+    digits.chars().next().is_some_and(|c| c.is_ascii_digit())
+        && digits.chars().all(|c| c.is_ascii_digit() || c == '.')
+        && digits.matches('.').count() <= 1
+}
+
+// `NAME=value` before a command keeps the next word in command position
+fn is_assignment(word: &str) -> bool {
+    word.split_once('=').is_some_and(|(name, _)| {
+        !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') && !name.starts_with(|c: char| c.is_ascii_digit())
+    })
+}
+
+/// The theme's syntax colours, as highlighter settings for the command editor.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SyntaxColors {
+    pub keyword: Color,
+    pub string: Color,
+    pub number: Color,
+    pub comment: Color,
+}
+
+impl SyntaxColors {
+    pub fn from_theme(theme: &ThemeConfig) -> Self {
+        SyntaxColors {
+            keyword: theme_color(theme, "syntax_keyword", Color::from_rgb(0.78, 0.46, 0.96)),
+            string: theme_color(theme, "syntax_string", Color::from_rgb(0.55, 0.78, 0.34)),
+            number: theme_color(theme, "syntax_number", Color::from_rgb(0.82, 0.60, 0.27)),
+            comment: theme_color(theme, "syntax_comment", Color::from_rgb(0.39, 0.42, 0.49)),
+        }
+    }
+
+    pub fn color(&self, kind: SyntaxKind) -> Color {
+        match kind {
+            SyntaxKind::Keyword => self.keyword,
+            SyntaxKind::String => self.string,
+            SyntaxKind::Number => self.number,
+            SyntaxKind::Comment => self.comment,
+        }
+    }
+}
+
+pub struct ShellHighlighter {
+    colors: SyntaxColors,
+    current_line: usize,
+}
+
+impl iced::advanced::text::Highlighter for ShellHighlighter {
+    type Settings = SyntaxColors;
+    type Highlight = Color;
+    type Iterator<'a> = std::vec::IntoIter<(Range<usize>, Color)>;
+
+    fn new(settings: &SyntaxColors) -> Self {
+        ShellHighlighter { colors: *settings, current_line: 0 }
+    }
+
+    fn update(&mut self, new_settings: &SyntaxColors) {
+        self.colors = *new_settings;
+        self.current_line = 0;
+    }
+
+    fn change_line(&mut self, line: usize) {
+        self.current_line = self.current_line.min(line);
+    }
+
+    fn highlight_line(&mut self, line: &str) -> Self::Iterator<'_> {
+        self.current_line += 1;
+        let colors = self.colors;
+        highlight_shell(line)
+            .into_iter()
+            .map(|(range, kind)| (range, colors.color(kind)))
+            .collect::<Vec<_>>()
+            .into_iter()
+    }
+
+    fn current_line(&self) -> usize {
+        self.current_line
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    include!(concat!(env!("CARGO_MANIFEST_DIR"), "/__tests__/command_editor_tests.rs"));
+}
@@ -0,0 +1,253 @@
+// Command-line completion: one suggestion for what's been typed so far, drawn
+// from block history, declarative specs for common CLIs and the filesystem
+// around the pane's working directory. A suggestion that extends the input is
+// shown inline as ghost text and accepted with the Right arrow
+
+use std::path::Path;
+use log::warn;
+use serde::Deserialize;
+use crate::paths;
+
+const BUILTIN_SPECS: [&str; 4] = [
+    include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/completion_specs/git.json")),
+    include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/completion_specs/cargo.json")),
+    include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/completion_specs/docker.json")),
+    include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/completion_specs/kubectl.json")),
+];
+
+// Fuzzy history matches on one or two characters are mostly noise
+const MIN_FUZZY_LEN: usize = 3;
+
+/// A command, or one of its subcommands, and what can follow it.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+pub struct CompletionSpec {
+    pub name: String,
+    #[serde(default)]
+    pub aliases: Vec<String>,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub subcommands: Vec<CompletionSpec>,
+    #[serde(default)]
+    pub options: Vec<OptionSpec>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+pub struct OptionSpec {
+    /// Spellings of the option, e.g. `["--message", "-m"]`.
+    pub names: Vec<String>,
+    #[serde(default)]
+    pub description: String,
+}
+
+impl CompletionSpec {
+    fn is_named(&self, word: &str) -> bool {
+        self.name == word || self.aliases.iter().any(|alias| alias == word)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SuggestionSource {
+    History,
+    Spec,
+    Path,
+    FuzzyHistory,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Suggestion {
+    /// The whole command line once the suggestion is accepted.
+    pub text: String,
+    pub source: SuggestionSource,
+}
+
+impl Suggestion {
+    /// The part still to be typed, when the suggestion extends `input`.
+    pub fn ghost<'a>(&'a self, input: &str) -> Option<&'a str> {
+        self.text.strip_prefix(input).filter(|rest| !rest.is_empty())
+    }
+}
+
+pub struct Completer {
+    specs: Vec<CompletionSpec>,
+}
+
+impl Completer {
+    pub fn new(specs: Vec<CompletionSpec>) -> Self {
+        Completer { specs }
+    }
+
+    /// The built-in specs plus any `*.json` specs in the config directory; a user
+    /// spec replaces the built-in one with the same name.
+    pub fn load() -> Self {
+        let mut specs: Vec<CompletionSpec> = BUILTIN_SPECS
+            .iter()
+            .filter_map(|json| serde_json::from_str(json).map_err(|err| warn!("Bad built-in completion spec: {}", err)).ok())
+            .collect();
+        let (user_specs, errors) = load_dir(&user_dir());
+        for err in errors {
+            warn!("Skipping completion spec {}", err);
+        }
+        for spec in user_specs {
+            specs.retain(|existing| existing.name != spec.name);
+            specs.push(spec);
+        }
+        Completer::new(specs)
+    }
+
+    /// The best suggestion for `input`. `history` is distinct commands, best first.
+    /// Prefix matches from history win, then spec completions, then paths, then
+    /// fuzzy history matches.
+    pub fn suggest(&self, input: &str, cwd: Option<&Path>, history: &[String]) -> Option<Suggestion> {
+        if input.trim().is_empty() || input.contains('\n') {
+            return None;
+        }
+        let prefix_match = history.iter().find(|command| command.starts_with(input) && command.len() > input.len());
+        if let Some(command) = prefix_match {
+            return Some(Suggestion { text: command.clone(), source: SuggestionSource::History });
+        }
+        if let Some(text) = self.complete_from_specs(input) {
+            return Some(Suggestion { text, source: SuggestionSource::Spec });
+        }
+        if let Some(text) = cwd.and_then(|cwd| complete_path(input, cwd)) {
+            return Some(Suggestion { text, source: SuggestionSource::Path });
+        }
+        if input.trim().chars().count() < MIN_FUZZY_LEN {
+            return None;
+        }
+        history
+            .iter()
+            .find(|command| command.as_str() != input && is_subsequence(input.trim(), command))
+            .map(|command| Suggestion { text: command.clone(), source: SuggestionSource::FuzzyHistory })
+    }
+
+    /// Completes the word being typed with a command, subcommand or option name.
+    pub fn complete_from_specs(&self, input: &str) -> Option<String> {
+        let (head, partial) = split_last_word(input);
+        if partial.is_empty() {
+            return None;
+        }
+        let words: Vec<&str> = head.split_whitespace().collect();
+        let candidates: Vec<&str> = match words.split_first() {
+            None => self.specs.iter().map(|spec| spec.name.as_str()).collect(),
+            Some((command, args)) => {
+                let root = self.specs.iter().find(|spec| spec.is_named(command))?;
+                let mut node = root;
+                for arg in args.iter().filter(|arg| !arg.starts_with('-')) {
+                    match node.subcommands.iter().find(|sub| sub.is_named(arg)) {
+                        Some(sub) => node = sub,
+                        None => break,
+                    }
+                }
+                if partial.starts_with('-') {
+                    let mut options: Vec<&OptionSpec> = node.options.iter().collect();
+                    if !std::ptr::eq(node, root) {
+                        options.extend(&root.options);
+                    }
+                    options.iter().flat_map(|option| option.names.iter().map(String::as_str)).collect()
+                } else {
+                    node.subcommands.iter().map(|sub| sub.name.as_str()).collect()
+                }
+            }
+        };
+        let word = candidates.into_iter().find(|candidate| candidate.starts_with(partial) && *candidate != partial)?;
+        Some(format!("{}{} ", head, word))
+    }
+}
+
+/// Completes the last word as a path relative to `cwd`, as far as every match
+/// agrees. A lone directory gets a trailing `/`, a lone file a trailing space.
+pub fn complete_path(input: &str, cwd: &Path) -> Option<String> {
+    let (head, partial) = split_last_word(input);
+    if partial.is_empty() || (head.trim().is_empty() && !partial.contains('/')) {
+        return None;
+    }
+    let (dir_part, name_part) = match partial.rfind('/') {
+        Some(slash) => partial.split_at(slash + 1),
+        None => ("", partial),
+    };
+    let dir = match dir_part.strip_prefix("~/") {
+        Some(rest) => paths::home_dir().join(rest),
+        None => cwd.join(dir_part),
+    };
+    let mut matches: Vec<(String, bool)> = std::fs::read_dir(dir)
+        .ok()?
+        .flatten()
+        .filter_map(|entry| {
+            let name = entry.file_name().into_string().ok()?;
+            let visible = !name.starts_with('.') || name_part.starts_with('.');
+            (visible && name.starts_with(name_part)).then(|| (name, entry.path().is_dir()))
+        })
+        .collect();
+    matches.sort();
+    let completed = match matches.as_slice() {
+        [] => return None,
+        [(name, is_dir)] => format!("{}{}", escape_spaces(name), if *is_dir { "/" } else { " " }),
+        _ => escape_spaces(&common_prefix(matches.iter().map(|(name, _)| name.as_str()))),
+    };
+    let text = format!("{}{}{}", head, dir_part, completed);
+    (text.len() > input.len()).then_some(text)
+}
+
+/// Whether the characters of `needle` appear in order in `haystack`, ignoring case.
+pub fn is_subsequence(needle: &str, haystack: &str) -> bool {
+    let mut haystack = haystack.chars().flat_map(char::to_lowercase);
+    needle.chars().flat_map(char::to_lowercase).all(|wanted| haystack.any(|c| c == wanted))
+}
+
+/// Specs in `dir`, with an error for each file that fails to parse.
+pub fn load_dir(dir: &Path) -> (Vec<CompletionSpec>, Vec<String>) {
+    let mut specs = Vec::new();
+    let mut errors = Vec::new();
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return (specs, errors);
+    };
+    let mut files: Vec<_> = entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+        .collect();
+    files.sort();
+    for path in files {
+        let parsed = std::fs::read_to_string(&path)
+            .map_err(|e| e.to_string())
+            .and_then(|contents| serde_json::from_str::<CompletionSpec>(&contents).map_err(|e| e.to_string()));
+        match parsed {
+            Ok(spec) => specs.push(spec),
+            Err(err) => errors.push(format!("{}: {}", path.display(), err)),
+        }
+    }
+    (specs, errors)
+}
+
+pub fn user_dir() -> std::path::PathBuf {
+    paths::config_dir().join("completions")
+}
+
+// The input up to the word being typed, and that word
+fn split_last_word(input: &str) -> (&str, &str) {
+    let start = input.char_indices().rev().find(|(_, c)| c.is_whitespace()).map_or(0, |(index, c)| index + c.len_utf8());
+    input.split_at(start)
+}
+
+fn common_prefix<'a>(mut names: impl Iterator<Item = &'a str>) -> String {
+    let Some(first) = names.next() else {
+        return String::new();
+    };
+    let mut prefix = first.to_string();
+    for name in names {
+        let shared = prefix.char_indices().zip(name.chars()).take_while(|((_, a), b)| a == b).count();
+        let end = prefix.char_indices().nth(shared).map_or(prefix.len(), |(index, _)| index);
+        prefix.truncate(end);
+    }
+    prefix
+}
+
+fn escape_spaces(name: &str) -> String {
+    name.replace(' ', "\\ ")
+}
+
+#[cfg(test)]
+mod tests {
+    include!(concat!(env!("CARGO_MANIFEST_DIR"), "/__tests__/completion_tests.rs"));
+}
@@ -0,0 +1,199 @@
+// Diagnostics: file, line, column, severity and message pulled out of a failed
+// block's output by regex matchers. Built-in matchers cover rustc/cargo,
+// gcc/clang, tsc, eslint, pytest and Go; users can add their own in config.json
+
+use std::path::{Path, PathBuf};
+use log::warn;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Error,
+    Warning,
+    Note,
+}
+
+impl Severity {
+    pub fn parse(text: &str) -> Option<Self> {
+        match text.trim().to_lowercase().as_str() {
+            "error" | "fatal error" | "err" | "e" | "failed" => Some(Severity::Error),
+            "warning" | "warn" | "w" => Some(Severity::Warning),
+            "note" | "help" | "info" | "hint" => Some(Severity::Note),
+            _ => None,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+            Severity::Note => "note",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Diagnostic {
+    /// As printed by the tool; usually relative to the block's directory.
+    pub file: String,
+    pub line: Option<u32>,
+    pub column: Option<u32>,
+    pub severity: Severity,
+    pub message: String,
+}
+
+impl Diagnostic {
+    /// `src/main.rs:10:5`, or as much of it as is known.
+    pub fn location(&self) -> String {
+        match (self.line, self.column) {
+            (Some(line), Some(column)) => format!("{}:{}:{}", self.file, line, column),
+            (Some(line), None) => format!("{}:{}", self.file, line),
+            _ => self.file.clone(),
+        }
+    }
+
+    /// The file resolved against the directory the block ran in.
+    pub fn path(&self, cwd: Option<&Path>) -> PathBuf {
+        match cwd {
+            Some(cwd) => cwd.join(&self.file),
+            None => PathBuf::from(&self.file),
+        }
+    }
+}
+
+/// A regex with named groups `file`, `line`, `col`, `severity` and `message`.
+/// Patterns run over the whole output, so `(?m)` anchors lines and a pattern may
+/// span several lines. Tools that print the file once above its diagnostics set
+/// `file_pattern`: each match then takes the file from the nearest header above it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MatcherConfig {
+    pub name: String,
+    pub pattern: String,
+    #[serde(default)]
+    pub file_pattern: Option<String>,
+    /// Used when the pattern has no `severity` group or it doesn't parse.
+    #[serde(default = "default_severity")]
+    pub severity: Severity,
+}
+
+fn default_severity() -> Severity {
+    Severity::Error
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DiagnosticSettings {
+    /// Extra matchers, tried after the built-in ones.
+    pub matchers: Vec<MatcherConfig>,
+}
+
+impl DiagnosticSettings {
+    /// Built-in matchers then the configured ones; patterns that don't compile
+    /// are skipped with a warning.
+    pub fn compile(&self) -> Vec<Matcher> {
+        builtin_matchers()
+            .iter()
+            .chain(&self.matchers)
+            .filter_map(|config| match Matcher::new(config) {
+                Ok(matcher) => Some(matcher),
+                Err(err) => {
+                    warn!("Skipping diagnostics matcher '{}': {}", config.name, err);
+                    None
+                }
+            })
+            .collect()
+    }
+}
+
+pub struct Matcher {
+    regex: Regex,
+    file_regex: Option<Regex>,
+    severity: Severity,
+}
+
+impl Matcher {
+    pub fn new(config: &MatcherConfig) -> Result<Self, String> {
+        let regex = Regex::new(&config.pattern).map_err(|err| err.to_string())?;
+        let file_regex = config.file_pattern.as_deref().map(Regex::new).transpose().map_err(|err| err.to_string())?;
+        Ok(Matcher { regex, file_regex, severity: config.severity })
+    }
+}
+
+pub fn builtin_matchers() -> Vec<MatcherConfig> {
+    let matcher = |name: &str, pattern: &str| MatcherConfig { name: name.to_string(), pattern: pattern.to_string(), file_pattern: None, severity: Severity::Error };
+    vec![
+        // error[E0425]: cannot find value `x` in this scope
+        //   --> src/main.rs:10:5
+        matcher("rustc", r"(?m)^(?P<severity>error|warning)(?:\[\w+\])?: (?P<message>.+)\n\s*--> (?P<file>[^:\n]+):(?P<line>\d+):(?P<col>\d+)"),
+        // main.c:3:5: error: unknown type name 'foo'
+        matcher("gcc", r"(?m)^(?P<file>[^\s:][^:\n]*):(?P<line>\d+):(?P<col>\d+): (?P<severity>fatal error|error|warning|note): (?P<message>.+)$"),
+        // src/app.ts(3,7): error TS2322: ... and the pretty form src/app.ts:3:7 - error TS2322: ...
+        matcher("tsc", r"(?m)^(?P<file>[^\s(:][^(:\n]*)(?:\((?P<line>\d+),(?P<col>\d+)\): |:(?P<line2>\d+):(?P<col2>\d+) - )(?P<severity>error|warning) (?P<message>TS\d+: .+)$"),
+        // /repo/src/app.js
+        //   3:7  error  'x' is assigned a value but never used  no-unused-vars
+        MatcherConfig {
+            file_pattern: Some(r"(?m)^(?P<file>[^\s].*\.(?:[cm]?[jt]sx?|vue|svelte))$".to_string()),
+            ..matcher("eslint", r"(?m)^\s+(?P<line>\d+):(?P<col>\d+)\s+(?P<severity>error|warning)\s+(?P<message>.+?)(?:\s{2,}\S+)?$")
+        },
+        // tests/test_app.py:12: AssertionError
+        matcher("pytest", r"(?m)^(?P<file>[^\s:][^:\n]*\.py):(?P<line>\d+): (?P<message>\w+(?:Error|Exception|Failed).*)$"),
+        // FAILED tests/test_app.py::test_add - assert 1 == 2
+        matcher("pytest summary", r"(?m)^FAILED (?P<file>[^\s:]+\.py)::(?P<message>.+)$"),
+        // ./main.go:12:5: undefined: foo, and     main_test.go:9: got 1, want 2
+        matcher("go", r"(?m)^\s*(?P<file>[^\s:][^:\n]*\.go):(?P<line>\d+)(?::(?P<col>\d+))?: (?P<message>.+)$"),
+    ]
+}
+
+/// Diagnostics in `output`, in the order they appear. The same location and
+/// message found by more than one matcher is listed once.
+pub fn extract(output: &str, matchers: &[Matcher]) -> Vec<Diagnostic> {
+    let mut found: Vec<(usize, Diagnostic)> = Vec::new();
+    for matcher in matchers {
+        let headers: Vec<(usize, String)> = matcher
+            .file_regex
+            .iter()
+            .flat_map(|regex| regex.captures_iter(output))
+            .filter_map(|captures| Some((captures.get(0)?.start(), captures.name("file")?.as_str().trim().to_string())))
+            .collect();
+        for captures in matcher.regex.captures_iter(output) {
+            let start = captures.get(0).map_or(0, |whole| whole.start());
+            let group = |name: &str| captures.name(name).map(|value| value.as_str().trim());
+            let file = match group("file") {
+                Some(file) => file.to_string(),
+                None => match headers.iter().rev().find(|(at, _)| *at < start) {
+                    Some((_, file)) => file.clone(),
+                    None => continue,
+                },
+            };
+            let number = |names: [&str; 2]| names.iter().find_map(|name| group(name)?.parse::<u32>().ok());
+            let diagnostic = Diagnostic {
+                file,
+                line: number(["line", "line2"]),
+                column: number(["col", "col2"]),
+                severity: group("severity").and_then(Severity::parse).unwrap_or(matcher.severity),
+                message: group("message").unwrap_or_default().to_string(),
+            };
+            if !found.iter().any(|(_, existing)| *existing == diagnostic) {
+                found.push((start, diagnostic));
+            }
+        }
+    }
+    found.sort_by_key(|(start, _)| *start);
+    found.into_iter().map(|(_, diagnostic)| diagnostic).collect()
+}
+
+/// One line per diagnostic, e.g. `error src/main.rs:10:5: cannot find value`.
+pub fn summary(diagnostics: &[Diagnostic]) -> String {
+    diagnostics
+        .iter()
+        .map(|diagnostic| format!("{} {}: {}", diagnostic.severity.label(), diagnostic.location(), diagnostic.message))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    include!(concat!(env!("CARGO_MANIFEST_DIR"), "/__tests__/diagnostics_tests.rs"));
+}
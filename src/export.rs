@@ -1,5 +1,6 @@
 use crate::{AiChatRole, AiCitation, Block};
 use crate::parser::GitStatus;
+use crate::diagnostics::summary;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use serde_json;
@@ -56,6 +57,7 @@ pub struct AiReferencedBlock {
     pub host: String,
     pub is_remote: bool,
     pub tags: Vec<String>,
+    pub note: String,
 }
 
 #[derive(Debug, Clone, Serialize)]
@@ -137,15 +139,33 @@ pub fn format_ai_conversation_export(export: &AiConversationExport, format: Expo
     }
 }
 
+/// Heading and body for a block's output: a failed block's diagnostics stand
+/// in for its raw output.
+pub fn output_section(block: &Block) -> (&'static str, String) {
+    if block.diagnostics.is_empty() {
+        ("Output", block.output.clone())
+    } else {
+        ("Diagnostics", summary(&block.diagnostics))
+    }
+}
+
 fn export_blocks_markdown(blocks: &[Block]) -> String {
     let mut out = String::new();
     for block in blocks {
         let exit_code = block.exit_code.unwrap_or(-1);
         let duration = block.duration_ms.unwrap_or(0);
+        let (heading, body) = output_section(block);
         out.push_str(&format!(
-            "## {}\n\n```bash\n{}\n```\n\n### Output\n\n```\n{}\n```\n\nExit Code: {}\nDuration: {}ms\n\n",
-            block.command, block.command, block.output, exit_code, duration
+            "## {}\n\n```bash\n{}\n```\n\n### {}\n\n```\n{}\n```\n\nExit Code: {}\nDuration: {}ms\n\n",
+            block.command, block.command, heading, body, exit_code, duration
         ));
+        if !block.tags.is_empty() {
+            out.push_str(&format!("Tags: {}\n\n", block.tags.join(", ")));
+        }
+        // Notes are markdown already
+        if !block.note.trim().is_empty() {
+            out.push_str(&format!("### Note\n\n{}\n\n", block.note.trim_end()));
+        }
     }
     out
 }
@@ -154,9 +174,16 @@ fn export_blocks_text(blocks: &[Block]) -> String {
     let mut out = String::new();
     for block in blocks {
         out.push_str(&format!("Command: {}\n", block.command));
-        out.push_str(&format!("Output:\n{}\n", block.output));
+        let (heading, body) = output_section(block);
+        out.push_str(&format!("{}:\n{}\n", heading, body));
         out.push_str(&format!("Exit Code: {}\n", block.exit_code.unwrap_or(-1)));
         out.push_str(&format!("Duration: {}ms\n", block.duration_ms.unwrap_or(0)));
+        if !block.tags.is_empty() {
+            out.push_str(&format!("Tags: {}\n", block.tags.join(", ")));
+        }
+        if !block.note.trim().is_empty() {
+            out.push_str(&format!("Note:\n{}\n", block.note.trim_end()));
+        }
         out.push_str("\n---\n\n");
     }
     out
@@ -167,13 +194,23 @@ fn export_blocks_html(blocks: &[Block]) -> String {
     for block in blocks {
         let exit_code = block.exit_code.unwrap_or(-1);
         let duration = block.duration_ms.unwrap_or(0);
+        let mut annotations = String::new();
+        if !block.tags.is_empty() {
+            annotations.push_str(&format!("<p>Tags: {}</p>\n", html_escape(&block.tags.join(", "))));
+        }
+        if !block.note.trim().is_empty() {
+            annotations.push_str(&format!("<h3>Note</h3>\n<pre class=\"note\">{}</pre>\n", html_escape(block.note.trim_end())));
+        }
+        let (heading, output) = output_section(block);
         body.push_str(&format!(
-            "<section class=\"block\">\n<h2>{}</h2>\n<h3>Command</h3>\n<pre><code>{}</code></pre>\n<h3>Output</h3>\n<pre><code>{}</code></pre>\n<p>Exit Code: {} | Duration: {}ms</p>\n</section>\n",
+            "<section class=\"block\">\n<h2>{}</h2>\n<h3>Command</h3>\n<pre><code>{}</code></pre>\n<h3>{}</h3>\n<pre><code>{}</code></pre>\n<p>Exit Code: {} | Duration: {}ms</p>\n{}</section>\n",
             html_escape(&block.command),
             html_escape(&block.command),
-            html_escape(&block.output),
+            heading,
+            html_escape(&output),
             exit_code,
-            duration
+            duration,
+            annotations
         ));
     }
     format!(
@@ -251,6 +288,12 @@ fn export_ai_markdown(export: &AiConversationExport) -> String {
             if let Some(status) = &block.git_status {
                 out.push_str(&format!("- Git Status: {:?}\n", status));
             }
+            if !block.tags.is_empty() {
+                out.push_str(&format!("- Tags: {}\n", block.tags.join(", ")));
+            }
+            if !block.note.trim().is_empty() {
+                out.push_str(&format!("- Note: {}\n", block.note.trim().replace('\n', " ")));
+            }
             out.push_str("\n```bash\n");
             out.push_str(&block.command);
             out.push_str("\n```\n\n### Output\n\n```text\n");
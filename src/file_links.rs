@@ -0,0 +1,161 @@
+// File links: `src/main.rs:1747:13` style references in block output, resolved
+// against the block's directory and kept only when the file exists, plus the
+// configurable command that opens one in an editor
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use crate::paths;
+
+// Scanning stops here so huge outputs don't stat thousands of words
+const MAX_CANDIDATES: usize = 2000;
+
+static TOKEN: OnceLock<Regex> = OnceLock::new();
+static REFERENCE: OnceLock<Regex> = OnceLock::new();
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileLink {
+    /// Line of the block's output the reference is on.
+    pub output_line: usize,
+    /// Byte range of the reference within that line.
+    pub start: usize,
+    pub end: usize,
+    pub path: PathBuf,
+    pub line: Option<u32>,
+    pub column: Option<u32>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FileLinkSettings {
+    /// Template with `{file}`, `{line}` and `{col}`, e.g. `code -g {file}:{line}:{col}`,
+    /// `nvim +{line} {file}` or `nvim --server /tmp/nvim.sock --remote-send '<C-\><C-n>:e +{line} {file}<CR>'`.
+    /// Unset opens `$EDITOR` in the pane.
+    pub editor_command: Option<String>,
+    /// Type the command into the pane rather than launching it in the background,
+    /// for editors that need a terminal.
+    pub run_in_pane: bool,
+}
+
+/// How a link or diagnostic gets opened.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OpenAction {
+    /// Typed into the pane's shell.
+    Pane(String),
+    /// Run with `sh -c` in the background.
+    Launch(String),
+}
+
+impl FileLinkSettings {
+    pub fn open_action(&self, path: &Path, line: Option<u32>, column: Option<u32>) -> OpenAction {
+        match &self.editor_command {
+            Some(template) if !template.trim().is_empty() => {
+                let command = fill_template(template, path, line, column);
+                if self.run_in_pane {
+                    OpenAction::Pane(command)
+                } else {
+                    OpenAction::Launch(command)
+                }
+            }
+            _ => OpenAction::Pane(default_editor_command(path, line)),
+        }
+    }
+}
+
+/// `{file}` is shell-quoted; a missing line or column becomes 1.
+pub fn fill_template(template: &str, path: &Path, line: Option<u32>, column: Option<u32>) -> String {
+    template
+        .replace("{file}", &shell_quote(&path.to_string_lossy()))
+        .replace("{line}", &line.unwrap_or(1).to_string())
+        .replace("{col}", &column.unwrap_or(1).to_string())
+}
+
+/// `$EDITOR` (or vi) at the line, typed into the pane.
+pub fn default_editor_command(path: &Path, line: Option<u32>) -> String {
+    let path = shell_quote(&path.to_string_lossy());
+    match line {
+        Some(line) => format!("${{EDITOR:-vi}} +{} {}", line, path),
+        None => format!("${{EDITOR:-vi}} {}", path),
+    }
+}
+
+pub fn shell_quote(text: &str) -> String {
+    format!("'{}'", text.replace('\'', r"'\''"))
+}
+
+/// Starts `command` detached from tant, in `cwd` when given.
+pub fn launch(command: &str, cwd: Option<&Path>) -> std::io::Result<()> {
+    let mut process = std::process::Command::new("sh");
+    process.arg("-c").arg(command).stdin(std::process::Stdio::null()).stdout(std::process::Stdio::null()).stderr(std::process::Stdio::null());
+    if let Some(cwd) = cwd.filter(|cwd| cwd.is_dir()) {
+        process.current_dir(cwd);
+    }
+    process.spawn().map(|_| ())
+}
+
+/// References in `output` to files that exist, relative to `cwd` unless absolute
+/// or under `~`. A token counts as a path when it has a `/` or an extension, with
+/// an optional `:line` or `:line:col` after it. Checks the disk for each
+/// candidate, so it belongs off the UI thread.
+pub fn find_links(output: &str, cwd: Option<&Path>) -> Vec<FileLink> {
+    let token = TOKEN.get_or_init(|| Regex::new(r#"[^\s'"`()\[\]{}<>,;|]+"#).expect("valid token regex"));
+    let reference = REFERENCE.get_or_init(|| Regex::new(r"^(?P<file>.+?)(?::(?P<line>\d+)(?::(?P<col>\d+))?)?:?$").expect("valid reference regex"));
+    let mut exists: HashMap<PathBuf, bool> = HashMap::new();
+    let mut links = Vec::new();
+    let mut candidates = 0;
+    for (output_line, text) in output.lines().enumerate() {
+        for found in token.find_iter(text) {
+            let word = found.as_str().trim_end_matches(['.', ':']);
+            let Some(captures) = reference.captures(word) else {
+                continue;
+            };
+            let file = &captures["file"];
+            if !looks_like_path(file) {
+                continue;
+            }
+            candidates += 1;
+            if candidates > MAX_CANDIDATES {
+                return links;
+            }
+            let path = resolve(file, cwd);
+            if !*exists.entry(path.clone()).or_insert_with(|| path.is_file()) {
+                continue;
+            }
+            let number = |name: &str| captures.name(name).and_then(|value| value.as_str().parse::<u32>().ok());
+            links.push(FileLink {
+                output_line,
+                start: found.start(),
+                end: found.start() + word.len(),
+                path,
+                line: number("line"),
+                column: number("col"),
+            });
+        }
+    }
+    links
+}
+
+fn looks_like_path(file: &str) -> bool {
+    if file.contains("://") || file.starts_with('-') {
+        return false;
+    }
+    let name = file.rsplit('/').next().unwrap_or(file);
+    file.contains('/') || name.rfind('.').is_some_and(|dot| dot > 0 && dot + 1 < name.len())
+}
+
+fn resolve(file: &str, cwd: Option<&Path>) -> PathBuf {
+    if let Some(rest) = file.strip_prefix("~/") {
+        return paths::home_dir().join(rest);
+    }
+    match cwd {
+        Some(cwd) => cwd.join(file),
+        None => PathBuf::from(file),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    include!(concat!(env!("CARGO_MANIFEST_DIR"), "/__tests__/file_links_tests.rs"));
+}
@@ -0,0 +1,281 @@
+// Find-in-terminal: searches the live screen and scrollback cells of a pane
+// Wrapped rows are searched as one logical line so matches can cross a wrap;
+// positions use the same line addressing as selections (negative = scrollback)
+// While output streams in, only the lines that scrolled off the old screen and
+// the live screen are searched again; matches further up just move up with them
+
+use std::ops::RangeInclusive;
+use std::time::{Duration, Instant};
+use regex::{Regex, RegexBuilder};
+use crate::selection::{GridLines, GridPoint};
+
+// Streaming output re-runs the search at most this often
+const REFRESH_INTERVAL: Duration = Duration::from_millis(200);
+// Bottom scrollback lines remembered after each search, to tell how far new
+// output has pushed them up since
+const ANCHOR_LINES: isize = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FindMode {
+    /// Plain text, ignoring case.
+    Literal,
+    /// Plain text, matching case exactly.
+    CaseSensitive,
+    Regex,
+}
+
+impl FindMode {
+    pub fn next(self) -> Self {
+        match self {
+            FindMode::Literal => FindMode::CaseSensitive,
+            FindMode::CaseSensitive => FindMode::Regex,
+            FindMode::Regex => FindMode::Literal,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            FindMode::Literal => "Aa off",
+            FindMode::CaseSensitive => "Aa on",
+            FindMode::Regex => ".* regex",
+        }
+    }
+}
+
+/// One match; `end.col` is exclusive on the last line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FindMatch {
+    pub start: GridPoint,
+    pub end: GridPoint,
+}
+
+pub struct FindState {
+    pub query: String,
+    pub mode: FindMode,
+    pub error: Option<String>,
+    matches: Vec<FindMatch>,
+    current: Option<usize>,
+    stale: bool,
+    searched_at: Option<Instant>,
+    anchor: Option<Anchor>,
+}
+
+// The scrollback as it ended at the last search
+struct Anchor {
+    scrollback_len: usize,
+    lines: Vec<String>,
+}
+
+impl Default for FindState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FindState {
+    pub fn new() -> Self {
+        FindState { query: String::new(), mode: FindMode::Literal, error: None, matches: Vec::new(), current: None, stale: false, searched_at: None, anchor: None }
+    }
+
+    pub fn matches(&self) -> &[FindMatch] {
+        &self.matches
+    }
+
+    pub fn current(&self) -> Option<FindMatch> {
+        self.current.and_then(|index| self.matches.get(index).copied())
+    }
+
+    /// Records new output; true when the matches should be searched again now.
+    pub fn refresh_due(&mut self, output_changed: bool, now: Instant) -> bool {
+        self.stale |= output_changed;
+        self.stale && self.searched_at.is_none_or(|at| now.duration_since(at) >= REFRESH_INTERVAL)
+    }
+
+    /// Searches the scrollback and live screen of `parser`. When `incremental`,
+    /// matches in scrollback that output only pushed further up are kept and the
+    /// rest is searched again; otherwise, or when the old scrollback can no longer
+    /// be found, everything is searched.
+    pub fn refresh(&mut self, parser: &mut vt100::Parser, incremental: bool) {
+        let first = -(GridLines::scrollback_len(parser) as isize);
+        let last = parser.screen().size().0 as isize - 1;
+        let shift = if incremental { self.scrolled_by(parser, -first as usize) } else { None };
+        let Some(shift) = shift else {
+            self.search(&GridLines::read(parser, (first..=last).chain(Self::anchor_range(0))), first, last);
+            return;
+        };
+        // The old screen's top row is now `-shift`; a logical line wrapped into it
+        // from above is searched again as a whole
+        let mut from = -(shift as isize);
+        while from > first && GridLines::read(parser, [from - 1]).wrapped(from - 1) {
+            from -= 1;
+        }
+        let lines = GridLines::read(parser, (from..=last).chain(Self::anchor_range(0)));
+        self.search_from(&lines, shift as isize, from, first, last);
+    }
+
+    // Anchor lines as they were `shift` lines ago
+    fn anchor_range(shift: usize) -> RangeInclusive<isize> {
+        -ANCHOR_LINES - shift as isize..=-1 - shift as isize
+    }
+
+    fn line_texts(lines: &GridLines, range: RangeInclusive<isize>) -> Vec<String> {
+        range.map(|line| lines.cells(line).concat()).collect()
+    }
+
+    // How many lines output has scrolled up since the last search, found by
+    // where the remembered bottom of scrollback is now
+    fn scrolled_by(&self, parser: &mut vt100::Parser, scrollback_len: usize) -> Option<usize> {
+        let anchor = self.anchor.as_ref()?;
+        // A full scrollback drops its oldest lines, so it grows by less than it scrolled
+        let grown = scrollback_len.checked_sub(anchor.scrollback_len)?;
+        (grown..=scrollback_len).find(|&shift| {
+            let range = Self::anchor_range(shift);
+            Self::line_texts(&GridLines::read(parser, range.clone()), range) == anchor.lines
+        })
+    }
+
+    /// Searches `first..=last`. The current match stays on the same text when it
+    /// still matches, otherwise it moves to the last match (closest to the prompt).
+    pub fn search(&mut self, lines: &GridLines, first: isize, last: isize) {
+        self.search_from(lines, 0, first, first, last);
+    }
+
+    // Moves the previous matches up by `shift` lines, keeps those wholly above
+    // `from` and still in scrollback, and searches `from..=last`
+    fn search_from(&mut self, lines: &GridLines, shift: isize, from: isize, first: isize, last: isize) {
+        let moved = |found: FindMatch| FindMatch { start: GridPoint::new(found.start.line - shift, found.start.col), end: GridPoint::new(found.end.line - shift, found.end.col) };
+        let previous = self.current().map(moved);
+        self.stale = false;
+        self.searched_at = Some(Instant::now());
+        self.anchor = Some(Anchor { scrollback_len: -first as usize, lines: Self::line_texts(lines, Self::anchor_range(0)) });
+        let kept: Vec<FindMatch> = self.matches.iter().copied().map(moved).filter(|found| found.start.line >= first && found.end.line < from).collect();
+        self.matches = kept;
+        self.current = None;
+        self.error = None;
+        if self.query.is_empty() {
+            self.matches.clear();
+            return;
+        }
+        let pattern = match self.pattern() {
+            Ok(pattern) => pattern,
+            Err(err) => {
+                self.matches.clear();
+                self.error = Some(err);
+                return;
+            }
+        };
+        let mut text = String::new();
+        let mut cells: Vec<(usize, GridPoint, u16)> = Vec::new();
+        for line in from..=last {
+            let row = lines.cells(line);
+            for (col, cell) in row.iter().enumerate() {
+                if cell.is_empty() {
+                    continue;
+                }
+                // Wide characters are followed by an empty continuation cell
+                let width = if row.get(col + 1).is_some_and(|next| next.is_empty()) { 2 } else { 1 };
+                cells.push((text.len(), GridPoint::new(line, col as u16), width));
+                text.push_str(cell);
+            }
+            if !lines.wrapped(line) || line == last {
+                self.collect_matches(&pattern, text.trim_end(), &cells);
+                text.clear();
+                cells.clear();
+            }
+        }
+        self.current = match previous {
+            Some(previous) => self.matches.iter().position(|found| *found == previous),
+            None => None,
+        }
+        .or_else(|| self.matches.len().checked_sub(1));
+    }
+
+    fn pattern(&self) -> Result<Regex, String> {
+        let source = match self.mode {
+            FindMode::Regex => self.query.clone(),
+            FindMode::Literal | FindMode::CaseSensitive => regex::escape(&self.query),
+        };
+        RegexBuilder::new(&source)
+            .case_insensitive(self.mode == FindMode::Literal)
+            .build()
+            .map_err(|err| match err {
+                regex::Error::Syntax(message) => message.lines().last().unwrap_or("invalid pattern").trim().to_string(),
+                other => other.to_string(),
+            })
+    }
+
+    fn collect_matches(&mut self, pattern: &Regex, text: &str, cells: &[(usize, GridPoint, u16)]) {
+        let cell_at = |offset: usize| {
+            let index = cells.partition_point(|(start, _, _)| *start <= offset);
+            cells[index.saturating_sub(1)]
+        };
+        for found in pattern.find_iter(text) {
+            if found.is_empty() {
+                continue;
+            }
+            let (_, start, _) = cell_at(found.start());
+            let (_, last, width) = cell_at(found.end() - 1);
+            self.matches.push(FindMatch { start, end: GridPoint::new(last.line, last.col + width) });
+        }
+    }
+
+    /// Moves to the next (or previous) match, wrapping around.
+    pub fn step(&mut self, forward: bool) -> Option<FindMatch> {
+        let count = self.matches.len();
+        if count == 0 {
+            return None;
+        }
+        self.current = Some(match (self.current, forward) {
+            (Some(index), true) => (index + 1) % count,
+            (Some(index), false) => (index + count - 1) % count,
+            (None, true) => 0,
+            (None, false) => count - 1,
+        });
+        self.current()
+    }
+
+    pub fn status(&self) -> String {
+        if let Some(error) = &self.error {
+            return error.clone();
+        }
+        match (self.current, self.matches.len()) {
+            (_, 0) if self.query.is_empty() => String::new(),
+            (_, 0) => "No matches".to_string(),
+            (Some(index), count) => format!("{} of {}", index + 1, count),
+            (None, count) => format!("{} matches", count),
+        }
+    }
+
+    /// Matched columns of `line` as half-open ranges, flagged when part of the current match.
+    pub fn columns_in_line(&self, line: isize, cols: u16) -> Vec<(u16, u16, bool)> {
+        // Matches never overlap, so both starts and ends are sorted
+        let upto = self.matches.partition_point(|found| found.start.line <= line);
+        let mut columns: Vec<(u16, u16, bool)> = self.matches[..upto]
+            .iter()
+            .enumerate()
+            .rev()
+            .take_while(|(_, found)| found.end.line >= line)
+            .filter_map(|(index, found)| {
+                let from = if found.start.line == line { found.start.col } else { 0 };
+                let to = if found.end.line == line { found.end.col.min(cols) } else { cols };
+                (from < to).then_some((from, to, self.current == Some(index)))
+            })
+            .collect();
+        columns.reverse();
+        columns
+    }
+}
+
+/// Scrollback offset that brings `line` into view, centring it when it is off screen.
+pub fn scroll_offset_for(line: isize, rows: u16, current_offset: usize, scrollback_len: usize) -> usize {
+    let top = -(current_offset as isize);
+    if line >= top && line < top + rows as isize {
+        return current_offset;
+    }
+    (rows as isize / 2 - line).clamp(0, scrollback_len as isize) as usize
+}
+
+#[cfg(test)]
+mod tests {
+    include!(concat!(env!("CARGO_MANIFEST_DIR"), "/__tests__/find_tests.rs"));
+}
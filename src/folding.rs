@@ -0,0 +1,84 @@
+// Output folding for history blocks
+// A collapsed block previews the first and last lines of its output around a
+// "lines hidden" marker. An expanded block scrolls inside its own region, and
+// only the lines in (or just around) that region's viewport get widgets
+
+use std::ops::Range;
+use serde::{Deserialize, Serialize};
+
+// Lines laid out above and below the viewport so fast scrolling doesn't show gaps
+const OVERSCAN_LINES: usize = 8;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FoldSettings {
+    /// Lines shown before the marker in a collapsed block.
+    pub head_lines: usize,
+    /// Lines shown after the marker in a collapsed block.
+    pub tail_lines: usize,
+    /// Height of an expanded block's scroll region, in lines.
+    pub max_visible_lines: usize,
+}
+
+impl Default for FoldSettings {
+    fn default() -> Self {
+        FoldSettings { head_lines: 5, tail_lines: 5, max_visible_lines: 25 }
+    }
+}
+
+/// Which lines of a collapsed block are shown.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Fold {
+    pub head: Range<usize>,
+    pub hidden: Range<usize>,
+    pub tail: Range<usize>,
+}
+
+impl FoldSettings {
+    /// Splits `total` lines into head, hidden and tail. Output that would hide
+    /// nothing is hidden entirely, so collapsing a short block still folds it.
+    pub fn fold(&self, total: usize) -> Fold {
+        if total <= self.head_lines + self.tail_lines {
+            return Fold { head: 0..0, hidden: 0..total, tail: total..total };
+        }
+        let tail_start = total - self.tail_lines;
+        Fold { head: 0..self.head_lines, hidden: self.head_lines..tail_start, tail: tail_start..total }
+    }
+
+    /// Lines the scroll region shows at once.
+    pub fn viewport_lines(&self, total: usize) -> usize {
+        total.min(self.max_visible_lines.max(1))
+    }
+}
+
+/// Lines to lay out for a scroll region scrolled `offset_y` pixels down.
+pub fn visible_window(total: usize, offset_y: f32, line_height: f32, viewport_lines: usize) -> Range<usize> {
+    let first = (offset_y.max(0.0) / line_height).floor() as usize;
+    let start = first.saturating_sub(OVERSCAN_LINES).min(total);
+    let end = (first + viewport_lines + OVERSCAN_LINES).min(total);
+    start..end
+}
+
+/// "… 12,345 lines hidden"
+pub fn hidden_label(count: usize) -> String {
+    let digits = count.to_string();
+    let mut grouped = String::new();
+    for (index, digit) in digits.chars().enumerate() {
+        if index > 0 && (digits.len() - index).is_multiple_of(3) {
+            grouped.push(',');
+        }
+        grouped.push(digit);
+    }
+    format!("… {} {} hidden", grouped, if count == 1 { "line" } else { "lines" })
+}
+
+/// Right-aligned line numbers for `lines`, one per row.
+pub fn gutter(lines: Range<usize>, total: usize) -> String {
+    let width = total.to_string().len();
+    lines.map(|line| format!("{:>width$}", line + 1, width = width)).collect::<Vec<_>>().join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    include!(concat!(env!("CARGO_MANIFEST_DIR"), "/__tests__/folding_tests.rs"));
+}
@@ -0,0 +1,173 @@
+// Font loading: configured family, fallback chain and real cell metrics
+// iced renders through cosmic-text, which loads the same system fonts, so faces
+// resolved here can be referenced by family name from widgets and canvases
+
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock};
+use fontdb::{Database, Family, Query, ID};
+use iced::font::{self, Font};
+use log::{debug, info, warn};
+
+// Used when the configured family is the generic "monospace" or is not installed
+const MONOSPACE_CANDIDATES: &[&str] = &[
+    "JetBrains Mono",
+    "Fira Code",
+    "Fira Mono",
+    "DejaVu Sans Mono",
+    "Noto Sans Mono",
+    "Liberation Mono",
+    "Menlo",
+    "SF Mono",
+    "Consolas",
+    "Cascadia Mono",
+    "Courier New",
+];
+
+// Leaked family names, so reloading a theme reuses the name it leaked last time
+static FAMILY_NAMES: OnceLock<Mutex<HashMap<String, &'static str>>> = OnceLock::new();
+
+/// Cell metrics as a fraction of the font size.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FontMetrics {
+    pub advance: f32,
+    pub line_height: f32,
+}
+
+impl Default for FontMetrics {
+    fn default() -> Self {
+        // Typical monospace proportions, used when no font could be measured
+        FontMetrics { advance: 0.6, line_height: 1.2 }
+    }
+}
+
+struct LoadedFace {
+    id: ID,
+    font: Font,
+}
+
+/// The primary terminal font followed by the fallback faces that were found.
+pub struct FontSet {
+    db: Database,
+    faces: Vec<LoadedFace>,
+    pub metrics: FontMetrics,
+    coverage: Mutex<HashMap<char, usize>>,
+}
+
+impl FontSet {
+    pub fn load(family: &str, fallbacks: &[String]) -> Self {
+        let mut db = Database::new();
+        db.load_system_fonts();
+        let mut faces = Vec::new();
+        match Self::resolve_primary(&db, family) {
+            Some(id) => faces.push(Self::loaded_face(&db, id)),
+            None => warn!("No monospace font found for '{}', using the default", family),
+        }
+        for fallback in fallbacks {
+            match Self::find_family(&db, fallback) {
+                Some(id) => faces.push(Self::loaded_face(&db, id)),
+                None => debug!("Fallback font '{}' is not installed", fallback),
+            }
+        }
+        let metrics = faces
+            .first()
+            .and_then(|face| Self::measure(&db, face.id))
+            .unwrap_or_default();
+        if let Some(face) = faces.first() {
+            info!("Terminal font: {:?} ({:?})", face.font.family, metrics);
+        }
+        FontSet { db, faces, metrics, coverage: Mutex::new(HashMap::new()) }
+    }
+
+    /// An empty set that renders with `Font::MONOSPACE` and default metrics.
+    pub fn fallback_only() -> Self {
+        FontSet { db: Database::new(), faces: Vec::new(), metrics: FontMetrics::default(), coverage: Mutex::new(HashMap::new()) }
+    }
+
+    pub fn primary(&self) -> Font {
+        self.faces.first().map(|face| face.font).unwrap_or(Font::MONOSPACE)
+    }
+
+    /// First font in the chain with a glyph for `c`; the primary font when none has one.
+    pub fn font_for_char(&self, c: char) -> Font {
+        match self.faces.get(self.face_index_for_char(c)) {
+            Some(face) => face.font,
+            None => Font::MONOSPACE,
+        }
+    }
+
+    /// Position of `font_for_char(c)` in the chain.
+    pub fn face_index_for_char(&self, c: char) -> usize {
+        if c.is_ascii() || self.faces.len() < 2 {
+            return 0;
+        }
+        let mut coverage = self.coverage.lock().unwrap();
+        *coverage.entry(c).or_insert_with(|| {
+            self.faces
+                .iter()
+                .position(|face| self.has_glyph(face.id, c))
+                .unwrap_or(0)
+        })
+    }
+
+    pub fn face_count(&self) -> usize {
+        self.faces.len()
+    }
+
+    /// Raw font file data and face index for rasterising outside of iced.
+    pub fn face_data(&self, index: usize) -> Option<(Vec<u8>, u32)> {
+        let face = self.faces.get(index)?;
+        self.db.with_face_data(face.id, |data, face_index| (data.to_vec(), face_index))
+    }
+
+    fn has_glyph(&self, id: ID, c: char) -> bool {
+        self.db
+            .with_face_data(id, |data, index| {
+                ttf_parser::Face::parse(data, index)
+                    .map(|face| face.glyph_index(c).is_some())
+                    .unwrap_or(false)
+            })
+            .unwrap_or(false)
+    }
+
+    fn resolve_primary(db: &Database, family: &str) -> Option<ID> {
+        let generic = matches!(family.trim().to_lowercase().as_str(), "" | "monospace" | "mono");
+        if !generic {
+            if let Some(id) = Self::find_family(db, family) {
+                return Some(id);
+            }
+            warn!("Font '{}' is not installed, falling back to a monospace font", family);
+        }
+        MONOSPACE_CANDIDATES
+            .iter()
+            .find_map(|candidate| Self::find_family(db, candidate))
+            .or_else(|| db.faces().find(|face| face.monospaced).map(|face| face.id))
+    }
+
+    fn find_family(db: &Database, family: &str) -> Option<ID> {
+        db.query(&Query { families: &[Family::Name(family)], ..Query::default() })
+    }
+
+    fn loaded_face(db: &Database, id: ID) -> LoadedFace {
+        let name = db
+            .face(id)
+            .and_then(|face| face.families.first().map(|(name, _)| name.clone()))
+            .unwrap_or_default();
+        // iced fonts reference families by &'static str; each name is leaked once
+        // however often themes reload the set
+        let mut names = FAMILY_NAMES.get_or_init(|| Mutex::new(HashMap::new())).lock().unwrap();
+        let family = *names.entry(name).or_insert_with_key(|name| Box::leak(name.clone().into_boxed_str()));
+        LoadedFace { id, font: Font { family: font::Family::Name(family), ..Font::MONOSPACE } }
+    }
+
+    fn measure(db: &Database, id: ID) -> Option<FontMetrics> {
+        db.with_face_data(id, |data, index| {
+            let face = ttf_parser::Face::parse(data, index).ok()?;
+            let units = face.units_per_em() as f32;
+            let glyph = face.glyph_index('0').or_else(|| face.glyph_index('M'))?;
+            let advance = face.glyph_hor_advance(glyph)? as f32 / units;
+            let line_height = (face.ascender() as f32 - face.descender() as f32 + face.line_gap() as f32) / units;
+            Some(FontMetrics { advance, line_height })
+        })
+        .flatten()
+    }
+}
@@ -0,0 +1,350 @@
+// Block history query language used by the search bar
+//
+//   exit:!0 cwd:~/svc dur:>30s branch:main tag:deploy after:2026-10-01 "panic"
+//
+// Terms are ANDed; a leading `-` negates a field or quoted term (-exit:0,
+// -"panic"), while `--force` or `-rf` are searched for as typed. Bare words and
+// quoted strings match the command or output. Fields:
+//   exit:   exit code, with optional !, >, >=, < or <= (exit:!0, exit:>1)
+//   dur:    duration with ms/s/m/h units, seconds by default (dur:>30s)
+//   cwd:    directory prefix, ~ is the home directory
+//   branch, host, tag: exact, case-insensitive
+//   status: git status (clean, dirty, conflicts)
+//   after, before: YYYY-MM-DD or YYYY-MM-DDTHH:MM in local time
+//   cmd, output, note: substring of just the command, output or block note
+//   is:     pinned, tagged, remote, running, success, failed
+
+use std::fmt;
+use std::path::{Path, PathBuf};
+use chrono::{DateTime, Local, NaiveDate, NaiveDateTime, NaiveTime, TimeZone, Utc};
+use crate::parser::GitStatus;
+use crate::Block;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct QueryError {
+    pub message: String,
+    /// Character offset of the offending term.
+    pub position: usize,
+}
+
+impl fmt::Display for QueryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} (column {})", self.message, self.position + 1)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum CmpOp {
+    Eq,
+    Ne,
+    Gt,
+    Ge,
+    Lt,
+    Le,
+}
+
+impl CmpOp {
+    fn test<T: PartialOrd>(self, actual: T, expected: T) -> bool {
+        match self {
+            CmpOp::Eq => actual == expected,
+            CmpOp::Ne => actual != expected,
+            CmpOp::Gt => actual > expected,
+            CmpOp::Ge => actual >= expected,
+            CmpOp::Lt => actual < expected,
+            CmpOp::Le => actual <= expected,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Flag {
+    Pinned,
+    Tagged,
+    Remote,
+    Running,
+    Success,
+    Failed,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Filter {
+    Text(String),
+    Command(String),
+    Output(String),
+    Note(String),
+    Exit(CmpOp, i64),
+    Duration(CmpOp, u64),
+    Cwd(PathBuf),
+    Branch(String),
+    Host(String),
+    Tag(String),
+    GitStatus(GitStatusKind),
+    After(DateTime<Utc>),
+    Before(DateTime<Utc>),
+    Is(Flag),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum GitStatusKind {
+    Clean,
+    Dirty,
+    Conflicts,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Term {
+    negated: bool,
+    filter: Filter,
+}
+
+/// Which parts of a block the query's text terms matched, for highlighting.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct TextMatches {
+    pub command: bool,
+    pub output: bool,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct HistoryQuery {
+    terms: Vec<Term>,
+}
+
+impl HistoryQuery {
+    pub fn parse(input: &str) -> Result<Self, QueryError> {
+        let home = std::env::var("HOME").ok();
+        let mut terms = Vec::new();
+        for token in tokenize(input)? {
+            let filter = parse_filter(&token, home.as_deref())?;
+            terms.push(Term { negated: token.negated, filter });
+        }
+        Ok(HistoryQuery { terms })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.terms.is_empty()
+    }
+
+    pub fn matches(&self, block: &Block) -> bool {
+        self.terms.iter().all(|term| term.filter.matches(block) != term.negated)
+    }
+
+    pub fn text_matches(&self, block: &Block) -> TextMatches {
+        let mut matches = TextMatches::default();
+        for term in self.terms.iter().filter(|term| !term.negated) {
+            match &term.filter {
+                Filter::Text(text) => {
+                    matches.command |= contains(&block.command, text);
+                    matches.output |= contains(&block.output, text);
+                }
+                Filter::Command(text) => matches.command |= contains(&block.command, text),
+                Filter::Output(text) => matches.output |= contains(&block.output, text),
+                _ => {}
+            }
+        }
+        matches
+    }
+}
+
+impl Filter {
+    fn matches(&self, block: &Block) -> bool {
+        match self {
+            Filter::Text(text) => contains(&block.command, text) || contains(&block.output, text),
+            Filter::Command(text) => contains(&block.command, text),
+            Filter::Output(text) => contains(&block.output, text),
+            Filter::Note(text) => contains(&block.note, text),
+            Filter::Exit(op, code) => block.exit_code.is_some_and(|exit| op.test(exit as i64, *code)),
+            Filter::Duration(op, ms) => block.duration_ms.is_some_and(|duration| op.test(duration, *ms)),
+            Filter::Cwd(prefix) => block.cwd.as_deref().is_some_and(|cwd| cwd.starts_with(prefix)),
+            Filter::Branch(branch) => block.git_branch.as_deref().is_some_and(|value| value.eq_ignore_ascii_case(branch)),
+            Filter::Host(host) => block.host.eq_ignore_ascii_case(host),
+            Filter::Tag(tag) => block.tags.iter().any(|value| value.eq_ignore_ascii_case(tag)),
+            Filter::GitStatus(kind) => matches!(
+                (kind, &block.git_status),
+                (GitStatusKind::Clean, Some(GitStatus::Clean))
+                    | (GitStatusKind::Dirty, Some(GitStatus::Dirty))
+                    | (GitStatusKind::Conflicts, Some(GitStatus::Conflicts))
+            ),
+            Filter::After(time) => block.started_at.is_some_and(|started| started >= *time),
+            Filter::Before(time) => block.started_at.is_some_and(|started| started < *time),
+            Filter::Is(Flag::Pinned) => block.pinned,
+            Filter::Is(Flag::Tagged) => !block.tags.is_empty(),
+            Filter::Is(Flag::Remote) => block.is_remote,
+            Filter::Is(Flag::Running) => block.exit_code.is_none(),
+            Filter::Is(Flag::Success) => block.exit_code == Some(0),
+            Filter::Is(Flag::Failed) => block.exit_code.is_some_and(|code| code != 0),
+        }
+    }
+}
+
+fn contains(haystack: &str, needle: &str) -> bool {
+    haystack.to_lowercase().contains(&needle.to_lowercase())
+}
+
+#[derive(Debug)]
+struct Token {
+    negated: bool,
+    field: Option<(String, usize)>,
+    value: String,
+    value_position: usize,
+}
+
+fn error(message: impl Into<String>, position: usize) -> QueryError {
+    QueryError { message: message.into(), position }
+}
+
+// End of the `name` in a `name:` field prefix starting at `start`
+fn field_name_end(chars: &[char], start: usize) -> Option<usize> {
+    let name_end = (start..chars.len()).find(|&end| !(chars[end].is_ascii_alphanumeric() || chars[end] == '_')).unwrap_or(chars.len());
+    (name_end > start && chars.get(name_end) == Some(&':')).then_some(name_end)
+}
+
+fn tokenize(input: &str) -> Result<Vec<Token>, QueryError> {
+    let chars: Vec<char> = input.chars().collect();
+    let mut tokens = Vec::new();
+    let mut index = 0;
+    while index < chars.len() {
+        if chars[index].is_whitespace() {
+            index += 1;
+            continue;
+        }
+        // `-` only negates a field or quoted term, so `--force` and `-rf` are searched for as typed
+        let negated = chars[index] == '-' && (chars.get(index + 1) == Some(&'"') || field_name_end(&chars, index + 1).is_some());
+        if negated {
+            index += 1;
+        }
+        let mut field = None;
+        if let Some(name_end) = field_name_end(&chars, index) {
+            field = Some((chars[index..name_end].iter().collect::<String>().to_lowercase(), index));
+            index = name_end + 1;
+        }
+        let value_position = index;
+        let value = if chars.get(index) == Some(&'"') {
+            let (value, end) = read_quoted(&chars, index)?;
+            index = end;
+            value
+        } else {
+            let end = (index..chars.len()).find(|&end| chars[end].is_whitespace()).unwrap_or(chars.len());
+            let value: String = chars[index..end].iter().collect();
+            index = end;
+            value
+        };
+        if value.is_empty() {
+            let message = match &field {
+                Some((name, _)) => format!("Missing value for '{}:'", name),
+                None => "Empty search term".to_string(),
+            };
+            return Err(error(message, value_position));
+        }
+        tokens.push(Token { negated, field, value, value_position });
+    }
+    Ok(tokens)
+}
+
+// Returns the unescaped contents and the index just past the closing quote
+fn read_quoted(chars: &[char], start: usize) -> Result<(String, usize), QueryError> {
+    let mut value = String::new();
+    let mut index = start + 1;
+    while index < chars.len() {
+        match chars[index] {
+            '\\' if index + 1 < chars.len() => {
+                value.push(chars[index + 1]);
+                index += 2;
+            }
+            '"' => return Ok((value, index + 1)),
+            c => {
+                value.push(c);
+                index += 1;
+            }
+        }
+    }
+    Err(error("Unterminated quote", start))
+}
+
+fn parse_filter(token: &Token, home: Option<&str>) -> Result<Filter, QueryError> {
+    let Some((field, field_position)) = &token.field else {
+        return Ok(Filter::Text(token.value.clone()));
+    };
+    let value = token.value.as_str();
+    let at = token.value_position;
+    let filter = match field.as_str() {
+        "exit" => {
+            let (op, number) = parse_comparison(value);
+            let code = number.parse::<i64>().map_err(|_| error(format!("Expected an exit code, found '{}'", value), at))?;
+            Filter::Exit(op, code)
+        }
+        "dur" | "duration" => {
+            let (op, amount) = parse_comparison(value);
+            Filter::Duration(op, parse_duration_ms(amount).ok_or_else(|| error(format!("Expected a duration like 30s or 2m, found '{}'", value), at))?)
+        }
+        "cwd" => Filter::Cwd(expand_home(value, home)),
+        "branch" => Filter::Branch(value.to_string()),
+        "host" => Filter::Host(value.to_string()),
+        "tag" => Filter::Tag(value.to_string()),
+        "status" => Filter::GitStatus(match value.to_lowercase().as_str() {
+            "clean" => GitStatusKind::Clean,
+            "dirty" => GitStatusKind::Dirty,
+            "conflicts" | "conflict" => GitStatusKind::Conflicts,
+            _ => return Err(error(format!("Unknown git status '{}' (clean, dirty or conflicts)", value), at)),
+        }),
+        "after" => Filter::After(parse_time(value).ok_or_else(|| error(format!("Expected a date like 2026-10-01, found '{}'", value), at))?),
+        "before" => Filter::Before(parse_time(value).ok_or_else(|| error(format!("Expected a date like 2026-10-01, found '{}'", value), at))?),
+        "cmd" | "command" => Filter::Command(value.to_string()),
+        "output" | "out" => Filter::Output(value.to_string()),
+        "note" => Filter::Note(value.to_string()),
+        "is" => Filter::Is(match value.to_lowercase().as_str() {
+            "pinned" => Flag::Pinned,
+            "tagged" => Flag::Tagged,
+            "remote" => Flag::Remote,
+            "running" => Flag::Running,
+            "success" | "ok" => Flag::Success,
+            "failed" | "failure" => Flag::Failed,
+            _ => return Err(error(format!("Unknown flag 'is:{}' (pinned, tagged, remote, running, success, failed)", value), at)),
+        }),
+        _ => {
+            return Err(error(format!("Unknown field '{}:'; quote the term to search for it as text", field), *field_position));
+        }
+    };
+    Ok(filter)
+}
+
+fn parse_comparison(value: &str) -> (CmpOp, &str) {
+    for (prefix, op) in [(">=", CmpOp::Ge), ("<=", CmpOp::Le), ("!=", CmpOp::Ne), (">", CmpOp::Gt), ("<", CmpOp::Lt), ("!", CmpOp::Ne), ("=", CmpOp::Eq)] {
+        if let Some(rest) = value.strip_prefix(prefix) {
+            return (op, rest);
+        }
+    }
+    (CmpOp::Eq, value)
+}
+
+fn parse_duration_ms(value: &str) -> Option<u64> {
+    let split = value.find(|c: char| !(c.is_ascii_digit() || c == '.')).unwrap_or(value.len());
+    let (amount, unit) = value.split_at(split);
+    let amount: f64 = amount.parse().ok()?;
+    let scale = match unit {
+        "ms" => 1.0,
+        "" | "s" => 1_000.0,
+        "m" => 60_000.0,
+        "h" => 3_600_000.0,
+        _ => return None,
+    };
+    Some((amount * scale).round() as u64)
+}
+
+fn parse_time(value: &str) -> Option<DateTime<Utc>> {
+    let naive = NaiveDateTime::parse_from_str(value, "%Y-%m-%dT%H:%M")
+        .ok()
+        .or_else(|| NaiveDate::parse_from_str(value, "%Y-%m-%d").ok().map(|date| date.and_time(NaiveTime::MIN)))?;
+    Local.from_local_datetime(&naive).earliest().map(|time| time.with_timezone(&Utc))
+}
+
+fn expand_home(value: &str, home: Option<&str>) -> PathBuf {
+    match (value.strip_prefix('~'), home) {
+        (Some(rest), Some(home)) if rest.is_empty() || rest.starts_with('/') => Path::new(home).join(rest.trim_start_matches('/')),
+        _ => PathBuf::from(value),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    include!(concat!(env!("CARGO_MANIFEST_DIR"), "/__tests__/history_query_tests.rs"));
+}
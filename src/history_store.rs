@@ -0,0 +1,251 @@
+// Cross-session command history, kept as an append-only JSON Lines log
+// Every finished block is appended with its metadata (not its output). Other
+// tant windows append to the same file, so `refresh` picks up whatever was
+// written since the last read before each Ctrl+R search. A version header line
+// says which version the records after it were written at
+
+use std::collections::{HashMap, HashSet};
+use std::fs::OpenOptions;
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use chrono::{DateTime, Utc};
+use log::warn;
+use serde::{Deserialize, Serialize};
+use crate::parser::GitStatus;
+use crate::shell_import::IMPORTED_TAG;
+use crate::versioning::{self, HISTORY};
+use crate::Block;
+
+// Runs in the directory Ctrl+R was opened from count this many times over
+const DIRECTORY_BOOST: f64 = 3.0;
+// Failed runs count for less so typos sink below the command that worked
+const FAILURE_WEIGHT: f64 = 0.5;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HistoryRecord {
+    pub command: String,
+    pub started_at: Option<DateTime<Utc>>,
+    pub duration_ms: Option<u64>,
+    pub exit_code: Option<i32>,
+    pub cwd: Option<PathBuf>,
+    pub git_branch: Option<String>,
+    pub git_status: Option<GitStatus>,
+    pub host: String,
+    #[serde(default)]
+    pub is_remote: bool,
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+impl HistoryRecord {
+    pub fn from_block(block: &Block) -> Self {
+        HistoryRecord {
+            command: block.command.clone(),
+            started_at: block.started_at,
+            duration_ms: block.duration_ms,
+            exit_code: block.exit_code,
+            cwd: block.cwd.clone(),
+            git_branch: block.git_branch.clone(),
+            git_status: block.git_status.clone(),
+            host: block.host.clone(),
+            is_remote: block.is_remote,
+            tags: block.tags.clone(),
+        }
+    }
+
+    /// Came from a shell's own history file rather than a tant session.
+    pub fn is_imported(&self) -> bool {
+        self.tags.iter().any(|tag| tag == IMPORTED_TAG)
+    }
+}
+
+/// A Ctrl+R result.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HistoryMatch {
+    pub command: String,
+    /// Every run of the command was imported from shell history.
+    pub imported: bool,
+}
+
+pub struct HistoryStore {
+    path: PathBuf,
+    records: Vec<HistoryRecord>,
+    // Bytes of the file already parsed into `records`
+    read_len: u64,
+    // Version of the records that follow the bytes already read; files from
+    // before the header existed are version 0 throughout
+    version: u32,
+}
+
+impl HistoryStore {
+    pub fn open(path: PathBuf) -> Self {
+        let mut store = HistoryStore { path, records: Vec::new(), read_len: 0, version: 0 };
+        store.refresh();
+        // What this tant appends must be read back at the current version
+        if store.read_len > 0 && store.version != HISTORY.version() {
+            if let Err(err) = write_lines(&store.path, format!("{}\n", versioning::header(&HISTORY)).into_bytes()) {
+                warn!("Failed to update history version in {}: {}", store.path.display(), err);
+            }
+        }
+        store
+    }
+
+    /// Reads records appended since the last read, by this or any other window.
+    pub fn refresh(&mut self) {
+        if let Err(err) = self.read_new_lines() {
+            if err.kind() != io::ErrorKind::NotFound {
+                warn!("Failed to read history from {}: {}", self.path.display(), err);
+            }
+        }
+    }
+
+    fn read_new_lines(&mut self) -> io::Result<()> {
+        let mut file = std::fs::File::open(&self.path)?;
+        let len = file.metadata()?.len();
+        if len < self.read_len {
+            // Truncated or replaced: start over
+            self.records.clear();
+            self.read_len = 0;
+        }
+        if self.read_len == 0 {
+            self.version = 0;
+        }
+        file.seek(SeekFrom::Start(self.read_len))?;
+        let mut bytes = Vec::new();
+        file.read_to_end(&mut bytes)?;
+        // A line still being written by another window is left for the next refresh
+        let complete = bytes.iter().rposition(|&byte| byte == b'\n').map_or(0, |end| end + 1);
+        for line in bytes[..complete].split(|&byte| byte == b'\n') {
+            if line.iter().all(u8::is_ascii_whitespace) {
+                continue;
+            }
+            let value = match serde_json::from_slice(line) {
+                Ok(value) => value,
+                Err(err) => {
+                    warn!("Skipping unreadable history entry: {}", err);
+                    continue;
+                }
+            };
+            if let Some(version) = versioning::parse_header(&value) {
+                if version > HISTORY.version() {
+                    warn!("Skipping history written by a newer tant (version {})", version);
+                }
+                self.version = version;
+                continue;
+            }
+            if self.version > HISTORY.version() {
+                continue;
+            }
+            match versioning::decode_value::<HistoryRecord>(&HISTORY, self.version, value) {
+                Ok(record) => self.records.push(record),
+                Err(err) => warn!("Skipping unreadable history entry: {}", err),
+            }
+        }
+        self.read_len += complete as u64;
+        Ok(())
+    }
+
+    /// Appending a finished block, returned as a write to run off the UI thread.
+    /// It is read back by the next `refresh`.
+    pub fn append_task(&self, block: &Block) -> impl FnOnce() -> io::Result<()> + Send + 'static {
+        let path = self.path.clone();
+        let record = (!block.command.trim().is_empty()).then(|| HistoryRecord::from_block(block));
+        move || match record {
+            Some(record) => write_records(&path, &[record]),
+            None => Ok(()),
+        }
+    }
+
+    /// Appends blocks that are not already recorded with the same command and
+    /// start time, returning how many were added.
+    pub fn import(&mut self, blocks: &[Block]) -> io::Result<usize> {
+        self.refresh();
+        let mut seen: HashSet<(String, Option<DateTime<Utc>>)> =
+            self.records.iter().map(|record| (record.command.clone(), record.started_at)).collect();
+        let new: Vec<HistoryRecord> = blocks
+            .iter()
+            .filter(|block| !block.command.trim().is_empty())
+            .filter(|block| seen.insert((block.command.clone(), block.started_at)))
+            .map(HistoryRecord::from_block)
+            .collect();
+        if !new.is_empty() {
+            write_records(&self.path, &new)?;
+            self.refresh();
+        }
+        Ok(new.len())
+    }
+
+    /// Distinct commands containing `query`, best first. Each run scores by how
+    /// recent it is, runs in `cwd` are boosted and failed runs count for less.
+    pub fn search(&self, query: &str, cwd: Option<&Path>, now: DateTime<Utc>, limit: usize) -> Vec<HistoryMatch> {
+        let query = query.to_lowercase();
+        let mut scores: HashMap<&str, (f64, Option<DateTime<Utc>>, bool)> = HashMap::new();
+        for record in &self.records {
+            let command = record.command.trim();
+            if command.is_empty() || !command.to_lowercase().contains(&query) {
+                continue;
+            }
+            let mut weight = recency_weight(record.started_at.map(|started| now - started));
+            if cwd.is_some() && record.cwd.as_deref() == cwd {
+                weight *= DIRECTORY_BOOST;
+            }
+            if record.exit_code.is_some_and(|code| code != 0) {
+                weight *= FAILURE_WEIGHT;
+            }
+            let entry = scores.entry(command).or_insert((0.0, None, true));
+            entry.0 += weight;
+            entry.1 = entry.1.max(record.started_at);
+            entry.2 &= record.is_imported();
+        }
+        let mut ranked: Vec<_> = scores.into_iter().collect();
+        ranked.sort_by(|(a_command, (a_score, a_last, _)), (b_command, (b_score, b_last, _))| {
+            b_score.total_cmp(a_score).then(b_last.cmp(a_last)).then(a_command.cmp(b_command))
+        });
+        ranked
+            .into_iter()
+            .take(limit)
+            .map(|(command, (_, _, imported))| HistoryMatch { command: command.to_string(), imported })
+            .collect()
+    }
+}
+
+fn write_records(path: &Path, records: &[HistoryRecord]) -> io::Result<()> {
+    let mut lines = Vec::new();
+    for record in records {
+        serde_json::to_writer(&mut lines, record)?;
+        lines.push(b'\n');
+    }
+    write_lines(path, lines)
+}
+
+fn write_lines(path: &Path, mut lines: Vec<u8>) -> io::Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    if file.metadata()?.len() == 0 {
+        lines.splice(0..0, format!("{}\n", versioning::header(&HISTORY)).into_bytes());
+    }
+    // One write per batch so concurrent appends from other windows never interleave
+    file.write_all(&lines)
+}
+
+// Frecency buckets in the style of browser history ranking
+fn recency_weight(age: Option<chrono::Duration>) -> f64 {
+    let Some(age) = age else {
+        return 10.0;
+    };
+    match age.num_hours() {
+        hours if hours < 4 => 100.0,
+        hours if hours < 24 => 70.0,
+        hours if hours < 24 * 7 => 50.0,
+        hours if hours < 24 * 30 => 30.0,
+        hours if hours < 24 * 90 => 10.0,
+        _ => 5.0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    include!(concat!(env!("CARGO_MANIFEST_DIR"), "/__tests__/history_store_tests.rs"));
+}
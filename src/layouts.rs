@@ -0,0 +1,181 @@
+// Layout files: tabs of split panes described in TOML or YAML, each pane with an
+// optional title, profile, working directory and commands to type once its shell
+// starts. They live in the config directory under `layouts` and open from the
+// palette or with `tant --layout <file>`
+
+use std::path::{Path, PathBuf};
+use serde::{Deserialize, Serialize};
+use crate::{paths, LayoutNode};
+
+const MIN_RATIO: f32 = 0.1;
+const MAX_RATIO: f32 = 0.9;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LayoutFile {
+    pub tabs: Vec<TabTemplate>,
+    #[serde(default)]
+    pub active_tab: usize,
+    /// Directory relative pane `cwd`s are resolved against.
+    #[serde(skip)]
+    pub dir: PathBuf,
+    /// The file's stem, which names the session a layout opened at launch saves to.
+    #[serde(skip)]
+    pub name: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TabTemplate {
+    #[serde(default)]
+    pub title: String,
+    /// Split tree whose leaves index into `panes`; a single pane when omitted.
+    #[serde(default = "single_pane")]
+    pub root: LayoutNode,
+    /// Leaves without an entry here get a plain shell.
+    #[serde(default)]
+    pub panes: Vec<PaneTemplate>,
+    #[serde(default)]
+    pub active_pane: usize,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct PaneTemplate {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub profile: Option<String>,
+    /// `~` is the home directory; relative paths are relative to the layout file.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cwd: Option<String>,
+    /// Typed into the shell in order once it starts.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub commands: Vec<String>,
+}
+
+fn single_pane() -> LayoutNode {
+    LayoutNode::Leaf { pane_id: 0 }
+}
+
+impl LayoutFile {
+    /// Parses by extension and checks every tab's split tree.
+    pub fn parse(path: &Path, contents: &str) -> Result<Self, String> {
+        let mut file: LayoutFile = match path.extension().and_then(|ext| ext.to_str()) {
+            Some("toml") => toml::from_str(contents).map_err(|e| e.to_string())?,
+            // serde_yaml wants `!Split` tags for enums; going through a JSON value
+            // lets YAML use the same `Split:` maps as TOML
+            Some("yaml") | Some("yml") => {
+                let value: serde_json::Value = serde_yaml::from_str(contents).map_err(|e| e.to_string())?;
+                serde_json::from_value(value).map_err(|e| e.to_string())?
+            }
+            _ => return Err(format!("Unrecognised layout file: {}", path.display())),
+        };
+        if file.tabs.is_empty() {
+            return Err("layout has no tabs".to_string());
+        }
+        for (index, tab) in file.tabs.iter_mut().enumerate() {
+            tab.validate().map_err(|err| format!("tab {}: {}", index + 1, err))?;
+        }
+        file.active_tab = file.active_tab.min(file.tabs.len() - 1);
+        file.dir = path.parent().map(Path::to_path_buf).unwrap_or_default();
+        file.name = path.file_stem().map(|stem| stem.to_string_lossy().to_string()).unwrap_or_default();
+        Ok(file)
+    }
+
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let contents = std::fs::read_to_string(path).map_err(|err| format!("{}: {}", path.display(), err))?;
+        Self::parse(path, &contents).map_err(|err| format!("{}: {}", path.display(), err))
+    }
+
+    pub fn to_toml(&self) -> Result<String, String> {
+        toml::to_string_pretty(self).map_err(|err| err.to_string())
+    }
+
+    /// Where a pane starts: its `cwd` resolved, or `None` for tant's own directory.
+    pub fn pane_directory(&self, pane: &PaneTemplate) -> Option<PathBuf> {
+        let cwd = pane.cwd.as_deref()?.trim();
+        let path = if cwd == "~" {
+            paths::home_dir()
+        } else if let Some(rest) = cwd.strip_prefix("~/") {
+            paths::home_dir().join(rest)
+        } else {
+            self.dir.join(cwd)
+        };
+        Some(path)
+    }
+}
+
+impl TabTemplate {
+    // Leaves must number the panes 0..n exactly once, since a tab's panes are
+    // looked up by position. Pads `panes` to one per leaf and clamps ratios to
+    // what dragging a divider allows
+    fn validate(&mut self) -> Result<(), String> {
+        let mut ids = Vec::new();
+        check_node(&mut self.root, &mut ids)?;
+        ids.sort_unstable();
+        if ids.iter().enumerate().any(|(index, id)| *id != index) {
+            return Err(format!("pane ids must be 0 to {} with each used once", ids.len() - 1));
+        }
+        if self.panes.len() > ids.len() {
+            return Err(format!("{} panes are described but the layout only has {}", self.panes.len(), ids.len()));
+        }
+        self.panes.resize_with(ids.len(), PaneTemplate::default);
+        self.active_pane = self.active_pane.min(ids.len() - 1);
+        Ok(())
+    }
+}
+
+fn check_node(node: &mut LayoutNode, ids: &mut Vec<usize>) -> Result<(), String> {
+    match node {
+        LayoutNode::Leaf { pane_id } => ids.push(*pane_id),
+        LayoutNode::Split { ratio, left, right, .. } => {
+            if ratio.is_nan() || *ratio <= 0.0 || *ratio >= 1.0 {
+                return Err(format!("split ratio {} is not between 0 and 1", ratio));
+            }
+            *ratio = ratio.clamp(MIN_RATIO, MAX_RATIO);
+            check_node(left, ids)?;
+            check_node(right, ids)?;
+        }
+    }
+    Ok(())
+}
+
+pub fn layouts_dir() -> PathBuf {
+    paths::config_dir().join("layouts")
+}
+
+/// Layout files in `dir`, sorted by path.
+pub fn list(dir: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut files: Vec<PathBuf> = entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.is_file() && matches!(path.extension().and_then(|ext| ext.to_str()), Some("toml" | "yaml" | "yml")))
+        .collect();
+    files.sort();
+    files
+}
+
+/// The file `--layout` names: a path, or the name of a file in `dir` with or
+/// without its extension.
+pub fn find(arg: &str, dir: &Path) -> Option<PathBuf> {
+    let path = PathBuf::from(arg);
+    if path.is_file() {
+        return Some(path);
+    }
+    let named = dir.join(arg);
+    if named.is_file() {
+        return Some(named);
+    }
+    ["toml", "yaml", "yml"].iter().map(|ext| dir.join(format!("{}.{}", arg, ext))).find(|path| path.is_file())
+}
+
+/// A layout name that is safe to use as a file name.
+pub fn template_name(input: &str) -> Result<String, String> {
+    paths::file_name_safe(input, "layout")
+}
+
+#[cfg(test)]
+mod tests {
+    include!(concat!(env!("CARGO_MANIFEST_DIR"), "/__tests__/layouts_tests.rs"));
+}
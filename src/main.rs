@@ -1,25 +1,72 @@
 use iced::{Application, Command, Element, Settings, Subscription, Theme, time, window, mouse, clipboard, Point, Length, Color, Size, Rectangle, Border, Background};
 use iced::keyboard::{self, Key, Modifiers};
-use iced::widget::{Row, Column, container, TextInput, text_input, scrollable};
+use iced::widget::{Row, Column, container, TextInput, text_editor, text_input, scrollable};
 use iced::widget::scrollable::RelativeOffset;
 use log::{debug, info, warn, error};
 use serde::{Deserialize, Serialize};
 use tokio::sync::Mutex as TokioMutex;
 use chrono::{DateTime, Utc};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::sync::{Arc, Mutex};
+use std::time::Instant;
 
 mod pty;
 mod parser;
 mod renderer;
 mod export;
 mod themes;
+mod theme_import;
+mod theme_manager;
+mod appearance;
+mod accessibility;
+mod fonts;
+mod atlas_renderer;
+mod selection;
+mod find;
+mod history_query;
+mod history_store;
+mod paths;
+mod shell_import;
+mod bookmarks;
+mod folding;
+mod block_diff;
+mod watch;
+mod workflows;
+mod completion;
+mod command_editor;
+mod safety;
+mod diagnostics;
+mod file_links;
+mod sessions;
+mod versioning;
+mod layouts;
 mod ai;
 
 use parser::{TerminalParser, ParserEvent, GitStatus};
-use renderer::{TerminalRenderer, StyleRun};
-use export::{AiConversationExport, AiConversationExportScope, AiConversationMessage, AiConversationMetadata, AiReferencedBlock, ExportFormat, format_ai_conversation_export, format_blocks, write_ai_export_file, write_export_file};
-use themes::preset_theme;
+use renderer::{BlockOverlay, CommandLine, GridOverlay, TerminalRenderer, WatchOverlay, TerminalPalette, StyleRun};
+use export::{AiConversationExport, AiConversationExportScope, AiConversationMessage, AiConversationMetadata, AiReferencedBlock, ExportFormat, format_ai_conversation_export, format_blocks, output_section, write_ai_export_file, write_export_file};
+use theme_import::list_importable_themes;
+use theme_manager::{ThemeManager, iced_theme, theme_color};
+use appearance::{AppearanceWatcher, ColorScheme};
+use accessibility::{StatusColors, DEFAULT_MIN_CONTRAST};
+use fonts::FontSet;
+use atlas_renderer::{GlyphAtlases, GridRaster};
+use selection::{mode_for_click, ClickTracker, GridPoint, Selection, DEFAULT_WORD_CHARS};
+use find::{scroll_offset_for, FindState};
+use history_store::{HistoryMatch, HistoryStore};
+use shell_import::{import_shell_history, summary_message, ShellKind};
+use bookmarks::{add_tags, collect_bookmarks, parse_tags, tag_query, BlockAnnotation};
+use folding::{hidden_label, FoldSettings};
+use block_diff::{context_ranges, diff_stats, previous_run, side_by_side, BlockDiffView, DiffKind, DiffLine, DiffSettings, CONTEXT_LINES};
+use watch::{next_watch_id, Watch, WatchForm, WatchSettings};
+use workflows::{load_workflows, Workflow, WorkflowForm, WorkflowSource};
+use completion::{Completer, Suggestion};
+use command_editor::{submission, CommandEditor};
+use safety::{CommandContext, PendingCommand, PendingRun, SafetyGuards};
+use diagnostics::{extract, Diagnostic, DiagnosticSettings, Matcher};
+use file_links::{find_links, launch, FileLink, FileLinkSettings, OpenAction};
+use sessions::{restore_directory, session_name, should_rerun, SessionStore, DEFAULT_SESSION};
+use layouts::{layouts_dir, template_name, LayoutFile, PaneTemplate, TabTemplate};
 use ai::{AiRequest, send_request};
 use pty::PtyManager;
 
@@ -33,7 +80,7 @@ pub struct ThemeConfig {
     pub colors: HashMap<String, [f32; 3]>, // RGB values
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct Block {
     pub command: String,
     pub started_at: Option<DateTime<Utc>>,
@@ -55,6 +102,65 @@ pub struct Block {
     pub is_remote: bool,
     #[serde(default)]
     pub collapsed: bool,
+    /// Free-form markdown annotation.
+    #[serde(default)]
+    pub note: String,
+    /// Runs of the same watch share an id and are stacked together.
+    #[serde(default)]
+    pub watch_id: Option<u64>,
+    /// Parsed from the output when the command failed.
+    #[serde(default)]
+    pub diagnostics: Vec<Diagnostic>,
+    /// File references in the output, found when the command ends.
+    #[serde(default)]
+    pub file_links: Vec<FileLink>,
+}
+
+/// A finished, successful block on localhost for tests to build on.
+#[cfg(test)]
+pub fn test_block(command: &str, output: &str) -> Block {
+    Block {
+        command: command.to_string(),
+        exit_code: Some(0),
+        output: output.to_string(),
+        host: "localhost".to_string(),
+        ..Block::default()
+    }
+}
+
+/// A scratch directory for one test, removed when it goes out of scope.
+#[cfg(test)]
+pub struct TestDir(std::path::PathBuf);
+
+#[cfg(test)]
+pub fn test_dir(module: &str, name: &str) -> TestDir {
+    let dir = std::env::temp_dir().join(format!("tant-{}-{}-{}", module, name, std::process::id()));
+    std::fs::remove_dir_all(&dir).ok();
+    std::fs::create_dir_all(&dir).unwrap();
+    TestDir(dir)
+}
+
+#[cfg(test)]
+impl std::ops::Deref for TestDir {
+    type Target = std::path::Path;
+
+    fn deref(&self) -> &std::path::Path {
+        &self.0
+    }
+}
+
+#[cfg(test)]
+impl AsRef<std::path::Path> for TestDir {
+    fn as_ref(&self) -> &std::path::Path {
+        &self.0
+    }
+}
+
+#[cfg(test)]
+impl Drop for TestDir {
+    fn drop(&mut self) {
+        std::fs::remove_dir_all(&self.0).ok();
+    }
 }
 
 pub struct Pane {
@@ -63,12 +169,26 @@ pub struct Pane {
     pub history: Vec<Block>,
     pub current_block: Option<Block>,
     pub current_command: String,
+    /// Editor state behind `current_command`, which mirrors its text.
+    pub editor: CommandEditor,
     pub working_directory: String,
     pub data_receiver: tokio::sync::mpsc::Receiver<Vec<u8>>,
     pub scroll_offset: usize,
     pub follow_mode: bool,
-    pub selection_start: Option<(usize, usize)>,
-    pub selection_end: Option<(usize, usize)>,
+    pub selection: Option<Selection>,
+    pub clicks: ClickTracker,
+    pub find: Option<FindState>,
+    pub annotation: Option<BlockAnnotation>,
+    /// Scroll offset of each expanded block's output region, by block index.
+    pub output_scroll: HashMap<usize, f32>,
+    pub watches: Vec<Watch>,
+    pub watch_form: Option<WatchForm>,
+    /// Watch stacks showing every run rather than just the latest.
+    pub expanded_watches: HashSet<u64>,
+    /// Completion for `current_command`, recomputed as it's typed.
+    pub suggestion: Option<Suggestion>,
+    /// History commands suggestions draw on, gathered once per prompt.
+    pub completion_history: Option<Vec<String>>,
     pub mouse_button_down: bool,
     pub last_cursor_pos: Point,
     pub title: String,
@@ -91,15 +211,25 @@ pub struct Pane {
     pub ai_last_redactions: Vec<String>,
     pub ai_last_redacted_preview: Option<String>,
     pub ai_selected_template: Option<AiPromptTemplateId>,
+    pub profile: Option<String>,
+    pub zoom: f32,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SerializablePane {
     pub history: Vec<Block>,
     pub current_command: String,
     pub working_directory: String,
     pub title: String,
     pub scroll_offset: usize,
+    #[serde(default)]
+    pub profile: Option<String>,
+    /// Scrollback and screen text, shown greyed out when the pane is restored.
+    #[serde(default)]
+    pub screen: Option<String>,
+    /// The command running in the foreground when the session was saved.
+    #[serde(default)]
+    pub last_command: Option<String>,
 }
 
 pub struct Tab {
@@ -109,7 +239,7 @@ pub struct Tab {
     pub title: String,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SerializableTab {
     pub root: LayoutNode,
     pub panes: Vec<SerializablePane>,
@@ -166,14 +296,122 @@ pub struct AppConfig {
     pub ai_share_link_enabled: bool,
     #[serde(default = "default_plan_tier")]
     pub plan_tier: PlanTier,
+    #[serde(default = "default_theme_name")]
+    pub theme: String,
+    #[serde(default)]
+    pub profiles: HashMap<String, ProfileConfig>,
+    /// When both are set, the theme follows the desktop colour scheme instead of `theme`.
+    #[serde(default)]
+    pub light_theme: Option<String>,
+    #[serde(default)]
+    pub dark_theme: Option<String>,
+    #[serde(default = "default_follow_system_appearance")]
+    pub follow_system_appearance: bool,
+    #[serde(default = "default_light_start")]
+    pub light_start: String,
+    #[serde(default = "default_dark_start")]
+    pub dark_start: String,
+    /// Minimum WCAG contrast ratio for terminal text, e.g. 4.5 for WCAG AA. The
+    /// default 1.0 leaves theme colours as they are.
+    #[serde(default = "default_min_contrast")]
+    pub min_contrast: f32,
+    #[serde(default)]
+    pub status_colors: StatusColors,
+    /// Tried in order for glyphs missing from the theme font (symbols, CJK, emoji).
+    #[serde(default = "default_font_fallbacks")]
+    pub font_fallbacks: Vec<String>,
+    /// Characters that double-click word selection treats as part of a word, besides letters and digits.
+    #[serde(default = "default_word_chars")]
+    pub word_chars: String,
+    /// Copy to the clipboard as soon as a mouse selection is made.
+    #[serde(default)]
+    pub copy_on_select: bool,
+    /// How much of a collapsed block's output stays visible, and how tall expanded output regions are.
+    #[serde(default)]
+    pub folding: FoldSettings,
+    /// Regex normalisers and the default layout of the block diff view.
+    #[serde(default)]
+    pub diff: DiffSettings,
+    /// Defaults for watching a block, and how many failures in a row stop a watch.
+    #[serde(default)]
+    pub watch: WatchSettings,
+    /// Extra regex matchers for turning failed output into diagnostics.
+    #[serde(default)]
+    pub diagnostics: DiagnosticSettings,
+    /// How Ctrl+clicked file paths and clicked diagnostics are opened in an editor.
+    #[serde(default)]
+    pub file_links: FileLinkSettings,
+    /// Seconds between automatic saves of the open session; 0 turns autosave off.
+    #[serde(default = "default_autosave_secs")]
+    pub autosave_secs: u64,
+    /// Globs of commands, e.g. `npm run dev`, started again when a session is
+    /// restored if they were running when it was saved.
+    #[serde(default)]
+    pub restore_commands: Vec<String>,
 }
 
 impl Default for AppConfig {
     fn default() -> Self {
-        Self { ai_onboarding_seen: false, ai_share_link_enabled: true, plan_tier: PlanTier::Free }
+        Self { ai_onboarding_seen: false, ai_share_link_enabled: true, plan_tier: PlanTier::Free, theme: default_theme_name(), profiles: HashMap::new(), light_theme: None, dark_theme: None, follow_system_appearance: true, light_start: default_light_start(), dark_start: default_dark_start(), min_contrast: default_min_contrast(), status_colors: StatusColors::Default, font_fallbacks: default_font_fallbacks(), word_chars: default_word_chars(), copy_on_select: false, folding: FoldSettings::default(), diff: DiffSettings::default(), watch: WatchSettings::default(), diagnostics: DiagnosticSettings::default(), file_links: FileLinkSettings::default(), autosave_secs: default_autosave_secs(), restore_commands: Vec::new() }
     }
 }
 
+/// Named pane profile. Panes assigned to a profile use its theme instead of the global one.
+/// The font family stays the global theme's: all panes share one loaded font set and glyph
+/// metrics, so a profile theme can change colours, size and spacing but not the font.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ProfileConfig {
+    #[serde(default)]
+    pub theme: Option<String>,
+}
+
+fn default_theme_name() -> String {
+    "one_dark".to_string()
+}
+
+fn default_follow_system_appearance() -> bool {
+    true
+}
+
+fn default_light_start() -> String {
+    "07:00".to_string()
+}
+
+fn default_dark_start() -> String {
+    "19:00".to_string()
+}
+
+fn default_min_contrast() -> f32 {
+    DEFAULT_MIN_CONTRAST
+}
+
+fn default_autosave_secs() -> u64 {
+    30
+}
+
+fn default_word_chars() -> String {
+    DEFAULT_WORD_CHARS.to_string()
+}
+
+fn default_font_fallbacks() -> Vec<String> {
+    vec![
+        "Symbols Nerd Font Mono".to_string(),
+        "Noto Sans Symbols 2".to_string(),
+        "Noto Sans Mono CJK SC".to_string(),
+        "Noto Color Emoji".to_string(),
+    ]
+}
+
+const MIN_PANE_ZOOM: f32 = 0.5;
+const MAX_PANE_ZOOM: f32 = 3.0;
+const PANE_ZOOM_STEP: f32 = 0.1;
+// Ctrl+R shows at most this many commands
+const HISTORY_SEARCH_LIMIT: usize = 15;
+// Commands from history considered for inline suggestions
+const COMPLETION_HISTORY_LIMIT: usize = 5000;
+// Width of the bookmarks sidebar, which pushes the panes to the right
+const BOOKMARKS_WIDTH: f32 = 280.0;
+
 fn default_ai_share_link_enabled() -> bool {
     true
 }
@@ -336,11 +574,31 @@ pub enum PaletteAction {
     ApplyAiTemplate(AiPromptTemplateId),
     ExportTheme,
     ImportTheme,
+    ImportThemeFile(std::path::PathBuf),
+    SelectTheme,
+    SetPaneProfile(String),
+    ClearPaneProfile,
     OpenBilling,
+    ImportShellHistory,
+    ToggleBookmarks,
+    DiffSelectedBlocks,
+    RunWorkflow(usize),
+    SaveSessionAs,
+    SwitchSession(String),
+    DeleteSession(String),
+    OpenLayout(std::path::PathBuf),
+    SaveLayoutTemplate,
     // Add more as needed
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// What the name being typed into the name form is for.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum NamePrompt {
+    SessionAs,
+    LayoutTemplate,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum LayoutNode {
     Split {
         axis: Axis,
@@ -366,12 +624,21 @@ impl Pane {
             history: vec![],
             current_block: None,
             current_command: String::new(),
+            editor: CommandEditor::default(),
             working_directory: wd,
             data_receiver: receiver,
             scroll_offset: 0,
             follow_mode: true,
-            selection_start: None,
-            selection_end: None,
+            selection: None,
+            clicks: ClickTracker::default(),
+            find: None,
+            annotation: None,
+            output_scroll: HashMap::new(),
+            watches: Vec::new(),
+            watch_form: None,
+            expanded_watches: HashSet::new(),
+            suggestion: None,
+            completion_history: None,
             mouse_button_down: false,
             last_cursor_pos: Point { x: 0.0, y: 0.0 },
             title: "Terminal".to_string(),
@@ -394,8 +661,22 @@ impl Pane {
             ai_last_redactions: Vec::new(),
             ai_last_redacted_preview: None,
             ai_selected_template: None,
+            profile: None,
+            zoom: 1.0,
         })
     }
+
+    /// Replaces the command being typed, as one undoable edit.
+    pub fn set_command(&mut self, command: String) {
+        self.editor.set_text(&command);
+        self.current_command = command;
+        self.suggestion = None;
+    }
+
+    /// Full-screen programs and the find bar draw the raw cell grid instead of blocks.
+    pub fn shows_grid(&self) -> bool {
+        self.parser.is_alt_screen_active() || self.find.is_some()
+    }
 }
 
 
@@ -429,7 +710,46 @@ pub enum Message {
     ToggleSearchPinned,
     ClearSearch,
     TogglePin(usize),
+    BlockOutputScrolled(usize, usize, usize, f32),
+    /// Links found in a finished block's output: tab, pane, block index, the
+    /// block's end time to confirm it's the same block, and the links.
+    FileLinksFound(usize, usize, usize, Option<DateTime<Utc>>, Vec<FileLink>),
+    EditBlockAnnotation(usize),
+    UpdateBlockTagInput(String),
+    SubmitBlockTags,
+    RemoveBlockTag(usize, String),
+    BlockNoteEdited(text_editor::Action),
+    CloseBlockAnnotation,
+    FilterByTag(String),
+    ToggleBookmarks,
+    JumpToBookmark(usize, usize, usize),
+    DiffSelectedBlocks,
+    DiffWithPreviousRun(usize),
+    ToggleDiffSideBySide,
+    ToggleDiffNormalise,
+    CloseDiff,
+    EditWatch(usize),
+    UpdateWatchInterval(String),
+    UpdateWatchGlob(String),
+    StartIntervalWatch,
+    StartFileWatch,
+    StopWatch(u64),
+    ToggleWatchRuns(u64),
+    UpdateWorkflowArg(usize, String),
+    SubmitWorkflow,
+    CloseWorkflow,
+    AcceptSuggestion,
+    /// Tab, pane, block index and the diagnostic or link within the block.
+    OpenDiagnostic(usize, usize, usize, usize),
+    OpenFileLink(usize, usize, usize, usize),
+    ConfirmPendingCommand,
+    CancelPendingCommand,
     SaveSession,
+    AutosaveSession,
+    UpdateNameInput(String),
+    SubmitNameInput,
+    CancelNameInput,
+    WindowCloseRequested,
     AiExplainError,
     AiSuggestFix,
     AiGenerateCommand,
@@ -458,13 +778,20 @@ pub enum Message {
     Paste(String),
     WindowFocused,
     WindowUnfocused,
-    TerminalInput(String),
+    CommandEdited(text_editor::Action),
     TerminalSubmit,
     MouseWheel(mouse::ScrollDelta),
     MouseButtonPressed(mouse::Button),
     MouseCursorMoved(Point),
     MouseButtonReleased(mouse::Button),
+    ModifiersChanged(Modifiers),
     CopySelected,
+    OpenFind,
+    CloseFind,
+    UpdateFindQuery(String),
+    CycleFindMode,
+    FindNext,
+    FindPrev,
     OpenCommandPalette,
     CloseCommandPalette,
     UpdatePaletteQuery(String),
@@ -493,6 +820,14 @@ pub enum Message {
     AdjustSplitRatio(Axis, f32),
     ExportTheme,
     ImportTheme,
+    ZoomIn,
+    ZoomOut,
+    ZoomReset,
+    OpenThemePicker,
+    ThemePickerMove(isize),
+    ThemePickerSelect(usize),
+    ThemePickerConfirm,
+    ThemePickerCancel,
     OpenBilling,
     CloseBilling,
     BillingUpgradeRequested,
@@ -501,6 +836,12 @@ pub enum Message {
     None,
 }
 
+struct ThemePickerState {
+    names: Vec<String>,
+    selected: usize,
+    original: String,
+}
+
 struct Tant {
     layout: Vec<Tab>,
     active_tab: usize,
@@ -510,31 +851,69 @@ struct Tant {
     search_failure_only: bool,
     search_pinned_only: bool,
     search_input_id: text_input::Id,
+    /// Set when the search box was last typed into, so arrow keys stay its own.
+    search_focused: bool,
     ai_settings: AiSettings,
     ai_response: Option<String>,
     app_config: AppConfig,
     ai_onboarding_open: bool,
     show_command_palette: bool,
+    // Built when the palette opens rather than on every frame, along with the
+    // saved sessions and layout files it lists
+    palette_actions: Vec<(String, PaletteAction)>,
+    saved_sessions: Vec<String>,
+    layout_files: Vec<std::path::PathBuf>,
     palette_query: String,
     palette_selected: usize,
     render_cache: Arc<Mutex<HashMap<(usize, usize, u16), Vec<StyleRun>>>>,
     row_hashes: Arc<Mutex<HashMap<(usize, usize, u16), u64>>>,
+    rasters: HashMap<(usize, usize), GridRaster>,
+    atlases: GlyphAtlases,
     theme_config: ThemeConfig,
+    theme_manager: ThemeManager,
+    profile_themes: HashMap<String, ThemeConfig>,
+    theme_picker: Option<ThemePickerState>,
+    appearance: AppearanceWatcher,
+    system_scheme: Option<ColorScheme>,
+    color_scheme: ColorScheme,
     host_info: HostInfo,
     window_size: Size,
     resize_state: Option<SplitResizeState>,
     last_cursor_pos: Point,
+    modifiers: Modifiers,
     renaming_tab: Option<usize>,
     rename_buffer: String,
     history_search_active: bool,
     history_search_query: String,
-    history_matches: Vec<String>,
+    history_matches: Vec<HistoryMatch>,
     history_selected: usize,
+    history_store: HistoryStore,
     export_toast: Option<ExportToast>,
     usage_ledger: UsageLedger,
     billing_profile: BillingProfile,
     usage_snapshot: UsageSnapshot,
     show_billing: bool,
+    bookmarks_open: bool,
+    block_diff: Option<BlockDiffView>,
+    // Reloaded each time the palette opens, so edits to workflow files show up
+    workflows: Vec<Workflow>,
+    workflow_form: Option<WorkflowForm>,
+    completer: Completer,
+    // Compiled from the built-in and configured matchers at startup
+    diagnostic_matchers: Vec<Matcher>,
+    sessions: SessionStore,
+    // Name of the open session, which autosave writes to
+    session_name: String,
+    // Files that exist but failed to load without being backed up; never saved over
+    kept_files: HashSet<std::path::PathBuf>,
+    // Saved tabs whose shell wouldn't start, written back with the session so
+    // autosave doesn't lose them
+    left_out_tabs: Vec<SerializableTab>,
+    // Name being typed for "Save Session As..." or "Save Layout As Template..."
+    name_input: Option<(NamePrompt, String)>,
+    // A command flagged by the safety guard, held until confirmed
+    pending_command: Option<PendingCommand>,
+    safety_guards: SafetyGuards,
 }
 
 #[derive(Debug, Clone)]
@@ -605,18 +984,30 @@ fn resolve_host_info() -> HostInfo {
 }
 
 impl Tant {
-    fn load_app_config() -> AppConfig {
-        let path = std::path::Path::new("config.json");
-        if let Ok(contents) = std::fs::read_to_string(path) {
-            serde_json::from_str::<AppConfig>(&contents).unwrap_or_default()
-        } else {
-            AppConfig::default()
-        }
+    fn config_path() -> std::path::PathBuf {
+        paths::config_dir().join("config.json")
+    }
+
+    fn load_app_config(problems: &mut Vec<String>, kept_files: &mut HashSet<std::path::PathBuf>) -> AppConfig {
+        versioning::load(&versioning::CONFIG, &Self::config_path()).unwrap_or_else(|err| {
+            kept_files.extend(err.kept().map(std::path::Path::to_path_buf));
+            problems.push(err.to_string());
+            None
+        }).unwrap_or_default()
     }
 
     fn save_app_config(&self) -> Result<(), Box<dyn std::error::Error>> {
-        let json = serde_json::to_string_pretty(&self.app_config)?;
-        std::fs::write("config.json", json)?;
+        self.check_not_kept(&Self::config_path())?;
+        versioning::save(&versioning::CONFIG, &Self::config_path(), &self.app_config)?;
+        Ok(())
+    }
+
+    // Files that failed to load but are still in place hold settings this run
+    // never saw, so writing defaults over them would lose those
+    fn check_not_kept(&self, path: &std::path::Path) -> Result<(), String> {
+        if self.kept_files.contains(path) {
+            return Err(format!("{} couldn't be loaded, so it isn't saved over", path.display()));
+        }
         Ok(())
     }
 
@@ -658,8 +1049,7 @@ impl Tant {
     }
 
     fn load_redaction_config(&self) -> RedactionConfig {
-        let path = std::path::Path::new("redaction_rules.json");
-        if let Ok(contents) = std::fs::read_to_string(path) {
+        if let Ok(contents) = std::fs::read_to_string(paths::config_dir().join("redaction_rules.json")) {
             serde_json::from_str::<RedactionConfig>(&contents).unwrap_or(RedactionConfig { rules: Vec::new() })
         } else {
             RedactionConfig { rules: Vec::new() }
@@ -741,7 +1131,9 @@ impl Tant {
                     for (index, block) in blocks {
                         let label = self.block_label(index, block);
                         sources.push(AiCitation { block_index: Some(index), label: label.clone() });
-                        context.push_str(&format!("\n[{}]\nCommand: {}\nExit: {:?}\nOutput:\n{}\n", label, block.command, block.exit_code, block.output));
+                        // Diagnostics say the same as a failed build's output in far fewer tokens
+                        let (heading, body) = output_section(block);
+                        context.push_str(&format!("\n[{}]\nCommand: {}\nExit: {:?}\n{}:\n{}\n", label, block.command, block.exit_code, heading, body));
                     }
                 }
             }
@@ -840,6 +1232,7 @@ impl Tant {
                                     host: block.host.clone(),
                                     is_remote: block.is_remote,
                                     tags: block.tags.clone(),
+                                    note: block.note.clone(),
                                 });
                             }
                         }
@@ -857,7 +1250,7 @@ impl Tant {
 
     fn handle_ai_export(&mut self, export: AiConversationExport, scope: AiConversationExportScope, format: ExportFormat) -> Command<Message> {
         if let Ok(result) = format_ai_conversation_export(&export, format) {
-            if let Ok(path) = write_ai_export_file(std::path::Path::new("exports"), scope, format, &result.content) {
+            if let Ok(path) = write_ai_export_file(&Self::exports_dir(), scope, format, &result.content) {
                 let mut toast_message = format!("AI export saved: {}", path.display());
                 if self.app_config.ai_share_link_enabled {
                     let link = format!("file://{}", path.display());
@@ -879,27 +1272,42 @@ impl Tant {
 
 
     fn update_history_matches(&mut self) {
-        let query = self.history_search_query.to_lowercase();
-        let mut matches = Vec::new();
-        if let Some(tab) = self.layout.get(self.active_tab) {
-            for block in tab.panes.get(tab.active_pane).map(|p| &p.history).into_iter().flatten().rev() {
-                if query.is_empty() || block.command.to_lowercase().contains(&query) {
-                    matches.push(block.command.clone());
-                }
-                if matches.len() >= 10 {
-                    break;
-                }
-            }
-        }
-        self.history_matches = matches;
+        self.history_store.refresh();
+        let cwd = self
+            .layout
+            .get(self.active_tab)
+            .and_then(|tab| tab.panes.get(tab.active_pane))
+            .map(|pane| std::path::PathBuf::from(&pane.working_directory));
+        self.history_matches = self.history_store.search(&self.history_search_query, cwd.as_deref(), Utc::now(), HISTORY_SEARCH_LIMIT);
         self.history_selected = 0;
     }
 
+    /// Recomputes the active pane's inline suggestion for what's been typed.
+    fn refresh_suggestion(&mut self) {
+        let Some(pane) = self.layout.get_mut(self.active_tab).and_then(|tab| tab.panes.get_mut(tab.active_pane)) else {
+            return;
+        };
+        if pane.current_command.trim().is_empty() {
+            pane.suggestion = None;
+            return;
+        }
+        let cwd = std::path::PathBuf::from(&pane.working_directory);
+        let history_store = &self.history_store;
+        let history = pane.completion_history.get_or_insert_with(|| {
+            history_store
+                .search("", Some(&cwd), Utc::now(), COMPLETION_HISTORY_LIMIT)
+                .into_iter()
+                .map(|found| found.command)
+                .collect()
+        });
+        pane.suggestion = self.completer.suggest(&pane.current_command, Some(&cwd), history);
+    }
+
     fn apply_history_selection(&mut self) {
         if let Some(selected) = self.history_matches.get(self.history_selected).cloned() {
             if let Some(tab) = self.layout.get_mut(self.active_tab) {
                 if let Some(pane) = tab.panes.get_mut(tab.active_pane) {
-                    pane.current_command = selected;
+                    pane.set_command(selected.command);
                 }
             }
         }
@@ -922,8 +1330,7 @@ impl Tant {
         let tab = Tab { root: LayoutNode::Leaf { pane_id: 0 }, panes: vec![pane], active_pane: 0, title };
         self.layout.push(tab);
         self.active_tab = self.layout.len().saturating_sub(1);
-        self.render_cache.lock().unwrap().clear();
-        self.row_hashes.lock().unwrap().clear();
+        self.clear_render_caches();
     }
 
     fn close_tab_at(&mut self, index: usize) {
@@ -948,8 +1355,7 @@ impl Tant {
             } else if self.active_tab > index {
                 self.active_tab = self.active_tab.saturating_sub(1);
             }
-            self.render_cache.lock().unwrap().clear();
-            self.row_hashes.lock().unwrap().clear();
+            self.clear_render_caches();
         }
     }
 
@@ -1123,8 +1529,12 @@ impl Tant {
                 .panes
                 .get(tab.active_pane)
                 .map(|pane| pane.working_directory.clone());
+            let profile = tab.panes.get(tab.active_pane).and_then(|pane| pane.profile.clone());
             let new_pane = match Pane::new(&shell, working_directory) {
-                Ok(pane) => pane,
+                Ok(mut pane) => {
+                    pane.profile = profile;
+                    pane
+                }
                 Err(err) => {
                     error!("Failed to create pane: {}", err);
                     return;
@@ -1254,211 +1664,1295 @@ impl Tant {
         }
     }
 
-    fn save_session(&self) -> Result<(), Box<dyn std::error::Error>> {
-        let serializable_tabs: Vec<SerializableTab> = self.layout.iter().map(|tab| {
+    fn session_layout(&mut self) -> Layout {
+        let serializable_tabs: Vec<SerializableTab> = self.layout.iter_mut().map(|tab| {
             SerializableTab {
                 root: tab.root.clone(),
-                panes: tab.panes.iter().map(|pane| {
+                panes: tab.panes.iter_mut().map(|pane| {
                     SerializablePane {
                         history: pane.history.clone(),
                         current_command: pane.current_command.clone(),
                         working_directory: pane.working_directory.clone(),
                         title: pane.title.clone(),
                         scroll_offset: pane.scroll_offset,
+                        profile: pane.profile.clone(),
+                        // A full-screen program's screen isn't worth keeping
+                        screen: (!pane.parser.is_alt_screen_active()).then(|| pane.parser.transcript()),
+                        last_command: pane.current_block.as_ref().map(|block| block.command.clone()).filter(|command| !command.is_empty()),
                     }
                 }).collect(),
                 active_pane: tab.active_pane,
                 title: tab.title.clone(),
             }
         }).collect();
-        let layout = Layout {
-            tabs: serializable_tabs,
+        Layout {
+            tabs: serializable_tabs.into_iter().chain(self.left_out_tabs.iter().cloned()).collect(),
             active_tab: self.active_tab,
-        };
-        let json = serde_json::to_string(&layout)?;
-        std::fs::write("session.json", json)?;
-        Ok(())
+        }
     }
 
-    fn load_session() -> Result<Layout, Box<dyn std::error::Error>> {
-        let json = std::fs::read_to_string("session.json")?;
-        let layout: Layout = serde_json::from_str(&json)?;
-        Ok(layout)
+    fn save_session(&mut self) -> Result<(), String> {
+        let name = self.session_name.clone();
+        self.save_session_to(&name)
     }
 
-    fn export_theme(&self) -> Result<(), Box<dyn std::error::Error>> {
-        let json = serde_json::to_string_pretty(&self.theme_config)?;
-        std::fs::write("theme.json", json)?;
-        Ok(())
+    fn save_session_to(&mut self, name: &str) -> Result<(), String> {
+        self.check_not_kept(&self.sessions.path(name))?;
+        let layout = self.session_layout();
+        self.sessions.save(name, &layout)
     }
 
-    fn import_theme(&mut self) -> Result<(), Box<dyn std::error::Error>> {
-        let json = std::fs::read_to_string("theme.json")?;
-        self.theme_config = serde_json::from_str(&json)?;
-        Ok(())
+    // Tabs and panes for a saved layout, each pane with a fresh shell below its
+    // old screen contents. Allow-listed commands that were running start again;
+    // a pane whose directory is gone opens in $HOME. Tabs whose shell won't
+    // start at all go to `left_out` as they were saved
+    fn restore_tabs(saved_layout: Layout, shell: &str, rerun_allowed: &[String], left_out: &mut Vec<SerializableTab>) -> Option<(Vec<Tab>, usize)> {
+        let mut tabs = vec![];
+        for saved_tab in saved_layout.tabs {
+            // A pane that can't start where it was falls back to tant's own directory
+            let started: Result<Vec<Pane>, _> = saved_tab
+                .panes
+                .iter()
+                .map(|saved_pane| {
+                    let working_directory = restore_directory(&saved_pane.working_directory);
+                    Pane::new(shell, Some(working_directory.clone())).or_else(|err| {
+                        warn!("Failed to start a shell in {}: {}", working_directory, err);
+                        Pane::new(shell, None)
+                    })
+                })
+                .collect();
+            let started = match started {
+                Ok(started) => started,
+                Err(err) => {
+                    warn!("Leaving out tab '{}': {}", saved_tab.title, err);
+                    left_out.push(saved_tab);
+                    continue;
+                }
+            };
+            let mut panes = vec![];
+            for (mut pane, saved_pane) in started.into_iter().zip(saved_tab.panes) {
+                if let Some(screen) = &saved_pane.screen {
+                    pane.parser.replay_transcript(screen);
+                }
+                // Restore history and current_command
+                pane.history = saved_pane.history;
+                pane.set_command(saved_pane.current_command);
+                pane.title = saved_pane.title;
+                pane.scroll_offset = saved_pane.scroll_offset;
+                pane.profile = saved_pane.profile;
+                if let Some(command) = saved_pane.last_command.filter(|command| should_rerun(command, rerun_allowed)) {
+                    info!("Restarting '{}' from the saved session", command);
+                    if let Ok(mut pty) = pane.pty.try_lock() {
+                        let cmd = format!("{}\r", command.trim());
+                        pty.writer().write_all(cmd.as_bytes()).ok();
+                        pty.writer().flush().ok();
+                    }
+                }
+                panes.push(pane);
+            }
+            let tab = Tab { root: saved_tab.root, panes, active_pane: saved_tab.active_pane, title: saved_tab.title };
+            tabs.push(tab);
+        }
+        let active_tab = saved_layout.active_tab.min(tabs.len().saturating_sub(1));
+        (!tabs.is_empty()).then_some((tabs, active_tab))
     }
 
-    fn plan_limits(plan: PlanTier) -> PlanLimits {
-        match plan {
-            PlanTier::Free => PlanLimits {
-                daily_messages: 50,
-                daily_chars: 20_000,
-                max_context_chars: 6_000,
-                allow_streaming: false,
-            },
-            PlanTier::Pro => PlanLimits {
-                daily_messages: 500,
-                daily_chars: 200_000,
-                max_context_chars: 24_000,
-                allow_streaming: true,
-            },
-            PlanTier::Team => PlanLimits {
-                daily_messages: 2_000,
-                daily_chars: 1_000_000,
-                max_context_chars: 64_000,
-                allow_streaming: true,
-            },
-        }
+    // Startup/switch message for tabs whose shell wouldn't start
+    fn left_out_message(left_out: &[SerializableTab]) -> Option<String> {
+        let titles: Vec<String> = left_out.iter().map(|tab| format!("'{}'", tab.title)).collect();
+        (!titles.is_empty()).then(|| format!("tab {}: the shell didn't start; kept in the saved session", titles.join(", ")))
     }
 
-    fn usage_ledger_path() -> &'static str {
-        "usage_ledger.json"
+    fn default_tabs(shell: &str) -> (Vec<Tab>, usize) {
+        let pane = Pane::new(shell, None).unwrap();
+        let root = LayoutNode::Leaf { pane_id: 0 };
+        let tab = Tab { root, panes: vec![pane], active_pane: 0, title: "Tab 1".to_string() };
+        (vec![tab], 0)
     }
 
-    fn load_usage_ledger() -> UsageLedger {
-        let path = std::path::Path::new(Self::usage_ledger_path());
-        if let Ok(contents) = std::fs::read_to_string(path) {
-            serde_json::from_str::<UsageLedger>(&contents).unwrap_or_else(|_| UsageLedger {
-                entries: Vec::new(),
-                last_reset: Utc::now(),
-            })
-        } else {
-            UsageLedger { entries: Vec::new(), last_reset: Utc::now() }
+    // Tabs for a layout file, each pane with a fresh shell that is then sent the
+    // pane's startup commands
+    fn layout_tabs(file: &LayoutFile, shell: &str) -> Result<Vec<Tab>, String> {
+        let mut tabs = vec![];
+        for (index, template) in file.tabs.iter().enumerate() {
+            let mut panes = vec![];
+            for pane_template in &template.panes {
+                let working_directory = file.pane_directory(pane_template).map(|dir| restore_directory(&dir.to_string_lossy()));
+                let mut pane = Pane::new(shell, working_directory).map_err(|err| format!("failed to start a shell: {}", err))?;
+                if let Some(title) = &pane_template.title {
+                    pane.title = title.clone();
+                }
+                pane.profile = pane_template.profile.clone();
+                if !pane_template.commands.is_empty() {
+                    if let Ok(mut pty) = pane.pty.try_lock() {
+                        for command in &pane_template.commands {
+                            let cmd = format!("{}\r", command.trim());
+                            pty.writer().write_all(cmd.as_bytes()).ok();
+                        }
+                        pty.writer().flush().ok();
+                    }
+                }
+                panes.push(pane);
+            }
+            let title = if template.title.is_empty() { format!("Tab {}", index + 1) } else { template.title.clone() };
+            tabs.push(Tab { root: template.root.clone(), panes, active_pane: template.active_pane, title });
         }
+        Ok(tabs)
     }
 
-    fn save_usage_ledger(&self) -> Result<(), Box<dyn std::error::Error>> {
-        let json = serde_json::to_string_pretty(&self.usage_ledger)?;
-        std::fs::write(Self::usage_ledger_path(), json)?;
+    // Opens a layout file's tabs after the existing ones
+    fn open_layout(&mut self, path: &std::path::Path) -> Result<(), String> {
+        let file = LayoutFile::load(path)?;
+        let shell = std::env::var("SHELL").unwrap_or_else(|_| "/bin/bash".to_string());
+        let tabs = Self::layout_tabs(&file, &shell)?;
+        self.active_tab = self.layout.len() + file.active_tab;
+        self.layout.extend(tabs);
+        self.refresh_profile_themes();
+        self.resize_panes();
         Ok(())
     }
 
+    // Writes the active tab's split tree, directories, titles and profiles as
+    // `layouts/<name>.toml`; history and running commands are left out
+    fn save_layout_template(&self, name: &str) -> Result<std::path::PathBuf, String> {
+        let tab = self.layout.get(self.active_tab).ok_or("there is no open tab")?;
+        let panes = tab
+            .panes
+            .iter()
+            .map(|pane| PaneTemplate {
+                title: Some(pane.title.clone()),
+                profile: pane.profile.clone(),
+                cwd: Some(pane.working_directory.clone()),
+                commands: Vec::new(),
+            })
+            .collect();
+        let template = TabTemplate { title: tab.title.clone(), root: tab.root.clone(), panes, active_pane: tab.active_pane };
+        let file = LayoutFile { tabs: vec![template], active_tab: 0, dir: layouts_dir(), name: name.to_string() };
+        let path = layouts_dir().join(format!("{}.toml", name));
+        paths::write_atomic(&path, file.to_toml()?.as_bytes()).map_err(|err| format!("{}: {}", path.display(), err))?;
+        Ok(path)
+    }
 
-    fn reset_usage_if_needed(&mut self) {
-        let now = Utc::now();
-        if now.date_naive() != self.usage_ledger.last_reset.date_naive() {
-            self.usage_ledger.entries.clear();
-            self.usage_ledger.last_reset = now;
+    // Saves the open session, then replaces every tab with the named one; a
+    // session that doesn't exist yet starts as a single fresh pane
+    fn switch_session(&mut self, name: String) {
+        if let Err(err) = self.save_session() {
+            warn!("Failed to save session '{}': {}", self.session_name, err);
+        }
+        let shell = std::env::var("SHELL").unwrap_or_else(|_| "/bin/bash".to_string());
+        let mut left_out = Vec::new();
+        let restored = match self.sessions.load(&name) {
+            Ok(layout) => {
+                self.kept_files.remove(&self.sessions.path(&name));
+                layout.and_then(|layout| Self::restore_tabs(layout, &shell, &self.app_config.restore_commands, &mut left_out))
+            }
+            Err(err) => {
+                warn!("Failed to load session '{}': {}", name, err);
+                self.kept_files.extend(err.kept().map(std::path::Path::to_path_buf));
+                None
+            }
+        };
+        let (layout, active_tab) = restored.unwrap_or_else(|| Self::default_tabs(&shell));
+        self.layout = layout;
+        self.active_tab = active_tab;
+        self.rasters.clear();
+        self.session_name = name;
+        let problem = Self::left_out_message(&left_out);
+        self.left_out_tabs = left_out;
+        if let Err(err) = self.sessions.set_current(&self.session_name) {
+            warn!("Failed to remember the current session: {}", err);
         }
+        self.refresh_profile_themes();
+        self.resize_panes();
+        let message = match problem {
+            Some(problem) => format!("Switched to session '{}', but couldn't load {}", self.session_name, problem),
+            None => format!("Switched to session '{}'", self.session_name),
+        };
+        self.export_toast = Some(ExportToast { message, expires_at: Utc::now() + chrono::Duration::seconds(6) });
     }
 
-    fn compute_usage_snapshot(&self, limits: &PlanLimits) -> UsageSnapshot {
-        let used_messages = self.usage_ledger.entries.len() as u32;
-        let used_chars = self
-            .usage_ledger
-            .entries
-            .iter()
-            .map(|entry| entry.request_chars as u32 + entry.response_chars as u32)
-            .sum::<u32>();
-        UsageSnapshot {
-            used_messages,
-            used_chars,
-            remaining_messages: limits.daily_messages.saturating_sub(used_messages),
-            remaining_chars: limits.daily_chars.saturating_sub(used_chars),
+    // Copies state files tant used to keep in its working directory to their XDG
+    // homes. Only files shaped like tant's are taken, since the directory tant
+    // starts in may well have a `config.json` of its own
+    fn adopt_legacy_files() {
+        let moves = [
+            ("config.json", Self::config_path(), paths::written_as::<AppConfig> as fn(&str) -> bool),
+            ("redaction_rules.json", paths::config_dir().join("redaction_rules.json"), paths::written_as::<RedactionConfig>),
+            ("safety_rules.json", paths::config_dir().join("safety_rules.json"), paths::written_as::<safety::SafetyConfig>),
+            ("theme.json", Self::theme_export_path(), paths::written_as::<ThemeConfig>),
+            ("usage_ledger.json", Self::usage_ledger_path(), paths::written_as::<UsageLedger>),
+            ("session.json", SessionStore::open().path(DEFAULT_SESSION), paths::written_as::<Layout>),
+        ];
+        for (legacy, target, is_tants) in moves {
+            match paths::adopt_legacy_file(std::path::Path::new(legacy), &target, is_tants) {
+                Ok(true) => info!("Copied ./{} to {}", legacy, target.display()),
+                Ok(false) => {}
+                Err(err) => warn!("Failed to copy ./{} to {}: {}", legacy, target.display(), err),
+            }
         }
     }
 
-    fn can_send_ai_request(&self) -> bool {
-        self.usage_snapshot.remaining_messages > 0 && self.usage_snapshot.remaining_chars > 0 && self.ai_settings.enabled
+    fn exports_dir() -> std::path::PathBuf {
+        paths::data_dir().join("exports")
     }
 
-    fn build_ai_prompt(&self, action: &str, user_text: &str, context: &str) -> AiRequest {
-        let system = match action {
-            "explain_error" => "Explain the error and likely cause in concise terms.",
-            "suggest_fix" => "Suggest a practical fix and a command to try.",
-            "generate_command" => "Generate a command for the user's request.",
-            "summarize_output" => "Summarize the command output briefly.",
-            _ => "Respond concisely.",
-        };
-        let model = match self.ai_settings.provider.as_str() {
-            "openai" => "gpt-4o-mini",
-            "anthropic" => "claude-3-5-sonnet-20240620",
-            "ollama" => "llama3.2:latest",
-            _ => "gpt-4o-mini",
-        };
-        let user = if user_text.trim().is_empty() {
-            format!("{}", context)
-        } else {
-            format!("{}\n\nUser request:\n{}", context, user_text)
-        };
-        AiRequest {
-            provider: self.ai_settings.provider.clone(),
-            model: model.to_string(),
-            system: system.to_string(),
-            user,
-        }
+    fn theme_export_path() -> std::path::PathBuf {
+        paths::config_dir().join("theme.json")
     }
 
+    fn export_theme(&self) -> Result<(), Box<dyn std::error::Error>> {
+        versioning::save(&versioning::THEME, &Self::theme_export_path(), &self.theme_config)?;
+        Ok(())
+    }
 
-    fn build_ai_request(&self, prompt: &str, action: &str) -> AiRequest {
-        self.build_ai_prompt(action, "", prompt)
+    fn import_theme(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        let json = std::fs::read_to_string(Self::theme_export_path())?;
+        self.theme_config = versioning::decode(&versioning::THEME, &json)?;
+        Ok(())
     }
 
-    fn execute_palette_action(&mut self, action: PaletteAction) {
-        match action {
-            PaletteAction::SplitPaneHorizontal => {
-                self.split_active_pane(Axis::Horizontal);
-            }
-            PaletteAction::SplitPaneVertical => {
-                self.split_active_pane(Axis::Vertical);
-            }
-            PaletteAction::ClosePane => {
-                self.close_active_pane();
-            }
-            PaletteAction::SwitchTab(index) => {
-                if index < self.layout.len() {
-                    self.active_tab = index;
-                }
-            }
-            PaletteAction::RunPinnedCommand(index) => {
-                if let Some(tab) = self.layout.get(self.active_tab) {
-                    if let Some(pane) = tab.panes.get(tab.active_pane) {
-                        if let Some(block) = pane.history.get(index) {
-                            if block.pinned {
-                                if let Ok(mut pty) = pane.pty.try_lock() {
-                                    let cmd = format!("{}\r", block.command);
-                                    if let Err(err) = pty.writer().write_all(cmd.as_bytes()) {
-                                        error!("Failed to write to PTY: {}", err);
-                                    }
-                                    if let Err(err) = pty.writer().flush() {
-                                        error!("Failed to flush PTY writer: {}", err);
-                                    }
-                                    info!("Run pinned command: {}", block.command);
-                                }
-                            }
-                        }
+    fn themes_dir() -> std::path::PathBuf {
+        paths::config_dir().join("themes")
+    }
+
+    fn apply_theme(&mut self, name: &str) {
+        let previous_family = self.theme_config.font_family.clone();
+        self.theme_config = self.theme_manager.resolve(name);
+        if self.theme_config.font_family != previous_family {
+            self.renderer.fonts = Arc::new(FontSet::load(&self.theme_config.font_family, &self.app_config.font_fallbacks));
+            self.atlases = GlyphAtlases::new(self.renderer.fonts.clone());
+            self.resize_panes();
+        }
+        self.refresh_profile_themes();
+        let scheme = ColorScheme::of_theme(&self.theme_config);
+        if scheme != self.color_scheme {
+            self.color_scheme = scheme;
+            for tab in &self.layout {
+                for pane in &tab.panes {
+                    if pane.parser.color_scheme_updates_enabled() {
+                        Self::report_color_scheme(pane, scheme);
                     }
                 }
             }
-            PaletteAction::ToggleAi => {
-                self.ai_settings.enabled = !self.ai_settings.enabled;
-            }
-            PaletteAction::ToggleAiPanel => {
-                if let Some(tab) = self.layout.get_mut(self.active_tab) {
-                    if let Some(pane) = tab.panes.get_mut(tab.active_pane) {
-                        pane.ai_panel_open = !pane.ai_panel_open;
-                    }
+        }
+    }
+
+    fn clear_render_caches(&mut self) {
+        self.render_cache.lock().unwrap().clear();
+        self.row_hashes.lock().unwrap().clear();
+        self.rasters.clear();
+    }
+
+    /// Composites dirty alt-screen panes into their rasters and drops caches for
+    /// panes that were closed or went back to block mode.
+    fn update_rasters(&mut self) {
+        let mut jobs = Vec::new();
+        for (tab_index, tab) in self.layout.iter().enumerate() {
+            for (pane_index, pane) in tab.panes.iter().enumerate() {
+                if !pane.shows_grid() {
+                    continue;
                 }
-            }
-            PaletteAction::SetAiContextScope(scope) => {
-                if let Some(tab) = self.layout.get_mut(self.active_tab) {
-                    if let Some(pane) = tab.panes.get_mut(tab.active_pane) {
-                        pane.ai_context_scope = scope;
-                    }
+                let key = (tab_index, pane_index);
+                if pane.parser.is_dirty() || !self.rasters.contains_key(&key) {
+                    let theme = self.pane_theme(pane);
+                    let palette = TerminalPalette::from_theme(theme, self.renderer.min_contrast);
+                    let (cell_w, cell_h) = self.pane_cell_size(pane);
+                    jobs.push((key, palette, theme.font_size * pane.zoom, cell_w, cell_h));
                 }
             }
-            PaletteAction::ApplyAiTemplate(template_id) => {
-                let pane_id = self.layout
+        }
+        for ((tab_index, pane_index), palette, font_px, cell_w, cell_h) in jobs {
+            let pane = &mut self.layout[tab_index].panes[pane_index];
+            let raster = self.rasters.entry((tab_index, pane_index)).or_default();
+            raster.update(pane.parser.screen(), &palette, self.atlases.get(font_px), cell_w, cell_h, GridOverlay { selection: pane.selection.as_ref(), find: pane.find.as_ref() });
+            pane.parser.mark_clean();
+        }
+        let layout = &self.layout;
+        let shows_grid = |tab_index: usize, pane_index: usize| {
+            layout
+                .get(tab_index)
+                .and_then(|tab| tab.panes.get(pane_index))
+                .is_some_and(Pane::shows_grid)
+        };
+        self.rasters.retain(|(tab_index, pane_index), _| shows_grid(*tab_index, *pane_index));
+        let mut cache = self.render_cache.lock().unwrap();
+        if !cache.is_empty() {
+            cache.retain(|(tab_index, pane_index, _), _| shows_grid(*tab_index, *pane_index));
+            self.row_hashes.lock().unwrap().retain(|(tab_index, pane_index, _), _| shows_grid(*tab_index, *pane_index));
+        }
+    }
+
+    fn pane_cell_size(&self, pane: &Pane) -> (f32, f32) {
+        self.renderer.cell_size(self.pane_theme(pane), pane.zoom)
+    }
+
+    fn pane_rect(node: &LayoutNode, rect: Rectangle, pane_id: usize) -> Option<Rectangle> {
+        match node {
+            LayoutNode::Leaf { pane_id: id } => (*id == pane_id).then_some(rect),
+            LayoutNode::Split { axis, ratio, left, right } => {
+                let (left_rect, right_rect) = match axis {
+                    Axis::Horizontal => (
+                        Rectangle { width: rect.width * ratio, ..rect },
+                        Rectangle { x: rect.x + rect.width * ratio, width: rect.width - rect.width * ratio, ..rect },
+                    ),
+                    Axis::Vertical => (
+                        Rectangle { height: rect.height * ratio, ..rect },
+                        Rectangle { y: rect.y + rect.height * ratio, height: rect.height - rect.height * ratio, ..rect },
+                    ),
+                };
+                Self::pane_rect(left, left_rect, pane_id).or_else(|| Self::pane_rect(right, right_rect, pane_id))
+            }
+        }
+    }
+
+    /// The part of the window the panes are laid out in: all of it, less the
+    /// bookmarks sidebar on the left when that is open.
+    fn layout_area(window_size: Size, bookmarks_open: bool) -> Rectangle {
+        let sidebar = if bookmarks_open { BOOKMARKS_WIDTH.min(window_size.width) } else { 0.0 };
+        Rectangle { x: sidebar, y: 0.0, width: window_size.width - sidebar, height: window_size.height }
+    }
+
+    /// Grid cell of the active pane under a window position. A press only counts
+    /// inside the pane; a drag that leaves it is clamped to the grid.
+    fn active_grid_point(&self, position: Point, clamp: bool) -> Option<GridPoint> {
+        let tab = self.layout.get(self.active_tab)?;
+        let pane = tab.panes.get(tab.active_pane)?;
+        let area = Self::layout_area(self.window_size, self.bookmarks_open);
+        let rect = Self::pane_rect(&tab.root, area, tab.active_pane)?;
+        Self::grid_point(&pane.parser, pane.shows_grid(), rect, self.pane_cell_size(pane), position, clamp)
+    }
+
+    /// Grid cell under a window position for a pane laid out in `rect`. A pane
+    /// in block mode draws blocks and widgets rather than cells, so it has none.
+    fn grid_point(parser: &TerminalParser, shows_grid: bool, rect: Rectangle, (cell_w, cell_h): (f32, f32), position: Point, clamp: bool) -> Option<GridPoint> {
+        if !shows_grid || !(clamp || rect.contains(position)) {
+            return None;
+        }
+        let screen = parser.screen();
+        let (rows, cols) = screen.size();
+        let row = (((position.y - rect.y) / cell_h).max(0.0) as u16).min(rows.saturating_sub(1));
+        let col = (((position.x - rect.x) / cell_w).max(0.0) as u16).min(cols.saturating_sub(1));
+        Some(GridPoint::new(row as isize - screen.scrollback() as isize, col))
+    }
+
+    // Whether keys are going to some input other than the command line: a form
+    // or panel with its own text field is open, or the search box was typed into
+    fn typing_elsewhere(&self) -> bool {
+        let pane_input = self
+            .layout
+            .get(self.active_tab)
+            .and_then(|tab| tab.panes.get(tab.active_pane))
+            .is_some_and(|pane| pane.ai_panel_open || pane.annotation.is_some() || pane.watch_form.is_some() || pane.find.is_some());
+        pane_input
+            || self.search_focused
+            || self.show_command_palette
+            || self.history_search_active
+            || self.renaming_tab.is_some()
+            || self.name_input.is_some()
+            || self.workflow_form.is_some()
+    }
+
+    fn active_pane_mut(&mut self) -> Option<&mut Pane> {
+        let tab = self.layout.get_mut(self.active_tab)?;
+        tab.panes.get_mut(tab.active_pane)
+    }
+
+    fn active_find_query(&self) -> String {
+        self.layout
+            .get(self.active_tab)
+            .and_then(|tab| tab.panes.get(tab.active_pane))
+            .and_then(|pane| pane.find.as_ref())
+            .map(|find| find.query.clone())
+            .unwrap_or_default()
+    }
+
+    // Writing to the history file can block on a slow disk, so it runs in the background
+    fn record_history(history_store: &HistoryStore, block: &Block) -> Command<Message> {
+        let append = history_store.append_task(block);
+        Command::perform(
+            async move {
+                let written = tokio::task::spawn_blocking(append).await;
+                if let Ok(Err(err)) = written {
+                    warn!("Failed to record command history: {}", err);
+                }
+            },
+            |_| Message::None,
+        )
+    }
+
+    /// Searches the scrollback and live screen of the pane; `incremental` only
+    /// rescans what new output could have changed since the last search.
+    fn run_find(pane: &mut Pane, incremental: bool) {
+        let Some(find) = pane.find.as_mut() else { return };
+        pane.parser.refresh_find(find, incremental);
+        pane.parser.mark_dirty();
+    }
+
+    fn reveal_find_match(pane: &mut Pane) {
+        let Some(found) = pane.find.as_ref().and_then(FindState::current) else { return };
+        let rows = pane.parser.screen().size().0;
+        let offset = scroll_offset_for(found.start.line, rows, pane.parser.screen().scrollback(), pane.parser.scrollback_len());
+        pane.parser.set_scrollback(offset);
+    }
+
+    fn expand_selection(pane: &mut Pane, word_chars: &str) {
+        if let Some(selection) = pane.selection.as_mut() {
+            let lines = pane.parser.grid_lines(selection.endpoint_lines());
+            selection.expand(&lines, word_chars);
+        }
+        pane.parser.mark_dirty();
+    }
+
+    fn selection_text(selection: Option<&Selection>, parser: &mut TerminalParser) -> Option<String> {
+        let selection = selection.filter(|selection| !selection.is_empty())?;
+        let (first, last) = selection.line_range();
+        let text = selection.text(&parser.grid_lines(first..=last));
+        (!text.is_empty()).then_some(text)
+    }
+
+    fn active_pane_cell_size(&self) -> (f32, f32) {
+        match self.layout.get(self.active_tab).and_then(|tab| tab.panes.get(tab.active_pane)) {
+            Some(pane) => self.pane_cell_size(pane),
+            None => self.renderer.cell_size(&self.theme_config, 1.0),
+        }
+    }
+
+    /// Recomputes every pane's grid from the window size and its own cell metrics.
+    fn resize_panes(&mut self) {
+        let cell_sizes: Vec<Vec<(f32, f32)>> = self
+            .layout
+            .iter()
+            .map(|tab| tab.panes.iter().map(|pane| self.pane_cell_size(pane)).collect())
+            .collect();
+        let window_size = self.window_size;
+        for (tab, tab_cells) in self.layout.iter_mut().zip(cell_sizes) {
+            for (pane, (cell_w, cell_h)) in tab.panes.iter_mut().zip(tab_cells) {
+                let cols = (window_size.width / cell_w) as u16;
+                let rows = (window_size.height / cell_h) as u16;
+                pane.parser.resize(rows, cols);
+                if let Ok(mut pty) = pane.pty.try_lock() {
+                    if let Err(err) = pty.resize(rows, cols, window_size.width as u16, window_size.height as u16) {
+                        error!("Failed to resize PTY: {}", err);
+                    }
+                }
+            }
+        }
+        // Clear render caches; cached runs carry pixel positions
+        self.clear_render_caches();
+    }
+
+    fn zoom_active_pane(&mut self, zoom: impl Fn(f32) -> f32) {
+        if let Some(tab) = self.layout.get_mut(self.active_tab) {
+            if let Some(pane) = tab.panes.get_mut(tab.active_pane) {
+                pane.zoom = zoom(pane.zoom).clamp(MIN_PANE_ZOOM, MAX_PANE_ZOOM);
+                debug!("Pane zoom set to {:.0}%", pane.zoom * 100.0);
+            }
+        }
+        self.resize_panes();
+    }
+
+    fn report_color_scheme(pane: &Pane, scheme: ColorScheme) {
+        if let Ok(mut pty) = pane.pty.try_lock() {
+            if let Err(err) = pty.writer().write_all(scheme.dsr_report()) {
+                error!("Failed to write colour scheme report to PTY: {}", err);
+            }
+            if let Err(err) = pty.writer().flush() {
+                error!("Failed to flush PTY writer: {}", err);
+            }
+        }
+    }
+
+    fn auto_theme_names(&self) -> Option<(&str, &str)> {
+        match (&self.app_config.light_theme, &self.app_config.dark_theme) {
+            (Some(light), Some(dark)) => Some((light.as_str(), dark.as_str())),
+            _ => None,
+        }
+    }
+
+    /// The configured theme for the current desktop colour scheme, or the fixed theme.
+    fn active_theme_name(&self) -> String {
+        match (self.auto_theme_names(), self.system_scheme) {
+            (Some((light, _)), Some(ColorScheme::Light)) => light.to_string(),
+            (Some((_, dark)), Some(ColorScheme::Dark)) => dark.to_string(),
+            _ => self.app_config.theme.clone(),
+        }
+    }
+
+    // Opens the live theme picker on the theme in use, so cancelling can restore it
+    fn open_theme_picker(&mut self) {
+        let names = self.theme_manager.names();
+        let original = self.active_theme_name();
+        let selected = names.iter().position(|name| *name == original).unwrap_or(0);
+        self.theme_picker = Some(ThemePickerState { names, selected, original });
+    }
+
+    fn poll_system_appearance(&mut self) {
+        if self.auto_theme_names().is_none() {
+            self.system_scheme = None;
+            return;
+        }
+        let scheme = self.appearance.current(&self.app_config.light_start, &self.app_config.dark_start);
+        if self.system_scheme != Some(scheme) {
+            info!("Desktop colour scheme is now {:?}", scheme);
+            self.system_scheme = Some(scheme);
+            if self.theme_picker.is_none() {
+                let name = self.active_theme_name();
+                self.apply_theme(&name);
+            }
+        }
+    }
+
+    fn refresh_profile_themes(&mut self) {
+        self.profile_themes = self
+            .app_config
+            .profiles
+            .iter()
+            .filter_map(|(profile, config)| {
+                let mut theme = self.theme_manager.resolve(config.theme.as_ref()?);
+                // Cell sizes are measured with the global font set
+                theme.font_family = self.theme_config.font_family.clone();
+                Some((profile.clone(), theme))
+            })
+            .collect();
+        self.clear_render_caches();
+    }
+
+    fn pane_theme(&self, pane: &Pane) -> &ThemeConfig {
+        pane.profile
+            .as_ref()
+            .and_then(|profile| self.profile_themes.get(profile))
+            .unwrap_or(&self.theme_config)
+    }
+
+    fn render_theme_picker(&self) -> Element<'_, Message> {
+        let mut column = Column::new().spacing(4).padding(12);
+        column = column.push(iced::widget::Text::new("Select Theme").size(16.0));
+        column = column.push(iced::widget::Text::new("↑/↓ preview · Enter apply · Esc cancel").size(12.0));
+        if let Some(picker) = &self.theme_picker {
+            let highlight = theme_color(&self.theme_config, "ui_input_border", Color::from_rgb(0.4, 0.7, 0.9));
+            for (index, name) in picker.names.iter().enumerate() {
+                let mut text = iced::widget::Text::new(name.as_str());
+                if index == picker.selected {
+                    text = text.style(highlight);
+                }
+                let button = iced::widget::Button::new(text)
+                    .on_press(Message::ThemePickerSelect(index))
+                    .padding(4);
+                column = column.push(button);
+            }
+            let actions = Row::new()
+                .spacing(8)
+                .push(iced::widget::Button::new(iced::widget::Text::new("Apply")).on_press(Message::ThemePickerConfirm))
+                .push(iced::widget::Button::new(iced::widget::Text::new("Cancel")).on_press(Message::ThemePickerCancel));
+            column = column.push(actions);
+        }
+        let panel_bg = theme_color(&self.theme_config, "ui_panel_bg", Color::from_rgb(0.15, 0.15, 0.15));
+        let border = theme_color(&self.theme_config, "ui_border", Color::from_rgb(0.3, 0.3, 0.3));
+        container(scrollable(column))
+            .width(Length::Fixed(260.0))
+            .height(Length::Fill)
+            .style(move |_theme: &Theme| container::Appearance {
+                background: Some(Background::Color(panel_bg)),
+                border: Border { color: border, width: 1.0, radius: 0.0.into() },
+                ..Default::default()
+            })
+            .into()
+    }
+
+    fn render_bookmarks(&self) -> Element<'_, Message> {
+        let bookmarks = collect_bookmarks(self.layout.iter().enumerate().flat_map(|(tab_index, tab)| {
+            tab.panes.iter().enumerate().map(move |(pane_id, pane)| (tab_index, pane_id, pane.history.as_slice()))
+        }));
+        let muted = Color::from_rgb(0.6, 0.6, 0.6);
+        let mut column = Column::new().spacing(6).padding(12);
+        column = column.push(
+            Row::new()
+                .spacing(8)
+                .align_items(iced::Alignment::Center)
+                .push(container(iced::widget::Text::new("Bookmarks").size(16.0)).width(Length::Fill))
+                .push(iced::widget::Button::new(iced::widget::Text::new("×").size(12.0)).on_press(Message::ToggleBookmarks).padding([2, 6])),
+        );
+        if bookmarks.is_empty() {
+            column = column.push(iced::widget::Text::new("Pin or tag a block to bookmark it.").size(12.0).style(muted));
+        }
+        for bookmark in bookmarks {
+            let tab_title = self.layout.get(bookmark.tab).map(|tab| tab.title.as_str()).unwrap_or("");
+            let when = bookmark
+                .started_at
+                .map(|started| started.with_timezone(&chrono::Local).format("%H:%M:%S").to_string())
+                .unwrap_or_default();
+            let mut entry = Column::new()
+                .spacing(2)
+                .push(iced::widget::Text::new(format!("{}{}", if bookmark.pinned { "📌 " } else { "" }, bookmark.command)).size(13.0))
+                .push(iced::widget::Text::new(format!("{} · {}", when, tab_title)).size(11.0).style(muted));
+            if let Some(note) = &bookmark.note {
+                entry = entry.push(iced::widget::Text::new(note.clone()).size(11.0));
+            }
+            if !bookmark.tags.is_empty() {
+                let tags = bookmark.tags.iter().fold(Row::new().spacing(4), |row, tag| {
+                    row.push(
+                        iced::widget::Button::new(iced::widget::Text::new(format!("#{}", tag)).size(10.0))
+                            .on_press(Message::FilterByTag(tag.clone()))
+                            .padding([1, 4]),
+                    )
+                });
+                entry = entry.push(tags);
+            }
+            column = column.push(
+                iced::widget::Button::new(entry)
+                    .on_press(Message::JumpToBookmark(bookmark.tab, bookmark.pane, bookmark.block))
+                    .width(Length::Fill)
+                    .padding(6),
+            );
+        }
+        let panel_bg = theme_color(&self.theme_config, "ui_panel_bg", Color::from_rgb(0.15, 0.15, 0.15));
+        let border = theme_color(&self.theme_config, "ui_border", Color::from_rgb(0.3, 0.3, 0.3));
+        container(scrollable(column))
+            .width(Length::Fixed(BOOKMARKS_WIDTH))
+            .height(Length::Fill)
+            .style(move |_theme: &Theme| container::Appearance {
+                background: Some(Background::Color(panel_bg)),
+                border: Border { color: border, width: 1.0, radius: 0.0.into() },
+                ..Default::default()
+            })
+            .into()
+    }
+
+    // Runs the pane's due watches while its shell is idle. A watch whose command
+    // now trips a safety rule is stopped rather than run unattended; the returned
+    // messages say which
+    fn run_due_watches(guards: &mut SafetyGuards, pane: &mut Pane, now: Instant) -> Vec<String> {
+        let mut idle = pane.current_block.is_none() && pane.current_command.is_empty() && !pane.parser.is_alt_screen_active();
+        let mut stopped = Vec::new();
+        let mut watches = std::mem::take(&mut pane.watches);
+        watches.retain_mut(|watch| {
+            // Always poll so file events are drained even while busy
+            let due = watch.due(now);
+            let in_place = watch.cwd.as_ref().is_none_or(|cwd| *cwd == std::path::Path::new(&pane.working_directory));
+            if !(due && idle && in_place) {
+                return true;
+            }
+            let reasons = Self::safety_reasons(guards, pane, &watch.command);
+            if !reasons.is_empty() {
+                stopped.push(format!("Stopped watching '{}': {}", watch.command, reasons.join(", ")));
+                return false;
+            }
+            if let Ok(mut pty) = pane.pty.try_lock() {
+                let cmd = format!("{}\r", watch.command);
+                pty.writer().write_all(cmd.as_bytes()).ok();
+                pty.writer().flush().ok();
+                watch.started();
+                idle = false;
+            }
+            true
+        });
+        pane.watches = watches;
+        stopped
+    }
+
+    // Starts a watch on the block the watch form is open on. A block that was
+    // watched before keeps its id, so new runs join its existing stack
+    fn start_watch(pane: &mut Pane, create: impl FnOnce(u64, &Block, &WatchForm) -> Result<Watch, String>) {
+        let Some(form) = pane.watch_form.as_mut() else {
+            return;
+        };
+        let Some(watch_id) = pane.history.get(form.block_index).map(|block| block.watch_id) else {
+            pane.watch_form = None;
+            return;
+        };
+        let id = watch_id.unwrap_or_else(|| next_watch_id(&pane.history, &pane.watches));
+        let block = &mut pane.history[form.block_index];
+        match create(id, block, form) {
+            Ok(watch) => {
+                info!("Watching '{}' {}", watch.command, watch.trigger.label());
+                block.watch_id = Some(id);
+                pane.watches.retain(|existing| existing.id != id);
+                pane.watches.push(watch);
+                pane.watch_form = None;
+            }
+            Err(err) => form.error = Some(err),
+        }
+    }
+
+    /// Holds `command` for confirmation if it trips a safety rule in the active
+    /// pane; returns whether it was held.
+    fn hold_if_dangerous(&mut self, command: String, run: PendingRun) -> bool {
+        let tab_index = self.active_tab;
+        let Some((pane_index, pane)) = self.layout.get(tab_index).and_then(|tab| tab.panes.get(tab.active_pane).map(|pane| (tab.active_pane, pane))) else {
+            return false;
+        };
+        let reasons = Self::safety_reasons(&mut self.safety_guards, pane, &command);
+        if reasons.is_empty() {
+            return false;
+        }
+        self.pending_command = Some(PendingCommand { tab: tab_index, pane: pane_index, command, reasons, run });
+        true
+    }
+
+    /// Labels of the safety rules `command` trips in `pane`.
+    fn safety_reasons(guards: &mut SafetyGuards, pane: &Pane, command: &str) -> Vec<String> {
+        if command.trim().is_empty() {
+            return Vec::new();
+        }
+        let kube_context = guards.kube_context();
+        let (guard, errors) = guards.guard(std::path::Path::new(&pane.working_directory));
+        for err in errors {
+            warn!("Skipping safety rule {}", err);
+        }
+        let context = CommandContext {
+            git_branch: pane.current_block.as_ref().or(pane.history.last()).and_then(|block| block.git_branch.clone()),
+            kube_context,
+        };
+        guard.check(command, &context)
+    }
+
+    // Sends the pane's editor contents to its shell and clears the editor
+    fn submit_command(&mut self, tab: usize, pane: usize) {
+        let Some(pane) = self.layout.get_mut(tab).and_then(|tab| tab.panes.get_mut(pane)) else {
+            return;
+        };
+        if let Ok(mut pty) = pane.pty.try_lock() {
+            let cmd = submission(&pane.current_command, pane.parser.screen().bracketed_paste());
+            if let Err(err) = pty.writer().write_all(cmd.as_bytes()) {
+                error!("Failed to write to PTY: {}", err);
+            }
+            if let Err(err) = pty.writer().flush() {
+                error!("Failed to flush PTY writer: {}", err);
+            }
+            pane.current_command.clear();
+            pane.editor.reset();
+            pane.suggestion = None;
+        }
+    }
+
+    // Opens a file with the configured editor command, either launched in the
+    // background or typed into the pane it was clicked in when that is idle
+    fn open_in_editor(&mut self, (tab, pane): (usize, usize), path: &std::path::Path, line: Option<u32>, column: Option<u32>, cwd: Option<&std::path::Path>) {
+        let message = match self.app_config.file_links.open_action(path, line, column) {
+            OpenAction::Launch(command) => match launch(&command, cwd) {
+                Ok(()) => return,
+                Err(err) => format!("Failed to launch '{}': {}", command, err),
+            },
+            OpenAction::Pane(command) => {
+                let Some(pane) = self.layout.get_mut(tab).and_then(|tab| tab.panes.get_mut(pane)) else {
+                    return;
+                };
+                // Don't type over a running program
+                if pane.current_block.is_none() && !pane.parser.is_alt_screen_active() {
+                    if let Ok(mut pty) = pane.pty.try_lock() {
+                        let cmd = format!("{}\r", command);
+                        pty.writer().write_all(cmd.as_bytes()).ok();
+                        pty.writer().flush().ok();
+                    }
+                    return;
+                }
+                "Finish the running command before opening the editor".to_string()
+            }
+        };
+        self.export_toast = Some(ExportToast { message, expires_at: Utc::now() + chrono::Duration::seconds(6) });
+    }
+
+    fn rerun_block(&self, tab: usize, pane: usize, index: usize) {
+        let Some(pane) = self.layout.get(tab).and_then(|tab| tab.panes.get(pane)) else {
+            return;
+        };
+        if let Some(block) = pane.history.get(index) {
+            if let Ok(mut pty) = pane.pty.try_lock() {
+                let cmd = format!("{}\r", block.command);
+                pty.writer().write_all(cmd.as_bytes()).ok();
+                pty.writer().flush().ok();
+            }
+        }
+    }
+
+    // Asks before a command flagged by the safety guard is sent to the shell
+    fn render_pending_command<'a>(&'a self, pending: &'a PendingCommand) -> Element<'a, Message> {
+        let warning = Color::from_rgb(0.95, 0.45, 0.35);
+        let mut column = Column::new()
+            .spacing(10)
+            .padding(20)
+            .push(iced::widget::Text::new("Run this command?").size(18.0))
+            .push(iced::widget::Text::new(format!("❯ {}", pending.command)).font(self.renderer.font()).size(13.0));
+        for reason in &pending.reasons {
+            column = column.push(iced::widget::Text::new(format!("⚠ {}", reason)).size(12.0).style(warning));
+        }
+        column = column
+            .push(iced::widget::Text::new("Rules live in ~/.config/tant/safety_rules.json and .tant/safety_rules.json.").size(11.0).style(Color::from_rgb(0.6, 0.6, 0.6)))
+            .push(
+                Row::new()
+                    .spacing(8)
+                    .push(iced::widget::Button::new(iced::widget::Text::new("Cancel").size(12.0)).on_press(Message::CancelPendingCommand))
+                    .push(iced::widget::Button::new(iced::widget::Text::new("Run anyway").size(12.0).style(warning)).on_press(Message::ConfirmPendingCommand)),
+            );
+        container(column)
+            .center_x()
+            .center_y()
+            .width(Length::Fill)
+            .height(Length::Fill)
+            .style(|_theme: &Theme| container::Appearance {
+                background: Some(Background::Color(Color::from_rgb(0.08, 0.09, 0.11))),
+                border: Border {
+                    radius: 8.0.into(),
+                    width: 1.0,
+                    color: Color::from_rgb(0.2, 0.2, 0.2),
+                },
+                ..Default::default()
+            })
+            .into()
+    }
+
+    fn run_in_active_pane(&mut self, command: String) {
+        if !self.hold_if_dangerous(command.clone(), PendingRun::Typed) {
+            self.type_command(self.active_tab, self.layout.get(self.active_tab).map_or(0, |tab| tab.active_pane), &command);
+        }
+    }
+
+    // Types `command` and Enter into a pane's shell
+    fn type_command(&self, tab: usize, pane: usize, command: &str) {
+        let Some(pane) = self.layout.get(tab).and_then(|tab| tab.panes.get(pane)) else {
+            return;
+        };
+        if let Ok(mut pty) = pane.pty.try_lock() {
+            let cmd = format!("{}\r", command);
+            if let Err(err) = pty.writer().write_all(cmd.as_bytes()) {
+                error!("Failed to write to PTY: {}", err);
+            }
+            if let Err(err) = pty.writer().flush() {
+                error!("Failed to flush PTY writer: {}", err);
+            }
+            info!("Run command: {}", command);
+        }
+    }
+
+    // Name prompt for saving the session or the tab as a layout template, listing
+    // the names it could replace
+    fn render_name_form<'a>(&'a self, prompt: NamePrompt, name: &'a str) -> Element<'a, Message> {
+        let muted = Color::from_rgb(0.6, 0.6, 0.6);
+        let (title, current, placeholder, valid, hint) = match prompt {
+            NamePrompt::SessionAs => {
+                let valid = session_name(name);
+                let hint = match &valid {
+                    Ok(name) if self.saved_sessions.contains(name) => format!("Replaces the saved session '{}'", name),
+                    Ok(_) => format!("Sessions are saved in {}", paths::state_dir().join("sessions").display()),
+                    Err(err) => err.clone(),
+                };
+                ("Save session as", format!("Currently '{}'", self.session_name), "work, oncall, ...", valid.is_ok(), hint)
+            }
+            NamePrompt::LayoutTemplate => {
+                let valid = template_name(name);
+                let hint = match &valid {
+                    Ok(name) if self.layout_files.contains(&layouts_dir().join(format!("{}.toml", name))) => format!("Replaces the layout '{}'", name),
+                    Ok(_) => format!("Layouts are saved in {}", layouts_dir().display()),
+                    Err(err) => err.clone(),
+                };
+                let tab_title = self.layout.get(self.active_tab).map(|tab| tab.title.as_str()).unwrap_or_default();
+                ("Save layout as template", format!("Splits, directories and profiles of '{}', without history", tab_title), "dev, morning, ...", valid.is_ok(), hint)
+            }
+        };
+        let mut save = iced::widget::Button::new(iced::widget::Text::new("Save").size(12.0));
+        if valid {
+            save = save.on_press(Message::SubmitNameInput);
+        }
+        let column = Column::new()
+            .spacing(10)
+            .padding(20)
+            .push(iced::widget::Text::new(title).size(18.0))
+            .push(iced::widget::Text::new(current).size(12.0).style(muted))
+            .push(TextInput::new(placeholder, name).on_input(Message::UpdateNameInput).on_submit(Message::SubmitNameInput).padding(6))
+            .push(iced::widget::Text::new(hint).size(11.0).style(muted))
+            .push(Row::new().spacing(8).push(save).push(iced::widget::Button::new(iced::widget::Text::new("Cancel").size(12.0)).on_press(Message::CancelNameInput)));
+        container(column)
+            .center_x()
+            .center_y()
+            .width(Length::Fill)
+            .height(Length::Fill)
+            .style(|_theme: &Theme| container::Appearance {
+                background: Some(Background::Color(Color::from_rgb(0.08, 0.09, 0.11))),
+                border: Border {
+                    radius: 8.0.into(),
+                    width: 1.0,
+                    color: Color::from_rgb(0.2, 0.2, 0.2),
+                },
+                ..Default::default()
+            })
+            .into()
+    }
+
+    // Argument prompts for a workflow, with a live preview of the command
+    fn render_workflow_form<'a>(&'a self, form: &'a WorkflowForm) -> Element<'a, Message> {
+        let muted = Color::from_rgb(0.6, 0.6, 0.6);
+        let mut column = Column::new()
+            .spacing(10)
+            .padding(20)
+            .push(iced::widget::Text::new(form.workflow.name.as_str()).size(18.0));
+        if !form.workflow.description.is_empty() {
+            column = column.push(iced::widget::Text::new(form.workflow.description.as_str()).size(12.0).style(muted));
+        }
+        for (index, arg) in form.arguments.iter().enumerate() {
+            let mut field = Column::new().spacing(4).push(iced::widget::Text::new(arg.name.as_str()).size(13.0));
+            if !arg.description.is_empty() {
+                field = field.push(iced::widget::Text::new(arg.description.as_str()).size(11.0).style(muted));
+            }
+            if arg.choices.is_empty() {
+                field = field.push(
+                    TextInput::new(arg.default.as_deref().unwrap_or(""), &form.values[index])
+                        .on_input(move |value| Message::UpdateWorkflowArg(index, value))
+                        .on_submit(Message::SubmitWorkflow)
+                        .font(self.renderer.font())
+                        .padding(6),
+                );
+            } else {
+                let choices = arg.choices.iter().fold(Row::new().spacing(6), |row, choice| {
+                    let mut label = iced::widget::Text::new(choice.as_str()).size(12.0);
+                    if form.values[index] == *choice {
+                        label = label.style(Color::from_rgb(0.4, 0.7, 0.9));
+                    }
+                    row.push(iced::widget::Button::new(label).on_press(Message::UpdateWorkflowArg(index, choice.clone())).padding([3, 8]))
+                });
+                field = field.push(choices);
+            }
+            column = column.push(field);
+        }
+        let command = form.command();
+        let preview = match &command {
+            Ok(command) => iced::widget::Text::new(format!("❯ {}", command)).font(self.renderer.font()).size(13.0),
+            Err(err) => iced::widget::Text::new(err.clone()).size(12.0).style(muted),
+        };
+        let mut run = iced::widget::Button::new(iced::widget::Text::new("Run").size(12.0));
+        if command.is_ok() {
+            run = run.on_press(Message::SubmitWorkflow);
+        }
+        column = column
+            .push(preview)
+            .push(Row::new().spacing(8).push(run).push(iced::widget::Button::new(iced::widget::Text::new("Cancel").size(12.0)).on_press(Message::CloseWorkflow)));
+        container(scrollable(column))
+            .center_x()
+            .center_y()
+            .width(Length::Fill)
+            .height(Length::Fill)
+            .style(|_theme: &Theme| container::Appearance {
+                background: Some(Background::Color(Color::from_rgb(0.08, 0.09, 0.11))),
+                border: Border {
+                    radius: 8.0.into(),
+                    width: 1.0,
+                    color: Color::from_rgb(0.2, 0.2, 0.2),
+                },
+                ..Default::default()
+            })
+            .into()
+    }
+
+    // Diffs the two selected blocks of the active pane, or says why it can't
+    fn diff_selected_blocks(&mut self) {
+        let selected: Vec<usize> = self
+            .active_pane_mut()
+            .map(|pane| pane.history.iter().enumerate().filter(|(_, block)| block.selected).map(|(index, _)| index).collect())
+            .unwrap_or_default();
+        match selected.as_slice() {
+            [first, second] => self.open_block_diff(*first, *second),
+            _ => {
+                self.export_toast = Some(ExportToast {
+                    message: "Select exactly two blocks to diff.".to_string(),
+                    expires_at: Utc::now() + chrono::Duration::seconds(6),
+                });
+            }
+        }
+    }
+
+    // Diffs two blocks of the active pane
+    fn open_block_diff(&mut self, first: usize, second: usize) {
+        let settings = &self.app_config.diff;
+        let tab = self.layout.get(self.active_tab);
+        if let Some(pane) = tab.and_then(|tab| tab.panes.get(tab.active_pane)) {
+            self.block_diff = BlockDiffView::new(&pane.history, first, second, settings);
+        }
+        self.report_diff_errors();
+    }
+
+    // Normalisers that don't compile are skipped rather than failing the diff
+    fn report_diff_errors(&mut self) {
+        let Some(diff) = &self.block_diff else { return };
+        for err in &diff.errors {
+            warn!("Skipping diff normaliser {}", err);
+        }
+        if let Some(err) = diff.errors.first() {
+            self.export_toast = Some(ExportToast {
+                message: format!("Skipping diff normaliser {}", err),
+                expires_at: Utc::now() + chrono::Duration::seconds(6),
+            });
+        }
+    }
+
+    fn render_block_diff<'a>(&'a self, diff: &'a BlockDiffView) -> Element<'a, Message> {
+        let muted = Color::from_rgb(0.6, 0.6, 0.6);
+        let added_bg = Color::from_rgba(0.2, 0.6, 0.3, 0.25);
+        let removed_bg = Color::from_rgba(0.8, 0.25, 0.25, 0.25);
+        let font = self.renderer.font();
+        let size = self.theme_config.font_size - 2.0;
+        let (added, removed) = diff_stats(&diff.lines);
+
+        // Line-number gutter plus text, tinted by kind
+        let cell = |line: Option<&DiffLine>| -> Element<'a, Message> {
+            let Some(line) = line else {
+                return container(iced::widget::Text::new("")).width(Length::Fill).into();
+            };
+            let (marker, background) = match line.kind {
+                DiffKind::Same => (" ", None),
+                DiffKind::Removed => ("-", Some(removed_bg)),
+                DiffKind::Added => ("+", Some(added_bg)),
+            };
+            let number = |n: Option<usize>| n.map(|n| format!("{:>5}", n)).unwrap_or_else(|| " ".repeat(5));
+            let gutter = if diff.side_by_side {
+                number(line.old.or(line.new))
+            } else {
+                format!("{} {}", number(line.old), number(line.new))
+            };
+            container(
+                Row::new()
+                    .spacing(8)
+                    .push(iced::widget::Text::new(format!("{} {}", gutter, marker)).font(font).size(size).style(muted))
+                    .push(iced::widget::Text::new(line.text.clone()).font(font).size(size)),
+            )
+            .width(Length::Fill)
+            .style(move |_theme: &Theme| container::Appearance { background: background.map(Background::Color), ..Default::default() })
+            .into()
+        };
+
+        let rows: Vec<Element<'a, Message>> = if diff.side_by_side {
+            let rows = side_by_side(&diff.lines);
+            let changed: Vec<bool> = rows.iter().map(|row| row.left.as_ref().map(|line| line.kind) != Some(DiffKind::Same)).collect();
+            self.fold_unchanged(&changed, |index| Row::new().spacing(12).push(cell(rows[index].left.as_ref())).push(cell(rows[index].right.as_ref())).into())
+        } else {
+            let changed: Vec<bool> = diff.lines.iter().map(|line| line.kind != DiffKind::Same).collect();
+            self.fold_unchanged(&changed, |index| cell(diff.lines.get(index)))
+        };
+
+        let header = Row::new()
+            .spacing(8)
+            .align_items(iced::Alignment::Center)
+            .push(container(iced::widget::Text::new(format!("Diff: {}", diff.command)).size(16.0)).width(Length::Fill))
+            .push(iced::widget::Text::new(format!("+{} −{}", added, removed)).size(12.0).style(muted))
+            .push(
+                iced::widget::Checkbox::new("Ignore timestamps and numbers", diff.normalise)
+                    .on_toggle(|_| Message::ToggleDiffNormalise)
+                    .size(14.0)
+                    .text_size(12.0),
+            )
+            .push(
+                iced::widget::Button::new(iced::widget::Text::new(if diff.side_by_side { "Unified" } else { "Side by side" }).size(12.0))
+                    .on_press(Message::ToggleDiffSideBySide)
+                    .padding([2, 8]),
+            )
+            .push(iced::widget::Button::new(iced::widget::Text::new("×").size(12.0)).on_press(Message::CloseDiff).padding([2, 6]));
+        let sides = Row::new()
+            .spacing(12)
+            .push(container(iced::widget::Text::new(format!("− {}", diff.old_label)).size(11.0).style(muted)).width(Length::Fill))
+            .push(container(iced::widget::Text::new(format!("+ {}", diff.new_label)).size(11.0).style(muted)).width(Length::Fill));
+        let body: Element<'a, Message> = if added + removed == 0 {
+            iced::widget::Text::new("Outputs are identical.").size(12.0).style(muted).into()
+        } else {
+            scrollable(Column::with_children(rows).spacing(1)).height(Length::Fill).into()
+        };
+        let panel_bg = theme_color(&self.theme_config, "ui_panel_bg", Color::from_rgb(0.15, 0.15, 0.15));
+        container(Column::new().spacing(8).padding(12).push(header).push(sides).push(body))
+            .width(Length::Fill)
+            .height(Length::Fill)
+            .style(move |_theme: &Theme| container::Appearance {
+                background: Some(Background::Color(panel_bg)),
+                ..Default::default()
+            })
+            .into()
+    }
+
+    // Rows around changes, with each long unchanged run folded into a marker
+    fn fold_unchanged<'a>(&self, changed: &[bool], mut row: impl FnMut(usize) -> Element<'a, Message>) -> Vec<Element<'a, Message>> {
+        let muted = Color::from_rgb(0.5, 0.5, 0.5);
+        let mut rows = Vec::new();
+        let mut shown = 0;
+        for range in context_ranges(changed, CONTEXT_LINES) {
+            if range.start > shown {
+                rows.push(iced::widget::Text::new(hidden_label(range.start - shown)).size(11.0).style(muted).into());
+            }
+            shown = range.end;
+            rows.extend(range.map(&mut row));
+        }
+        if changed.len() > shown && shown > 0 {
+            rows.push(iced::widget::Text::new(hidden_label(changed.len() - shown)).size(11.0).style(muted).into());
+        }
+        rows
+    }
+
+    // Converts a theme file into a tant theme in the themes directory and selects
+    // it, so hot reload, light/dark switching and restarts keep it
+    fn import_theme_file(&mut self, path: &std::path::Path) -> Result<String, String> {
+        let theme = ThemeConfig::import_file(path)?;
+        let name = path.file_stem().map(|stem| stem.to_string_lossy().to_string()).ok_or_else(|| format!("No theme name in {}", path.display()))?;
+        let target = Self::themes_dir().join(format!("{}.json", name));
+        versioning::save(&versioning::THEME, &target, &theme)?;
+        self.theme_manager.reload_path(&target);
+        self.apply_theme(&name);
+        self.clear_render_caches();
+        self.choose_theme(name.clone())?;
+        info!("Imported theme '{}' from {}", name, path.display());
+        Ok(name)
+    }
+
+    // Saves a theme choice. With automatic switching it replaces the theme for the
+    // current scheme
+    fn choose_theme(&mut self, name: String) -> Result<(), String> {
+        match (self.auto_theme_names().is_some(), self.system_scheme) {
+            (true, Some(ColorScheme::Light)) => self.app_config.light_theme = Some(name),
+            (true, Some(ColorScheme::Dark)) => self.app_config.dark_theme = Some(name),
+            _ => self.app_config.theme = name,
+        }
+        self.save_app_config().map_err(|err| err.to_string())
+    }
+
+    fn plan_limits(plan: PlanTier) -> PlanLimits {
+        match plan {
+            PlanTier::Free => PlanLimits {
+                daily_messages: 50,
+                daily_chars: 20_000,
+                max_context_chars: 6_000,
+                allow_streaming: false,
+            },
+            PlanTier::Pro => PlanLimits {
+                daily_messages: 500,
+                daily_chars: 200_000,
+                max_context_chars: 24_000,
+                allow_streaming: true,
+            },
+            PlanTier::Team => PlanLimits {
+                daily_messages: 2_000,
+                daily_chars: 1_000_000,
+                max_context_chars: 64_000,
+                allow_streaming: true,
+            },
+        }
+    }
+
+    fn usage_ledger_path() -> std::path::PathBuf {
+        paths::data_dir().join("usage_ledger.json")
+    }
+
+    fn load_usage_ledger(problems: &mut Vec<String>, kept_files: &mut HashSet<std::path::PathBuf>) -> UsageLedger {
+        versioning::load(&versioning::USAGE_LEDGER, &Self::usage_ledger_path()).unwrap_or_else(|err| {
+            kept_files.extend(err.kept().map(std::path::Path::to_path_buf));
+            problems.push(err.to_string());
+            None
+        }).unwrap_or_else(|| UsageLedger { entries: Vec::new(), last_reset: Utc::now() })
+    }
+
+    fn save_usage_ledger(&self) -> Result<(), Box<dyn std::error::Error>> {
+        self.check_not_kept(&Self::usage_ledger_path())?;
+        versioning::save(&versioning::USAGE_LEDGER, &Self::usage_ledger_path(), &self.usage_ledger)?;
+        Ok(())
+    }
+
+
+    fn reset_usage_if_needed(&mut self) {
+        let now = Utc::now();
+        if now.date_naive() != self.usage_ledger.last_reset.date_naive() {
+            self.usage_ledger.entries.clear();
+            self.usage_ledger.last_reset = now;
+        }
+    }
+
+    fn compute_usage_snapshot(&self, limits: &PlanLimits) -> UsageSnapshot {
+        let used_messages = self.usage_ledger.entries.len() as u32;
+        let used_chars = self
+            .usage_ledger
+            .entries
+            .iter()
+            .map(|entry| entry.request_chars as u32 + entry.response_chars as u32)
+            .sum::<u32>();
+        UsageSnapshot {
+            used_messages,
+            used_chars,
+            remaining_messages: limits.daily_messages.saturating_sub(used_messages),
+            remaining_chars: limits.daily_chars.saturating_sub(used_chars),
+        }
+    }
+
+    fn can_send_ai_request(&self) -> bool {
+        self.usage_snapshot.remaining_messages > 0 && self.usage_snapshot.remaining_chars > 0 && self.ai_settings.enabled
+    }
+
+    fn build_ai_prompt(&self, action: &str, user_text: &str, context: &str) -> AiRequest {
+        let system = match action {
+            "explain_error" => "Explain the error and likely cause in concise terms.",
+            "suggest_fix" => "Suggest a practical fix and a command to try.",
+            "generate_command" => "Generate a command for the user's request.",
+            "summarize_output" => "Summarize the command output briefly.",
+            _ => "Respond concisely.",
+        };
+        let model = match self.ai_settings.provider.as_str() {
+            "openai" => "gpt-4o-mini",
+            "anthropic" => "claude-3-5-sonnet-20240620",
+            "ollama" => "llama3.2:latest",
+            _ => "gpt-4o-mini",
+        };
+        let user = if user_text.trim().is_empty() {
+            format!("{}", context)
+        } else {
+            format!("{}\n\nUser request:\n{}", context, user_text)
+        };
+        AiRequest {
+            provider: self.ai_settings.provider.clone(),
+            model: model.to_string(),
+            system: system.to_string(),
+            user,
+        }
+    }
+
+
+    fn build_ai_request(&self, prompt: &str, action: &str) -> AiRequest {
+        self.build_ai_prompt(action, "", prompt)
+    }
+
+    fn execute_palette_action(&mut self, action: PaletteAction) {
+        match action {
+            PaletteAction::SplitPaneHorizontal => {
+                self.split_active_pane(Axis::Horizontal);
+            }
+            PaletteAction::SplitPaneVertical => {
+                self.split_active_pane(Axis::Vertical);
+            }
+            PaletteAction::ClosePane => {
+                self.close_active_pane();
+            }
+            PaletteAction::SwitchTab(index) => {
+                if index < self.layout.len() {
+                    self.active_tab = index;
+                }
+            }
+            PaletteAction::RunPinnedCommand(index) => {
+                let pinned = self.active_pane_mut().and_then(|pane| pane.history.get(index)).filter(|block| block.pinned).map(|block| block.command.clone());
+                if let Some(command) = pinned {
+                    if !self.hold_if_dangerous(command, PendingRun::Block(index)) {
+                        self.rerun_block(self.active_tab, self.layout.get(self.active_tab).map_or(0, |tab| tab.active_pane), index);
+                    }
+                }
+            }
+            PaletteAction::ToggleAi => {
+                self.ai_settings.enabled = !self.ai_settings.enabled;
+            }
+            PaletteAction::ToggleAiPanel => {
+                if let Some(tab) = self.layout.get_mut(self.active_tab) {
+                    if let Some(pane) = tab.panes.get_mut(tab.active_pane) {
+                        pane.ai_panel_open = !pane.ai_panel_open;
+                    }
+                }
+            }
+            PaletteAction::SetAiContextScope(scope) => {
+                if let Some(tab) = self.layout.get_mut(self.active_tab) {
+                    if let Some(pane) = tab.panes.get_mut(tab.active_pane) {
+                        pane.ai_context_scope = scope;
+                    }
+                }
+            }
+            PaletteAction::ApplyAiTemplate(template_id) => {
+                let pane_id = self.layout
                     .get(self.active_tab)
                     .map(|tab| tab.active_pane)
                     .unwrap_or(0);
@@ -1474,15 +2968,98 @@ impl Tant {
                     error!("Failed to import theme: {}", e);
                 }
             }
+            PaletteAction::ImportThemeFile(path) => {
+                let message = match self.import_theme_file(&path) {
+                    Ok(name) => format!("Imported theme '{}'", name),
+                    Err(err) => format!("Failed to import theme {}: {}", path.display(), err),
+                };
+                self.export_toast = Some(ExportToast {
+                    message,
+                    expires_at: Utc::now() + chrono::Duration::seconds(6),
+                });
+            }
+            PaletteAction::SelectTheme => {
+                self.open_theme_picker();
+            }
+            PaletteAction::SetPaneProfile(profile) => {
+                if let Some(tab) = self.layout.get_mut(self.active_tab) {
+                    if let Some(pane) = tab.panes.get_mut(tab.active_pane) {
+                        pane.profile = Some(profile);
+                    }
+                }
+                self.refresh_profile_themes();
+            }
+            PaletteAction::ClearPaneProfile => {
+                if let Some(tab) = self.layout.get_mut(self.active_tab) {
+                    if let Some(pane) = tab.panes.get_mut(tab.active_pane) {
+                        pane.profile = None;
+                    }
+                }
+                self.refresh_profile_themes();
+            }
             PaletteAction::OpenBilling => {
                 self.show_billing = true;
             }
+            PaletteAction::ToggleBookmarks => {
+                self.bookmarks_open = !self.bookmarks_open;
+            }
+            PaletteAction::DiffSelectedBlocks => {
+                self.diff_selected_blocks();
+            }
+            PaletteAction::RunWorkflow(index) => {
+                if let Some(workflow) = self.workflows.get(index).cloned() {
+                    let form = WorkflowForm::new(workflow);
+                    if form.arguments.is_empty() {
+                        self.run_in_active_pane(form.workflow.command);
+                    } else {
+                        self.workflow_form = Some(form);
+                    }
+                }
+            }
+            PaletteAction::SaveSessionAs => {
+                self.name_input = Some((NamePrompt::SessionAs, String::new()));
+            }
+            PaletteAction::SwitchSession(name) => {
+                self.switch_session(name);
+            }
+            PaletteAction::DeleteSession(name) => {
+                let message = match self.sessions.delete(&name) {
+                    Ok(()) => format!("Deleted session '{}'", name),
+                    Err(err) => format!("Failed to delete session '{}': {}", name, err),
+                };
+                self.export_toast = Some(ExportToast {
+                    message,
+                    expires_at: Utc::now() + chrono::Duration::seconds(6),
+                });
+            }
+            PaletteAction::OpenLayout(path) => {
+                let message = match self.open_layout(&path) {
+                    Ok(()) => format!("Opened layout {}", path.display()),
+                    Err(err) => format!("Failed to open layout: {}", err),
+                };
+                self.export_toast = Some(ExportToast {
+                    message,
+                    expires_at: Utc::now() + chrono::Duration::seconds(6),
+                });
+            }
+            PaletteAction::SaveLayoutTemplate => {
+                let title = self.layout.get(self.active_tab).map(|tab| tab.title.clone()).unwrap_or_default();
+                self.name_input = Some((NamePrompt::LayoutTemplate, title));
+            }
+            PaletteAction::ImportShellHistory => {
+                let results = import_shell_history(&mut self.history_store, &ShellKind::ALL);
+                let message = summary_message(&results);
+                info!("{}", message);
+                self.export_toast = Some(ExportToast {
+                    message,
+                    expires_at: Utc::now() + chrono::Duration::seconds(6),
+                });
+            }
         }
     }
 
     fn render_command_palette(&self) -> Element<Message> {
-        let actions = self.get_available_actions();
-        let filtered_actions: Vec<_> = actions.iter()
+        let filtered_actions: Vec<_> = self.palette_actions.iter()
             .filter(|(name, _)| name.to_lowercase().contains(&self.palette_query.to_lowercase()))
             .collect();
 
@@ -1497,7 +3074,7 @@ impl Tant {
         // Action list
         for (index, (name, action)) in filtered_actions.iter().enumerate() {
             let is_selected = index == self.palette_selected;
-            let mut text = iced::widget::Text::new(*name);
+            let mut text = iced::widget::Text::new(name.as_str());
             if is_selected {
                 text = text.style(Color::from_rgb(0.4, 0.7, 0.9));
             }
@@ -1613,8 +3190,9 @@ impl Tant {
             .into()
     }
 
-    fn get_available_actions(&self) -> Vec<(&str, PaletteAction)> {
-        let mut actions = vec![
+    // Everything the palette offers, built when it opens
+    fn get_available_actions(&self) -> Vec<(String, PaletteAction)> {
+        let mut actions: Vec<(String, PaletteAction)> = [
             ("Split Pane Horizontal", PaletteAction::SplitPaneHorizontal),
             ("Split Pane Vertical", PaletteAction::SplitPaneVertical),
             ("Close Pane", PaletteAction::ClosePane),
@@ -1632,11 +3210,65 @@ impl Tant {
             ("AI Context: All", PaletteAction::SetAiContextScope(AiContextScope::EntireSession)),
             ("Export Theme", PaletteAction::ExportTheme),
             ("Import Theme", PaletteAction::ImportTheme),
-        ];
+            ("Select Theme...", PaletteAction::SelectTheme),
+            ("Import Shell History", PaletteAction::ImportShellHistory),
+            ("Toggle Bookmarks", PaletteAction::ToggleBookmarks),
+            ("Diff Selected Blocks", PaletteAction::DiffSelectedBlocks),
+        ]
+        .into_iter()
+        .map(|(label, action)| (label.to_string(), action))
+        .collect();
+
+        for (index, workflow) in self.workflows.iter().enumerate() {
+            let kind = match workflow.source {
+                WorkflowSource::Team => "Team Workflow",
+                WorkflowSource::Personal => "Workflow",
+            };
+            let mut label = format!("{}: {}", kind, workflow.name);
+            if !workflow.description.is_empty() {
+                label.push_str(&format!(" · {}", workflow.description));
+            }
+            for tag in &workflow.tags {
+                label.push_str(&format!(" #{}", tag));
+            }
+            actions.push((label, PaletteAction::RunWorkflow(index)));
+        }
+
+        // Add session actions; the open session can't be switched to or deleted
+        actions.push(("Save Session As...".to_string(), PaletteAction::SaveSessionAs));
+        for name in &self.saved_sessions {
+            if *name != self.session_name {
+                actions.push((format!("Switch Session: {}", name), PaletteAction::SwitchSession(name.clone())));
+                actions.push((format!("Delete Session: {}", name), PaletteAction::DeleteSession(name.clone())));
+            }
+        }
+
+        // Add layout actions
+        actions.push(("Save Layout As Template...".to_string(), PaletteAction::SaveLayoutTemplate));
+        for path in &self.layout_files {
+            let name = path.file_stem().map(|stem| stem.to_string_lossy().to_string()).unwrap_or_default();
+            actions.push((format!("Open Layout: {}", name), PaletteAction::OpenLayout(path.clone())));
+        }
+
+        // Add pane profile actions
+        let mut profiles: Vec<&String> = self.app_config.profiles.keys().collect();
+        profiles.sort();
+        for profile in profiles {
+            actions.push((format!("Set Pane Profile: {}", profile), PaletteAction::SetPaneProfile(profile.clone())));
+        }
+        if self.layout.get(self.active_tab).and_then(|tab| tab.panes.get(tab.active_pane)).is_some_and(|pane| pane.profile.is_some()) {
+            actions.push(("Clear Pane Profile".to_string(), PaletteAction::ClearPaneProfile));
+        }
+
+        // Add theme files that can be imported
+        for (path, format) in list_importable_themes(&Self::themes_dir()) {
+            let file_name = path.file_name().map(|name| name.to_string_lossy().to_string()).unwrap_or_default();
+            actions.push((format!("Import Theme: {} ({})", file_name, format.label()), PaletteAction::ImportThemeFile(path)));
+        }
 
         // Add switch tab actions
         for i in 0..self.layout.len() {
-            actions.push((Box::leak(format!("Switch to Tab {}", i + 1).into_boxed_str()), PaletteAction::SwitchTab(i)));
+            actions.push((format!("Switch to Tab {}", i + 1), PaletteAction::SwitchTab(i)));
         }
 
         // Add pinned commands
@@ -1644,7 +3276,7 @@ impl Tant {
             if let Some(pane) = tab.panes.get(tab.active_pane) {
                 for (index, block) in pane.history.iter().enumerate() {
                     if block.pinned {
-                        actions.push((Box::leak(format!("Run: {}", block.command).into_boxed_str()), PaletteAction::RunPinnedCommand(index)));
+                        actions.push((format!("Run: {}", block.command), PaletteAction::RunPinnedCommand(index)));
                     }
                 }
             }
@@ -1658,41 +3290,48 @@ impl Application for Tant {
     type Executor = iced::executor::Default;
     type Message = Message;
     type Theme = Theme;
-    type Flags = ();
+    /// A layout file passed with `--layout`, opened in place of the last session.
+    type Flags = Option<LayoutFile>;
 
-    fn new(_flags: ()) -> (Self, Command<Message>) {
+    fn new(flags: Option<LayoutFile>) -> (Self, Command<Message>) {
         let shell = std::env::var("SHELL").unwrap_or_else(|_| "/bin/bash".to_string());
-        let (layout, active_tab) = if let Ok(saved_layout) = Self::load_session() {
-            // Restore from saved session
-            let mut tabs = vec![];
-            for saved_tab in saved_layout.tabs {
-                let mut panes = vec![];
-                for saved_pane in saved_tab.panes {
-                    let pane = Pane::new(&shell, Some(saved_pane.working_directory.clone())).unwrap();
-                    // Restore history and current_command
-                    let mut pane = pane;
-                    pane.history = saved_pane.history;
-                    pane.current_command = saved_pane.current_command;
-                    pane.working_directory = saved_pane.working_directory;
-                    pane.title = saved_pane.title;
-                    pane.scroll_offset = saved_pane.scroll_offset;
-                    panes.push(pane);
-                }
-                let tab = Tab { root: saved_tab.root, panes, active_pane: saved_tab.active_pane, title: saved_tab.title };
-                tabs.push(tab);
-            }
-            (tabs, saved_layout.active_tab)
-        } else {
-            // Default: single pane
-            let pane = Pane::new(&shell, None).unwrap();
-            let root = LayoutNode::Leaf { pane_id: 0 };
-            let tab = Tab { root, panes: vec![pane], active_pane: 0, title: "Tab 1".to_string() };
-            (vec![tab], 0)
-        };
-        let renderer = TerminalRenderer::new();
-        let app_config = Self::load_app_config();
+        Self::adopt_legacy_files();
+        // Files that fail to load are backed up and reported once the window is up
+        let mut problems = Vec::new();
+        let mut kept_files = HashSet::new();
+        let app_config = Self::load_app_config(&mut problems, &mut kept_files);
+        let sessions = SessionStore::open();
+        let mut session_name = sessions.current();
+        let from_file = flags.and_then(|file| match Self::layout_tabs(&file, &shell) {
+            Ok(tabs) => {
+                // Autosave must not overwrite the session that was left unopened
+                session_name = sessions.unused_name(&file.name);
+                Some((tabs, file.active_tab))
+            }
+            Err(err) => {
+                problems.push(format!("layout: {}", err));
+                None
+            }
+        });
+        let mut left_out_tabs = Vec::new();
+        let restored = from_file.or_else(|| match sessions.load(&session_name) {
+            Ok(saved_layout) => saved_layout.and_then(|saved_layout| Self::restore_tabs(saved_layout, &shell, &app_config.restore_commands, &mut left_out_tabs)),
+            Err(err) => {
+                kept_files.extend(err.kept().map(std::path::Path::to_path_buf));
+                problems.push(err.to_string());
+                None
+            }
+        });
+        problems.extend(Self::left_out_message(&left_out_tabs));
+        // Default: single pane
+        let (layout, active_tab) = restored.unwrap_or_else(|| Self::default_tabs(&shell));
+        let mut renderer = TerminalRenderer::new();
+        renderer.min_contrast = app_config.min_contrast;
+        renderer.status_colors = app_config.status_colors;
+        renderer.folding = app_config.folding;
+        let diagnostic_matchers = app_config.diagnostics.compile();
         let ai_onboarding_open = !app_config.ai_onboarding_seen;
-        let usage_ledger = Self::load_usage_ledger();
+        let usage_ledger = Self::load_usage_ledger(&mut problems, &mut kept_files);
         let billing_profile = BillingProfile {
             plan: app_config.plan_tier,
             customer_id: None,
@@ -1733,11 +3372,25 @@ impl Application for Tant {
             redact_secrets: true,
             allow_sensitive: false,
         };
-        let theme_config = preset_theme("one_dark");
-        (
-            Tant { layout, active_tab, renderer, search_query: String::new(), search_success_only: false, search_failure_only: false, search_pinned_only: false, search_input_id: text_input::Id::unique(), ai_settings, ai_response: None, app_config, ai_onboarding_open, show_command_palette: false, palette_query: String::new(), palette_selected: 0, render_cache: Arc::new(Mutex::new(HashMap::new())), row_hashes: Arc::new(Mutex::new(HashMap::new())), theme_config, host_info: resolve_host_info(), window_size: Size::new(1024.0, 768.0), resize_state: None, last_cursor_pos: Point { x: 0.0, y: 0.0 }, renaming_tab: None, rename_buffer: String::new(), history_search_active: false, history_search_query: String::new(), history_matches: Vec::new(), history_selected: 0, export_toast: None, usage_ledger, billing_profile, usage_snapshot, show_billing: false },
-            window::gain_focus(window::Id::MAIN)
-        )
+        let theme_manager = ThemeManager::new(Self::themes_dir());
+        let theme_config = theme_manager.resolve(&app_config.theme);
+        renderer.fonts = Arc::new(FontSet::load(&theme_config.font_family, &app_config.font_fallbacks));
+        let atlases = GlyphAtlases::new(renderer.fonts.clone());
+        let appearance = AppearanceWatcher::new(app_config.follow_system_appearance);
+        let color_scheme = ColorScheme::of_theme(&theme_config);
+        let mut tant = Tant { layout, active_tab, renderer, search_query: String::new(), search_success_only: false, search_failure_only: false, search_pinned_only: false, search_input_id: text_input::Id::unique(), search_focused: false, ai_settings, ai_response: None, app_config, ai_onboarding_open, show_command_palette: false, palette_actions: Vec::new(), saved_sessions: Vec::new(), layout_files: Vec::new(), palette_query: String::new(), palette_selected: 0, render_cache: Arc::new(Mutex::new(HashMap::new())), row_hashes: Arc::new(Mutex::new(HashMap::new())), rasters: HashMap::new(), atlases, theme_config, theme_manager, profile_themes: HashMap::new(), theme_picker: None, appearance, system_scheme: None, color_scheme, host_info: resolve_host_info(), window_size: Size::new(1024.0, 768.0), resize_state: None, last_cursor_pos: Point { x: 0.0, y: 0.0 }, modifiers: Modifiers::default(), renaming_tab: None, rename_buffer: String::new(), history_search_active: false, history_search_query: String::new(), history_matches: Vec::new(), history_selected: 0, history_store: HistoryStore::open(paths::data_dir().join("history.jsonl")), export_toast: None, usage_ledger, billing_profile, usage_snapshot, show_billing: false, bookmarks_open: false, block_diff: None, workflows: Vec::new(), workflow_form: None, completer: Completer::load(), diagnostic_matchers, sessions, session_name, kept_files, left_out_tabs, name_input: None, pending_command: None, safety_guards: SafetyGuards::default() };
+        tant.refresh_profile_themes();
+        tant.poll_system_appearance();
+        for problem in &problems {
+            warn!("Failed to load {}", problem);
+        }
+        if let Some(problem) = problems.first() {
+            tant.export_toast = Some(ExportToast {
+                message: format!("Couldn't load {}", problem),
+                expires_at: Utc::now() + chrono::Duration::seconds(6),
+            });
+        }
+        (tant, window::gain_focus(window::Id::MAIN))
     }
 
     fn title(&self) -> String {
@@ -1747,6 +3400,15 @@ impl Application for Tant {
     fn update(&mut self, message: Message) -> Command<Message> {
         match message {
             Message::Tick => {
+                if !self.theme_manager.poll_changes().is_empty() {
+                    // Re-resolve the visible theme so edits show up immediately
+                    let current = match &self.theme_picker {
+                        Some(picker) => picker.names[picker.selected].clone(),
+                        None => self.active_theme_name(),
+                    };
+                    self.apply_theme(&current);
+                }
+                self.poll_system_appearance();
                 self.reset_usage_if_needed();
                 let limits = Self::plan_limits(self.billing_profile.plan);
                 self.usage_snapshot = self.compute_usage_snapshot(&limits);
@@ -1785,8 +3447,9 @@ impl Application for Tant {
                     }
                 }
                 let mut has_new_data = false;
-                for tab in &mut self.layout {
-                    for pane in &mut tab.panes {
+                let mut background = Vec::new();
+                for (tab_index, tab) in self.layout.iter_mut().enumerate() {
+                    for (pane_index, pane) in tab.panes.iter_mut().enumerate() {
                         // Receive data from the async reader
                         while let Ok(data) = pane.data_receiver.try_recv() {
                             pane.parser.process(&data);
@@ -1809,30 +3472,23 @@ impl Application for Tant {
                                         }
                                         // Capture output at command end
                                         block.output = pane.parser.screen_text();
+                                        background.push(Self::record_history(&self.history_store, &block));
                                         pane.history.push(block);
                                     }
                                     // Clear screen text to start fresh for new command
                                     // Note: We can't actually clear the vt100 screen, but we'll capture the delta
                                     pane.current_block = Some(Block {
-                                        command: String::new(),
                                         started_at: Some(Utc::now()),
-                                        ended_at: None,
-                                        duration_ms: None,
-                                        exit_code: None,
                                         cwd: Some(std::path::PathBuf::from(&pane.working_directory)),
-                                        output_range: None,
-                                        pinned: false,
-                                        tags: vec![],
-                                        selected: false,
-                                        output: String::new(),
-                                        git_branch: None,
-                                        git_status: None,
                                         host: self.host_info.display.clone(),
                                         is_remote: self.host_info.is_remote,
-                                        collapsed: false,
+                                        ..Block::default()
                                     });
                                     debug!("[Block Detection] Command started - new block created");
                                 }
+                                ParserEvent::ColorSchemeQuery => {
+                                    Self::report_color_scheme(pane, self.color_scheme);
+                                }
                                 ParserEvent::Command(cmd) => {
                                     if let Some(ref mut block) = pane.current_block {
                                         block.command = cmd;
@@ -1843,6 +3499,7 @@ impl Application for Tant {
                                         block.cwd = Some(std::path::PathBuf::from(&dir));
                                     }
                                     pane.working_directory = dir;
+                                    pane.completion_history = None;
                                 }
                                 ParserEvent::GitInfo { branch, status } => {
                                     if let Some(ref mut block) = pane.current_block {
@@ -1857,9 +3514,31 @@ impl Application for Tant {
                                             block.ended_at = Some(Utc::now());
                                             block.duration_ms = Some((Utc::now() - start).num_milliseconds() as u64);
                                         }
+                                        if let Some(watch) = pane.watches.iter_mut().find(|watch| watch.running) {
+                                            block.watch_id = Some(watch.id);
+                                            if !watch.finished(block.exit_code, Instant::now()) {
+                                                let id = watch.id;
+                                                self.export_toast = Some(ExportToast {
+                                                    message: format!("Stopped watching '{}' after {} failures in a row", watch.command, watch.failures_in_row),
+                                                    expires_at: Utc::now() + chrono::Duration::seconds(6),
+                                                });
+                                                pane.watches.retain(|watch| watch.id != id);
+                                            }
+                                        }
                                         // Capture output - this gets the visible screen at command end
                                         block.output = pane.parser.screen_text();
+                                        if status != 0 {
+                                            block.diagnostics = extract(&block.output, &self.diagnostic_matchers);
+                                        }
+                                        // Finding links stats files, so it runs in the background
+                                        let (output, cwd, ended_at, index) = (block.output.clone(), block.cwd.clone(), block.ended_at, pane.history.len());
+                                        background.push(Command::perform(
+                                            async move { tokio::task::spawn_blocking(move || find_links(&output, cwd.as_deref())).await.unwrap_or_default() },
+                                            move |links| Message::FileLinksFound(tab_index, pane_index, index, ended_at, links),
+                                        ));
+                                        background.push(Self::record_history(&self.history_store, &block));
                                         pane.history.push(block);
+                                        pane.completion_history = None;
                                         debug!("[Block Detection] Command ended with status {} - block saved", status);
                                     }
                                 }
@@ -1867,6 +3546,21 @@ impl Application for Tant {
                         }
                     }
                 }
+                let now = Instant::now();
+                let mut stopped_watches = Vec::new();
+                for tab in &mut self.layout {
+                    for pane in &mut tab.panes {
+                        stopped_watches.extend(Self::run_due_watches(&mut self.safety_guards, pane, now));
+                        let output_changed = pane.parser.is_dirty();
+                        if pane.find.as_mut().is_some_and(|find| find.refresh_due(output_changed, now)) {
+                            Self::run_find(pane, true);
+                        }
+                    }
+                }
+                if let Some(message) = stopped_watches.pop() {
+                    self.export_toast = Some(ExportToast { message, expires_at: Utc::now() + chrono::Duration::seconds(6) });
+                }
+                self.update_rasters();
                 // If follow mode and new data, scroll to bottom per pane
                 if has_new_data {
                     for tab in &mut self.layout {
@@ -1877,6 +3571,13 @@ impl Application for Tant {
                         }
                     }
                 }
+                Command::batch(background)
+            }
+            Message::FileLinksFound(tab, pane_id, index, ended_at, links) => {
+                let block = self.layout.get_mut(tab).and_then(|tab| tab.panes.get_mut(pane_id)).and_then(|pane| pane.history.get_mut(index));
+                if let Some(block) = block.filter(|block| block.ended_at == ended_at) {
+                    block.file_links = links;
+                }
                 Command::none()
             }
             Message::KeyPress(key, modifiers) => {
@@ -1898,6 +3599,7 @@ impl Application for Tant {
                 Command::none()
             }
             Message::TextInput(text) => {
+                self.search_focused = false;
                 if self.history_search_active {
                     let next = format!("{}{}", self.history_search_query, text);
                     self.history_search_query = next;
@@ -1938,34 +3640,109 @@ impl Application for Tant {
                         }
                     }
                 }
-                Command::none()
-            }
-            Message::Resize(width, height) => {
-                self.window_size = Size::new(width as f32, height as f32);
-                let (cell_w, cell_h) = self.renderer.cell_size(&self.theme_config);
-                let cols = (width as f32 / cell_w) as u16;
-                let rows = (height as f32 / cell_h) as u16;
-                let pixel_width = width as u16;
-                let pixel_height = height as u16;
-                for tab in &mut self.layout {
-                    for pane in &mut tab.panes {
-                        pane.parser.resize(rows, cols);
-                        if let Ok(mut pty) = pane.pty.try_lock() {
-                            if let Err(err) = pty.resize(rows, cols, pixel_width, pixel_height) {
-                                error!("Failed to resize PTY: {}", err);
-                            }
+                Command::none()
+            }
+            Message::Resize(width, height) => {
+                self.window_size = Size::new(width as f32, height as f32);
+                self.resize_panes();
+                Command::none()
+            }
+            Message::ZoomIn => {
+                self.zoom_active_pane(|zoom| zoom + PANE_ZOOM_STEP);
+                Command::none()
+            }
+            Message::ZoomOut => {
+                self.zoom_active_pane(|zoom| zoom - PANE_ZOOM_STEP);
+                Command::none()
+            }
+            Message::ZoomReset => {
+                self.zoom_active_pane(|_| 1.0);
+                Command::none()
+            }
+            Message::KeyboardEvent(key, modifiers, text) => {
+                let is_cmd = modifiers.command();
+                let is_ctrl = modifiers.control();
+                let is_shift = modifiers.shift();
+
+                if self.theme_picker.is_some() {
+                    return match key {
+                        Key::Named(iced::keyboard::key::Named::Escape) => self.update(Message::ThemePickerCancel),
+                        Key::Named(iced::keyboard::key::Named::ArrowUp) => self.update(Message::ThemePickerMove(-1)),
+                        Key::Named(iced::keyboard::key::Named::ArrowDown) => self.update(Message::ThemePickerMove(1)),
+                        Key::Named(iced::keyboard::key::Named::Enter) => self.update(Message::ThemePickerConfirm),
+                        _ => Command::none(),
+                    };
+                }
+                if self.block_diff.is_some() && matches!(key, Key::Named(iced::keyboard::key::Named::Escape)) {
+                    return self.update(Message::CloseDiff);
+                }
+                if self.workflow_form.is_some() && matches!(key, Key::Named(iced::keyboard::key::Named::Escape)) {
+                    return self.update(Message::CloseWorkflow);
+                }
+                if self.pending_command.is_some() && matches!(key, Key::Named(iced::keyboard::key::Named::Escape)) {
+                    return self.update(Message::CancelPendingCommand);
+                }
+                if self.name_input.is_some() && matches!(key, Key::Named(iced::keyboard::key::Named::Escape)) {
+                    return self.update(Message::CancelNameInput);
+                }
+
+                if is_ctrl && is_shift && matches!(key, Key::Character(ref c) if c.eq_ignore_ascii_case("f")) {
+                    return self.update(Message::OpenFind);
+                }
+                if is_ctrl && is_shift && matches!(key, Key::Character(ref c) if c.eq_ignore_ascii_case("b")) {
+                    return self.update(Message::ToggleBookmarks);
+                }
+                // Undo/redo in the command editor; while a program runs, Ctrl+Z still reaches it
+                if is_cmd && matches!(key, Key::Character(ref c) if c.eq_ignore_ascii_case("z") || c.eq_ignore_ascii_case("y")) {
+                    let redo = is_shift || matches!(key, Key::Character(ref c) if c.eq_ignore_ascii_case("y"));
+                    if let Some(pane) = self.active_pane_mut() {
+                        if pane.current_block.is_none() && !pane.parser.is_alt_screen_active() {
+                            let changed = if redo { pane.editor.redo() } else { pane.editor.undo() };
+                            if changed {
+                                pane.current_command = pane.editor.text();
+                                self.refresh_suggestion();
+                                return Command::none();
+                            }
+                        }
+                    }
+                }
+
+                let find_open = self
+                    .layout
+                    .get(self.active_tab)
+                    .and_then(|tab| tab.panes.get(tab.active_pane))
+                    .is_some_and(|pane| pane.find.is_some());
+                if find_open && !is_ctrl && !modifiers.alt() && !modifiers.logo() {
+                    match key {
+                        Key::Named(iced::keyboard::key::Named::Escape) => return self.update(Message::CloseFind),
+                        Key::Named(iced::keyboard::key::Named::Enter) if is_shift => return self.update(Message::FindPrev),
+                        Key::Named(iced::keyboard::key::Named::Enter) => return self.update(Message::FindNext),
+                        Key::Named(iced::keyboard::key::Named::Backspace) => {
+                            let query = self.active_find_query();
+                            let mut chars = query.chars();
+                            chars.next_back();
+                            return self.update(Message::UpdateFindQuery(chars.as_str().to_string()));
                         }
+                        _ => {}
+                    }
+                    if let Some(txt) = text.clone().filter(|txt| !txt.is_empty() && !txt.chars().any(char::is_control)) {
+                        return self.update(Message::UpdateFindQuery(format!("{}{}", self.active_find_query(), txt)));
                     }
                 }
-                // Clear render caches on resize
-                self.render_cache.lock().unwrap().clear();
-                self.row_hashes.lock().unwrap().clear();
-                Command::none()
-            }
-            Message::KeyboardEvent(key, modifiers, text) => {
-                let is_cmd = modifiers.command();
-                let is_ctrl = modifiers.control();
-                let is_shift = modifiers.shift();
+
+                if ((is_cmd && !is_ctrl) || (is_ctrl && is_shift)) && matches!(key, Key::Character(ref c) if c.eq_ignore_ascii_case("c")) {
+                    return self.update(Message::CopySelected);
+                }
+
+                if is_ctrl && matches!(key, Key::Character(ref c) if c == "=" || c == "+") {
+                    return self.update(Message::ZoomIn);
+                }
+                if is_ctrl && matches!(key, Key::Character(ref c) if c == "-") {
+                    return self.update(Message::ZoomOut);
+                }
+                if is_ctrl && matches!(key, Key::Character(ref c) if c == "0") {
+                    return self.update(Message::ZoomReset);
+                }
 
                 if (is_cmd || is_ctrl) && matches!(key, Key::Character(ref c) if c == "i") {
                     return self.update(Message::ToggleAiPanel);
@@ -2271,15 +4048,10 @@ impl Application for Tant {
                 Command::none()
             }
             Message::RerunCommand(index) => {
-                if let Some(tab) = self.layout.get_mut(self.active_tab) {
-                    if let Some(pane) = tab.panes.get_mut(tab.active_pane) {
-                        if let Some(block) = pane.history.get(index) {
-                            if let Ok(mut pty) = pane.pty.try_lock() {
-                                let cmd = format!("{}\r", block.command);
-                                pty.writer().write_all(cmd.as_bytes()).ok();
-                                pty.writer().flush().ok();
-                            }
-                        }
+                let command = self.active_pane_mut().and_then(|pane| pane.history.get(index)).map(|block| block.command.clone());
+                if let Some(command) = command {
+                    if !self.hold_if_dangerous(command, PendingRun::Block(index)) {
+                        self.rerun_block(self.active_tab, self.layout.get(self.active_tab).map_or(0, |tab| tab.active_pane), index);
                     }
                 }
                 Command::none()
@@ -2339,7 +4111,7 @@ impl Application for Tant {
                     if let Some(pane) = tab.panes.get(tab.active_pane) {
                         if let Some(block) = pane.history.get(index) {
                             if let Ok(result) = format_blocks(std::slice::from_ref(block), format) {
-                                let _ = write_export_file(std::path::Path::new("exports"), format, &result.content);
+                                let _ = write_export_file(&Self::exports_dir(), format, &result.content);
                                 return clipboard::write(result.content);
                             }
                         }
@@ -2353,7 +4125,7 @@ impl Application for Tant {
                         let selected: Vec<Block> = pane.history.iter().filter(|b| b.selected).cloned().collect();
                         if !selected.is_empty() {
                             if let Ok(result) = format_blocks(&selected, format) {
-                                let _ = write_export_file(std::path::Path::new("exports"), format, &result.content);
+                                let _ = write_export_file(&Self::exports_dir(), format, &result.content);
                                 return clipboard::write(result.content);
                             }
                         }
@@ -2406,6 +4178,13 @@ impl Application for Tant {
                 }
                 Command::none()
             }
+            Message::BlockOutputScrolled(tab, pane_id, index, offset_y) => {
+                // Any split pane's blocks can be scrolled, not just the focused one
+                if let Some(pane) = self.layout.get_mut(tab).and_then(|tab| tab.panes.get_mut(pane_id)) {
+                    pane.output_scroll.insert(index, offset_y);
+                }
+                Command::none()
+            }
             Message::ToggleCollapsed(index) => {
                 if let Some(tab) = self.layout.get_mut(self.active_tab) {
                     if let Some(pane) = tab.panes.get_mut(tab.active_pane) {
@@ -2419,27 +4198,21 @@ impl Application for Tant {
             Message::UpdateCurrent(cmd) => {
                 if let Some(tab) = self.layout.get_mut(self.active_tab) {
                     if let Some(pane) = tab.panes.get_mut(tab.active_pane) {
-                        pane.current_command = cmd;
-                    }
-                }
-                Command::none()
-            }
-            Message::RunCurrent => {
-                if let Some(tab) = self.layout.get_mut(self.active_tab) {
-                    if let Some(pane) = tab.panes.get_mut(tab.active_pane) {
-                        let cmd = format!("{}\n", pane.current_command);
-                        // Dummy: process as input
-                        pane.parser.process(cmd.as_bytes());
-                        pane.current_command.clear();
+                        pane.set_command(cmd);
                     }
                 }
                 Command::none()
             }
+            Message::RunCurrent => self.update(Message::TerminalSubmit),
             Message::UpdateSearch(query) => {
                 self.search_query = query;
+                self.search_focused = true;
                 Command::none()
             }
-            Message::FocusSearch => text_input::focus(self.search_input_id.clone()),
+            Message::FocusSearch => {
+                self.search_focused = true;
+                text_input::focus(self.search_input_id.clone())
+            }
             Message::ToggleSearchSuccess => {
                 self.search_success_only = !self.search_success_only;
                 if self.search_success_only {
@@ -2462,6 +4235,242 @@ impl Application for Tant {
                 self.search_query.clear();
                 Command::none()
             }
+            Message::EditBlockAnnotation(index) => {
+                if let Some(pane) = self.active_pane_mut() {
+                    let already_open = pane.annotation.as_ref().is_some_and(|annotation| annotation.block_index == index);
+                    pane.annotation = match pane.history.get(index) {
+                        Some(block) if !already_open => Some(BlockAnnotation::new(index, block)),
+                        _ => None,
+                    };
+                }
+                Command::none()
+            }
+            Message::UpdateBlockTagInput(input) => {
+                if let Some(annotation) = self.active_pane_mut().and_then(|pane| pane.annotation.as_mut()) {
+                    annotation.tag_input = input;
+                }
+                Command::none()
+            }
+            Message::SubmitBlockTags => {
+                if let Some(pane) = self.active_pane_mut() {
+                    if let Some(annotation) = pane.annotation.as_mut() {
+                        if let Some(block) = pane.history.get_mut(annotation.block_index) {
+                            add_tags(&mut block.tags, parse_tags(&annotation.tag_input));
+                        }
+                        annotation.tag_input.clear();
+                    }
+                }
+                Command::none()
+            }
+            Message::RemoveBlockTag(index, tag) => {
+                if let Some(block) = self.active_pane_mut().and_then(|pane| pane.history.get_mut(index)) {
+                    block.tags.retain(|existing| *existing != tag);
+                }
+                Command::none()
+            }
+            Message::BlockNoteEdited(action) => {
+                if let Some(pane) = self.active_pane_mut() {
+                    if let Some(annotation) = pane.annotation.as_mut() {
+                        annotation.note.perform(action);
+                        if let Some(block) = pane.history.get_mut(annotation.block_index) {
+                            block.note = annotation.note_text();
+                        }
+                    }
+                }
+                Command::none()
+            }
+            Message::CloseBlockAnnotation => {
+                if let Some(pane) = self.active_pane_mut() {
+                    pane.annotation = None;
+                }
+                Command::none()
+            }
+            Message::FilterByTag(tag) => {
+                self.search_query = tag_query(&tag);
+                Command::none()
+            }
+            Message::ToggleBookmarks => {
+                self.bookmarks_open = !self.bookmarks_open;
+                Command::none()
+            }
+            Message::JumpToBookmark(tab_index, pane_id, block_index) => {
+                let Some(tab) = self.layout.get_mut(tab_index) else {
+                    return Command::none();
+                };
+                if pane_id >= tab.panes.len() {
+                    return Command::none();
+                }
+                tab.active_pane = pane_id;
+                self.active_tab = tab_index;
+                self.update(Message::AiPanelJumpToBlock(pane_id, block_index))
+            }
+            Message::DiffSelectedBlocks => {
+                self.diff_selected_blocks();
+                Command::none()
+            }
+            Message::DiffWithPreviousRun(index) => {
+                let previous = self.active_pane_mut().and_then(|pane| previous_run(&pane.history, index));
+                match previous {
+                    Some(previous) => self.open_block_diff(previous, index),
+                    None => {
+                        self.export_toast = Some(ExportToast {
+                            message: "No earlier run of this command in this directory.".to_string(),
+                            expires_at: Utc::now() + chrono::Duration::seconds(6),
+                        });
+                    }
+                }
+                Command::none()
+            }
+            Message::ToggleDiffSideBySide => {
+                if let Some(diff) = self.block_diff.as_mut() {
+                    diff.side_by_side = !diff.side_by_side;
+                }
+                Command::none()
+            }
+            Message::ToggleDiffNormalise => {
+                if let Some(diff) = self.block_diff.as_mut() {
+                    diff.normalise = !diff.normalise;
+                    diff.refresh(&self.app_config.diff);
+                }
+                self.report_diff_errors();
+                Command::none()
+            }
+            Message::CloseDiff => {
+                self.block_diff = None;
+                Command::none()
+            }
+            Message::UpdateWorkflowArg(index, value) => {
+                if let Some(value_slot) = self.workflow_form.as_mut().and_then(|form| form.values.get_mut(index)) {
+                    *value_slot = value;
+                }
+                Command::none()
+            }
+            Message::SubmitWorkflow => {
+                if let Some(Ok(command)) = self.workflow_form.as_ref().map(|form| form.command()) {
+                    self.run_in_active_pane(command);
+                    self.workflow_form = None;
+                }
+                Command::none()
+            }
+            Message::CloseWorkflow => {
+                self.workflow_form = None;
+                Command::none()
+            }
+            Message::ConfirmPendingCommand => {
+                if let Some(pending) = self.pending_command.take() {
+                    info!("Running flagged command after confirmation ({}): {}", pending.reasons.join(", "), pending.command);
+                    match pending.run {
+                        PendingRun::Editor => self.submit_command(pending.tab, pending.pane),
+                        PendingRun::Block(index) => self.rerun_block(pending.tab, pending.pane, index),
+                        PendingRun::Typed => self.type_command(pending.tab, pending.pane, &pending.command),
+                    }
+                }
+                Command::none()
+            }
+            Message::CancelPendingCommand => {
+                self.pending_command = None;
+                Command::none()
+            }
+            Message::AcceptSuggestion => {
+                if self.typing_elsewhere() {
+                    return Command::none();
+                }
+                let Some(pane) = self.active_pane_mut() else {
+                    return Command::none();
+                };
+                // Right arrow only accepts once the cursor has reached the end of the line
+                if !pane.editor.cursor_at_end() {
+                    return Command::none();
+                }
+                let Some(suggestion) = pane.suggestion.take() else {
+                    return Command::none();
+                };
+                pane.set_command(suggestion.text);
+                self.refresh_suggestion();
+                Command::none()
+            }
+            Message::OpenDiagnostic(tab, pane_id, index, diag_index) => {
+                // Diagnostics are listed in every split pane, not just the focused one
+                let target = self.layout.get(tab).and_then(|tab| tab.panes.get(pane_id)).and_then(|pane| pane.history.get(index)).and_then(|block| {
+                    let diagnostic = block.diagnostics.get(diag_index)?;
+                    Some((diagnostic.path(block.cwd.as_deref()), diagnostic.line, diagnostic.column, block.cwd.clone()))
+                });
+                if let Some((path, line, column, cwd)) = target {
+                    self.open_in_editor((tab, pane_id), &path, line, column, cwd.as_deref());
+                }
+                Command::none()
+            }
+            Message::OpenFileLink(tab, pane_id, index, link_index) => {
+                // Plain clicks are left for selecting text
+                if !self.modifiers.control() {
+                    return Command::none();
+                }
+                let target = self
+                    .layout
+                    .get(tab)
+                    .and_then(|tab| tab.panes.get(pane_id))
+                    .and_then(|pane| pane.history.get(index))
+                    .and_then(|block| Some((block.file_links.get(link_index)?.clone(), block.cwd.clone())));
+                if let Some((link, cwd)) = target {
+                    self.open_in_editor((tab, pane_id), &link.path, link.line, link.column, cwd.as_deref());
+                }
+                Command::none()
+            }
+            Message::EditWatch(index) => {
+                let settings = self.app_config.watch.clone();
+                if let Some(pane) = self.active_pane_mut() {
+                    pane.watch_form = match &pane.watch_form {
+                        Some(form) if form.block_index == index => None,
+                        _ => Some(WatchForm::new(index, &settings)),
+                    };
+                }
+                Command::none()
+            }
+            Message::UpdateWatchInterval(value) => {
+                if let Some(form) = self.active_pane_mut().and_then(|pane| pane.watch_form.as_mut()) {
+                    form.interval = value;
+                    form.error = None;
+                }
+                Command::none()
+            }
+            Message::UpdateWatchGlob(value) => {
+                if let Some(form) = self.active_pane_mut().and_then(|pane| pane.watch_form.as_mut()) {
+                    form.glob = value;
+                    form.error = None;
+                }
+                Command::none()
+            }
+            Message::StartIntervalWatch => {
+                let max_failures = self.app_config.watch.max_failures;
+                if let Some(pane) = self.active_pane_mut() {
+                    Self::start_watch(pane, |id, block, form| {
+                        form.interval().map(|interval| Watch::every(id, block, interval, max_failures, Instant::now()))
+                    });
+                }
+                Command::none()
+            }
+            Message::StartFileWatch => {
+                let max_failures = self.app_config.watch.max_failures;
+                let exclude = self.app_config.watch.exclude.clone();
+                if let Some(pane) = self.active_pane_mut() {
+                    Self::start_watch(pane, |id, block, form| Watch::files_changed(id, block, &form.glob, &exclude, max_failures));
+                }
+                Command::none()
+            }
+            Message::StopWatch(id) => {
+                if let Some(pane) = self.active_pane_mut() {
+                    pane.watches.retain(|watch| watch.id != id);
+                }
+                Command::none()
+            }
+            Message::ToggleWatchRuns(id) => {
+                if let Some(pane) = self.active_pane_mut() {
+                    if !pane.expanded_watches.remove(&id) {
+                        pane.expanded_watches.insert(id);
+                    }
+                }
+                Command::none()
+            }
             Message::TogglePin(index) => {
                 if let Some(tab) = self.layout.get_mut(self.active_tab) {
                     if let Some(pane) = tab.panes.get_mut(tab.active_pane) {
@@ -2472,8 +4481,63 @@ impl Application for Tant {
                 }
                 Command::none()
             }
-            Message::SaveSession => {
-                self.save_session().ok();
+            Message::SaveSession | Message::AutosaveSession => {
+                if let Err(err) = self.save_session() {
+                    warn!("Failed to save session '{}': {}", self.session_name, err);
+                }
+                Command::none()
+            }
+            Message::WindowCloseRequested => {
+                if let Err(err) = self.save_session() {
+                    warn!("Failed to save session '{}': {}", self.session_name, err);
+                }
+                window::close(window::Id::MAIN)
+            }
+            Message::UpdateNameInput(name) => {
+                if let Some((_, input)) = &mut self.name_input {
+                    *input = name;
+                }
+                Command::none()
+            }
+            Message::SubmitNameInput => {
+                let Some((prompt, input)) = self.name_input.clone() else {
+                    return Command::none();
+                };
+                let message = match prompt {
+                    NamePrompt::SessionAs => {
+                        let Ok(name) = session_name(&input) else {
+                            return Command::none();
+                        };
+                        // Save-as copies the open session under the new name and carries on
+                        // in it, but only once the copy exists and is the current session
+                        match self.save_session_to(&name).and_then(|()| self.sessions.set_current(&name)) {
+                            Ok(()) => {
+                                let message = format!("Saved session as '{}'", name);
+                                self.session_name = name;
+                                message
+                            }
+                            Err(err) => format!("Failed to save session '{}': {}", name, err),
+                        }
+                    }
+                    NamePrompt::LayoutTemplate => {
+                        let Ok(name) = template_name(&input) else {
+                            return Command::none();
+                        };
+                        match self.save_layout_template(&name) {
+                            Ok(path) => format!("Saved layout to {}", path.display()),
+                            Err(err) => format!("Failed to save layout '{}': {}", name, err),
+                        }
+                    }
+                };
+                self.name_input = None;
+                self.export_toast = Some(ExportToast {
+                    message,
+                    expires_at: Utc::now() + chrono::Duration::seconds(6),
+                });
+                Command::none()
+            }
+            Message::CancelNameInput => {
+                self.name_input = None;
                 Command::none()
             }
             Message::AiExplainError => {
@@ -2811,36 +4875,41 @@ impl Application for Tant {
                 debug!("Window unfocused");
                 Command::none()
             }
-            Message::TerminalInput(input) => {
-                // Update the current command text
-                if let Some(tab) = self.layout.get_mut(self.active_tab) {
-                    if let Some(pane) = tab.panes.get_mut(tab.active_pane) {
-                        pane.current_command = input;
-                    }
+            Message::CommandEdited(action) => {
+                self.search_focused = false;
+                // Enter submits; Shift+Enter is left to the editor as a new line
+                if matches!(action, text_editor::Action::Edit(text_editor::Edit::Enter)) && !self.modifiers.shift() {
+                    return self.update(Message::TerminalSubmit);
+                }
+                if let Some(pane) = self.active_pane_mut() {
+                    pane.editor.perform(action);
+                    pane.current_command = pane.editor.text();
                 }
+                self.refresh_suggestion();
                 Command::none()
             }
             Message::TerminalSubmit => {
-                // Send the current command to PTY with enter
-                if let Some(tab) = self.layout.get_mut(self.active_tab) {
-                    if let Some(pane) = tab.panes.get_mut(tab.active_pane) {
-                        if let Ok(mut pty) = pane.pty.try_lock() {
-                            let cmd = format!("{}\r", pane.current_command);
-                            if let Err(err) = pty.writer().write_all(cmd.as_bytes()) {
-                                error!("Failed to write to PTY: {}", err);
-                            }
-                            if let Err(err) = pty.writer().flush() {
-                                error!("Failed to flush PTY writer: {}", err);
-                            }
-                            pane.current_command.clear();
-                        }
-                    }
+                let command = self.active_pane_mut().map(|pane| pane.current_command.clone()).unwrap_or_default();
+                if !self.hold_if_dangerous(command, PendingRun::Editor) {
+                    self.submit_command(self.active_tab, self.layout.get(self.active_tab).map_or(0, |tab| tab.active_pane));
                 }
                 Command::none()
             }
             Message::MouseWheel(delta) => {
+                let cell_height = self.active_pane_cell_size().1;
                 if let Some(tab) = self.layout.get_mut(self.active_tab) {
                     if let Some(pane) = tab.panes.get_mut(tab.active_pane) {
+                        // While finding, the wheel scrolls the grid through scrollback
+                        if pane.find.is_some() && !pane.parser.is_alt_screen_active() {
+                            let lines = match delta {
+                                mouse::ScrollDelta::Lines { y, .. } => y.round() as isize,
+                                mouse::ScrollDelta::Pixels { y, .. } => (y / cell_height) as isize,
+                            };
+                            let offset = pane.parser.screen().scrollback() as isize + lines;
+                            let offset = offset.clamp(0, pane.parser.scrollback_len() as isize) as usize;
+                            pane.parser.set_scrollback(offset);
+                            return Command::none();
+                        }
                         match delta {
                             mouse::ScrollDelta::Lines { y, .. } => {
                                 if y > 0.0 {
@@ -2860,8 +4929,8 @@ impl Application for Tant {
                                 }
                             }
                             mouse::ScrollDelta::Pixels { y, .. } => {
-                                // Convert pixels to lines, assuming cell_height
-                                let lines = (y.abs() / 16.0) as usize;
+                                // Convert pixels to lines using the pane's cell height
+                                let lines = (y.abs() / cell_height) as usize;
                                 if y > 0.0 {
                                     if pane.scroll_offset < usize::MAX / 2 {
                                         pane.scroll_offset += lines;
@@ -2880,14 +4949,16 @@ impl Application for Tant {
                 Command::none()
             }
             Message::MouseButtonPressed(button) => {
+                // X11 and Wayland convention: middle click pastes the primary selection
+                if cfg!(target_os = "linux") && button == mouse::Button::Middle {
+                    return clipboard::read_primary(|text| text.map(Message::Paste).unwrap_or(Message::None));
+                }
                 if button == mouse::Button::Left {
+                    let grid_point = self.active_grid_point(self.last_cursor_pos, false);
+                    let block = self.modifiers.alt();
+                    let word_chars = self.app_config.word_chars.clone();
+                    let rect = Self::layout_area(self.window_size, self.bookmarks_open);
                     if let Some(tab) = self.layout.get_mut(self.active_tab) {
-                        let rect = Rectangle {
-                            x: 0.0,
-                            y: 0.0,
-                            width: self.window_size.width,
-                            height: self.window_size.height,
-                        };
                         let mut path = Vec::new();
                         if let Some(state) = Self::find_split_hit(&tab.root, rect, self.last_cursor_pos, 6.0, &mut path) {
                             self.resize_state = Some(state);
@@ -2895,13 +4966,12 @@ impl Application for Tant {
                         }
 
                         if let Some(pane) = tab.panes.get_mut(tab.active_pane) {
-                            pane.mouse_button_down = true;
-                            let cell_w = self.renderer.cell_size(&self.theme_config).0;
-                            let cell_h = self.renderer.cell_size(&self.theme_config).1;
-                            let col = (pane.last_cursor_pos.x / cell_w) as usize;
-                            let row = (pane.last_cursor_pos.y / cell_h) as usize;
-                            pane.selection_start = Some((row, col));
-                            pane.selection_end = Some((row, col));
+                            // Presses off the grid (block mode, buttons, other panes) leave
+                            // nothing selected, so their release copies nothing
+                            pane.mouse_button_down = grid_point.is_some();
+                            let clicks = pane.clicks.register(Instant::now(), self.last_cursor_pos);
+                            pane.selection = grid_point.map(|point| Selection::new(mode_for_click(clicks, block), point));
+                            Self::expand_selection(pane, &word_chars);
                         }
                     }
                 }
@@ -2909,6 +4979,8 @@ impl Application for Tant {
             }
             Message::MouseCursorMoved(position) => {
                 self.last_cursor_pos = position;
+                let grid_point = self.active_grid_point(position, true);
+                let word_chars = self.app_config.word_chars.clone();
                 if let Some(tab) = self.layout.get_mut(self.active_tab) {
                     if let Some(state) = self.resize_state.clone() {
                         Self::update_split_ratio(&mut tab.root, &state, position);
@@ -2917,11 +4989,13 @@ impl Application for Tant {
                     if let Some(pane) = tab.panes.get_mut(tab.active_pane) {
                         pane.last_cursor_pos = position;
                         if pane.mouse_button_down {
-                            let cell_w = self.renderer.cell_size(&self.theme_config).0;
-                            let cell_h = self.renderer.cell_size(&self.theme_config).1;
-                            let col = (position.x / cell_w) as usize;
-                            let row = (position.y / cell_h) as usize;
-                            pane.selection_end = Some((row, col));
+                            let moved = match (pane.selection.as_mut(), grid_point) {
+                                (Some(selection), Some(point)) => selection.extend_to(point),
+                                _ => false,
+                            };
+                            if moved {
+                                Self::expand_selection(pane, &word_chars);
+                            }
                         }
                     }
                 }
@@ -2930,43 +5004,98 @@ impl Application for Tant {
             Message::MouseButtonReleased(button) => {
                 if button == mouse::Button::Left {
                     self.resize_state = None;
+                    let copy_on_select = self.app_config.copy_on_select;
                     if let Some(tab) = self.layout.get_mut(self.active_tab) {
                         if let Some(pane) = tab.panes.get_mut(tab.active_pane) {
-                            pane.mouse_button_down = false;
+                            let was_selecting = std::mem::replace(&mut pane.mouse_button_down, false) && pane.shows_grid();
+                            if let Some(text) = Self::selection_text(pane.selection.as_ref(), &mut pane.parser).filter(|_| was_selecting) {
+                                let mut commands = Vec::new();
+                                if cfg!(target_os = "linux") {
+                                    commands.push(clipboard::write_primary(text.clone()));
+                                }
+                                if copy_on_select {
+                                    commands.push(clipboard::write(text));
+                                }
+                                return Command::batch(commands);
+                            }
                         }
                     }
                 }
                 Command::none()
             }
+            Message::ModifiersChanged(modifiers) => {
+                self.modifiers = modifiers;
+                Command::none()
+            }
             Message::CopySelected => {
-                if let Some(tab) = self.layout.get(self.active_tab) {
-                    if let Some(pane) = tab.panes.get(tab.active_pane) {
-                        if let (Some(start), Some(end)) = (pane.selection_start, pane.selection_end) {
-                            let contents = pane.parser.screen().contents();
-                            let lines: Vec<&str> = contents.lines().collect();
-                            let mut selected = String::new();
-                            let (start_line, start_col) = start.min(end);
-                            let (end_line, end_col) = start.max(end);
-                            for line_idx in start_line..=end_line {
-                                if let Some(line) = lines.get(line_idx) {
-                                    let start_c = if line_idx == start_line { start_col } else { 0 };
-                                    let end_c = if line_idx == end_line { end_col } else { line.len() };
-                                    if start_c < line.len() {
-                                        let end_c = end_c.min(line.len());
-                                        selected.push_str(&line[start_c..end_c]);
-                                        if line_idx < end_line {
-                                            selected.push('\n');
-                                        }
-                                    }
-                                }
-                            }
-                            return clipboard::write(selected);
+                if let Some(tab) = self.layout.get_mut(self.active_tab) {
+                    if let Some(pane) = tab.panes.get_mut(tab.active_pane) {
+                        if let Some(text) = Self::selection_text(pane.selection.as_ref(), &mut pane.parser) {
+                            return clipboard::write(text);
                         }
                     }
                 }
                 Command::none()
             }
+            Message::OpenFind => {
+                if let Some(pane) = self.active_pane_mut() {
+                    if pane.find.is_none() {
+                        pane.find = Some(FindState::new());
+                        pane.parser.mark_dirty();
+                    }
+                }
+                Command::none()
+            }
+            Message::CloseFind => {
+                if let Some(pane) = self.active_pane_mut() {
+                    if pane.find.take().is_some() {
+                        pane.parser.set_scrollback(0);
+                        pane.parser.mark_dirty();
+                    }
+                }
+                Command::none()
+            }
+            Message::UpdateFindQuery(query) => {
+                if let Some(pane) = self.active_pane_mut() {
+                    if let Some(find) = pane.find.as_mut() {
+                        find.query = query;
+                        Self::run_find(pane, false);
+                        Self::reveal_find_match(pane);
+                    }
+                }
+                Command::none()
+            }
+            Message::CycleFindMode => {
+                if let Some(pane) = self.active_pane_mut() {
+                    if let Some(find) = pane.find.as_mut() {
+                        find.mode = find.mode.next();
+                        Self::run_find(pane, false);
+                        Self::reveal_find_match(pane);
+                    }
+                }
+                Command::none()
+            }
+            Message::FindNext | Message::FindPrev => {
+                let forward = matches!(message, Message::FindNext);
+                if let Some(pane) = self.active_pane_mut() {
+                    if let Some(find) = pane.find.as_mut() {
+                        find.step(forward);
+                        Self::reveal_find_match(pane);
+                        pane.parser.mark_dirty();
+                    }
+                }
+                Command::none()
+            }
             Message::OpenCommandPalette => {
+                let cwd = self.active_pane_mut().map(|pane| std::path::PathBuf::from(&pane.working_directory)).unwrap_or_default();
+                let (workflows, errors) = load_workflows(&cwd);
+                for err in errors {
+                    warn!("Failed to load workflow {}", err);
+                }
+                self.workflows = workflows;
+                self.saved_sessions = self.sessions.list();
+                self.layout_files = layouts::list(&layouts_dir());
+                self.palette_actions = self.get_available_actions();
                 self.show_command_palette = true;
                 self.palette_query.clear();
                 self.palette_selected = 0;
@@ -2982,8 +5111,7 @@ impl Application for Tant {
                 Command::none()
             }
             Message::UpdatePaletteSelection(delta) => {
-                let actions = self.get_available_actions();
-                let filtered_count = actions.iter()
+                let filtered_count = self.palette_actions.iter()
                     .filter(|(name, _)| name.to_lowercase().contains(&self.palette_query.to_lowercase()))
                     .count();
                 if filtered_count > 0 {
@@ -2999,6 +5127,44 @@ impl Application for Tant {
                 self.execute_palette_action(action);
                 Command::none()
             }
+            Message::OpenThemePicker => {
+                self.open_theme_picker();
+                Command::none()
+            }
+            Message::ThemePickerMove(delta) => {
+                if let Some(picker) = &self.theme_picker {
+                    let count = picker.names.len() as isize;
+                    let next = (picker.selected as isize + delta).rem_euclid(count.max(1)) as usize;
+                    return self.update(Message::ThemePickerSelect(next));
+                }
+                Command::none()
+            }
+            Message::ThemePickerSelect(index) => {
+                let name = match self.theme_picker.as_mut() {
+                    Some(picker) if index < picker.names.len() => {
+                        picker.selected = index;
+                        picker.names[index].clone()
+                    }
+                    _ => return Command::none(),
+                };
+                self.apply_theme(&name);
+                Command::none()
+            }
+            Message::ThemePickerConfirm => {
+                if let Some(picker) = self.theme_picker.take() {
+                    let name = picker.names[picker.selected].clone();
+                    if let Err(e) = self.choose_theme(name) {
+                        error!("Failed to save theme selection: {}", e);
+                    }
+                }
+                Command::none()
+            }
+            Message::ThemePickerCancel => {
+                if let Some(picker) = self.theme_picker.take() {
+                    self.apply_theme(&picker.original);
+                }
+                Command::none()
+            }
             Message::ExportTheme => {
                 if let Err(e) = self.export_theme() {
                     error!("Failed to export theme: {}", e);
@@ -3050,7 +5216,7 @@ impl Application for Tant {
             self.build_layout_view(&tab.root, &tab.panes)
         } else {
             let dummy_parser = TerminalParser::new(24, 80);
-            self.renderer.view(&[], &None, "", &self.search_query, self.search_success_only, self.search_failure_only, self.search_pinned_only, self.search_input_id.clone(), dummy_parser.screen(), false, &self.ai_settings, &self.ai_response, 0, None, None, &self.render_cache, &self.row_hashes, 0, 0, &self.theme_config, &self.layout, self.active_tab, self.renaming_tab, &self.rename_buffer, self.history_search_active, &self.history_search_query, &self.history_matches, self.history_selected, false, AiContextScope::LastNBlocks, &[], "", false, false, AiContextPreview { block_count: 0, char_count: 0, token_estimate: 0 }, None, scrollable::Id::unique(), false, &[], None, None, self.export_toast.as_ref(), self.billing_profile.plan, Self::plan_limits(self.billing_profile.plan), self.usage_snapshot.clone())
+            self.renderer.view(&[], &None, CommandLine::default(), &self.search_query, self.search_success_only, self.search_failure_only, self.search_pinned_only, self.search_input_id.clone(), dummy_parser.screen(), false, &self.ai_settings, &self.ai_response, 0, GridOverlay::default(), &self.render_cache, &self.row_hashes, None, 0, 0, &self.theme_config, &self.layout, self.active_tab, self.renaming_tab, &self.rename_buffer, self.history_search_active, &self.history_search_query, &self.history_matches, self.history_selected, false, AiContextScope::LastNBlocks, &[], "", false, false, AiContextPreview { block_count: 0, char_count: 0, token_estimate: 0 }, BlockOverlay::default(), scrollable::Id::unique(), false, &[], None, None, self.export_toast.as_ref(), self.billing_profile.plan, Self::plan_limits(self.billing_profile.plan), self.usage_snapshot.clone(), 1.0)
         };
 
         if self.ai_onboarding_open {
@@ -3059,23 +5225,27 @@ impl Application for Tant {
             self.render_billing()
         } else if self.show_command_palette {
             self.render_command_palette()
+        } else if let Some(pending) = &self.pending_command {
+            self.render_pending_command(pending)
+        } else if let Some(form) = &self.workflow_form {
+            self.render_workflow_form(form)
+        } else if let Some((prompt, name)) = &self.name_input {
+            self.render_name_form(*prompt, name)
+        } else if let Some(diff) = &self.block_diff {
+            self.render_block_diff(diff)
+        } else if self.theme_picker.is_some() {
+            // Keep the terminal visible so the selection previews live
+            Row::new().push(layout_view).push(self.render_theme_picker()).into()
+        } else if self.bookmarks_open {
+            Row::new().push(self.render_bookmarks()).push(layout_view).into()
         } else {
             layout_view
         }
     }
 
     fn theme(&self) -> Theme {
-        // Create a dark theme with high-contrast input
-        Theme::custom(
-            "Tant Dark".to_string(),
-            iced::theme::Palette {
-                background: Color::from_rgb(0.12, 0.12, 0.12),
-                text: Color::from_rgb(1.0, 1.0, 1.0),  // Pure white text
-                primary: Color::from_rgb(0.4, 0.7, 0.9),
-                success: Color::from_rgb(0.2, 0.8, 0.2),
-                danger: Color::from_rgb(0.9, 0.3, 0.3),
-            },
-        )
+        // Chrome follows the active terminal theme
+        iced_theme(&self.active_theme_name(), &self.theme_config)
     }
 
     fn subscription(&self) -> Subscription<Message> {
@@ -3091,6 +5261,9 @@ impl Application for Tant {
                 iced::Event::Window(_, window::Event::Unfocused) => {
                     Message::WindowUnfocused
                 }
+                iced::Event::Window(_, window::Event::CloseRequested) => {
+                    Message::WindowCloseRequested
+                }
                 iced::Event::Mouse(mouse::Event::WheelScrolled { delta }) => {
                     Message::MouseWheel(delta)
                 }
@@ -3103,13 +5276,28 @@ impl Application for Tant {
                 iced::Event::Mouse(mouse::Event::ButtonReleased(button)) => {
                     Message::MouseButtonReleased(button)
                 }
+                iced::Event::Keyboard(keyboard::Event::ModifiersChanged(modifiers)) => {
+                    Message::ModifiersChanged(modifiers)
+                }
                 iced::Event::Keyboard(keyboard::Event::KeyPressed { key, modifiers, text, .. }) => {
                     Message::KeyboardEvent(key, modifiers, text.map(|value| value.to_string()))
                 }
                 _ => Message::None,
             }
         });
-        Subscription::batch(vec![time_sub, event_sub])
+        // The focused command input captures arrow keys, so this listens to captured
+        // events too; the handler ignores it when another input has focus
+        let accept_sub = iced::event::listen_with(|event, _status| match event {
+            iced::Event::Keyboard(keyboard::Event::KeyPressed { key: Key::Named(keyboard::key::Named::ArrowRight), modifiers, .. }) if modifiers.is_empty() => {
+                Some(Message::AcceptSuggestion)
+            }
+            _ => None,
+        });
+        let mut subscriptions = vec![time_sub, event_sub, accept_sub];
+        if self.app_config.autosave_secs > 0 {
+            subscriptions.push(time::every(std::time::Duration::from_secs(self.app_config.autosave_secs)).map(|_| Message::AutosaveSession));
+        }
+        Subscription::batch(subscriptions)
     }
 }
 
@@ -3119,7 +5307,7 @@ impl Tant {
             LayoutNode::Leaf { pane_id } => {
                 if let Some(pane) = panes.get(*pane_id) {
                     let ai_preview = self.resolve_context_preview(pane, pane.ai_context_scope);
-                    let view = self.renderer.view(&pane.history, &pane.current_block, &pane.current_command, &self.search_query, self.search_success_only, self.search_failure_only, self.search_pinned_only, self.search_input_id.clone(), pane.parser.screen(), pane.parser.is_alt_screen_active(), &self.ai_settings, &self.ai_response, pane.scroll_offset, pane.selection_start, pane.selection_end, &self.render_cache, &self.row_hashes, self.active_tab, *pane_id, &self.theme_config, &self.layout, self.active_tab, self.renaming_tab, &self.rename_buffer, self.history_search_active, &self.history_search_query, &self.history_matches, self.history_selected, pane.ai_panel_open, pane.ai_context_scope, &pane.ai_chat, &pane.ai_input, pane.ai_pending, pane.ai_streaming, ai_preview, pane.highlighted_block, pane.history_scroll_id.clone(), pane.ai_redaction_override, &pane.ai_last_redactions, pane.ai_last_redacted_preview.as_deref(), pane.ai_selected_template, self.export_toast.as_ref(), self.billing_profile.plan, Self::plan_limits(self.billing_profile.plan), self.usage_snapshot.clone());
+                    let view = self.renderer.view(&pane.history, &pane.current_block, CommandLine { editor: Some(&pane.editor), text: &pane.current_command, suggestion: pane.suggestion.as_ref() }, &self.search_query, self.search_success_only, self.search_failure_only, self.search_pinned_only, self.search_input_id.clone(), pane.parser.screen(), pane.parser.is_alt_screen_active(), &self.ai_settings, &self.ai_response, pane.scroll_offset, GridOverlay { selection: pane.selection.as_ref(), find: pane.find.as_ref() }, &self.render_cache, &self.row_hashes, self.rasters.get(&(self.active_tab, *pane_id)), self.active_tab, *pane_id, self.pane_theme(pane), &self.layout, self.active_tab, self.renaming_tab, &self.rename_buffer, self.history_search_active, &self.history_search_query, &self.history_matches, self.history_selected, pane.ai_panel_open, pane.ai_context_scope, &pane.ai_chat, &pane.ai_input, pane.ai_pending, pane.ai_streaming, ai_preview, BlockOverlay { tab: self.active_tab, pane: *pane_id, highlighted: pane.highlighted_block, annotation: pane.annotation.as_ref(), output_scroll: Some(&pane.output_scroll), watch: WatchOverlay { watches: &pane.watches, form: pane.watch_form.as_ref(), expanded: Some(&pane.expanded_watches) } }, pane.history_scroll_id.clone(), pane.ai_redaction_override, &pane.ai_last_redactions, pane.ai_last_redacted_preview.as_deref(), pane.ai_selected_template, self.export_toast.as_ref(), self.billing_profile.plan, Self::plan_limits(self.billing_profile.plan), self.usage_snapshot.clone(), pane.zoom);
                     let is_active = self
                         .layout
                         .get(self.active_tab)
@@ -3145,7 +5333,7 @@ impl Tant {
                         .into()
                 } else {
                     let dummy_parser = TerminalParser::new(24, 80);
-                    self.renderer.view(&[], &None, "", &self.search_query, self.search_success_only, self.search_failure_only, self.search_pinned_only, self.search_input_id.clone(), dummy_parser.screen(), false, &self.ai_settings, &self.ai_response, 0, None, None, &self.render_cache, &self.row_hashes, self.active_tab, *pane_id, &self.theme_config, &self.layout, self.active_tab, self.renaming_tab, &self.rename_buffer, self.history_search_active, &self.history_search_query, &self.history_matches, self.history_selected, false, AiContextScope::LastNBlocks, &[], "", false, false, AiContextPreview { block_count: 0, char_count: 0, token_estimate: 0 }, None, scrollable::Id::unique(), false, &[], None, None, self.export_toast.as_ref(), self.billing_profile.plan, Self::plan_limits(self.billing_profile.plan), self.usage_snapshot.clone())
+                    self.renderer.view(&[], &None, CommandLine::default(), &self.search_query, self.search_success_only, self.search_failure_only, self.search_pinned_only, self.search_input_id.clone(), dummy_parser.screen(), false, &self.ai_settings, &self.ai_response, 0, GridOverlay::default(), &self.render_cache, &self.row_hashes, None, self.active_tab, *pane_id, &self.theme_config, &self.layout, self.active_tab, self.renaming_tab, &self.rename_buffer, self.history_search_active, &self.history_search_query, &self.history_matches, self.history_selected, false, AiContextScope::LastNBlocks, &[], "", false, false, AiContextPreview { block_count: 0, char_count: 0, token_estimate: 0 }, BlockOverlay::default(), scrollable::Id::unique(), false, &[], None, None, self.export_toast.as_ref(), self.billing_profile.plan, Self::plan_limits(self.billing_profile.plan), self.usage_snapshot.clone(), 1.0)
                 }
             }
             LayoutNode::Split { axis, ratio, left, right } => {
@@ -3182,6 +5370,46 @@ fn main() -> iced::Result {
     }
     log_builder.init();
 
+    // --import-history[=bash,zsh,fish] imports shell history and exits
+    if let Some(arg) = args.iter().find(|arg| arg.starts_with("--import-history")) {
+        let shells = match arg.strip_prefix("--import-history=") {
+            Some(names) => ShellKind::from_list(names).unwrap_or_else(|err| {
+                eprintln!("--import-history: {}", err);
+                std::process::exit(2);
+            }),
+            None => ShellKind::ALL.to_vec(),
+        };
+        let mut store = HistoryStore::open(paths::data_dir().join("history.jsonl"));
+        let results = import_shell_history(&mut store, &shells);
+        println!("{}", summary_message(&results));
+        std::process::exit(if results.iter().any(Result::is_err) { 1 } else { 0 });
+    }
+
+    // --layout <file> (or --layout=<file>) opens a layout file instead of the
+    // last session; a bare name is looked up in the layouts directory
+    let layout_arg = args.iter().position(|arg| arg == "--layout").map(|index| args.get(index + 1).cloned())
+        .or_else(|| args.iter().find_map(|arg| arg.strip_prefix("--layout=").map(|name| Some(name.to_string()))));
+    let layout = match layout_arg {
+        None => None,
+        Some(None) => {
+            eprintln!("--layout needs a file");
+            std::process::exit(2);
+        }
+        Some(Some(arg)) => {
+            let Some(path) = layouts::find(&arg, &layouts_dir()) else {
+                eprintln!("No layout file '{}' here or in {}", arg, layouts_dir().display());
+                std::process::exit(1);
+            };
+            match LayoutFile::load(&path) {
+                Ok(file) => Some(file),
+                Err(err) => {
+                    eprintln!("{}", err);
+                    std::process::exit(1);
+                }
+            }
+        }
+    };
+
     Tant::run(Settings {
         window: window::Settings {
             size: iced::Size::new(1024.0, 768.0),
@@ -3195,8 +5423,14 @@ fn main() -> iced::Result {
             level: window::Level::Normal,
             icon: None,
             platform_specific: Default::default(),
-            exit_on_close_request: true,
+            // Closing saves the session first, then closes the window itself
+            exit_on_close_request: false,
         },
-        ..Settings::default()
+        ..Settings::with_flags(layout)
     })
 }
+
+#[cfg(test)]
+mod tests {
+    include!(concat!(env!("CARGO_MANIFEST_DIR"), "/__tests__/main_tests.rs"));
+}
@@ -3,6 +3,8 @@
 
 use vt100::Parser;
 use serde::{Deserialize, Serialize};
+use crate::find::FindState;
+use crate::selection::GridLines;
 
 #[derive(Debug, Clone)]
 pub enum ParserEvent {
@@ -12,6 +14,7 @@ pub enum ParserEvent {
     Directory(String),
     GitInfo { branch: String, status: Option<GitStatus> },
     PromptShown,
+    ColorSchemeQuery,
 }
 
 // OSC 133 sequence markers (Warp/FinalTerm style)
@@ -22,6 +25,24 @@ const OSC_COMMAND_END_PREFIX: &str = "\x1b]133;D";
 const OSC_GIT_INFO_PREFIX: &str = "\x1b]133;G;";
 const OSC_DIRECTORY_PREFIX: &str = "\x1b]7;file://";
 
+// DEC mode 2031 colour-scheme update notifications (contour/kitty convention),
+// set and reset with `CSI ? 2031 h/l` and queried with `CSI ? 996 n`
+const DEC_COLOR_SCHEME_UPDATES: u32 = 2031;
+const DEC_COLOR_SCHEME_QUERY: u32 = 996;
+// Longer parameter lists than this aren't private modes anyone sends
+const MAX_PRIVATE_CSI_PARAMS: usize = 64;
+
+// Where the scan for private-mode CSI sequences (`ESC [ ? params final`) is,
+// kept between reads so a sequence split across them is still seen
+#[derive(Debug, Default)]
+enum PrivateCsi {
+    #[default]
+    Ground,
+    Escape,
+    Csi,
+    Params(String),
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum GitStatus {
     Clean,
@@ -36,6 +57,8 @@ pub struct TerminalParser {
     buffer: Vec<u8>, // Buffer for detecting OSC sequences
     in_command: bool,
     alt_screen_active: bool,
+    color_scheme_updates: bool,
+    private_csi: PrivateCsi,
 }
 
 impl TerminalParser {
@@ -48,10 +71,14 @@ impl TerminalParser {
             buffer: Vec::new(),
             in_command: false,
             alt_screen_active: false,
+            color_scheme_updates: false,
+            private_csi: PrivateCsi::Ground,
         }
     }
 
     pub fn process(&mut self, data: &[u8]) {
+        self.detect_color_scheme_sequences(data);
+
         // Append to buffer for OSC sequence detection
         self.buffer.extend_from_slice(data);
         
@@ -63,6 +90,39 @@ impl TerminalParser {
         self.dirty = true;
     }
     
+    // Reads private-mode CSI parameters byte by byte, so mode lists such as
+    // `CSI ? 2004 ; 2031 h` and sequences split across reads are handled once
+    fn detect_color_scheme_sequences(&mut self, data: &[u8]) {
+        for &byte in data {
+            self.private_csi = match (std::mem::take(&mut self.private_csi), byte) {
+                (_, 0x1b) => PrivateCsi::Escape,
+                (PrivateCsi::Escape, b'[') => PrivateCsi::Csi,
+                (PrivateCsi::Csi, b'?') => PrivateCsi::Params(String::new()),
+                (PrivateCsi::Params(mut params), b'0'..=b'9' | b';') if params.len() < MAX_PRIVATE_CSI_PARAMS => {
+                    params.push(byte as char);
+                    PrivateCsi::Params(params)
+                }
+                (PrivateCsi::Params(params), b'h' | b'l' | b'n') => {
+                    self.handle_private_csi(&params, byte);
+                    PrivateCsi::Ground
+                }
+                _ => PrivateCsi::Ground,
+            };
+        }
+    }
+
+    fn handle_private_csi(&mut self, params: &str, action: u8) {
+        let mut modes = params.split(';').filter_map(|param| param.parse::<u32>().ok());
+        match action {
+            b'h' | b'l' if modes.any(|mode| mode == DEC_COLOR_SCHEME_UPDATES) => {
+                self.color_scheme_updates = action == b'h';
+                log::debug!("[Color Scheme] Update notifications {}", if self.color_scheme_updates { "enabled" } else { "disabled" });
+            }
+            b'n' if params.parse::<u32>() == Ok(DEC_COLOR_SCHEME_QUERY) => self.events.push(ParserEvent::ColorSchemeQuery),
+            _ => {}
+        }
+    }
+
     fn detect_shell_integration_markers(&mut self) {
         let buffer_str = String::from_utf8_lossy(&self.buffer);
         
@@ -196,7 +256,7 @@ impl TerminalParser {
     }
 
     pub fn resize(&mut self, rows: u16, cols: u16) {
-        self.parser.set_size(rows, cols);
+        self.parser.screen_mut().set_size(rows, cols);
         self.dirty = true;
     }
 
@@ -209,7 +269,7 @@ impl TerminalParser {
         for row in 0..self.parser.screen().size().1 as usize {
             for col in 0..self.parser.screen().size().0 as usize {
                 if let Some(cell) = self.parser.screen().cell(row as u16, col as u16) {
-                    text.push_str(&cell.contents());
+                    text.push_str(cell.contents());
                 }
             }
             text.push('\n');
@@ -222,8 +282,77 @@ impl TerminalParser {
     pub fn mark_clean(&mut self) {
         self.dirty = false;
     }
+
+    /// Forces a redraw without new output, e.g. when the selection changes.
+    pub fn mark_dirty(&mut self) {
+        self.dirty = true;
+    }
+
+    pub fn grid_lines(&mut self, lines: impl IntoIterator<Item = isize>) -> GridLines {
+        GridLines::read(&mut self.parser, lines)
+    }
+
+    pub fn scrollback_len(&mut self) -> usize {
+        GridLines::scrollback_len(&mut self.parser)
+    }
+
+    pub fn refresh_find(&mut self, find: &mut FindState, incremental: bool) {
+        find.refresh(&mut self.parser, incremental);
+    }
+
+    /// Scrolls the grid view back by `offset` lines; 0 shows the live screen.
+    pub fn set_scrollback(&mut self, offset: usize) {
+        if self.parser.screen().scrollback() != offset {
+            self.parser.screen_mut().set_scrollback(offset);
+            self.dirty = true;
+        }
+    }
     
+    /// Scrollback and screen as plain text, soft-wrapped rows joined and
+    /// trailing blank space dropped.
+    pub fn transcript(&mut self) -> String {
+        let rows = self.parser.screen().size().0 as isize;
+        let first = -(self.scrollback_len() as isize);
+        let grid = self.grid_lines(first..rows);
+        let mut text = String::new();
+        for line in first..rows {
+            let row = grid.cells(line).concat();
+            if grid.wrapped(line) {
+                text.push_str(&row);
+            } else {
+                text.push_str(row.trim_end());
+                text.push('\n');
+            }
+        }
+        text.trim_end().to_string()
+    }
+
+    /// Draws a saved transcript greyed out, leaving the cursor below it for
+    /// whatever the shell prints next. Bypasses shell integration detection.
+    pub fn replay_transcript(&mut self, transcript: &str) {
+        if transcript.trim().is_empty() {
+            return;
+        }
+        let mut bytes = b"\x1b[90m".to_vec();
+        for line in transcript.lines() {
+            bytes.extend_from_slice(line.as_bytes());
+            bytes.extend_from_slice(b"\r\n");
+        }
+        bytes.extend_from_slice(b"\x1b[0m");
+        self.parser.process(&bytes);
+        self.dirty = true;
+    }
+
     pub fn is_alt_screen_active(&self) -> bool {
         self.alt_screen_active
     }
+
+    pub fn color_scheme_updates_enabled(&self) -> bool {
+        self.color_scheme_updates
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    include!(concat!(env!("CARGO_MANIFEST_DIR"), "/__tests__/parser_tests.rs"));
 }
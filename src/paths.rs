@@ -0,0 +1,111 @@
+// Per-user locations for tant's files, following the XDG base directory spec
+
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::Value;
+
+/// `$XDG_DATA_HOME`, falling back to `~/.local/share`.
+pub fn data_home() -> PathBuf {
+    xdg_dir("XDG_DATA_HOME", ".local/share")
+}
+
+/// `$XDG_DATA_HOME/tant`.
+pub fn data_dir() -> PathBuf {
+    data_home().join("tant")
+}
+
+/// `$XDG_CONFIG_HOME`, falling back to `~/.config`.
+pub fn config_home() -> PathBuf {
+    xdg_dir("XDG_CONFIG_HOME", ".config")
+}
+
+/// `$XDG_CONFIG_HOME/tant`.
+pub fn config_dir() -> PathBuf {
+    config_home().join("tant")
+}
+
+/// `$XDG_STATE_HOME`, falling back to `~/.local/state`.
+pub fn state_home() -> PathBuf {
+    xdg_dir("XDG_STATE_HOME", ".local/state")
+}
+
+/// `$XDG_STATE_HOME/tant`.
+pub fn state_dir() -> PathBuf {
+    state_home().join("tant")
+}
+
+pub fn home_dir() -> PathBuf {
+    std::env::var_os("HOME").map(PathBuf::from).unwrap_or_default()
+}
+
+fn xdg_dir(var: &str, home_relative: &str) -> PathBuf {
+    // The spec says relative values must be ignored
+    match std::env::var_os(var).map(PathBuf::from) {
+        Some(dir) if dir.is_absolute() => dir,
+        _ => home_dir().join(home_relative),
+    }
+}
+
+/// Writes through a temporary file in the same directory and renames it over
+/// `path`, so a crash mid-write never leaves a truncated file behind.
+pub fn write_atomic(path: &Path, contents: &[u8]) -> std::io::Result<()> {
+    let dir = path.parent().filter(|dir| !dir.as_os_str().is_empty()).unwrap_or(Path::new("."));
+    std::fs::create_dir_all(dir)?;
+    let name = path.file_name().map(|name| name.to_string_lossy().to_string()).unwrap_or_default();
+    let temp = dir.join(format!(".{}.{}.tmp", name, std::process::id()));
+    let result = std::fs::File::create(&temp).and_then(|mut file| {
+        file.write_all(contents)?;
+        file.sync_all()
+    });
+    match result.and_then(|()| std::fs::rename(&temp, path)) {
+        Ok(()) => Ok(()),
+        Err(err) => {
+            std::fs::remove_file(&temp).ok();
+            Err(err)
+        }
+    }
+}
+
+/// `input` trimmed, if it can name a file of its own in one of tant's
+/// directories: not empty, hidden, a path or holding control characters. `kind`
+/// is what the name is for, as in "session" or "layout".
+pub fn file_name_safe(input: &str, kind: &str) -> Result<String, String> {
+    let name = input.trim();
+    if name.is_empty() {
+        return Err(format!("The {} name is empty", kind));
+    }
+    if name.starts_with('.') || name.contains(['/', '\\']) || name.chars().any(char::is_control) {
+        return Err(format!("'{}' can't be used as a {} name", name, kind));
+    }
+    Ok(name.to_string())
+}
+
+/// Copies a file tant used to keep in the working directory to its new home,
+/// unless something is already there or `is_tants` doesn't recognise it.
+/// Returns whether it copied.
+pub fn adopt_legacy_file(legacy: &Path, target: &Path, is_tants: fn(&str) -> bool) -> std::io::Result<bool> {
+    if target.exists() || !legacy.is_file() {
+        return Ok(false);
+    }
+    let contents = std::fs::read_to_string(legacy)?;
+    if !is_tants(&contents) {
+        return Ok(false);
+    }
+    write_atomic(target, contents.as_bytes())?;
+    Ok(true)
+}
+
+/// Whether `json` is a `T` as tant wrote it: it parses as one and has no
+/// top-level keys a `T` lacks, so some other program's `config.json` doesn't
+/// pass just because every field has a default.
+pub fn written_as<T: Serialize + DeserializeOwned>(json: &str) -> bool {
+    let Ok(Value::Object(fields)) = serde_json::from_str::<Value>(json) else {
+        return false;
+    };
+    let Ok(Value::Object(known)) = serde_json::from_str::<T>(json).and_then(|data| serde_json::to_value(data)) else {
+        return false;
+    };
+    !fields.is_empty() && fields.keys().all(|key| known.contains_key(key))
+}
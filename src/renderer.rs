@@ -1,56 +1,267 @@
 // Renderer + UI shell
 
-use iced::widget::{Canvas, Column, Row, Text, Scrollable, Container, container, Checkbox, mouse_area, scrollable};
+use iced::widget::{Canvas, Column, Row, Text, Scrollable, Container, Space, container, Checkbox, mouse_area, scrollable, text_editor};
 use iced::widget::button::Button;
 use iced::widget::text_input::TextInput;
 use iced::{Element, Length, Color, Point, Size, Rectangle, Theme, Pixels, Font, Alignment, Border, Background};
 use iced::widget::canvas::{self, Program, Frame};
+use iced::widget::text::{LineHeight, Shaping};
 use iced::mouse::Cursor;
 use vt100;
 use chrono::Utc;
 use crate::{ExportToast, Message, AiSettings, Block, ThemeConfig, Tab, AiChatMessage, AiChatRole, AiContextScope, AiQuickAction, AiContextPreview, AiPromptTemplateId, PlanTier, PlanLimits, UsageSnapshot, AiCitation, LayoutNode, Axis};
 use crate::export::ExportFormat;
-use std::collections::HashMap;
+use crate::theme_manager::theme_color;
+use crate::fonts::FontSet;
+use crate::atlas_renderer::GridRaster;
+use crate::selection::Selection;
+use crate::find::FindState;
+use crate::history_query::{HistoryQuery, TextMatches};
+use crate::history_store::HistoryMatch;
+use crate::bookmarks::BlockAnnotation;
+use crate::folding::{gutter, hidden_label, visible_window, FoldSettings};
+use crate::watch::{sparkline, Watch, WatchForm, SPARKLINE_RUNS};
+use crate::completion::Suggestion;
+use crate::command_editor::{CommandEditor, ShellHighlighter, SyntaxColors};
+use crate::diagnostics::{Diagnostic, Severity};
+use crate::file_links::FileLink;
+use unicode_width::UnicodeWidthStr;
+use iced::widget::image::{FilterMethod, Image};
+use iced::ContentFit;
+use crate::accessibility::{ensure_contrast, text_color_on, StatusColors, DEFAULT_MIN_CONTRAST};
+use std::collections::{HashMap, HashSet};
 use std::hash::{Hash, Hasher, DefaultHasher};
 use std::sync::{Arc, Mutex};
 
+// Line height of block output, relative to its text size
+const OUTPUT_LINE_HEIGHT: f32 = 1.3;
+// Diagnostics listed under a failed block before the rest are summarised
+const DIAGNOSTICS_SHOWN: usize = 8;
+
 #[derive(Clone)]
 pub struct StyleRun {
     text: String,
     fg: Color,
     bg: Color,
+    font: Font,
     x: f32,
     width: f32,
 }
 
-fn compute_row_hash(screen: &vt100::Screen, row: u16) -> u64 {
+fn hash_color(color: vt100::Color, hasher: &mut DefaultHasher) {
+    match color {
+        vt100::Color::Default => 0u8.hash(hasher),
+        vt100::Color::Idx(idx) => (1u8, idx).hash(hasher),
+        vt100::Color::Rgb(r, g, b) => (2u8, r, g, b).hash(hasher),
+    }
+}
+
+pub(crate) fn compute_row_hash(screen: &vt100::Screen, row: u16, highlights: &[(u16, u16, Highlight)]) -> u64 {
     let mut hasher = DefaultHasher::new();
-    let cols = screen.size().0;
+    let cols = screen.size().1;
+    highlights.hash(&mut hasher);
     for col in 0..cols {
         if let Some(cell) = screen.cell(row, col) {
             cell.contents().hash(&mut hasher);
-            format!("{:?}", cell.fgcolor()).hash(&mut hasher);
-            format!("{:?}", cell.bgcolor()).hash(&mut hasher);
+            hash_color(cell.fgcolor(), &mut hasher);
+            hash_color(cell.bgcolor(), &mut hasher);
         }
     }
     hasher.finish()
 }
 
-fn compute_runs(screen: &vt100::Screen, row: u16, cell_width: f32, _cell_height: f32) -> Vec<StyleRun> {
-    let cols = screen.size().0;
+/// Terminal colours resolved from the active `ThemeConfig`.
+#[derive(Debug, Clone, Copy)]
+pub struct TerminalPalette {
+    pub foreground: Color,
+    pub background: Color,
+    pub ansi: [Color; 16],
+    pub selection: Color,
+    pub find_match: Color,
+    pub find_current: Color,
+    pub min_contrast: f32,
+}
+
+impl TerminalPalette {
+    pub fn from_theme(theme_config: &ThemeConfig, min_contrast: f32) -> Self {
+        let defaults = default_ansi_colors();
+        let mut ansi = defaults;
+        for (index, color) in ansi.iter_mut().enumerate() {
+            *color = theme_color(theme_config, &format!("ansi_{}", index), defaults[index]);
+        }
+        TerminalPalette {
+            foreground: theme_color(theme_config, "foreground", Color::from_rgb(0.9, 0.9, 0.9)),
+            background: theme_color(theme_config, "background", default_bg_color()),
+            ansi,
+            selection: theme_color(theme_config, "selection", Color::from_rgb(0.24, 0.27, 0.33)),
+            find_match: theme_color(theme_config, "find_match", Color::from_rgb(0.42, 0.36, 0.12)),
+            find_current: theme_color(theme_config, "find_current", Color::from_rgb(0.85, 0.60, 0.10)),
+            min_contrast,
+        }
+    }
+
+    fn highlight(&self, highlight: Highlight) -> Color {
+        match highlight {
+            Highlight::FindMatch => self.find_match,
+            Highlight::CurrentMatch => self.find_current,
+            Highlight::Selection => self.selection,
+        }
+    }
+}
+
+/// Cell backgrounds drawn over the grid; later variants win where they overlap.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Highlight {
+    FindMatch,
+    CurrentMatch,
+    Selection,
+}
+
+/// Selection and find matches to paint over a pane's grid.
+#[derive(Clone, Copy, Default)]
+pub struct GridOverlay<'a> {
+    pub selection: Option<&'a Selection>,
+    pub find: Option<&'a FindState>,
+}
+
+/// A pane's active watches and the state of its watch stacks and form.
+#[derive(Clone, Copy, Default)]
+pub struct WatchOverlay<'a> {
+    pub watches: &'a [Watch],
+    pub form: Option<&'a WatchForm>,
+    pub expanded: Option<&'a HashSet<u64>>,
+}
+
+/// What a pane shows over its blocks: the block jumped to from a bookmark, the
+/// annotation being edited, each block's output scroll offset and its watches.
+#[derive(Clone, Copy, Default)]
+pub struct BlockOverlay<'a> {
+    /// Tab and pane the blocks belong to, so scrolling reaches the right pane.
+    pub tab: usize,
+    pub pane: usize,
+    pub highlighted: Option<usize>,
+    pub annotation: Option<&'a BlockAnnotation>,
+    pub output_scroll: Option<&'a HashMap<usize, f32>>,
+    pub watch: WatchOverlay<'a>,
+}
+
+impl<'a> BlockOverlay<'a> {
+    fn block(&self, index: usize) -> BlockState<'a> {
+        BlockState {
+            highlighted: self.highlighted == Some(index),
+            annotation: self.annotation.filter(|annotation| annotation.block_index == index),
+            watch_form: self.watch.form.filter(|form| form.block_index == index),
+            scroll: OutputScroll {
+                tab: self.tab,
+                pane: self.pane,
+                offset_y: self.output_scroll.and_then(|scroll| scroll.get(&index)).copied().unwrap_or(0.0),
+            },
+        }
+    }
+}
+
+// One block's share of the overlay
+struct BlockState<'a> {
+    highlighted: bool,
+    annotation: Option<&'a BlockAnnotation>,
+    watch_form: Option<&'a WatchForm>,
+    scroll: OutputScroll,
+}
+
+// Where an expanded block's output region is scrolled to, and whose it is
+#[derive(Clone, Copy)]
+struct OutputScroll {
+    tab: usize,
+    pane: usize,
+    offset_y: f32,
+}
+
+impl OutputScroll {
+    fn block(&self, index: usize) -> PaneBlock {
+        PaneBlock { tab: self.tab, pane: self.pane, index }
+    }
+}
+
+// A history block and the tab and pane it is in, so clicks on its links and
+// diagnostics act on that pane rather than the focused one
+#[derive(Clone, Copy)]
+struct PaneBlock {
+    tab: usize,
+    pane: usize,
+    index: usize,
+}
+
+impl WatchOverlay<'_> {
+    fn is_expanded(&self, id: u64) -> bool {
+        self.expanded.is_some_and(|expanded| expanded.contains(&id))
+    }
+}
+
+impl GridOverlay<'_> {
+    /// Highlighted column ranges of a visible screen row.
+    pub fn row(&self, screen: &vt100::Screen, row: u16) -> Vec<(u16, u16, Highlight)> {
+        let line = row as isize - screen.scrollback() as isize;
+        let cols = screen.size().1;
+        let mut ranges = Vec::new();
+        if let Some(find) = self.find {
+            for (from, to, current) in find.columns_in_line(line, cols) {
+                ranges.push((from, to, if current { Highlight::CurrentMatch } else { Highlight::FindMatch }));
+            }
+        }
+        if let Some((from, to)) = self.selection.and_then(|selection| selection.columns_in_line(line, cols)) {
+            ranges.push((from, to, Highlight::Selection));
+        }
+        ranges
+    }
+}
+
+pub(crate) fn highlight_at(highlights: &[(u16, u16, Highlight)], col: u16) -> Option<Highlight> {
+    highlights
+        .iter()
+        .filter(|(from, to, _)| col >= *from && col < *to)
+        .map(|(_, _, highlight)| *highlight)
+        .max()
+}
+
+fn cell_font(cell: &vt100::Cell, fonts: &FontSet) -> Font {
+    match cell.contents().chars().next() {
+        Some(c) => fonts.font_for_char(c),
+        None => fonts.primary(),
+    }
+}
+
+/// Foreground and background for one cell, with the minimum contrast applied.
+pub(crate) fn cell_colors(cell: &vt100::Cell, palette: &TerminalPalette, highlight: Option<Highlight>) -> (Color, Color) {
+    let bg = match highlight {
+        Some(highlight) => palette.highlight(highlight),
+        None => bgcolor_to_iced(cell.bgcolor(), palette),
+    };
+    let fg = ensure_contrast(color_to_iced(cell.fgcolor(), palette), bg, palette.min_contrast);
+    (fg, bg)
+}
+
+fn compute_runs(screen: &vt100::Screen, row: u16, cell_width: f32, _cell_height: f32, palette: &TerminalPalette, fonts: &FontSet, highlights: &[(u16, u16, Highlight)]) -> Vec<StyleRun> {
+    let cols = screen.size().1;
+    let cell_bg = |cell: &vt100::Cell, col: u16| match highlight_at(highlights, col) {
+        Some(highlight) => palette.highlight(highlight),
+        None => bgcolor_to_iced(cell.bgcolor(), palette),
+    };
     let mut runs = vec![];
     let mut col = 0;
     while col < cols {
         if let Some(cell) = screen.cell(row, col) {
             let start_col = col;
-            let fg = color_to_iced(cell.fgcolor());
-            let bg = bgcolor_to_iced(cell.bgcolor());
+            let fg = color_to_iced(cell.fgcolor(), palette);
+            let bg = cell_bg(cell, col);
+            let font = cell_font(cell, fonts);
             let mut text = cell.contents().to_string();
             col += 1;
             while col < cols {
                 if let Some(next_cell) = screen.cell(row, col) {
-                    if color_to_iced(next_cell.fgcolor()) == fg && bgcolor_to_iced(next_cell.bgcolor()) == bg {
-                        text.push_str(&next_cell.contents());
+                    // Wide-character continuation cells have no contents and stay in the run
+                    let same_font = next_cell.is_wide_continuation() || cell_font(next_cell, fonts) == font;
+                    if same_font && color_to_iced(next_cell.fgcolor(), palette) == fg && cell_bg(next_cell, col) == bg {
+                        text.push_str(next_cell.contents());
                         col += 1;
                     } else {
                         break;
@@ -61,7 +272,9 @@ fn compute_runs(screen: &vt100::Screen, row: u16, cell_width: f32, _cell_height:
             }
             let x = start_col as f32 * cell_width;
             let width = (col - start_col) as f32 * cell_width;
-            runs.push(StyleRun { text, fg, bg, x, width });
+            // Programs pick their own colour pairs; keep them readable on this theme
+            let fg = ensure_contrast(fg, bg, palette.min_contrast);
+            runs.push(StyleRun { text, fg, bg, font, x, width });
         } else {
             col += 1;
         }
@@ -69,16 +282,20 @@ fn compute_runs(screen: &vt100::Screen, row: u16, cell_width: f32, _cell_height:
     runs
 }
 
-fn draw_runs(frame: &mut Frame, runs: &[StyleRun], y: f32, cell_height: f32) {
+fn draw_runs(frame: &mut Frame, runs: &[StyleRun], y: f32, cell_height: f32, font_size: f32, ligatures: bool) {
     for run in runs {
         frame.fill_rectangle(Point::new(run.x, y), Size::new(run.width, cell_height), run.bg);
         if !run.text.is_empty() && run.text != " ".repeat(run.text.len()) {
+            // Basic shaping never forms ligatures; non-ASCII runs need advanced shaping for fallback glyphs
+            let shaping = if ligatures || !run.text.is_ascii() { Shaping::Advanced } else { Shaping::Basic };
             let text_canvas = canvas::Text {
                 content: run.text.clone(),
                 position: Point::new(run.x, y),
-                size: Pixels(cell_height),
+                size: Pixels(font_size),
+                line_height: LineHeight::Absolute(Pixels(cell_height)),
                 color: run.fg,
-                font: Font::MONOSPACE,
+                font: run.font,
+                shaping,
                 ..canvas::Text::default()
             };
             frame.fill_text(text_canvas);
@@ -86,29 +303,65 @@ fn draw_runs(frame: &mut Frame, runs: &[StyleRun], y: f32, cell_height: f32) {
     }
 }
 
-pub struct TerminalRenderer;
+const COMMAND_INPUT_PADDING: f32 = 18.0;
 
-#[derive(Debug, Clone, Copy)]
-struct MatchRanges {
-    command: bool,
-    output: bool,
-}
+// text_editor has no size setting in iced 0.12, so it draws at the default text size
+const COMMAND_TEXT_SIZE: f32 = 16.0;
 
-fn normalize_query(query: &str) -> String {
-    query.trim().to_lowercase()
+/// The command being typed in a pane and its inline suggestion.
+#[derive(Clone, Copy)]
+#[derive(Default)]
+pub struct CommandLine<'a> {
+    /// `None` for a view with no shell behind it.
+    pub editor: Option<&'a CommandEditor>,
+    pub text: &'a str,
+    pub suggestion: Option<&'a Suggestion>,
 }
 
-fn block_matches_query(block: &Block, query: &str) -> MatchRanges {
-    if query.is_empty() {
-        return MatchRanges { command: false, output: false };
+// The command editor draws no box of its own so a ghost suggestion beside it reads
+// as part of the same line; the surrounding container provides the frame
+struct CommandEditorStyle;
+
+impl text_editor::StyleSheet for CommandEditorStyle {
+    type Style = Theme;
+
+    fn active(&self, _style: &Theme) -> text_editor::Appearance {
+        text_editor::Appearance { background: Background::Color(Color::TRANSPARENT), border: Border::default() }
+    }
+
+    fn focused(&self, style: &Theme) -> text_editor::Appearance {
+        self.active(style)
+    }
+
+    fn placeholder_color(&self, _style: &Theme) -> Color {
+        Color::from_rgb(0.7, 0.7, 0.7)
+    }
+
+    fn value_color(&self, style: &Theme) -> Color {
+        style.palette().text
+    }
+
+    fn disabled_color(&self, _style: &Theme) -> Color {
+        Color::from_rgb(0.5, 0.5, 0.5)
+    }
+
+    fn selection_color(&self, style: &Theme) -> Color {
+        style.palette().primary
+    }
+
+    fn disabled(&self, style: &Theme) -> text_editor::Appearance {
+        self.active(style)
     }
-    let q = normalize_query(query);
-    let cmd_match = block.command.to_lowercase().contains(&q);
-    let out_match = block.output.to_lowercase().contains(&q);
-    MatchRanges { command: cmd_match, output: out_match }
 }
 
-fn matches_filters(block: &Block, query: &str, success_only: bool, failure_only: bool, pinned_only: bool) -> Option<MatchRanges> {
+pub struct TerminalRenderer {
+    pub min_contrast: f32,
+    pub status_colors: StatusColors,
+    pub fonts: Arc<FontSet>,
+    pub folding: FoldSettings,
+}
+
+fn matches_filters(block: &Block, query: &HistoryQuery, success_only: bool, failure_only: bool, pinned_only: bool) -> Option<TextMatches> {
     if pinned_only && !block.pinned {
         return None;
     }
@@ -118,15 +371,7 @@ fn matches_filters(block: &Block, query: &str, success_only: bool, failure_only:
     if failure_only && matches!(block.exit_code, Some(0) | None) {
         return None;
     }
-    if query.is_empty() {
-        return Some(MatchRanges { command: false, output: false });
-    }
-    let ranges = block_matches_query(block, query);
-    if ranges.command || ranges.output {
-        Some(ranges)
-    } else {
-        None
-    }
+    query.matches(block).then(|| query.text_matches(block))
 }
 
 fn screen_to_text(screen: &vt100::Screen) -> String {
@@ -138,7 +383,7 @@ fn screen_to_text(screen: &vt100::Screen) -> String {
         let mut line = String::new();
         for col in 0..cols {
             if let Some(cell) = screen.cell(row as u16, col as u16) {
-                line.push_str(&cell.contents());
+                line.push_str(cell.contents());
             }
         }
         out.push_str(line.trim_end());
@@ -174,39 +419,45 @@ fn strip_prompt_line(output: &str, prompt_line: &str) -> String {
     lines.join("\n")
 }
 
-fn color_to_iced(color: vt100::Color) -> Color {
+fn default_ansi_colors() -> [Color; 16] {
+    [
+        Color::from_rgb(0.0, 0.0, 0.0),       // Black
+        Color::from_rgb(0.8, 0.0, 0.0),       // Red
+        Color::from_rgb(0.0, 0.8, 0.0),       // Green
+        Color::from_rgb(0.8, 0.8, 0.0),       // Yellow
+        Color::from_rgb(0.0, 0.0, 0.8),       // Blue
+        Color::from_rgb(0.8, 0.0, 0.8),       // Magenta
+        Color::from_rgb(0.0, 0.8, 0.8),       // Cyan
+        Color::from_rgb(0.9, 0.9, 0.9),       // White
+        Color::from_rgb(0.5, 0.5, 0.5),       // Bright Black
+        Color::from_rgb(1.0, 0.0, 0.0),       // Bright Red
+        Color::from_rgb(0.0, 1.0, 0.0),       // Bright Green
+        Color::from_rgb(1.0, 1.0, 0.0),       // Bright Yellow
+        Color::from_rgb(0.0, 0.0, 1.0),       // Bright Blue
+        Color::from_rgb(1.0, 0.0, 1.0),       // Bright Magenta
+        Color::from_rgb(0.0, 1.0, 1.0),       // Bright Cyan
+        Color::from_rgb(1.0, 1.0, 1.0),       // Bright White
+    ]
+}
+
+fn color_to_iced(color: vt100::Color, palette: &TerminalPalette) -> Color {
     match color {
         vt100::Color::Rgb(r, g, b) => Color::from_rgb(r as f32 / 255.0, g as f32 / 255.0, b as f32 / 255.0),
         vt100::Color::Idx(idx) => {
-            // Standard ANSI colors
-            match idx {
-                0 => Color::from_rgb(0.0, 0.0, 0.0),       // Black
-                1 => Color::from_rgb(0.8, 0.0, 0.0),       // Red
-                2 => Color::from_rgb(0.0, 0.8, 0.0),       // Green
-                3 => Color::from_rgb(0.8, 0.8, 0.0),       // Yellow
-                4 => Color::from_rgb(0.0, 0.0, 0.8),       // Blue
-                5 => Color::from_rgb(0.8, 0.0, 0.8),       // Magenta
-                6 => Color::from_rgb(0.0, 0.8, 0.8),       // Cyan
-                7 => Color::from_rgb(0.9, 0.9, 0.9),       // White
-                8 => Color::from_rgb(0.5, 0.5, 0.5),       // Bright Black
-                9 => Color::from_rgb(1.0, 0.0, 0.0),       // Bright Red
-                10 => Color::from_rgb(0.0, 1.0, 0.0),      // Bright Green
-                11 => Color::from_rgb(1.0, 1.0, 0.0),      // Bright Yellow
-                12 => Color::from_rgb(0.0, 0.0, 1.0),      // Bright Blue
-                13 => Color::from_rgb(1.0, 0.0, 1.0),      // Bright Magenta
-                14 => Color::from_rgb(0.0, 1.0, 1.0),      // Bright Cyan
-                15 => Color::from_rgb(1.0, 1.0, 1.0),      // Bright White
-                _ => Color::from_rgb(0.9, 0.9, 0.9),       // Default to white
+            // Standard ANSI colors come from the theme
+            match palette.ansi.get(idx as usize) {
+                Some(color) => *color,
+                None => palette.foreground, // Default to foreground
             }
         }
-        vt100::Color::Default => Color::from_rgb(0.9, 0.9, 0.9), // Default foreground
+        vt100::Color::Default => palette.foreground, // Default foreground
     }
 }
 
-fn bgcolor_to_iced(color: vt100::Color) -> Color {
+fn bgcolor_to_iced(color: vt100::Color, palette: &TerminalPalette) -> Color {
     match color {
-        vt100::Color::Default => default_bg_color(), // Use ash gray for default background
-        _ => color_to_iced(color), // Use regular color mapping for explicit colors
+        vt100::Color::Default => palette.background, // Theme background for default cells
+        _ => color_to_iced(color, palette), // Use regular color mapping for explicit colors
     }
 }
 
@@ -216,67 +467,144 @@ fn default_bg_color() -> Color {
 
 impl TerminalRenderer {
     pub fn new() -> Self {
-        TerminalRenderer
+        TerminalRenderer { min_contrast: DEFAULT_MIN_CONTRAST, status_colors: StatusColors::Default, fonts: Arc::new(FontSet::fallback_only()), folding: FoldSettings::default() }
     }
 
-    pub fn cell_size(&self, theme_config: &ThemeConfig) -> (f32, f32) {
-        (8.0, theme_config.line_height * theme_config.font_size)
+    /// Terminal cell size in pixels for a pane at the given zoom level. The theme's
+    /// `line_height` is a minimum so fonts with tall ascenders are never clipped.
+    pub fn cell_size(&self, theme_config: &ThemeConfig, zoom: f32) -> (f32, f32) {
+        let font_size = theme_config.font_size * zoom;
+        let metrics = self.fonts.metrics;
+        (metrics.advance * font_size, metrics.line_height.max(theme_config.line_height) * font_size)
     }
 
-    pub fn view<'a>(&self, history: &'a [Block], current: &'a Option<Block>, current_command: &'a str, search_query: &'a str, search_success_only: bool, search_failure_only: bool, search_pinned_only: bool, search_input_id: iced::widget::text_input::Id, screen: &vt100::Screen, alt_screen_active: bool, ai_settings: &'a AiSettings, _ai_response: &'a Option<String>, _scroll_offset: usize, _selection_start: Option<(usize, usize)>, _selection_end: Option<(usize, usize)>, render_cache: &Arc<Mutex<HashMap<(usize, usize, u16), Vec<StyleRun>>>>, row_hashes: &Arc<Mutex<HashMap<(usize, usize, u16), u64>>>, tab_id: usize, pane_id: usize, theme_config: &'a ThemeConfig, tabs: &'a [Tab], active_tab: usize, renaming_tab: Option<usize>, rename_buffer: &'a str, history_search_active: bool, history_search_query: &'a str, history_matches: &'a [String], history_selected: usize, ai_panel_open: bool, ai_context_scope: AiContextScope, ai_chat: &'a [AiChatMessage], ai_input: &'a str, ai_pending: bool, ai_streaming: bool, ai_preview: AiContextPreview, highlighted_block: Option<usize>, history_scroll_id: scrollable::Id, ai_redaction_override: bool, ai_last_redactions: &'a [String], ai_last_redacted_preview: Option<&'a str>, ai_selected_template: Option<AiPromptTemplateId>, export_toast: Option<&'a ExportToast>, plan_tier: PlanTier, plan_limits: PlanLimits, usage_snapshot: UsageSnapshot) -> Element<'a, Message> {
-        // Use raw terminal mode for TUI apps (vim, top, etc.), block mode for normal shell
-        if alt_screen_active {
-            let canvas = Canvas::new(TerminalCanvas {
-                screen: screen.clone(),
-                cell_width: 8.0,
-                cell_height: theme_config.line_height * theme_config.font_size,
-                render_cache: render_cache.clone(),
-                row_hashes: row_hashes.clone(),
-                tab_id,
-                pane_id,
-            })
-            .width(Length::Fill)
-            .height(Length::Fill);
+    pub fn font(&self) -> Font {
+        self.fonts.primary()
+    }
+
+    pub fn view<'a>(&self, history: &'a [Block], current: &'a Option<Block>, command_line: CommandLine<'a>, search_query: &'a str, search_success_only: bool, search_failure_only: bool, search_pinned_only: bool, search_input_id: iced::widget::text_input::Id, screen: &vt100::Screen, alt_screen_active: bool, ai_settings: &'a AiSettings, _ai_response: &'a Option<String>, _scroll_offset: usize, overlay: GridOverlay, render_cache: &Arc<Mutex<HashMap<(usize, usize, u16), Vec<StyleRun>>>>, row_hashes: &Arc<Mutex<HashMap<(usize, usize, u16), u64>>>, raster: Option<&GridRaster>, tab_id: usize, pane_id: usize, theme_config: &'a ThemeConfig, tabs: &'a [Tab], active_tab: usize, renaming_tab: Option<usize>, rename_buffer: &'a str, history_search_active: bool, history_search_query: &'a str, history_matches: &'a [HistoryMatch], history_selected: usize, ai_panel_open: bool, ai_context_scope: AiContextScope, ai_chat: &'a [AiChatMessage], ai_input: &'a str, ai_pending: bool, ai_streaming: bool, ai_preview: AiContextPreview, blocks: BlockOverlay<'a>, history_scroll_id: scrollable::Id, ai_redaction_override: bool, ai_last_redactions: &'a [String], ai_last_redacted_preview: Option<&'a str>, ai_selected_template: Option<AiPromptTemplateId>, export_toast: Option<&'a ExportToast>, plan_tier: PlanTier, plan_limits: PlanLimits, usage_snapshot: UsageSnapshot, zoom: f32) -> Element<'a, Message> {
+        // Use raw terminal mode for TUI apps (vim, top, etc.) and while finding in the grid, block mode for normal shell
+        if alt_screen_active || overlay.find.is_some() {
+            let (cell_width, cell_height) = self.cell_size(theme_config, zoom);
+            let palette = TerminalPalette::from_theme(theme_config, self.min_contrast);
+            // The atlas raster draws cell by cell; ligatures need shaped text, so they keep the canvas
+            let terminal: Element<'a, Message> = if theme_config.enable_ligatures {
+                Canvas::new(TerminalCanvas {
+                    screen: screen.clone(),
+                    cell_width,
+                    cell_height,
+                    render_cache: render_cache.clone(),
+                    row_hashes: row_hashes.clone(),
+                    tab_id,
+                    pane_id,
+                    palette,
+                    fonts: self.fonts.clone(),
+                    font_size: theme_config.font_size * zoom,
+                    ligatures: theme_config.enable_ligatures,
+                    highlights: (0..screen.size().0).map(|row| overlay.row(screen, row)).collect(),
+                })
+                .width(Length::Fill)
+                .height(Length::Fill)
+                .into()
+            } else {
+                let content: Element<'a, Message> = match raster.and_then(|raster| raster.handle().map(|handle| (handle.clone(), raster.size()))) {
+                    Some((handle, (width, height))) => Image::new(handle)
+                        .width(Length::Fixed(width as f32))
+                        .height(Length::Fixed(height as f32))
+                        .content_fit(ContentFit::None)
+                        .filter_method(FilterMethod::Nearest)
+                        .into(),
+                    None => Column::new().into(),
+                };
+                Container::new(content)
+                    .width(Length::Fill)
+                    .height(Length::Fill)
+                    .clip(true)
+                    .style(move |_theme: &Theme| container::Appearance {
+                        background: Some(Background::Color(palette.background)),
+                        ..Default::default()
+                    })
+                    .into()
+            };
+
+            let terminal: Element<'a, Message> = match overlay.find {
+                // Below the grid so cell coordinates still start at the pane origin
+                Some(find) => Column::new()
+                    .push(terminal)
+                    .push(self.render_find_bar(find, theme_config))
+                    .into(),
+                None => terminal,
+            };
 
             if ai_panel_open {
                 let panel = self.render_ai_panel(ai_context_scope, ai_chat, ai_input, ai_pending, ai_streaming, pane_id, theme_config, ai_preview, ai_settings, ai_redaction_override, ai_last_redactions, ai_last_redacted_preview, ai_selected_template, plan_tier, plan_limits, usage_snapshot);
                 Row::new()
-                    .push(Container::new(canvas).width(Length::FillPortion(7)))
+                    .push(Container::new(terminal).width(Length::FillPortion(7)))
                     .push(Container::new(panel).width(Length::FillPortion(3)))
                     .height(Length::Fill)
                     .into()
             } else {
-                canvas.into()
+                terminal
             }
         } else {
-            self.render_blocks(history, current, current_command, search_query, search_success_only, search_failure_only, search_pinned_only, search_input_id, screen, theme_config, tabs, active_tab, renaming_tab, rename_buffer, history_search_active, history_search_query, history_matches, history_selected, ai_panel_open, ai_context_scope, ai_chat, ai_input, ai_pending, ai_streaming, pane_id, highlighted_block, ai_preview, history_scroll_id, ai_settings, ai_redaction_override, ai_last_redactions, ai_last_redacted_preview, ai_selected_template, export_toast, plan_tier, plan_limits, usage_snapshot)
+            self.render_blocks(history, current, command_line, search_query, search_success_only, search_failure_only, search_pinned_only, search_input_id, screen, theme_config, tabs, active_tab, renaming_tab, rename_buffer, history_search_active, history_search_query, history_matches, history_selected, ai_panel_open, ai_context_scope, ai_chat, ai_input, ai_pending, ai_streaming, pane_id, blocks, ai_preview, history_scroll_id, ai_settings, ai_redaction_override, ai_last_redactions, ai_last_redacted_preview, ai_selected_template, export_toast, plan_tier, plan_limits, usage_snapshot, zoom)
         }
     }
 
-    fn render_blocks<'a>(&self, history: &'a [Block], current: &'a Option<Block>, current_command: &'a str, search_query: &'a str, search_success_only: bool, search_failure_only: bool, search_pinned_only: bool, search_input_id: iced::widget::text_input::Id, screen: &vt100::Screen, theme_config: &'a ThemeConfig, tabs: &'a [Tab], active_tab: usize, renaming_tab: Option<usize>, rename_buffer: &'a str, history_search_active: bool, history_search_query: &'a str, history_matches: &'a [String], history_selected: usize, ai_panel_open: bool, ai_context_scope: AiContextScope, ai_chat: &'a [AiChatMessage], ai_input: &'a str, ai_pending: bool, ai_streaming: bool, pane_id: usize, highlighted_block: Option<usize>, ai_preview: AiContextPreview, history_scroll_id: scrollable::Id, ai_settings: &'a AiSettings, ai_redaction_override: bool, ai_last_redactions: &'a [String], ai_last_redacted_preview: Option<&'a str>, ai_selected_template: Option<AiPromptTemplateId>, export_toast: Option<&'a ExportToast>, plan_tier: PlanTier, plan_limits: PlanLimits, usage_snapshot: UsageSnapshot) -> Element<'a, Message> {
+    fn render_find_bar<'a>(&self, find: &FindState, theme_config: &'a ThemeConfig) -> Element<'a, Message> {
+        let palette = TerminalPalette::from_theme(theme_config, self.min_contrast);
+        let status_color = if find.error.is_some() { self.status_colors.failure() } else { Color::from_rgb(0.7, 0.7, 0.7) };
+        let query = if find.query.is_empty() { "Find in terminal...".to_string() } else { find.query.clone() };
+        let row = Row::new()
+            .push(Text::new(query).font(self.font()).size(12.0).width(Length::Fill))
+            .push(Text::new(find.status()).size(11.0).style(status_color))
+            .push(Button::new(Text::new(find.mode.label()).size(11.0)).on_press(Message::CycleFindMode))
+            .push(Button::new(Text::new("Prev").size(11.0)).on_press(Message::FindPrev))
+            .push(Button::new(Text::new("Next").size(11.0)).on_press(Message::FindNext))
+            .push(Button::new(Text::new("Close").size(11.0)).on_press(Message::CloseFind))
+            .spacing(8)
+            .align_items(Alignment::Center);
+        Container::new(row)
+            .padding(6)
+            .width(Length::Fill)
+            .style(move |_theme: &Theme| container::Appearance {
+                background: Some(Background::Color(palette.selection)),
+                text_color: Some(palette.foreground),
+                ..Default::default()
+            })
+            .into()
+    }
+
+    fn render_blocks<'a>(&self, history: &'a [Block], current: &'a Option<Block>, command_line: CommandLine<'a>, search_query: &'a str, search_success_only: bool, search_failure_only: bool, search_pinned_only: bool, search_input_id: iced::widget::text_input::Id, screen: &vt100::Screen, theme_config: &'a ThemeConfig, tabs: &'a [Tab], active_tab: usize, renaming_tab: Option<usize>, rename_buffer: &'a str, history_search_active: bool, history_search_query: &'a str, history_matches: &'a [HistoryMatch], history_selected: usize, ai_panel_open: bool, ai_context_scope: AiContextScope, ai_chat: &'a [AiChatMessage], ai_input: &'a str, ai_pending: bool, ai_streaming: bool, pane_id: usize, blocks: BlockOverlay<'a>, ai_preview: AiContextPreview, history_scroll_id: scrollable::Id, ai_settings: &'a AiSettings, ai_redaction_override: bool, ai_last_redactions: &'a [String], ai_last_redacted_preview: Option<&'a str>, ai_selected_template: Option<AiPromptTemplateId>, export_toast: Option<&'a ExportToast>, plan_tier: PlanTier, plan_limits: PlanLimits, usage_snapshot: UsageSnapshot, zoom: f32) -> Element<'a, Message> {
         let mut column = Column::new().spacing(10).padding(theme_config.padding as u16);
+        let watch = blocks.watch;
 
         let live_screen_text = screen_to_text(screen);
         let prompt_line = last_non_empty_line(&live_screen_text).unwrap_or_default();
 
+        // An invalid query leaves the history unfiltered and shows the error under the search bar
+        let (query, query_error) = match HistoryQuery::parse(search_query) {
+            Ok(query) => (query, None),
+            Err(err) => (HistoryQuery::default(), Some(err)),
+        };
         let mut match_count = 0usize;
-        let mut filtered_blocks: Vec<(usize, &Block, MatchRanges)> = vec![];
+        let mut filtered_blocks: Vec<(usize, &Block, TextMatches)> = vec![];
         for (index, block) in history.iter().enumerate() {
-            if let Some(ranges) = matches_filters(block, search_query, search_success_only, search_failure_only, search_pinned_only) {
-                if !search_query.trim().is_empty() {
-                    if ranges.command { match_count += 1; }
-                    if ranges.output { match_count += 1; }
+            if let Some(ranges) = matches_filters(block, &query, search_success_only, search_failure_only, search_pinned_only) {
+                if !query.is_empty() {
+                    // Field-only queries count each matching block once
+                    match_count += (ranges.command as usize + ranges.output as usize).max(1);
                 }
                 filtered_blocks.push((index, block, ranges));
             }
         }
 
-        let search_input = TextInput::new("Search commands/output...", search_query)
+        let search_input = TextInput::new("Search... e.g. exit:!0 cwd:~/svc dur:>30s \"panic\"", search_query)
             .id(search_input_id)
             .on_input(Message::UpdateSearch)
             .size(12.0)
             .padding(6)
-            .font(Font::MONOSPACE);
+            .font(self.font());
 
         let search_badge = Text::new(format!("Matches: {}", match_count))
             .size(11.0)
@@ -295,6 +623,8 @@ impl TerminalRenderer {
             .on_press(Message::SelectAllBlocks);
         let deselect_all = Button::new(Text::new("Deselect all").size(11.0))
             .on_press(Message::DeselectAllBlocks);
+        let diff_selected = Button::new(Text::new("Diff").size(11.0))
+            .on_press(Message::DiffSelectedBlocks);
         let export_selected_md = Button::new(Text::new("Export MD").size(11.0))
             .on_press(Message::ExportSelected(ExportFormat::Markdown));
         let export_selected_json = Button::new(Text::new("Export JSON").size(11.0))
@@ -313,6 +643,7 @@ impl TerminalRenderer {
             .push(clear_filter)
             .push(select_all)
             .push(deselect_all)
+            .push(diff_selected)
             .push(export_selected_md)
             .push(export_selected_json)
             .push(export_selected_html)
@@ -322,6 +653,13 @@ impl TerminalRenderer {
 
         let tab_bar = self.render_tab_bar(tabs, active_tab, renaming_tab, rename_buffer, theme_config);
         column = column.push(tab_bar).push(search_row);
+        if let Some(err) = query_error {
+            column = column.push(
+                Text::new(err.to_string())
+                    .size(11.0)
+                    .style(self.status_colors.failure()),
+            );
+        }
 
         // Show live screen text if no history yet (so prompts are visible)
         if history.is_empty() && current.is_none() {
@@ -331,21 +669,34 @@ impl TerminalRenderer {
                 column = column.push(welcome);
             } else {
                 let live_output = Text::new(live_screen_text.clone())
-                    .font(Font::MONOSPACE)
-                    .size(theme_config.font_size - 2.0);
+                    .font(self.font())
+                    .size(theme_config.font_size * zoom - 2.0);
                 column = column.push(live_output);
             }
         }
 
-        // Render history blocks
+        // Render history blocks. Runs of a watch stack under one header; only the
+        // latest run shows unless the stack is expanded
+        let mut stacks_shown = HashSet::new();
+        let latest_runs: HashMap<u64, usize> = history.iter().enumerate().filter_map(|(index, run)| Some((run.watch_id?, index))).collect();
         for (index, block, ranges) in filtered_blocks {
-            let block_widget = self.render_block(block, index, theme_config, search_query, ranges, &prompt_line, highlighted_block == Some(index));
+            if let Some(id) = block.watch_id {
+                let latest = latest_runs.get(&id).copied().unwrap_or(index);
+                if latest != index && !watch.is_expanded(id) {
+                    continue;
+                }
+                if stacks_shown.insert(id) {
+                    let runs: Vec<&Block> = history.iter().filter(|run| run.watch_id == Some(id)).collect();
+                    column = column.push(self.render_watch_stack(id, latest, &runs, watch));
+                }
+            }
+            let block_widget = self.render_block(block, index, theme_config, search_query, ranges, &prompt_line, blocks.block(index), zoom);
             column = column.push(block_widget);
         }
 
         // Render current block if running
         if let Some(block) = current {
-            let current_block_widget = self.render_current_block(block, screen, theme_config);
+            let current_block_widget = self.render_current_block(block, screen, theme_config, zoom);
             column = column.push(current_block_widget);
         }
 
@@ -362,16 +713,24 @@ impl TerminalRenderer {
                 } else {
                     Color::from_rgb(0.8, 0.8, 0.8)
                 };
-                list = list.push(
-                    Text::new(item.clone())
-                        .font(Font::MONOSPACE)
-                        .size(theme_config.font_size - 2.0)
+                let mut entry = Row::new().spacing(8).align_items(Alignment::Center).push(
+                    Text::new(item.command.clone())
+                        .font(self.font())
+                        .size(theme_config.font_size * zoom - 2.0)
                         .style(color),
                 );
+                if item.imported {
+                    entry = entry.push(
+                        Text::new("imported")
+                            .size(10.0)
+                            .style(Color::from_rgb(0.55, 0.55, 0.55)),
+                    );
+                }
+                list = list.push(entry);
             }
             let header = Text::new(format!("Reverse search: {}", history_search_query))
-                .font(Font::MONOSPACE)
-                .size(theme_config.font_size - 2.0)
+                .font(self.font())
+                .size(theme_config.font_size * zoom - 2.0)
                 .style(Color::from_rgb(0.7, 0.7, 0.7));
             Some(
                 Container::new(Column::new().spacing(6).push(header).push(list))
@@ -390,16 +749,48 @@ impl TerminalRenderer {
             None
         };
 
-        // Command input area with better styling and increased height
-        let input = TextInput::new("Type a command here...", current_command)
-            .on_input(Message::TerminalInput)
-            .on_submit(Message::TerminalSubmit)
-            .padding(18)
-            .size(theme_config.font_size)
-            .font(Font::MONOSPACE); // TODO: use theme_config.font_family
-        
+        // Command editor area with better styling and increased height
+        let current_command = command_line.text;
+        let input: Element<'a, Message> = match command_line.editor {
+            Some(editor) => text_editor(&editor.content)
+                .on_action(Message::CommandEdited)
+                .padding(COMMAND_INPUT_PADDING)
+                .font(self.font())
+                .style(iced::theme::TextEditor::Custom(Box::new(CommandEditorStyle)))
+                .highlight::<ShellHighlighter>(SyntaxColors::from_theme(theme_config), |color, _theme| iced::advanced::text::highlighter::Format {
+                    color: Some(*color),
+                    font: None,
+                })
+                .into(),
+            None => Space::with_height(Length::Fixed(COMMAND_TEXT_SIZE + COMMAND_INPUT_PADDING * 2.0)).into(),
+        };
+        let ghost_color = Color::from_rgb(0.62, 0.62, 0.62);
+        let ghost = if current_command.is_empty() {
+            Some("Type a command here... (Shift+Enter for a new line)")
+        } else {
+            command_line.suggestion.and_then(|suggestion| suggestion.ghost(current_command))
+        };
+        let input_line: Element<'a, Message> = match (ghost, command_line.suggestion) {
+            // The editor is sized to its text so the rest of the suggestion sits right after the cursor
+            (Some(ghost), _) => {
+                let text_width = current_command.width() as f32 * self.fonts.metrics.advance * COMMAND_TEXT_SIZE;
+                Row::new()
+                    .align_items(Alignment::Center)
+                    .push(Container::new(input).width(Length::Fixed(text_width + COMMAND_INPUT_PADDING * 2.0 + 2.0)))
+                    .push(Text::new(ghost).font(self.font()).size(COMMAND_TEXT_SIZE).style(ghost_color).width(Length::Fill))
+                    .into()
+            }
+            (None, Some(suggestion)) => Row::new()
+                .align_items(Alignment::Center)
+                .push(input)
+                .push(Text::new(format!("→ {}", suggestion.text)).font(self.font()).size(COMMAND_TEXT_SIZE * 0.85).style(ghost_color))
+                .push(Space::with_width(Length::Fixed(COMMAND_INPUT_PADDING)))
+                .into(),
+            (None, None) => input,
+        };
+
         // Wrap input in a highly visible container
-        let input_with_bg = Container::new(input)
+        let input_with_bg = Container::new(input_line)
             .width(Length::Fill)
             .padding(4)
             .style(|_theme: &Theme| container::Appearance {
@@ -444,7 +835,7 @@ impl TerminalRenderer {
         // Directory label
         let dir_label = Row::new()
             .push(Text::new("📁 ").size(10.0))
-            .push(Text::new(cwd_str).size(10.0).font(Font::MONOSPACE))
+            .push(Text::new(cwd_str).size(10.0).font(self.font()))
             .spacing(3);
         metadata_row = metadata_row.push(dir_label);
         
@@ -468,7 +859,7 @@ impl TerminalRenderer {
             };
             let branch_label = Row::new()
                 .push(Text::new("🌿 ").size(10.0))
-                .push(Text::new(branch).size(10.0).font(Font::MONOSPACE).style(branch_color))
+                .push(Text::new(branch).size(10.0).font(self.font()).style(branch_color))
                 .spacing(3);
             let branch_with_status = if status_indicator.is_empty() {
                 branch_label
@@ -488,13 +879,13 @@ impl TerminalRenderer {
         };
         let host_label = Row::new()
             .push(Text::new(host_icon).size(10.0))
-            .push(Text::new(host).size(10.0).font(Font::MONOSPACE).style(host_color))
+            .push(Text::new(host).size(10.0).font(self.font()).style(host_color))
             .spacing(3);
         metadata_row = metadata_row.push(host_label);
 
         let prompt_color = match current.as_ref().and_then(|block| block.exit_code) {
-            Some(0) => Color::from_rgb(0.2, 0.8, 0.4),
-            Some(_) => Color::from_rgb(0.9, 0.35, 0.35),
+            Some(0) => self.status_colors.success(),
+            Some(_) => self.status_colors.failure(),
             None => Color::from_rgb(0.6, 0.8, 1.0),
         };
         let prompt_text = if prompt_line.trim().is_empty() {
@@ -504,8 +895,8 @@ impl TerminalRenderer {
         };
         let prompt_row = Container::new(
             Text::new(prompt_text)
-                .font(Font::MONOSPACE)
-                .size(theme_config.font_size)
+                .font(self.font())
+                .size(theme_config.font_size * zoom)
                 .style(prompt_color),
         )
         .padding([6, 12, 0, 12]);
@@ -764,7 +1155,7 @@ impl TerminalRenderer {
                     .into()
             } else {
                 let title_text = Text::new(tab.title.clone())
-                    .font(Font::MONOSPACE)
+                    .font(self.font())
                     .size(12.0)
                     .style(if is_active { Color::WHITE } else { Color::from_rgb(0.7, 0.7, 0.7) });
                 Button::new(title_text)
@@ -818,11 +1209,12 @@ impl TerminalRenderer {
             .into()
     }
 
-    fn render_block<'a>(&self, block: &'a Block, index: usize, theme_config: &'a ThemeConfig, search_query: &'a str, ranges: MatchRanges, prompt_line: &str, highlighted: bool) -> Element<'a, Message> {
+    fn render_block<'a>(&self, block: &'a Block, index: usize, theme_config: &'a ThemeConfig, search_query: &'a str, ranges: TextMatches, prompt_line: &str, state: BlockState<'a>, zoom: f32) -> Element<'a, Message> {
+        let BlockState { highlighted, annotation, watch_form, scroll } = state;
         let (status_display, status_color) = match block.exit_code {
-            Some(0) => ("Success".to_string(), Color::from_rgb(0.25, 0.8, 0.4)),
-            Some(code) => (format!("Exit {}", code), Color::from_rgb(0.9, 0.35, 0.35)),
-            None => ("Running".to_string(), Color::from_rgb(0.6, 0.6, 0.6)),
+            Some(0) => ("Success".to_string(), self.status_colors.success()),
+            Some(code) => (format!("Exit {}", code), self.status_colors.failure()),
+            None => ("Running".to_string(), self.status_colors.running()),
         };
 
         let duration_text = block.duration_ms
@@ -831,8 +1223,8 @@ impl TerminalRenderer {
 
         // Command line with prompt symbol
         let prompt = Text::new("❯")
-            .font(Font::MONOSPACE)
-            .size(theme_config.font_size + 2.0)
+            .font(self.font())
+            .size(theme_config.font_size * zoom + 2.0)
             .style(Color::from_rgb(0.6, 0.8, 1.0));
 
         let command_color = if ranges.command && !search_query.trim().is_empty() {
@@ -841,14 +1233,14 @@ impl TerminalRenderer {
             Color::WHITE
         };
         let command = Text::new(&block.command)
-            .font(Font::MONOSPACE)
-            .size(theme_config.font_size)
+            .font(self.font())
+            .size(theme_config.font_size * zoom)
             .style(command_color);
 
         let status = Container::new(
             Text::new(status_display)
                 .size(11.0)
-                .style(Color::WHITE),
+                .style(text_color_on(status_color)),
         )
         .padding([2, 8])
         .style(move |_theme: &Theme| container::Appearance {
@@ -874,11 +1266,22 @@ impl TerminalRenderer {
             .spacing(8)
             .align_items(Alignment::Center);
 
-        let meta_row = Row::new()
+        let mut meta_row = Row::new()
             .push(status)
             .push(duration)
             .spacing(8)
             .align_items(Alignment::Center);
+        for tag in &block.tags {
+            // Clicking a tag filters history to it; the × only shows while editing
+            let mut chip = Row::new()
+                .spacing(2)
+                .align_items(Alignment::Center)
+                .push(Button::new(Text::new(format!("#{}", tag)).size(10.0)).on_press(Message::FilterByTag(tag.clone())).padding([1, 6]));
+            if annotation.is_some() {
+                chip = chip.push(Button::new(Text::new("×").size(10.0)).on_press(Message::RemoveBlockTag(index, tag.clone())).padding([1, 4]));
+            }
+            meta_row = meta_row.push(chip);
+        }
 
         let buttons = Row::new()
             .push(Button::new(Text::new("Copy").size(11.0)).on_press(Message::CopyCommand(index)))
@@ -888,8 +1291,11 @@ impl TerminalRenderer {
             .push(Button::new(Text::new("Export HTML").size(11.0)).on_press(Message::ExportBlock(index, ExportFormat::Html)))
             .push(Button::new(Text::new("Export TXT").size(11.0)).on_press(Message::ExportBlock(index, ExportFormat::Text)))
             .push(Button::new(Text::new("Rerun").size(11.0)).on_press(Message::RerunCommand(index)))
+            .push(Button::new(Text::new("Diff prev").size(11.0)).on_press(Message::DiffWithPreviousRun(index)))
+            .push(Button::new(Text::new("Watch").size(11.0)).on_press(Message::EditWatch(index)))
             .push(Button::new(Text::new(if block.collapsed { "Show" } else { "Hide" }).size(11.0)).on_press(Message::ToggleCollapsed(index)))
             .push(Button::new(Text::new(if block.pinned { "📌" } else { "Pin" }).size(11.0)).on_press(Message::TogglePin(index)))
+            .push(Button::new(Text::new(if annotation.is_some() { "Done" } else { "Annotate" }).size(11.0)).on_press(Message::EditBlockAnnotation(index)))
             .spacing(6);
 
         let mut column = Column::new()
@@ -902,19 +1308,57 @@ impl TerminalRenderer {
             .spacing(6)
             .padding([10, 12]);
 
-        if !block.collapsed && !block.output.is_empty() {
+        if let Some(annotation) = annotation {
+            let tag_input = TextInput::new("Add tags, comma separated...", &annotation.tag_input)
+                .on_input(Message::UpdateBlockTagInput)
+                .on_submit(Message::SubmitBlockTags)
+                .size(12.0)
+                .padding(6);
+            let note_editor = text_editor(&annotation.note)
+                .on_action(Message::BlockNoteEdited)
+                .font(self.font())
+                .height(Length::Fixed(120.0));
+            column = column
+                .push(tag_input)
+                .push(Text::new("Note (markdown)").size(11.0).style(Color::from_rgb(0.7, 0.7, 0.7)))
+                .push(note_editor);
+        } else if !block.note.trim().is_empty() {
+            let note = Container::new(Text::new(&block.note).size(theme_config.font_size * zoom - 2.0))
+                .padding([6, 10])
+                .width(Length::Fill)
+                .style(|_theme: &Theme| container::Appearance {
+                    background: Some(Background::Color(Color::from_rgb(0.16, 0.15, 0.11))),
+                    border: Border {
+                        radius: 6.0.into(),
+                        width: 1.0,
+                        color: Color::from_rgb(0.35, 0.32, 0.2),
+                    },
+                    ..Default::default()
+                });
+            column = column.push(note);
+        }
+
+        if let Some(form) = watch_form {
+            column = column.push(self.render_watch_form(form));
+        }
+
+        if !block.output.is_empty() {
             let output = strip_prompt_line(&block.output, prompt_line);
             let output_color = if ranges.output && !search_query.trim().is_empty() {
                 Color::from_rgb(1.0, 0.9, 0.55)
             } else {
                 Color::from_rgb(0.85, 0.85, 0.85)
             };
-            let output_text = Text::new(output)
-                .font(Font::MONOSPACE)
-                .size(theme_config.font_size - 3.0)
-                .style(output_color);
-            let output_container = Container::new(output_text)
+            let lines: Vec<&str> = output.lines().collect();
+            let text_size = theme_config.font_size * zoom - 3.0;
+            let output_body = if block.collapsed {
+                self.render_folded_output(scroll.block(index), &lines, &block.file_links, text_size, output_color)
+            } else {
+                self.render_output_region(index, &lines, &block.file_links, scroll, text_size, output_color)
+            };
+            let output_container = Container::new(output_body)
                 .padding(8)
+                .width(Length::Fill)
                 .style(|_theme: &Theme| container::Appearance {
                     background: Some(Background::Color(Color::from_rgb(0.18, 0.18, 0.18))),
                     border: Border {
@@ -927,6 +1371,10 @@ impl TerminalRenderer {
             column = column.push(output_container);
         }
 
+        if !block.diagnostics.is_empty() {
+            column = column.push(self.render_diagnostics(scroll.block(index), &block.diagnostics, theme_config.font_size * zoom - 3.0));
+        }
+
         let highlight_color = if highlighted {
             Color::from_rgb(0.28, 0.24, 0.12)
         } else {
@@ -953,20 +1401,212 @@ impl TerminalRenderer {
             .into()
     }
 
-    fn render_current_block<'a>(&self, block: &'a Block, screen: &vt100::Screen, theme_config: &'a ThemeConfig) -> Element<'a, Message> {
+    // One button per diagnostic; clicking opens $EDITOR at its location
+    fn render_diagnostics<'a>(&self, block: PaneBlock, diagnostics: &'a [Diagnostic], text_size: f32) -> Element<'a, Message> {
+        let mut list = Column::new().spacing(2);
+        for (diag_index, diagnostic) in diagnostics.iter().enumerate().take(DIAGNOSTICS_SHOWN) {
+            let (icon, color) = match diagnostic.severity {
+                Severity::Error => ("✖", self.status_colors.failure()),
+                Severity::Warning => ("⚠", Color::from_rgb(0.95, 0.75, 0.35)),
+                Severity::Note => ("ℹ", Color::from_rgb(0.6, 0.8, 1.0)),
+            };
+            let row = Row::new()
+                .push(Text::new(icon).size(text_size).style(color))
+                .push(Text::new(diagnostic.location()).font(self.font()).size(text_size).style(Color::from_rgb(0.6, 0.8, 1.0)))
+                .push(Text::new(&diagnostic.message).font(self.font()).size(text_size).style(Color::from_rgb(0.85, 0.85, 0.85)))
+                .spacing(8)
+                .align_items(Alignment::Center);
+            list = list.push(
+                Button::new(row)
+                    .on_press(Message::OpenDiagnostic(block.tab, block.pane, block.index, diag_index))
+                    .padding([2, 6])
+                    .width(Length::Fill)
+                    .style(iced::theme::Button::Text),
+            );
+        }
+        if diagnostics.len() > DIAGNOSTICS_SHOWN {
+            list = list.push(Text::new(format!("{} more", diagnostics.len() - DIAGNOSTICS_SHOWN)).size(11.0).style(Color::from_rgb(0.6, 0.6, 0.6)));
+        }
+        Column::new()
+            .push(Text::new(format!("Diagnostics ({})", diagnostics.len())).size(11.0).style(Color::from_rgb(0.7, 0.7, 0.7)))
+            .push(list)
+            .spacing(4)
+            .into()
+    }
+
+    // Re-run every N seconds, or whenever files matching a glob change
+    fn render_watch_form<'a>(&self, form: &'a WatchForm) -> Element<'a, Message> {
+        let label = |text: &'a str| Text::new(text).size(11.0).style(Color::from_rgb(0.7, 0.7, 0.7));
+        let mut column = Column::new().spacing(4).push(
+            Row::new()
+                .spacing(6)
+                .align_items(Alignment::Center)
+                .push(label("Re-run every"))
+                .push(TextInput::new("5", &form.interval).on_input(Message::UpdateWatchInterval).on_submit(Message::StartIntervalWatch).size(12.0).padding(4).width(Length::Fixed(50.0)))
+                .push(label("s"))
+                .push(Button::new(Text::new("Start").size(11.0)).on_press(Message::StartIntervalWatch))
+                .push(label("or when"))
+                .push(TextInput::new("**/*.rs", &form.glob).on_input(Message::UpdateWatchGlob).on_submit(Message::StartFileWatch).size(12.0).padding(4).width(Length::Fixed(160.0)))
+                .push(label("changes"))
+                .push(Button::new(Text::new("Start").size(11.0)).on_press(Message::StartFileWatch)),
+        );
+        if let Some(err) = &form.error {
+            column = column.push(Text::new(err.as_str()).size(11.0).style(self.status_colors.failure()));
+        }
+        column.into()
+    }
+
+    // Header of a watch's stack of runs: trigger, run count and a sparkline of
+    // recent durations coloured by exit status
+    fn render_watch_stack<'a>(&self, id: u64, latest: usize, runs: &[&Block], watch: WatchOverlay<'a>) -> Element<'a, Message> {
+        let muted = Color::from_rgb(0.7, 0.7, 0.7);
+        let active = watch.watches.iter().find(|active| active.id == id);
+        let run_count = format!("{} {}", runs.len(), if runs.len() == 1 { "run" } else { "runs" });
+        let status = match active {
+            Some(active) if active.failures_in_row > 0 => format!("⟳ Watching {} · {} · {} failed in a row", active.trigger.label(), run_count, active.failures_in_row),
+            Some(active) => format!("⟳ Watching {} · {}", active.trigger.label(), run_count),
+            None => format!("Watch stopped · {}", run_count),
+        };
+        let recent = &runs[runs.len().saturating_sub(SPARKLINE_RUNS)..];
+        let bars = sparkline(&recent.iter().map(|run| run.duration_ms).collect::<Vec<_>>());
+        let spark = recent.iter().zip(bars).fold(Row::new(), |row, (run, bar)| {
+            let color = match run.exit_code {
+                Some(0) => self.status_colors.success(),
+                Some(_) => self.status_colors.failure(),
+                None => self.status_colors.running(),
+            };
+            row.push(Text::new(bar.to_string()).font(self.font()).size(13.0).style(color))
+        });
+        let mut row = Row::new()
+            .spacing(10)
+            .align_items(Alignment::Center)
+            .push(Container::new(Text::new(status).size(11.0).style(muted)).width(Length::Fill))
+            .push(spark);
+        if runs.len() > 1 {
+            let toggle = if watch.is_expanded(id) { "Hide runs" } else { "Show runs" };
+            row = row.push(Button::new(Text::new(toggle).size(11.0)).on_press(Message::ToggleWatchRuns(id)));
+        }
+        row = match active {
+            Some(_) => row.push(Button::new(Text::new("Stop").size(11.0)).on_press(Message::StopWatch(id))),
+            None => row.push(Button::new(Text::new("Watch again").size(11.0)).on_press(Message::EditWatch(latest))),
+        };
+        Container::new(row).padding([4, 12]).into()
+    }
+
+    // Head and tail of a collapsed block around a marker that expands it
+    fn render_folded_output<'a>(&self, block: PaneBlock, lines: &[&str], links: &[FileLink], text_size: f32, color: Color) -> Element<'a, Message> {
+        let fold = self.folding.fold(lines.len());
+        let output_text = |range: std::ops::Range<usize>| self.render_output_lines(block, lines, range, links, text_size, color);
+        let mut column = Column::new().spacing(4);
+        if !fold.head.is_empty() {
+            column = column.push(output_text(fold.head));
+        }
+        column = column.push(
+            Button::new(Text::new(hidden_label(fold.hidden.len())).size(11.0).style(Color::from_rgb(0.6, 0.7, 0.9)))
+                .on_press(Message::ToggleCollapsed(block.index))
+                .style(iced::theme::Button::Text)
+                .padding([2, 0]),
+        );
+        if !fold.tail.is_empty() {
+            column = column.push(output_text(fold.tail));
+        }
+        column.into()
+    }
+
+    // Output lines as one text, except that lines with file links are split so each
+    // link is an underlined segment that opens on Ctrl+click. Every line keeps the
+    // same height so the gutter stays aligned
+    fn render_output_lines<'a>(&self, block: PaneBlock, lines: &[&str], range: std::ops::Range<usize>, links: &[FileLink], text_size: f32, color: Color) -> Element<'a, Message> {
+        let line_height = (text_size * OUTPUT_LINE_HEIGHT).round();
+        let text = |content: String| Text::new(content).font(self.font()).size(text_size).line_height(LineHeight::Absolute(line_height.into())).style(color);
+        let link_color = Color::from_rgb(0.6, 0.8, 1.0);
+        let mut column = Column::new();
+        let mut plain: Vec<&str> = Vec::new();
+        for line_index in range {
+            let line = lines[line_index];
+            let line_links: Vec<(usize, &FileLink)> = links.iter().enumerate().filter(|(_, link)| link.output_line == line_index && link.end <= line.len()).collect();
+            if line_links.is_empty() {
+                plain.push(line);
+                continue;
+            }
+            if !plain.is_empty() {
+                column = column.push(text(plain.join("\n")));
+                plain.clear();
+            }
+            let mut row = Row::new();
+            let mut at = 0;
+            for (link_index, link) in line_links {
+                if link.start < at {
+                    continue;
+                }
+                if link.start > at {
+                    row = row.push(text(line[at..link.start].to_string()));
+                }
+                let label = &line[link.start..link.end];
+                let underline_width = self.fonts.metrics.advance * text_size * UnicodeWidthStr::width(label) as f32;
+                let segment = Column::new()
+                    .push(Text::new(label.to_string()).font(self.font()).size(text_size).line_height(LineHeight::Absolute((line_height - 1.0).into())).style(link_color))
+                    .push(Container::new(Space::new(Length::Fixed(underline_width), Length::Fixed(1.0))).style(move |_theme: &Theme| container::Appearance {
+                        background: Some(Background::Color(link_color)),
+                        ..Default::default()
+                    }));
+                row = row.push(mouse_area(segment).on_press(Message::OpenFileLink(block.tab, block.pane, block.index, link_index)));
+                at = link.end;
+            }
+            if at < line.len() {
+                row = row.push(text(line[at..].to_string()));
+            }
+            column = column.push(row);
+        }
+        if !plain.is_empty() {
+            column = column.push(text(plain.join("\n")));
+        }
+        column.into()
+    }
+
+    // Expanded output scrolls in its own region with a line-number gutter. Only the
+    // lines around the viewport are laid out; spacers stand in for the rest so the
+    // scrollbar still reflects the whole output
+    fn render_output_region<'a>(&self, index: usize, lines: &[&str], links: &[FileLink], scroll: OutputScroll, text_size: f32, color: Color) -> Element<'a, Message> {
+        let total = lines.len();
+        let line_height = (text_size * OUTPUT_LINE_HEIGHT).round();
+        let viewport_lines = self.folding.viewport_lines(total);
+        let window = visible_window(total, scroll.offset_y, line_height, viewport_lines);
+        let numbers = Text::new(gutter(window.clone(), total))
+            .font(self.font())
+            .size(text_size)
+            .line_height(LineHeight::Absolute(line_height.into()))
+            .style(Color::from_rgb(0.45, 0.45, 0.45));
+        let text = self.render_output_lines(scroll.block(index), lines, window.clone(), links, text_size, color);
+        let content = Column::new()
+            .push(Space::with_height(window.start as f32 * line_height))
+            .push(Row::new().spacing(12).push(numbers).push(text))
+            .push(Space::with_height((total - window.end) as f32 * line_height));
+        Scrollable::new(content)
+            .direction(scrollable::Direction::Both {
+                vertical: scrollable::Properties::default(),
+                horizontal: scrollable::Properties::default(),
+            })
+            .height(Length::Fixed(viewport_lines as f32 * line_height))
+            .width(Length::Fill)
+            .on_scroll(move |viewport| Message::BlockOutputScrolled(scroll.tab, scroll.pane, index, viewport.absolute_offset().y))
+            .into()
+    }
+
+    fn render_current_block<'a>(&self, block: &'a Block, screen: &vt100::Screen, theme_config: &'a ThemeConfig, zoom: f32) -> Element<'a, Message> {
         let duration_text = block.started_at
             .map(|start| format!("{:.2}s", (Utc::now() - start).num_milliseconds() as f64 / 1000.0))
             .unwrap_or_else(|| "...".to_string());
 
         // Command line with prompt symbol
         let prompt = Text::new("❯")
-            .font(Font::MONOSPACE)
-            .size(theme_config.font_size + 2.0)
+            .font(self.font())
+            .size(theme_config.font_size * zoom + 2.0)
             .style(Color::from_rgb(0.6, 0.8, 1.0));
 
         let command = Text::new(&block.command)
-            .font(Font::MONOSPACE)
-            .size(theme_config.font_size);
+            .font(self.font())
+            .size(theme_config.font_size * zoom);
 
         let status = Container::new(
             Text::new("Running")
@@ -1014,8 +1654,8 @@ impl TerminalRenderer {
 
         if !live_output_text.trim().is_empty() {
             let output_text = Text::new(live_output_text)
-                .font(Font::MONOSPACE)
-                .size(theme_config.font_size - 3.0);
+                .font(self.font())
+                .size(theme_config.font_size * zoom - 3.0);
             let output_container = Container::new(output_text)
                 .padding(8)
                 .style(|_theme: &Theme| container::Appearance {
@@ -1054,6 +1694,12 @@ pub struct TerminalCanvas {
     pub row_hashes: Arc<Mutex<HashMap<(usize, usize, u16), u64>>>,
     pub tab_id: usize,
     pub pane_id: usize,
+    pub palette: TerminalPalette,
+    pub fonts: Arc<FontSet>,
+    pub font_size: f32,
+    pub ligatures: bool,
+    // Per visible row, resolved when the view is built
+    pub highlights: Vec<Vec<(u16, u16, Highlight)>>,
 }
 
 impl Program<Message> for TerminalCanvas {
@@ -1063,25 +1709,26 @@ impl Program<Message> for TerminalCanvas {
         let mut frame = Frame::new(renderer, bounds.size());
 
         // Fill with default background
-        frame.fill_rectangle(Point::ORIGIN, bounds.size(), default_bg_color());
+        frame.fill_rectangle(Point::ORIGIN, bounds.size(), self.palette.background);
 
         let size = self.screen.size();
-        let rows = size.1 as usize;
+        let rows = size.0 as usize;
 
         let mut cache = self.render_cache.lock().unwrap();
         let mut hashes = self.row_hashes.lock().unwrap();
         for row in 0..rows {
             let y = row as f32 * self.cell_height;
             let key = (self.tab_id, self.pane_id, row as u16);
-            let hash = compute_row_hash(&self.screen, row as u16);
+            let highlights = self.highlights.get(row).map(Vec::as_slice).unwrap_or(&[]);
+            let hash = compute_row_hash(&self.screen, row as u16, highlights);
             if hashes.get(&key) != Some(&hash) {
-                let runs = compute_runs(&self.screen, row as u16, self.cell_width, self.cell_height);
+                let runs = compute_runs(&self.screen, row as u16, self.cell_width, self.cell_height, &self.palette, &self.fonts, highlights);
                 cache.insert(key, runs.clone());
                 hashes.insert(key, hash);
-                draw_runs(&mut frame, &runs, y, self.cell_height);
+                draw_runs(&mut frame, &runs, y, self.cell_height, self.font_size, self.ligatures);
             } else {
                 if let Some(runs) = cache.get(&key) {
-                    draw_runs(&mut frame, runs, y, self.cell_height);
+                    draw_runs(&mut frame, runs, y, self.cell_height, self.font_size, self.ligatures);
                 }
             }
         }
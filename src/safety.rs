@@ -0,0 +1,258 @@
+// Command safety guard: regex rules that flag destructive commands before they
+// reach the shell, so they run only after an explicit confirmation. Rules come
+// from built-in defaults, `safety_rules.json` in the config directory and any
+// `.tant/safety_rules.json` in the working directory or above it, applied
+// outermost first. Only the user's own file can replace or disable a rule; a
+// checked-out repository can add rules but not switch one off
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use crate::paths;
+
+const RULES_FILE: &str = "safety_rules.json";
+const DIRECTORY_RULES_FILE: &str = ".tant/safety_rules.json";
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SafetyRuleConfig {
+    pub label: String,
+    pub pattern: String,
+    /// Only flag on these branches: the pane's current git branch or a branch
+    /// named in the command.
+    #[serde(default)]
+    pub branches: Vec<String>,
+    /// Only flag when the kubectl context (current or `--context`) matches this regex.
+    #[serde(default)]
+    pub kube_context: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SafetyConfig {
+    /// Added rules; one with the label of an existing rule replaces it.
+    #[serde(default)]
+    pub rules: Vec<SafetyRuleConfig>,
+    /// Labels of rules turned off from here down.
+    #[serde(default)]
+    pub disabled: Vec<String>,
+}
+
+struct SafetyRule {
+    label: String,
+    regex: Regex,
+    branches: Vec<String>,
+    kube_context: Option<Regex>,
+}
+
+/// What a command is checked against besides its text.
+#[derive(Debug, Clone, Default)]
+pub struct CommandContext {
+    pub git_branch: Option<String>,
+    pub kube_context: Option<String>,
+}
+
+pub struct SafetyGuard {
+    rules: Vec<SafetyRule>,
+}
+
+/// A flagged command waiting for the user to confirm or cancel it.
+pub struct PendingCommand {
+    pub tab: usize,
+    pub pane: usize,
+    pub command: String,
+    /// Labels of the rules the command matched.
+    pub reasons: Vec<String>,
+    pub run: PendingRun,
+}
+
+/// How a held command is sent once confirmed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PendingRun {
+    /// Submit the pane's command editor.
+    Editor,
+    /// Re-run the history block at this index.
+    Block(usize),
+    /// Type `command` into the shell as is, e.g. a workflow.
+    Typed,
+}
+
+pub fn default_rules() -> Vec<SafetyRuleConfig> {
+    let rule = |label: &str, pattern: &str| SafetyRuleConfig { label: label.to_string(), pattern: pattern.to_string(), branches: Vec::new(), kube_context: None };
+    vec![
+        rule("Recursive delete of / or home", r"\brm\s+(?:-\S*\s+)*-\S*[rR]\S*\s+(?:-\S*\s+)*(?:/\*?|~/?|\$HOME/?)(?:\s|$)"),
+        SafetyRuleConfig {
+            branches: vec!["main".to_string(), "master".to_string(), "production".to_string()],
+            ..rule("Force push to a protected branch", r"\bgit\s+push\b.*\s(?:--force(?:-with-lease)?|-f)\b")
+        },
+        SafetyRuleConfig {
+            kube_context: Some("(?i)prod".to_string()),
+            ..rule("kubectl delete in production", r"\bkubectl\b.*\sdelete\b")
+        },
+        rule("Drop table or database", r"(?i)\bdrop\s+(?:table|database|schema)\b"),
+        rule("Write to a block device", r"\bdd\b.*\bof=/dev/"),
+        rule("Format a filesystem", r"\bmkfs(?:\.\w+)?\b"),
+    ]
+}
+
+impl SafetyGuard {
+    /// The defaults as changed by the user's config, then rules added by each
+    /// directory config in turn. Directory configs can't replace or disable
+    /// rules, and rules whose patterns don't compile are skipped; both are reported.
+    pub fn from_configs(user: &SafetyConfig, directories: &[SafetyConfig]) -> (Self, Vec<String>) {
+        let mut rules: Vec<SafetyRuleConfig> = default_rules();
+        rules.retain(|rule| !user.disabled.contains(&rule.label) && !user.rules.iter().any(|new| new.label == rule.label));
+        rules.extend(user.rules.iter().cloned());
+        let mut errors = Vec::new();
+        for config in directories {
+            for label in &config.disabled {
+                errors.push(format!("{}: only the user's {} can disable a rule", label, RULES_FILE));
+            }
+            for rule in &config.rules {
+                if rules.iter().any(|existing| existing.label == rule.label) {
+                    errors.push(format!("{}: only the user's {} can replace a rule", rule.label, RULES_FILE));
+                } else {
+                    rules.push(rule.clone());
+                }
+            }
+        }
+        let mut compiled = Vec::new();
+        for rule in rules {
+            let regex = Regex::new(&rule.pattern);
+            let kube_context = rule.kube_context.as_deref().map(Regex::new).transpose();
+            match (regex, kube_context) {
+                (Ok(regex), Ok(kube_context)) => compiled.push(SafetyRule { label: rule.label, regex, branches: rule.branches, kube_context }),
+                (Err(err), _) | (_, Err(err)) => errors.push(format!("{}: {}", rule.label, err)),
+            }
+        }
+        (SafetyGuard { rules: compiled }, errors)
+    }
+
+    /// The guard for commands run in `cwd`.
+    pub fn load(cwd: &Path) -> (Self, Vec<String>) {
+        let mut errors = Vec::new();
+        let mut read = |path: &Path| {
+            let contents = std::fs::read_to_string(path).ok()?;
+            serde_json::from_str::<SafetyConfig>(&contents).map_err(|err| errors.push(format!("{}: {}", path.display(), err))).ok()
+        };
+        let user = read(&paths::config_dir().join(RULES_FILE)).unwrap_or_default();
+        let directories: Vec<SafetyConfig> = directory_rule_files(cwd).iter().filter_map(|path| read(path)).collect();
+        let (guard, rule_errors) = SafetyGuard::from_configs(&user, &directories);
+        errors.extend(rule_errors);
+        (guard, errors)
+    }
+
+    /// Labels of the rules `command` trips in `context`.
+    pub fn check(&self, command: &str, context: &CommandContext) -> Vec<String> {
+        self.rules
+            .iter()
+            .filter(|rule| rule.regex.is_match(command))
+            .filter(|rule| rule.branches.is_empty() || on_protected_branch(command, context.git_branch.as_deref(), &rule.branches))
+            .filter(|rule| {
+                rule.kube_context.as_ref().is_none_or(|regex| {
+                    command_kube_context(command).or(context.kube_context.as_deref()).is_some_and(|kube| regex.is_match(kube))
+                })
+            })
+            .map(|rule| rule.label.clone())
+            .collect()
+    }
+}
+
+// The current branch is protected, or the command names a protected branch
+// (`origin main`, `HEAD:main`, `+main`)
+fn on_protected_branch(command: &str, current: Option<&str>, branches: &[String]) -> bool {
+    let protected = |name: &str| branches.iter().any(|branch| branch == name);
+    current.is_some_and(protected)
+        || command.split_whitespace().any(|word| {
+            let target = word.rsplit(':').next().unwrap_or(word);
+            let target = target.trim_start_matches('+');
+            protected(target.strip_prefix("refs/heads/").unwrap_or(target))
+        })
+}
+
+// `--context prod` or `--context=prod` on the command line
+fn command_kube_context(command: &str) -> Option<&str> {
+    let mut words = command.split_whitespace();
+    while let Some(word) = words.next() {
+        if word == "--context" {
+            return words.next();
+        }
+        if let Some(value) = word.strip_prefix("--context=") {
+            return Some(value);
+        }
+    }
+    None
+}
+
+/// `.tant/safety_rules.json` files at or above `cwd`, outermost first.
+pub fn directory_rule_files(cwd: &Path) -> Vec<PathBuf> {
+    let mut files: Vec<PathBuf> = cwd.ancestors().map(|dir| dir.join(DIRECTORY_RULES_FILE)).filter(|path| path.is_file()).collect();
+    files.reverse();
+    files
+}
+
+// A file with its modification time and size, `None` while it doesn't exist
+type FileStamp = (PathBuf, Option<(SystemTime, u64)>);
+
+fn stamp(path: PathBuf) -> FileStamp {
+    let metadata = std::fs::metadata(&path).and_then(|metadata| Ok((metadata.modified()?, metadata.len()))).ok();
+    (path, metadata)
+}
+
+/// Guards by working directory and the kubectl context, each loaded once and
+/// reloaded only when one of the files it came from changes.
+#[derive(Default)]
+pub struct SafetyGuards {
+    guards: HashMap<PathBuf, (Vec<FileStamp>, SafetyGuard)>,
+    kube_context: Option<(FileStamp, Option<String>)>,
+}
+
+impl SafetyGuards {
+    /// The guard for commands run in `cwd`. Load errors are returned when the
+    /// rules are (re)read, and are empty while the cached guard is used.
+    pub fn guard(&mut self, cwd: &Path) -> (&SafetyGuard, Vec<String>) {
+        let stamps: Vec<FileStamp> = std::iter::once(paths::config_dir().join(RULES_FILE)).chain(directory_rule_files(cwd)).map(stamp).collect();
+        let mut errors = Vec::new();
+        let cached = self.guards.get(cwd).is_some_and(|(cached, _)| *cached == stamps);
+        if !cached {
+            let (guard, load_errors) = SafetyGuard::load(cwd);
+            errors = load_errors;
+            self.guards.insert(cwd.to_path_buf(), (stamps, guard));
+        }
+        (&self.guards[cwd].1, errors)
+    }
+
+    /// `current-context` of the kubeconfig, re-read only when the file changes.
+    pub fn kube_context(&mut self) -> Option<String> {
+        let path = kube_config_path()?;
+        let stamp = stamp(path);
+        match &self.kube_context {
+            Some((cached, context)) if *cached == stamp => context.clone(),
+            _ => {
+                let context = current_kube_context();
+                self.kube_context = Some((stamp, context.clone()));
+                context
+            }
+        }
+    }
+}
+
+// The first `$KUBECONFIG` file, or `~/.kube/config`
+fn kube_config_path() -> Option<PathBuf> {
+    match std::env::var_os("KUBECONFIG") {
+        Some(value) => std::env::split_paths(&value).next(),
+        None => Some(paths::home_dir().join(".kube/config")),
+    }
+}
+
+/// `current-context` from the first `$KUBECONFIG` file, or `~/.kube/config`.
+pub fn current_kube_context() -> Option<String> {
+    let contents = std::fs::read_to_string(kube_config_path()?).ok()?;
+    let config: serde_yaml::Value = serde_yaml::from_str(&contents).ok()?;
+    config.get("current-context")?.as_str().filter(|context| !context.is_empty()).map(str::to_string)
+}
+
+#[cfg(test)]
+mod tests {
+    include!(concat!(env!("CARGO_MANIFEST_DIR"), "/__tests__/safety_tests.rs"));
+}
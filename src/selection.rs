@@ -0,0 +1,270 @@
+// Text selection over the terminal grid
+// Points are addressed by line rather than screen row: line 0 is the top row of
+// the live screen and negative lines reach back into scrollback, so a selection
+// keeps pointing at the same text while the view is scrolled
+
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+use iced::Point;
+
+/// Characters treated as part of a word on double-click, besides letters and digits.
+pub const DEFAULT_WORD_CHARS: &str = "_-./~:@%+=?&#";
+
+const MULTI_CLICK_INTERVAL: Duration = Duration::from_millis(400);
+const MULTI_CLICK_DISTANCE: f32 = 4.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SelectionMode {
+    /// Character-wise drag.
+    Simple,
+    /// Double-click: snaps both ends to word boundaries.
+    Word,
+    /// Triple-click: whole lines.
+    Line,
+    /// Alt+drag: a rectangle of columns.
+    Block,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct GridPoint {
+    pub line: isize,
+    pub col: u16,
+}
+
+impl GridPoint {
+    pub fn new(line: isize, col: u16) -> Self {
+        GridPoint { line, col }
+    }
+}
+
+struct GridLine {
+    // One entry per column; blank cells are " " and wide-character continuations are ""
+    cells: Vec<String>,
+    wrapped: bool,
+}
+
+/// Cell contents of selected lines, read from the live screen and scrollback.
+pub struct GridLines {
+    cols: u16,
+    lines: HashMap<isize, GridLine>,
+}
+
+impl GridLines {
+    pub fn read(parser: &mut vt100::Parser, lines: impl IntoIterator<Item = isize>) -> Self {
+        let original_offset = parser.screen().scrollback();
+        let (rows, cols) = parser.screen().size();
+        let mut read = HashMap::new();
+        for line in lines {
+            if read.contains_key(&line) {
+                continue;
+            }
+            // Scrolling back by `n` puts line `-n` on the top row
+            let (offset, row) = if line < 0 { (line.unsigned_abs(), 0) } else { (0, line as u16) };
+            parser.screen_mut().set_scrollback(offset);
+            let screen = parser.screen();
+            if screen.scrollback() != offset || row >= rows {
+                continue;
+            }
+            let cells = (0..cols)
+                .map(|col| match screen.cell(row, col) {
+                    Some(cell) if cell.is_wide_continuation() => String::new(),
+                    Some(cell) if cell.has_contents() => cell.contents().to_string(),
+                    _ => " ".to_string(),
+                })
+                .collect();
+            read.insert(line, GridLine { cells, wrapped: screen.row_wrapped(row) });
+        }
+        parser.screen_mut().set_scrollback(original_offset);
+        GridLines { cols, lines: read }
+    }
+
+    /// Number of lines held in scrollback above the live screen.
+    pub fn scrollback_len(parser: &mut vt100::Parser) -> usize {
+        let original_offset = parser.screen().scrollback();
+        // vt100 clamps the offset to the scrollback length
+        parser.screen_mut().set_scrollback(usize::MAX);
+        let len = parser.screen().scrollback();
+        parser.screen_mut().set_scrollback(original_offset);
+        len
+    }
+
+    pub fn cells(&self, line: isize) -> &[String] {
+        self.lines.get(&line).map(|line| line.cells.as_slice()).unwrap_or(&[])
+    }
+
+    pub fn wrapped(&self, line: isize) -> bool {
+        self.lines.get(&line).is_some_and(|line| line.wrapped)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Selection {
+    pub mode: SelectionMode,
+    anchor: GridPoint,
+    head: GridPoint,
+    // Resolved span: `start` is inclusive, `end.col` is exclusive on the last line
+    start: GridPoint,
+    end: GridPoint,
+}
+
+impl Selection {
+    pub fn new(mode: SelectionMode, point: GridPoint) -> Self {
+        Selection { mode, anchor: point, head: point, start: point, end: point }
+    }
+
+    /// Moves the dragged end; returns false when it stays in the same cell.
+    pub fn extend_to(&mut self, head: GridPoint) -> bool {
+        let moved = self.head != head;
+        self.head = head;
+        moved
+    }
+
+    /// Lines whose contents `expand` needs.
+    pub fn endpoint_lines(&self) -> [isize; 2] {
+        [self.anchor.line, self.head.line]
+    }
+
+    /// First and last line covered by the resolved span.
+    pub fn line_range(&self) -> (isize, isize) {
+        (self.start.line, self.end.line)
+    }
+
+    /// A plain click selects nothing until the pointer moves to another cell.
+    pub fn is_empty(&self) -> bool {
+        self.mode == SelectionMode::Simple && self.anchor == self.head
+    }
+
+    /// Resolves the selected span from the anchor and head; word selection
+    /// snaps to the text on the endpoint lines.
+    pub fn expand(&mut self, lines: &GridLines, word_chars: &str) {
+        let (first, last) = if self.anchor <= self.head { (self.anchor, self.head) } else { (self.head, self.anchor) };
+        let (start, end) = match self.mode {
+            SelectionMode::Simple => (first, GridPoint::new(last.line, last.col + 1)),
+            SelectionMode::Word => {
+                let (start_col, _) = word_bounds(lines.cells(first.line), first.col as usize, word_chars);
+                let (_, end_col) = word_bounds(lines.cells(last.line), last.col as usize, word_chars);
+                (GridPoint::new(first.line, start_col as u16), GridPoint::new(last.line, end_col as u16))
+            }
+            SelectionMode::Line => (GridPoint::new(first.line, 0), GridPoint::new(last.line, lines.cols)),
+            SelectionMode::Block => {
+                let (left, right) = (self.anchor.col.min(self.head.col), self.anchor.col.max(self.head.col));
+                (GridPoint::new(first.line, left), GridPoint::new(last.line, right + 1))
+            }
+        };
+        self.start = start;
+        self.end = end;
+    }
+
+    /// Selected columns of `line` as a half-open range.
+    pub fn columns_in_line(&self, line: isize, cols: u16) -> Option<(u16, u16)> {
+        if self.is_empty() || line < self.start.line || line > self.end.line {
+            return None;
+        }
+        let (from, to) = if self.mode == SelectionMode::Block {
+            (self.start.col, self.end.col)
+        } else {
+            let from = if line == self.start.line { self.start.col } else { 0 };
+            let to = if line == self.end.line { self.end.col } else { cols };
+            (from, to)
+        };
+        let to = to.min(cols);
+        (from < to).then_some((from, to))
+    }
+
+    /// Selected text; trailing blanks are trimmed and soft-wrapped lines are joined.
+    pub fn text(&self, lines: &GridLines) -> String {
+        let mut text = String::new();
+        if self.is_empty() {
+            return text;
+        }
+        for line in self.start.line..=self.end.line {
+            let cells = lines.cells(line);
+            let (from, to) = self.columns_in_line(line, lines.cols).unwrap_or((0, 0));
+            let content = cells.get(from as usize..(to as usize).min(cells.len())).unwrap_or(&[]).concat();
+            if self.mode != SelectionMode::Block && lines.wrapped(line) && to == lines.cols && line != self.end.line {
+                text.push_str(&content);
+                continue;
+            }
+            text.push_str(content.trim_end());
+            if line != self.end.line {
+                text.push('\n');
+            }
+        }
+        text
+    }
+}
+
+// Half-open column range of the word under `col`; anything that is not a word
+// character selects just its own cell
+fn word_bounds(cells: &[String], col: usize, word_chars: &str) -> (usize, usize) {
+    if cells.is_empty() {
+        return (col, col + 1);
+    }
+    let col = owner(cells, col.min(cells.len() - 1));
+    let is_word = |index: usize| {
+        cells[owner(cells, index)]
+            .chars()
+            .next()
+            .is_some_and(|c| c.is_alphanumeric() || word_chars.contains(c))
+    };
+    let cell_end = |index: usize| {
+        let mut end = index + 1;
+        while end < cells.len() && cells[end].is_empty() {
+            end += 1;
+        }
+        end
+    };
+    if !is_word(col) {
+        return (col, cell_end(col));
+    }
+    let mut start = col;
+    while start > 0 && is_word(start - 1) {
+        start = owner(cells, start - 1);
+    }
+    let mut end = cell_end(col);
+    while end < cells.len() && is_word(end) {
+        end = cell_end(end);
+    }
+    (start, end)
+}
+
+// Column holding the character drawn at `col` (steps left over wide-character continuations)
+fn owner(cells: &[String], mut col: usize) -> usize {
+    while col > 0 && cells[col].is_empty() {
+        col -= 1;
+    }
+    col
+}
+
+/// Counts consecutive clicks at the same spot for double and triple click.
+#[derive(Debug, Default)]
+pub struct ClickTracker {
+    last: Option<(Instant, Point)>,
+    count: u8,
+}
+
+impl ClickTracker {
+    /// Registers a press and returns 1, 2 or 3; a fourth click starts over.
+    pub fn register(&mut self, at: Instant, position: Point) -> u8 {
+        let repeated = self.last.is_some_and(|(time, last)| {
+            at.duration_since(time) <= MULTI_CLICK_INTERVAL && last.distance(position) <= MULTI_CLICK_DISTANCE
+        });
+        self.count = if repeated { self.count % 3 + 1 } else { 1 };
+        self.last = Some((at, position));
+        self.count
+    }
+}
+
+pub fn mode_for_click(count: u8, block: bool) -> SelectionMode {
+    match (block, count) {
+        (true, _) => SelectionMode::Block,
+        (false, 2) => SelectionMode::Word,
+        (false, 3) => SelectionMode::Line,
+        _ => SelectionMode::Simple,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    include!(concat!(env!("CARGO_MANIFEST_DIR"), "/__tests__/selection_tests.rs"));
+}
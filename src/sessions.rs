@@ -0,0 +1,131 @@
+// Named sessions ("work", "oncall"): each is a saved Layout in its own file under
+// the XDG state directory, plus a pointer to the one last used so it reopens on
+// the next launch. Restored panes get their old screen back, and allow-listed
+// commands that were running start again
+
+use std::path::{Path, PathBuf};
+use globset::Glob;
+use log::warn;
+use crate::versioning::{self, LoadError};
+use crate::{paths, Layout};
+
+pub const DEFAULT_SESSION: &str = "default";
+const CURRENT_FILE: &str = "current";
+
+pub struct SessionStore {
+    dir: PathBuf,
+}
+
+impl SessionStore {
+    pub fn new(dir: PathBuf) -> Self {
+        SessionStore { dir }
+    }
+
+    /// `$XDG_STATE_HOME/tant/sessions`.
+    pub fn open() -> Self {
+        SessionStore::new(paths::state_dir().join("sessions"))
+    }
+
+    pub fn path(&self, name: &str) -> PathBuf {
+        self.dir.join(format!("{}.json", name))
+    }
+
+    /// Saved session names, sorted.
+    pub fn list(&self) -> Vec<String> {
+        let Ok(entries) = std::fs::read_dir(&self.dir) else {
+            return Vec::new();
+        };
+        let mut names: Vec<String> = entries
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+            .filter_map(|path| Some(path.file_stem()?.to_string_lossy().to_string()))
+            .collect();
+        names.sort();
+        names
+    }
+
+    /// `Ok(None)` for a session that was never saved. One that can't be read
+    /// is backed up, so starting afresh doesn't overwrite it.
+    pub fn load(&self, name: &str) -> Result<Option<Layout>, LoadError> {
+        versioning::load(&versioning::SESSION, &self.path(name))
+    }
+
+    pub fn save(&self, name: &str, layout: &Layout) -> Result<(), String> {
+        versioning::save(&versioning::SESSION, &self.path(name), layout)
+    }
+
+    pub fn delete(&self, name: &str) -> Result<(), String> {
+        let path = self.path(name);
+        std::fs::remove_file(&path).map_err(|err| format!("{}: {}", path.display(), err))
+    }
+
+    /// The session last switched to, or `default`.
+    pub fn current(&self) -> String {
+        std::fs::read_to_string(self.dir.join(CURRENT_FILE))
+            .ok()
+            .and_then(|name| session_name(&name).ok())
+            .unwrap_or_else(|| DEFAULT_SESSION.to_string())
+    }
+
+    /// `base` if no session has that name yet, otherwise the first free `base-2`,
+    /// `base-3`, ...; names that aren't usable become `layout`.
+    pub fn unused_name(&self, base: &str) -> String {
+        let base = session_name(base).unwrap_or_else(|_| "layout".to_string());
+        std::iter::once(base.clone())
+            .chain((2..).map(|n| format!("{}-{}", base, n)))
+            .find(|name| !self.path(name).exists())
+            .expect("an unused session name")
+    }
+
+    pub fn set_current(&self, name: &str) -> Result<(), String> {
+        write(&self.dir.join(CURRENT_FILE), name.as_bytes())
+    }
+}
+
+/// A session name that is safe to use as a file name and isn't the pointer to
+/// the current session.
+pub fn session_name(input: &str) -> Result<String, String> {
+    let name = paths::file_name_safe(input, "session")?;
+    if name == CURRENT_FILE {
+        return Err(format!("'{}' can't be used as a session name", name));
+    }
+    Ok(name)
+}
+
+/// The saved working directory if it still exists, otherwise `$HOME`, or `/`
+/// when that isn't set either.
+pub fn restore_directory(saved: &str) -> String {
+    let home = paths::home_dir();
+    if Path::new(saved).is_dir() {
+        saved.to_string()
+    } else if home.is_dir() {
+        home.to_string_lossy().to_string()
+    } else {
+        "/".to_string()
+    }
+}
+
+/// Whether a command that was running when the session was saved should be
+/// started again: it has to match one of the allow-listed globs, e.g.
+/// `npm run dev` or `cargo watch *`.
+pub fn should_rerun(command: &str, allowed: &[String]) -> bool {
+    let command = command.trim();
+    !command.is_empty()
+        && allowed.iter().any(|pattern| match Glob::new(pattern.trim()) {
+            Ok(glob) => glob.compile_matcher().is_match(command),
+            Err(err) => {
+                warn!("Skipping restore pattern '{}': {}", pattern, err);
+                false
+            }
+        })
+}
+
+fn write(path: &Path, contents: &[u8]) -> Result<(), String> {
+    paths::write_atomic(path, contents).map_err(|err| format!("{}: {}", path.display(), err))
+}
+
+#[cfg(test)]
+mod tests {
+    include!(concat!(env!("CARGO_MANIFEST_DIR"), "/__tests__/sessions_tests.rs"));
+}
@@ -0,0 +1,235 @@
+// Importers for existing shell history: bash (~/.bash_history, with optional
+// `#<epoch>` timestamp lines), zsh (~/.zsh_history, plain or extended
+// `: <start>:<elapsed>;<command>` format) and fish (fish_history)
+// Imported commands become output-less blocks tagged "imported"
+
+use std::path::PathBuf;
+use chrono::{DateTime, TimeZone, Utc};
+use crate::history_store::HistoryStore;
+use crate::paths;
+use crate::Block;
+
+pub const IMPORTED_TAG: &str = "imported";
+
+// zsh stores bytes that clash with its tokens as this marker plus the byte xor 32
+const ZSH_META: u8 = 0x83;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShellKind {
+    Bash,
+    Zsh,
+    Fish,
+}
+
+impl ShellKind {
+    pub const ALL: [ShellKind; 3] = [ShellKind::Bash, ShellKind::Zsh, ShellKind::Fish];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            ShellKind::Bash => "bash",
+            ShellKind::Zsh => "zsh",
+            ShellKind::Fish => "fish",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|shell| shell.label().eq_ignore_ascii_case(name.trim()))
+    }
+
+    /// Parses a comma-separated list such as `bash,zsh`, rejecting any name
+    /// that isn't a supported shell.
+    pub fn from_list(names: &str) -> Result<Vec<Self>, String> {
+        names
+            .split(',')
+            .map(|name| {
+                Self::from_name(name).ok_or_else(|| {
+                    let supported: Vec<&str> = Self::ALL.iter().map(|shell| shell.label()).collect();
+                    format!("Unknown shell '{}' (expected {})", name.trim(), supported.join(", "))
+                })
+            })
+            .collect()
+    }
+
+    pub fn default_path(self) -> PathBuf {
+        match self {
+            ShellKind::Bash => paths::home_dir().join(".bash_history"),
+            ShellKind::Zsh => std::env::var_os("ZDOTDIR").map(PathBuf::from).unwrap_or_else(paths::home_dir).join(".zsh_history"),
+            ShellKind::Fish => paths::data_home().join("fish").join("fish_history"),
+        }
+    }
+
+    pub fn parse(self, bytes: &[u8]) -> Vec<Block> {
+        match self {
+            ShellKind::Bash => parse_bash(&String::from_utf8_lossy(bytes)),
+            ShellKind::Zsh => parse_zsh(bytes),
+            ShellKind::Fish => parse_fish(&String::from_utf8_lossy(bytes)),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct ImportSummary {
+    pub shell: ShellKind,
+    pub path: PathBuf,
+    /// Commands added to the history store; ones already there are skipped.
+    pub added: usize,
+}
+
+/// Imports each shell's default history file that exists.
+pub fn import_shell_history(store: &mut HistoryStore, shells: &[ShellKind]) -> Vec<Result<ImportSummary, String>> {
+    shells
+        .iter()
+        .map(|&shell| (shell, shell.default_path()))
+        .filter(|(_, path)| path.is_file())
+        .map(|(shell, path)| {
+            let bytes = std::fs::read(&path).map_err(|err| format!("Failed to read {}: {}", path.display(), err))?;
+            let added = store
+                .import(&shell.parse(&bytes))
+                .map_err(|err| format!("Failed to import {} history: {}", shell.label(), err))?;
+            Ok(ImportSummary { shell, path, added })
+        })
+        .collect()
+}
+
+/// One-line summary for the CLI and the in-app toast.
+pub fn summary_message(results: &[Result<ImportSummary, String>]) -> String {
+    if results.is_empty() {
+        return "No shell history found to import".to_string();
+    }
+    results
+        .iter()
+        .map(|result| match result {
+            Ok(summary) => format!("Imported {} commands from {} ({})", summary.added, summary.shell.label(), summary.path.display()),
+            Err(err) => err.clone(),
+        })
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
+pub fn parse_bash(contents: &str) -> Vec<Block> {
+    let mut blocks = Vec::new();
+    let mut started_at = None;
+    for line in contents.lines() {
+        // HISTTIMEFORMAT writes the start time on its own line before the command
+        if let Some(seconds) = line.strip_prefix('#').and_then(|rest| rest.trim().parse::<i64>().ok()) {
+            started_at = timestamp(seconds);
+            continue;
+        }
+        if !line.trim().is_empty() {
+            blocks.push(imported_block(line, started_at.take(), None));
+        }
+    }
+    blocks
+}
+
+pub fn parse_zsh(bytes: &[u8]) -> Vec<Block> {
+    let contents = String::from_utf8_lossy(&unmetafy(bytes)).into_owned();
+    let mut blocks = Vec::new();
+    let mut lines = contents.lines();
+    while let Some(first) = lines.next() {
+        // Newlines inside a command are stored as a backslash at the end of the line
+        let mut entry = first.to_string();
+        while entry.ends_with('\\') {
+            let Some(next) = lines.next() else { break };
+            entry.pop();
+            entry.push('\n');
+            entry.push_str(next);
+        }
+        let (started_at, duration_ms, command) = match parse_zsh_extended(&entry) {
+            Some((start, elapsed, command)) => (timestamp(start), Some(elapsed * 1_000), command),
+            None => (None, None, entry.as_str()),
+        };
+        if !command.trim().is_empty() {
+            blocks.push(imported_block(command, started_at, duration_ms));
+        }
+    }
+    blocks
+}
+
+// `: 1700000000:12;command`
+fn parse_zsh_extended(entry: &str) -> Option<(i64, u64, &str)> {
+    let rest = entry.strip_prefix(": ")?;
+    let (meta, command) = rest.split_once(';')?;
+    let (start, elapsed) = meta.split_once(':')?;
+    Some((start.trim().parse().ok()?, elapsed.trim().parse().ok()?, command))
+}
+
+fn unmetafy(bytes: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut iter = bytes.iter();
+    while let Some(&byte) = iter.next() {
+        if byte == ZSH_META {
+            if let Some(&next) = iter.next() {
+                out.push(next ^ 0x20);
+            }
+        } else {
+            out.push(byte);
+        }
+    }
+    out
+}
+
+// fish_history is YAML-like but not valid YAML, so it is read line by line:
+//   - cmd: git status
+//     when: 1700000000
+//     paths:
+//       - src
+pub fn parse_fish(contents: &str) -> Vec<Block> {
+    let mut blocks = Vec::new();
+    let mut pending: Option<(String, Option<DateTime<Utc>>)> = None;
+    for line in contents.lines() {
+        if let Some(command) = line.strip_prefix("- cmd: ") {
+            if let Some((command, started_at)) = pending.take() {
+                blocks.push(imported_block(&command, started_at, None));
+            }
+            pending = Some((unescape_fish(command), None));
+        } else if let Some(seconds) = line.trim_start().strip_prefix("when: ") {
+            if let Some((_, started_at)) = pending.as_mut() {
+                *started_at = seconds.trim().parse().ok().and_then(timestamp);
+            }
+        }
+    }
+    if let Some((command, started_at)) = pending {
+        blocks.push(imported_block(&command, started_at, None));
+    }
+    blocks.retain(|block| !block.command.trim().is_empty());
+    blocks
+}
+
+fn unescape_fish(command: &str) -> String {
+    let mut out = String::with_capacity(command.len());
+    let mut chars = command.chars();
+    while let Some(c) = chars.next() {
+        match (c, chars.clone().next()) {
+            ('\\', Some('n')) => {
+                out.push('\n');
+                chars.next();
+            }
+            ('\\', Some('\\')) => {
+                out.push('\\');
+                chars.next();
+            }
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+fn timestamp(seconds: i64) -> Option<DateTime<Utc>> {
+    Utc.timestamp_opt(seconds, 0).single()
+}
+
+fn imported_block(command: &str, started_at: Option<DateTime<Utc>>, duration_ms: Option<u64>) -> Block {
+    Block {
+        command: command.trim_end().to_string(),
+        started_at,
+        duration_ms,
+        tags: vec![IMPORTED_TAG.to_string()],
+        ..Block::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    include!(concat!(env!("CARGO_MANIFEST_DIR"), "/__tests__/shell_import_tests.rs"));
+}
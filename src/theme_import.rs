@@ -0,0 +1,425 @@
+// Theme importers for other terminal emulators' colour scheme formats
+// iTerm2 (.itermcolors), Alacritty (TOML/YAML), kitty (.conf), Windows Terminal (JSON) and base16 (YAML)
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use crate::ThemeConfig;
+use crate::themes::preset_theme;
+use crate::versioning;
+
+const ANSI_NAMES: [&str; 8] = ["black", "red", "green", "yellow", "blue", "magenta", "cyan", "white"];
+const WINDOWS_TERMINAL_NAMES: [&str; 8] = ["black", "red", "green", "yellow", "blue", "purple", "cyan", "white"];
+
+// base16 slot for each of the 16 ANSI colours (base16-shell mapping)
+const BASE16_ANSI: [&str; 16] = [
+    "base00", "base08", "base0B", "base0A", "base0D", "base0E", "base0C", "base05",
+    "base03", "base08", "base0B", "base0A", "base0D", "base0E", "base0C", "base07",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ThemeFormat {
+    Tant,
+    ITerm2,
+    AlacrittyToml,
+    AlacrittyYaml,
+    Kitty,
+    WindowsTerminal,
+    Base16,
+}
+
+impl ThemeFormat {
+    pub fn label(&self) -> &'static str {
+        match self {
+            ThemeFormat::Tant => "Tant",
+            ThemeFormat::ITerm2 => "iTerm2",
+            ThemeFormat::AlacrittyToml => "Alacritty TOML",
+            ThemeFormat::AlacrittyYaml => "Alacritty YAML",
+            ThemeFormat::Kitty => "kitty",
+            ThemeFormat::WindowsTerminal => "Windows Terminal",
+            ThemeFormat::Base16 => "base16",
+        }
+    }
+
+    /// Guess the format from the file name, sniffing the contents where the
+    /// extension is shared between formats (YAML and JSON).
+    pub fn detect(path: &Path, contents: &str) -> Option<Self> {
+        let extension = path.extension()?.to_str()?.to_lowercase();
+        match extension.as_str() {
+            "itermcolors" => Some(ThemeFormat::ITerm2),
+            "toml" => Some(ThemeFormat::AlacrittyToml),
+            "conf" => Some(ThemeFormat::Kitty),
+            "yml" | "yaml" => {
+                if contents.contains("base00") {
+                    Some(ThemeFormat::Base16)
+                } else {
+                    Some(ThemeFormat::AlacrittyYaml)
+                }
+            }
+            "json" => {
+                if contents.contains("\"font_family\"") {
+                    Some(ThemeFormat::Tant)
+                } else {
+                    Some(ThemeFormat::WindowsTerminal)
+                }
+            }
+            _ => None,
+        }
+    }
+
+    fn has_known_extension(path: &Path) -> bool {
+        matches!(
+            path.extension().and_then(|ext| ext.to_str()).map(|ext| ext.to_lowercase()).as_deref(),
+            Some("itermcolors" | "toml" | "conf" | "yml" | "yaml" | "json")
+        )
+    }
+}
+
+/// Lists theme files in `dir` that one of the importers understands, sorted by file name.
+pub fn list_importable_themes(dir: &Path) -> Vec<(PathBuf, ThemeFormat)> {
+    let mut themes = Vec::new();
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return themes;
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if !path.is_file() || !ThemeFormat::has_known_extension(&path) {
+            continue;
+        }
+        if let Ok(contents) = std::fs::read_to_string(&path) {
+            if let Some(format) = ThemeFormat::detect(&path, &contents) {
+                themes.push((path, format));
+            }
+        }
+    }
+    themes.sort_by(|a, b| a.0.file_name().cmp(&b.0.file_name()));
+    themes
+}
+
+impl ThemeConfig {
+    pub fn import_file(path: &Path) -> Result<Self, String> {
+        let contents = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+        let format = ThemeFormat::detect(path, &contents)
+            .ok_or_else(|| format!("Unrecognised theme file: {}", path.display()))?;
+        Self::import_str(format, &contents)
+    }
+
+    pub fn import_str(format: ThemeFormat, contents: &str) -> Result<Self, String> {
+        match format {
+            ThemeFormat::Tant => versioning::decode(&versioning::THEME, contents),
+            ThemeFormat::ITerm2 => Self::from_itermcolors(contents),
+            ThemeFormat::AlacrittyToml => Self::from_alacritty_toml(contents),
+            ThemeFormat::AlacrittyYaml => Self::from_alacritty_yaml(contents),
+            ThemeFormat::Kitty => Self::from_kitty_conf(contents),
+            ThemeFormat::WindowsTerminal => Self::from_windows_terminal(contents),
+            ThemeFormat::Base16 => Self::from_base16(contents),
+        }
+    }
+
+    pub fn export_str(&self, format: ThemeFormat) -> Result<String, String> {
+        match format {
+            ThemeFormat::Tant => versioning::encode(&versioning::THEME, self),
+            ThemeFormat::ITerm2 => self.to_itermcolors(),
+            ThemeFormat::AlacrittyToml => toml::to_string(&self.alacritty_value()).map_err(|e| e.to_string()),
+            ThemeFormat::AlacrittyYaml => serde_yaml::to_string(&self.alacritty_value()).map_err(|e| e.to_string()),
+            ThemeFormat::Kitty => Ok(self.to_kitty_conf()),
+            ThemeFormat::WindowsTerminal => serde_json::to_string_pretty(&self.windows_terminal_value()).map_err(|e| e.to_string()),
+            ThemeFormat::Base16 => serde_yaml::to_string(&self.base16_value()).map_err(|e| e.to_string()),
+        }
+    }
+
+    pub fn from_itermcolors(contents: &str) -> Result<Self, String> {
+        let value = plist::Value::from_reader(std::io::Cursor::new(contents.as_bytes())).map_err(|e| e.to_string())?;
+        let dict = value.as_dictionary().ok_or("iTerm2 theme is not a dictionary")?;
+        let mut colors = HashMap::new();
+        for (key, entry) in dict {
+            let Some(name) = iterm_key_to_color(key) else { continue };
+            let Some(components) = entry.as_dictionary() else { continue };
+            let component = |label: &str| {
+                components
+                    .get(label)
+                    .and_then(|v| v.as_real().or_else(|| v.as_signed_integer().map(|i| i as f64)))
+                    .map(|v| v as f32)
+            };
+            if let (Some(r), Some(g), Some(b)) = (component("Red Component"), component("Green Component"), component("Blue Component")) {
+                colors.insert(name, [r, g, b]);
+            }
+        }
+        finish_palette(colors)
+    }
+
+    pub fn from_alacritty_toml(contents: &str) -> Result<Self, String> {
+        let value: toml::Value = toml::from_str(contents).map_err(|e| e.to_string())?;
+        let value = serde_json::to_value(value).map_err(|e| e.to_string())?;
+        Self::from_alacritty_value(&value)
+    }
+
+    pub fn from_alacritty_yaml(contents: &str) -> Result<Self, String> {
+        let value: serde_yaml::Value = serde_yaml::from_str(contents).map_err(|e| e.to_string())?;
+        let value = serde_json::to_value(value).map_err(|e| e.to_string())?;
+        Self::from_alacritty_value(&value)
+    }
+
+    fn from_alacritty_value(value: &serde_json::Value) -> Result<Self, String> {
+        let colors_table = value.get("colors").ok_or("Alacritty theme has no [colors] section")?;
+        let lookup = |section: &str, key: &str| {
+            colors_table
+                .get(section)
+                .and_then(|s| s.get(key))
+                .and_then(|v| v.as_str())
+                .and_then(parse_hex_color)
+        };
+        let mut colors = HashMap::new();
+        insert_some(&mut colors, "foreground", lookup("primary", "foreground"));
+        insert_some(&mut colors, "background", lookup("primary", "background"));
+        insert_some(&mut colors, "cursor", lookup("cursor", "cursor"));
+        insert_some(&mut colors, "selection", lookup("selection", "background"));
+        for (index, name) in ANSI_NAMES.iter().enumerate() {
+            insert_some(&mut colors, &format!("ansi_{}", index), lookup("normal", name));
+            insert_some(&mut colors, &format!("ansi_{}", index + 8), lookup("bright", name));
+        }
+        finish_palette(colors)
+    }
+
+    pub fn from_kitty_conf(contents: &str) -> Result<Self, String> {
+        let mut colors = HashMap::new();
+        for line in contents.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let mut parts = line.split_whitespace();
+            let (Some(key), Some(value)) = (parts.next(), parts.next()) else { continue };
+            let name = match key {
+                "foreground" | "background" | "cursor" => key.to_string(),
+                "selection_background" => "selection".to_string(),
+                _ => match key.strip_prefix("color").and_then(|n| n.parse::<usize>().ok()) {
+                    Some(index) if index < 16 => format!("ansi_{}", index),
+                    _ => continue,
+                },
+            };
+            insert_some(&mut colors, &name, parse_hex_color(value));
+        }
+        finish_palette(colors)
+    }
+
+    pub fn from_windows_terminal(contents: &str) -> Result<Self, String> {
+        let value: serde_json::Value = serde_json::from_str(contents).map_err(|e| e.to_string())?;
+        // Accept either a bare scheme or a full settings.json with a "schemes" list
+        let scheme = match value.get("schemes").and_then(|s| s.as_array()) {
+            Some(schemes) => schemes.first().ok_or("Windows Terminal settings contain no schemes")?,
+            None => &value,
+        };
+        let lookup = |key: &str| scheme.get(key).and_then(|v| v.as_str()).and_then(parse_hex_color);
+        let mut colors = HashMap::new();
+        insert_some(&mut colors, "foreground", lookup("foreground"));
+        insert_some(&mut colors, "background", lookup("background"));
+        insert_some(&mut colors, "cursor", lookup("cursorColor"));
+        insert_some(&mut colors, "selection", lookup("selectionBackground"));
+        for (index, name) in WINDOWS_TERMINAL_NAMES.iter().enumerate() {
+            insert_some(&mut colors, &format!("ansi_{}", index), lookup(name));
+            insert_some(&mut colors, &format!("ansi_{}", index + 8), lookup(&bright_name(name)));
+        }
+        finish_palette(colors)
+    }
+
+    pub fn from_base16(contents: &str) -> Result<Self, String> {
+        let value: serde_yaml::Value = serde_yaml::from_str(contents).map_err(|e| e.to_string())?;
+        let lookup = |key: &str| value.get(key).and_then(|v| v.as_str()).and_then(parse_hex_color);
+        let mut colors = HashMap::new();
+        insert_some(&mut colors, "foreground", lookup("base05"));
+        insert_some(&mut colors, "background", lookup("base00"));
+        insert_some(&mut colors, "cursor", lookup("base05"));
+        insert_some(&mut colors, "selection", lookup("base02"));
+        for (index, slot) in BASE16_ANSI.iter().enumerate() {
+            insert_some(&mut colors, &format!("ansi_{}", index), lookup(slot));
+        }
+        finish_palette(colors)
+    }
+
+    fn to_itermcolors(&self) -> Result<String, String> {
+        let mut dict = plist::Dictionary::new();
+        for (name, key) in iterm_keys() {
+            if let Some(rgb) = self.colors.get(&name) {
+                let mut components = plist::Dictionary::new();
+                components.insert("Color Space".to_string(), plist::Value::String("sRGB".to_string()));
+                components.insert("Red Component".to_string(), plist::Value::Real(rgb[0] as f64));
+                components.insert("Green Component".to_string(), plist::Value::Real(rgb[1] as f64));
+                components.insert("Blue Component".to_string(), plist::Value::Real(rgb[2] as f64));
+                components.insert("Alpha Component".to_string(), plist::Value::Real(1.0));
+                dict.insert(key, plist::Value::Dictionary(components));
+            }
+        }
+        let mut out = Vec::new();
+        plist::Value::Dictionary(dict).to_writer_xml(&mut out).map_err(|e| e.to_string())?;
+        String::from_utf8(out).map_err(|e| e.to_string())
+    }
+
+    fn alacritty_value(&self) -> serde_json::Value {
+        let mut normal = serde_json::Map::new();
+        let mut bright = serde_json::Map::new();
+        for (index, name) in ANSI_NAMES.iter().enumerate() {
+            normal.insert(name.to_string(), self.hex(&format!("ansi_{}", index)).into());
+            bright.insert(name.to_string(), self.hex(&format!("ansi_{}", index + 8)).into());
+        }
+        serde_json::json!({
+            "colors": {
+                "primary": { "foreground": self.hex("foreground"), "background": self.hex("background") },
+                "cursor": { "cursor": self.hex("cursor"), "text": self.hex("background") },
+                "selection": { "background": self.hex("selection"), "text": "CellForeground" },
+                "normal": normal,
+                "bright": bright,
+            }
+        })
+    }
+
+    fn to_kitty_conf(&self) -> String {
+        let mut out = String::from("# Exported from Tant\n");
+        out.push_str(&format!("foreground {}\n", self.hex("foreground")));
+        out.push_str(&format!("background {}\n", self.hex("background")));
+        out.push_str(&format!("cursor {}\n", self.hex("cursor")));
+        out.push_str(&format!("selection_background {}\n", self.hex("selection")));
+        for index in 0..16 {
+            out.push_str(&format!("color{} {}\n", index, self.hex(&format!("ansi_{}", index))));
+        }
+        out
+    }
+
+    fn windows_terminal_value(&self) -> serde_json::Value {
+        let mut scheme = serde_json::Map::new();
+        scheme.insert("name".to_string(), "Tant".into());
+        scheme.insert("foreground".to_string(), self.hex("foreground").into());
+        scheme.insert("background".to_string(), self.hex("background").into());
+        scheme.insert("cursorColor".to_string(), self.hex("cursor").into());
+        scheme.insert("selectionBackground".to_string(), self.hex("selection").into());
+        for (index, name) in WINDOWS_TERMINAL_NAMES.iter().enumerate() {
+            scheme.insert(name.to_string(), self.hex(&format!("ansi_{}", index)).into());
+            scheme.insert(bright_name(name), self.hex(&format!("ansi_{}", index + 8)).into());
+        }
+        serde_json::Value::Object(scheme)
+    }
+
+    fn base16_value(&self) -> serde_json::Value {
+        let bare = |key: &str| self.hex(key).trim_start_matches('#').to_string();
+        let mut scheme = serde_json::Map::new();
+        scheme.insert("scheme".to_string(), "Tant".into());
+        scheme.insert("author".to_string(), "".into());
+        // Slots that have no ANSI equivalent are filled with their nearest neighbour
+        let slots = [
+            ("base00", "background"), ("base01", "ui_panel_bg"), ("base02", "selection"), ("base03", "ansi_8"),
+            ("base04", "ansi_7"), ("base05", "foreground"), ("base06", "ansi_7"), ("base07", "ansi_15"),
+            ("base08", "ansi_1"), ("base09", "ansi_3"), ("base0A", "ansi_3"), ("base0B", "ansi_2"),
+            ("base0C", "ansi_6"), ("base0D", "ansi_4"), ("base0E", "ansi_5"), ("base0F", "ansi_9"),
+        ];
+        for (slot, key) in slots {
+            scheme.insert(slot.to_string(), bare(key).into());
+        }
+        serde_json::Value::Object(scheme)
+    }
+
+    fn hex(&self, key: &str) -> String {
+        color_to_hex(self.colors.get(key).copied().unwrap_or([0.0, 0.0, 0.0]))
+    }
+}
+
+fn bright_name(name: &str) -> String {
+    let mut chars = name.chars();
+    let first = chars.next().map(|c| c.to_ascii_uppercase()).unwrap_or_default();
+    format!("bright{}{}", first, chars.as_str())
+}
+
+fn iterm_keys() -> Vec<(String, String)> {
+    let mut keys = vec![
+        ("foreground".to_string(), "Foreground Color".to_string()),
+        ("background".to_string(), "Background Color".to_string()),
+        ("cursor".to_string(), "Cursor Color".to_string()),
+        ("selection".to_string(), "Selection Color".to_string()),
+    ];
+    for index in 0..16 {
+        keys.push((format!("ansi_{}", index), format!("Ansi {} Color", index)));
+    }
+    keys
+}
+
+fn iterm_key_to_color(key: &str) -> Option<String> {
+    iterm_keys().into_iter().find(|(_, iterm)| iterm == key).map(|(name, _)| name)
+}
+
+fn insert_some(colors: &mut HashMap<String, [f32; 3]>, key: &str, value: Option<[f32; 3]>) {
+    if let Some(value) = value {
+        colors.insert(key.to_string(), value);
+    }
+}
+
+/// Parses `#rrggbb`, `0xrrggbb`, `rrggbb` and `#rgb` colour strings.
+pub fn parse_hex_color(value: &str) -> Option<[f32; 3]> {
+    let value = value.trim().trim_matches(|c| c == '\'' || c == '"');
+    let hex = value
+        .strip_prefix('#')
+        .or_else(|| value.strip_prefix("0x"))
+        .or_else(|| value.strip_prefix("0X"))
+        .unwrap_or(value);
+    // Checked byte by byte so multibyte input can't split a character below
+    if !hex.bytes().all(|byte| byte.is_ascii_hexdigit()) {
+        return None;
+    }
+    let hex = match hex.len() {
+        3 => hex.chars().flat_map(|c| [c, c]).collect::<String>(),
+        6 => hex.to_string(),
+        _ => return None,
+    };
+    let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).ok().map(|v| v as f32 / 255.0);
+    Some([channel(0)?, channel(2)?, channel(4)?])
+}
+
+pub fn color_to_hex(rgb: [f32; 3]) -> String {
+    let channel = |v: f32| (v.clamp(0.0, 1.0) * 255.0).round() as u8;
+    format!("#{:02x}{:02x}{:02x}", channel(rgb[0]), channel(rgb[1]), channel(rgb[2]))
+}
+
+fn mix(a: [f32; 3], b: [f32; 3], amount: f32) -> [f32; 3] {
+    [
+        a[0] + (b[0] - a[0]) * amount,
+        a[1] + (b[1] - a[1]) * amount,
+        a[2] + (b[2] - a[2]) * amount,
+    ]
+}
+
+/// Builds a full `ThemeConfig` from an imported palette. Imported formats only
+/// describe terminal colours, so the UI and syntax colours are derived from them
+/// and anything still missing falls back to `one_dark`.
+fn finish_palette(mut colors: HashMap<String, [f32; 3]>) -> Result<ThemeConfig, String> {
+    let foreground = *colors.get("foreground").ok_or("Theme has no foreground colour")?;
+    let background = *colors.get("background").ok_or("Theme has no background colour")?;
+    let fallback = preset_theme("one_dark");
+    let ansi = |colors: &HashMap<String, [f32; 3]>, index: usize| {
+        colors
+            .get(&format!("ansi_{}", index))
+            .copied()
+            .unwrap_or_else(|| fallback.colors[&format!("ansi_{}", index)])
+    };
+    let accent = ansi(&colors, 4);
+    let derived = [
+        ("cursor", foreground),
+        ("selection", mix(background, foreground, 0.2)),
+        ("ui_border", mix(background, foreground, 0.12)),
+        ("ui_input_bg", mix(background, foreground, 0.08)),
+        ("ui_input_border", accent),
+        ("ui_panel_bg", mix(background, foreground, 0.04)),
+        ("syntax_keyword", ansi(&colors, 5)),
+        ("syntax_string", ansi(&colors, 2)),
+        ("syntax_number", ansi(&colors, 3)),
+        ("syntax_comment", ansi(&colors, 8)),
+    ];
+    for (key, value) in derived {
+        colors.entry(key.to_string()).or_insert(value);
+    }
+    for (key, value) in &fallback.colors {
+        colors.entry(key.clone()).or_insert(*value);
+    }
+    Ok(ThemeConfig { colors, ..fallback })
+}
+
+#[cfg(test)]
+mod tests {
+    include!(concat!(env!("CARGO_MANIFEST_DIR"), "/__tests__/theme_import_tests.rs"));
+}
@@ -0,0 +1,160 @@
+// Theme registry: built-in presets plus user theme files
+// The user themes directory is watched so edited files are reloaded while tant is running
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{channel, Receiver};
+use iced::theme::Palette;
+use iced::{Color, Theme};
+use log::{info, warn};
+use notify::{RecommendedWatcher, RecursiveMode, Watcher};
+use crate::ThemeConfig;
+use crate::themes::{preset_theme, PRESET_THEMES};
+use crate::theme_import::list_importable_themes;
+
+pub struct ThemeManager {
+    dir: PathBuf,
+    user_themes: BTreeMap<String, ThemeConfig>,
+    #[allow(dead_code)]
+    watcher: Option<RecommendedWatcher>,
+    events: Option<Receiver<notify::Result<notify::Event>>>,
+}
+
+impl ThemeManager {
+    pub fn new(dir: PathBuf) -> Self {
+        let mut manager = ThemeManager {
+            dir,
+            user_themes: BTreeMap::new(),
+            watcher: None,
+            events: None,
+        };
+        for (path, _format) in list_importable_themes(&manager.dir) {
+            manager.reload_path(&path);
+        }
+        manager.start_watcher();
+        manager
+    }
+
+    fn start_watcher(&mut self) {
+        // Created up front so themes dropped in on a fresh install are picked up
+        if let Err(err) = std::fs::create_dir_all(&self.dir) {
+            warn!("Failed to create themes directory {}: {}", self.dir.display(), err);
+            return;
+        }
+        let (sender, receiver) = channel();
+        let watcher = notify::recommended_watcher(move |event| {
+            let _ = sender.send(event);
+        });
+        match watcher {
+            Ok(mut watcher) => {
+                if let Err(err) = watcher.watch(&self.dir, RecursiveMode::NonRecursive) {
+                    warn!("Failed to watch themes directory {}: {}", self.dir.display(), err);
+                    return;
+                }
+                self.watcher = Some(watcher);
+                self.events = Some(receiver);
+            }
+            Err(err) => warn!("Failed to create themes watcher: {}", err),
+        }
+    }
+
+    /// Built-in presets followed by user themes. A user theme with the same
+    /// name as a preset replaces it rather than being listed twice.
+    pub fn names(&self) -> Vec<String> {
+        let mut names: Vec<String> = PRESET_THEMES.iter().map(|name| name.to_string()).collect();
+        for name in self.user_themes.keys() {
+            if !names.contains(name) {
+                names.push(name.clone());
+            }
+        }
+        names
+    }
+
+    pub fn resolve(&self, name: &str) -> ThemeConfig {
+        self.user_themes
+            .get(name)
+            .cloned()
+            .unwrap_or_else(|| preset_theme(name))
+    }
+
+    /// Applies pending file system events and returns the names of themes that changed.
+    pub fn poll_changes(&mut self) -> Vec<String> {
+        let mut paths = Vec::new();
+        if let Some(events) = &self.events {
+            while let Ok(event) = events.try_recv() {
+                match event {
+                    Ok(event) => paths.extend(event.paths),
+                    Err(err) => warn!("Themes watcher error: {}", err),
+                }
+            }
+        }
+        paths.sort();
+        paths.dedup();
+        let mut changed = Vec::new();
+        for path in paths {
+            if let Some(name) = self.reload_path(&path) {
+                if !changed.contains(&name) {
+                    changed.push(name);
+                }
+            }
+        }
+        changed
+    }
+
+    /// Loads, reloads or forgets the theme in one file, returning its name if it changed.
+    pub fn reload_path(&mut self, path: &Path) -> Option<String> {
+        let name = theme_name_for_path(path)?;
+        if !path.exists() {
+            self.user_themes.remove(&name).map(|_| name)
+        } else {
+            match ThemeConfig::import_file(path) {
+                Ok(theme) => {
+                    info!("Loaded theme '{}' from {}", name, path.display());
+                    self.user_themes.insert(name.clone(), theme);
+                    Some(name)
+                }
+                Err(err) => {
+                    // Keep the last good version while the file is being edited
+                    warn!("Failed to load theme {}: {}", path.display(), err);
+                    None
+                }
+            }
+        }
+    }
+}
+
+fn theme_name_for_path(path: &Path) -> Option<String> {
+    let extension = path.extension()?.to_str()?.to_lowercase();
+    if !matches!(extension.as_str(), "itermcolors" | "toml" | "conf" | "yml" | "yaml" | "json") {
+        return None;
+    }
+    path.file_stem().map(|stem| stem.to_string_lossy().to_string())
+}
+
+pub fn theme_color(theme: &ThemeConfig, key: &str, fallback: Color) -> Color {
+    theme
+        .colors
+        .get(key)
+        .map(|rgb| Color::from_rgb(rgb[0], rgb[1], rgb[2]))
+        .unwrap_or(fallback)
+}
+
+/// Derives the iced palette for widget chrome from the terminal theme.
+pub fn iced_palette(theme: &ThemeConfig) -> Palette {
+    Palette {
+        background: theme_color(theme, "background", Color::from_rgb(0.12, 0.12, 0.12)),
+        text: theme_color(theme, "foreground", Color::WHITE),
+        primary: theme_color(theme, "ui_input_border", Color::from_rgb(0.4, 0.7, 0.9)),
+        success: theme_color(theme, "ansi_2", Color::from_rgb(0.2, 0.8, 0.2)),
+        danger: theme_color(theme, "ansi_1", Color::from_rgb(0.9, 0.3, 0.3)),
+    }
+}
+
+pub fn iced_theme(name: &str, theme: &ThemeConfig) -> Theme {
+    Theme::custom(name.to_string(), iced_palette(theme))
+}
+
+#[cfg(test)]
+mod tests {
+    include!(concat!(env!("CARGO_MANIFEST_DIR"), "/__tests__/theme_manager_tests.rs"));
+}
@@ -1,6 +1,8 @@
 use std::collections::HashMap;
 use crate::ThemeConfig;
 
+pub const PRESET_THEMES: &[&str] = &["one_dark", "dracula", "nord", "tokyo_night", "light", "solarized_light"];
+
 pub fn preset_theme(name: &str) -> ThemeConfig {
     match name {
         "dracula" => dracula(),
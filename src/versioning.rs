@@ -0,0 +1,159 @@
+// Versioned files: everything tant persists is written as
+// `{"version": N, "data": ...}`. Loading runs the file through a migration per
+// version until it reaches the current one; files written before versioning
+// existed are bare data and count as version 0. A file that doesn't decode is
+// renamed to a backup instead of being overwritten by the next save; one written
+// by a newer tant, or that can't be read at all, is left where it is and the
+// caller must not save over it. Append-only JSON Lines logs can't be wrapped, so
+// they start with a `{"version": N}` header line instead and each record after
+// it is migrated on its own
+
+use std::path::{Path, PathBuf};
+use chrono::Utc;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::{json, Value};
+use crate::paths;
+
+/// Upgrades the data of one version to the next.
+pub type Migration = fn(Value) -> Result<Value, String>;
+
+pub struct Schema {
+    /// Used in error messages.
+    pub name: &'static str,
+    /// `migrations[n]` upgrades version `n` to `n + 1`, so the current version
+    /// is the number of migrations.
+    pub migrations: &'static [Migration],
+    pub pretty: bool,
+}
+
+impl Schema {
+    pub fn version(&self) -> u32 {
+        self.migrations.len() as u32
+    }
+}
+
+pub const SESSION: Schema = Schema { name: "session", migrations: &[bare_to_v1], pretty: false };
+pub const CONFIG: Schema = Schema { name: "config", migrations: &[bare_to_v1], pretty: true };
+pub const USAGE_LEDGER: Schema = Schema { name: "usage ledger", migrations: &[bare_to_v1], pretty: true };
+pub const THEME: Schema = Schema { name: "theme", migrations: &[bare_to_v1], pretty: true };
+pub const HISTORY: Schema = Schema { name: "history", migrations: &[bare_to_v1], pretty: false };
+
+// Version 1 only added the envelope; the data inside is what version 0 held
+fn bare_to_v1(data: Value) -> Result<Value, String> {
+    Ok(data)
+}
+
+#[derive(Debug)]
+pub struct LoadError {
+    pub message: String,
+    pub path: PathBuf,
+    /// Where the unreadable file was moved to, if it was.
+    pub backup: Option<PathBuf>,
+}
+
+impl LoadError {
+    /// The file when it was left in place, in which case saving over it would
+    /// lose it.
+    pub fn kept(&self) -> Option<&Path> {
+        self.backup.is_none().then_some(self.path.as_path())
+    }
+}
+
+impl std::fmt::Display for LoadError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.backup {
+            Some(backup) => write!(f, "{} (backed up to {})", self.message, backup.display()),
+            None => write!(f, "{} (left as it is and not saved over)", self.message),
+        }
+    }
+}
+
+pub fn encode<T: Serialize>(schema: &Schema, data: &T) -> Result<String, String> {
+    let envelope = json!({ "version": schema.version(), "data": data });
+    let json = if schema.pretty { serde_json::to_string_pretty(&envelope) } else { serde_json::to_string(&envelope) };
+    json.map_err(|err| err.to_string())
+}
+
+/// Parses any version up to the current one, migrating as needed.
+pub fn decode<T: DeserializeOwned>(schema: &Schema, json: &str) -> Result<T, String> {
+    let value: Value = serde_json::from_str(json).map_err(|err| err.to_string())?;
+    let (version, data) = split_envelope(value);
+    decode_value(schema, version, data)
+}
+
+/// Parses `data` written at `version`, migrating as needed.
+pub fn decode_value<T: DeserializeOwned>(schema: &Schema, version: u32, mut data: Value) -> Result<T, String> {
+    if version > schema.version() {
+        return Err(newer_version(schema, version));
+    }
+    for (from, migrate) in schema.migrations.iter().enumerate().skip(version as usize) {
+        data = migrate(data).map_err(|err| format!("migrating {} from version {}: {}", schema.name, from, err))?;
+    }
+    serde_json::from_value(data).map_err(|err| err.to_string())
+}
+
+/// The header line records of the current version follow in a JSON Lines log.
+pub fn header(schema: &Schema) -> String {
+    json!({ "version": schema.version() }).to_string()
+}
+
+/// The version a JSON Lines header declares, or `None` for a record.
+pub fn parse_header(line: &Value) -> Option<u32> {
+    match line {
+        Value::Object(map) if map.len() == 1 => map.get("version")?.as_u64().map(|version| version as u32),
+        _ => None,
+    }
+}
+
+fn newer_version(schema: &Schema, version: u32) -> String {
+    format!("{} version {} is newer than this tant understands ({})", schema.name, version, schema.version())
+}
+
+// An object with exactly `version` and `data` is an envelope; anything else is
+// bare version 0 data
+fn split_envelope(value: Value) -> (u32, Value) {
+    match value {
+        Value::Object(mut map) if map.len() == 2 && map.contains_key("data") && map.get("version").is_some_and(Value::is_u64) => {
+            let version = map["version"].as_u64().unwrap_or_default() as u32;
+            (version, map.remove("data").unwrap_or(Value::Null))
+        }
+        value => (0, value),
+    }
+}
+
+/// `Ok(None)` when there is no file. A file that exists but doesn't decode is
+/// moved aside so it survives the next save; see [`LoadError::kept`] for the
+/// ones that aren't.
+pub fn load<T: DeserializeOwned>(schema: &Schema, path: &Path) -> Result<Option<T>, LoadError> {
+    let error = |message: String, backup| LoadError { message: format!("{}: {}", path.display(), message), path: path.to_path_buf(), backup };
+    let json = match std::fs::read_to_string(path) {
+        Ok(json) => json,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(err) => return Err(error(err.to_string(), None)),
+    };
+    // A newer tant's file is fine as it is, so it stays put for that tant
+    let version = serde_json::from_str(&json).map_or(0, |value| split_envelope(value).0);
+    if version > schema.version() {
+        return Err(error(newer_version(schema, version), None));
+    }
+    decode(schema, &json).map(Some).map_err(|err| error(err, back_up(path)))
+}
+
+pub fn save<T: Serialize>(schema: &Schema, path: &Path, data: &T) -> Result<(), String> {
+    let json = encode(schema, data)?;
+    paths::write_atomic(path, json.as_bytes()).map_err(|err| format!("{}: {}", path.display(), err))
+}
+
+// `config.json` becomes `config.json.corrupt-20250101-120000`
+fn back_up(path: &Path) -> Option<PathBuf> {
+    let name = path.file_name()?.to_string_lossy().to_string();
+    let backup = path.with_file_name(format!("{}.corrupt-{}", name, Utc::now().format("%Y%m%d-%H%M%S")));
+    std::fs::rename(path, &backup).ok()?;
+    Some(backup)
+}
+
+#[cfg(test)]
+mod tests {
+    include!(concat!(env!("CARGO_MANIFEST_DIR"), "/__tests__/versioning_tests.rs"));
+}
@@ -0,0 +1,217 @@
+// Watch mode: a history block's command re-runs every few seconds or whenever
+// files under its directory matching a glob change. Every run is tagged with the
+// watch's id so the renderer can stack them, and a watch stops itself once its
+// command has failed too many times in a row
+
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{channel, Receiver};
+use std::time::{Duration, Instant};
+use globset::{Glob, GlobMatcher, GlobSet, GlobSetBuilder};
+use notify::{RecommendedWatcher, RecursiveMode, Watcher};
+use serde::{Deserialize, Serialize};
+use crate::Block;
+
+// File changes usually arrive in bursts (editor save, formatter); wait for them to settle
+const FILE_DEBOUNCE: Duration = Duration::from_millis(300);
+
+// Runs shown in a stack's sparkline
+pub const SPARKLINE_RUNS: usize = 30;
+
+const SPARK_BARS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WatchSettings {
+    /// Default interval for "every N seconds" watches.
+    pub interval_secs: u64,
+    /// Default glob for file watches, relative to the block's directory.
+    pub glob: String,
+    /// Failed runs in a row after which a watch stops; 0 never stops.
+    pub max_failures: u32,
+    /// Globs file watches ignore, so build output and VCS files a run writes
+    /// don't trigger the next one.
+    pub exclude: Vec<String>,
+}
+
+impl Default for WatchSettings {
+    fn default() -> Self {
+        let exclude = ["target/**", ".git/**", "node_modules/**"].iter().map(|glob| glob.to_string()).collect();
+        WatchSettings { interval_secs: 5, glob: "**/*".to_string(), max_failures: 3, exclude }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum WatchTrigger {
+    Every(Duration),
+    FilesChanged(String),
+}
+
+impl WatchTrigger {
+    pub fn label(&self) -> String {
+        match self {
+            WatchTrigger::Every(interval) => format!("every {}s", interval.as_secs()),
+            WatchTrigger::FilesChanged(glob) => format!("on changes to {}", glob),
+        }
+    }
+}
+
+/// Inline form on a block for starting a watch.
+pub struct WatchForm {
+    pub block_index: usize,
+    pub interval: String,
+    pub glob: String,
+    pub error: Option<String>,
+}
+
+impl WatchForm {
+    pub fn new(block_index: usize, settings: &WatchSettings) -> Self {
+        WatchForm { block_index, interval: settings.interval_secs.to_string(), glob: settings.glob.clone(), error: None }
+    }
+
+    pub fn interval(&self) -> Result<Duration, String> {
+        match self.interval.trim().parse::<u64>() {
+            Ok(secs) if secs > 0 => Ok(Duration::from_secs(secs)),
+            _ => Err(format!("'{}' is not a whole number of seconds", self.interval.trim())),
+        }
+    }
+}
+
+pub struct Watch {
+    pub id: u64,
+    pub command: String,
+    pub cwd: Option<PathBuf>,
+    pub trigger: WatchTrigger,
+    pub max_failures: u32,
+    pub failures_in_row: u32,
+    /// A run has been sent to the shell and hasn't finished yet.
+    pub running: bool,
+    next_run: Option<Instant>,
+    matcher: Option<GlobMatcher>,
+    excluded: GlobSet,
+    // Only held so file events keep arriving until the watch is dropped
+    _watcher: Option<RecommendedWatcher>,
+    events: Option<Receiver<notify::Result<notify::Event>>>,
+}
+
+impl Watch {
+    pub fn every(id: u64, block: &Block, interval: Duration, max_failures: u32, now: Instant) -> Self {
+        Watch {
+            id,
+            command: block.command.clone(),
+            cwd: block.cwd.clone(),
+            trigger: WatchTrigger::Every(interval),
+            max_failures,
+            failures_in_row: 0,
+            running: false,
+            next_run: Some(now + interval),
+            matcher: None,
+            excluded: GlobSet::empty(),
+            _watcher: None,
+            events: None,
+        }
+    }
+
+    /// Watches files under the block's directory, less `exclude`; fails if a glob
+    /// is invalid or the directory can't be watched.
+    pub fn files_changed(id: u64, block: &Block, glob: &str, exclude: &[String], max_failures: u32) -> Result<Self, String> {
+        let matcher = Glob::new(glob.trim()).map_err(|err| err.kind().to_string())?.compile_matcher();
+        let mut excluded = GlobSetBuilder::new();
+        for pattern in exclude {
+            excluded.add(Glob::new(pattern.trim()).map_err(|err| format!("exclude '{}': {}", pattern, err.kind()))?);
+        }
+        let excluded = excluded.build().map_err(|err| err.to_string())?;
+        let cwd = block.cwd.clone().ok_or_else(|| "this block has no working directory".to_string())?;
+        let (sender, receiver) = channel();
+        let mut watcher = notify::recommended_watcher(move |event| {
+            let _ = sender.send(event);
+        })
+        .map_err(|err| err.to_string())?;
+        watcher.watch(&cwd, RecursiveMode::Recursive).map_err(|err| format!("cannot watch {}: {}", cwd.display(), err))?;
+        Ok(Watch {
+            id,
+            command: block.command.clone(),
+            cwd: Some(cwd),
+            trigger: WatchTrigger::FilesChanged(glob.trim().to_string()),
+            max_failures,
+            failures_in_row: 0,
+            running: false,
+            next_run: None,
+            matcher: Some(matcher),
+            excluded,
+            _watcher: Some(watcher),
+            events: Some(receiver),
+        })
+    }
+
+    /// Whether a run should start now. File changes seen since the last call
+    /// schedule a run once they settle; nothing is due while a run is in flight,
+    /// and changes made during a run (usually by the run itself) are dropped.
+    pub fn due(&mut self, now: Instant) -> bool {
+        let mut changed = Vec::new();
+        if let Some(events) = &self.events {
+            while let Ok(event) = events.try_recv() {
+                if let Ok(event) = event {
+                    changed.extend(event.paths);
+                }
+            }
+        }
+        if !self.running && changed.iter().any(|path| self.matches(path)) {
+            self.next_run = Some(now + FILE_DEBOUNCE);
+        }
+        !self.running && self.next_run.is_some_and(|at| at <= now)
+    }
+
+    pub fn matches(&self, path: &Path) -> bool {
+        let (Some(matcher), Some(cwd)) = (&self.matcher, &self.cwd) else {
+            return false;
+        };
+        path.strip_prefix(cwd).is_ok_and(|relative| matcher.is_match(relative) && !self.excluded.is_match(relative))
+    }
+
+    pub fn started(&mut self) {
+        self.running = true;
+        self.next_run = None;
+    }
+
+    /// Records a finished run. Returns false once the watch has failed
+    /// `max_failures` times in a row and should stop.
+    pub fn finished(&mut self, exit_code: Option<i32>, now: Instant) -> bool {
+        self.running = false;
+        if exit_code == Some(0) {
+            self.failures_in_row = 0;
+        } else {
+            self.failures_in_row += 1;
+        }
+        if let WatchTrigger::Every(interval) = self.trigger {
+            self.next_run = Some(now + interval);
+        }
+        self.max_failures == 0 || self.failures_in_row < self.max_failures
+    }
+}
+
+/// An id not used by any block in `history` or any active watch.
+pub fn next_watch_id(history: &[Block], watches: &[Watch]) -> u64 {
+    history
+        .iter()
+        .filter_map(|block| block.watch_id)
+        .chain(watches.iter().map(|watch| watch.id))
+        .max()
+        .map_or(1, |id| id + 1)
+}
+
+/// One bar per run, its height scaled to the slowest run shown.
+pub fn sparkline(durations: &[Option<u64>]) -> Vec<char> {
+    let longest = durations.iter().flatten().copied().max().unwrap_or(0).max(1);
+    durations
+        .iter()
+        .map(|duration| {
+            let level = duration.map_or(0, |ms| (ms * (SPARK_BARS.len() as u64 - 1) + longest / 2) / longest);
+            SPARK_BARS[level as usize]
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    include!(concat!(env!("CARGO_MANIFEST_DIR"), "/__tests__/watch_tests.rs"));
+}
@@ -0,0 +1,190 @@
+// Workflows: saved command templates with `{{arg}}` placeholders
+// Each YAML or TOML file holds one workflow. Personal workflows live in the
+// config directory; team workflows are checked into a repo under `.tant/workflows`
+// and are found by walking up from the pane's working directory
+
+use std::path::{Path, PathBuf};
+use serde::Deserialize;
+use crate::paths;
+
+const TEAM_DIR: &str = ".tant/workflows";
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct Workflow {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    pub command: String,
+    /// Declared arguments; placeholders without a declaration are free-text arguments.
+    #[serde(default)]
+    pub arguments: Vec<WorkflowArg>,
+    #[serde(skip)]
+    pub source: WorkflowSource,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+pub struct WorkflowArg {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub default: Option<String>,
+    /// When set, the value must be one of these.
+    #[serde(default)]
+    pub choices: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum WorkflowSource {
+    #[default]
+    Personal,
+    Team,
+}
+
+impl Workflow {
+    pub fn parse(path: &Path, contents: &str) -> Result<Self, String> {
+        let workflow: Workflow = match path.extension().and_then(|ext| ext.to_str()) {
+            Some("toml") => toml::from_str(contents).map_err(|e| e.to_string())?,
+            Some("yaml") | Some("yml") => serde_yaml::from_str(contents).map_err(|e| e.to_string())?,
+            _ => return Err(format!("Unrecognised workflow file: {}", path.display())),
+        };
+        if workflow.name.trim().is_empty() {
+            return Err("workflow has no name".to_string());
+        }
+        for arg in &workflow.arguments {
+            if let Some(default) = &arg.default {
+                if !arg.choices.is_empty() && !arg.choices.contains(default) {
+                    return Err(format!("default '{}' for '{}' is not one of its choices", default, arg.name));
+                }
+            }
+        }
+        Ok(workflow)
+    }
+
+    /// Declared arguments in order, then any other placeholders in the order they appear.
+    pub fn all_arguments(&self) -> Vec<WorkflowArg> {
+        let mut args = self.arguments.clone();
+        for name in placeholders(&self.command) {
+            if !args.iter().any(|arg| arg.name == name) {
+                args.push(WorkflowArg { name, ..WorkflowArg::default() });
+            }
+        }
+        args
+    }
+}
+
+/// Names of the `{{name}}` placeholders in `template`, without duplicates.
+pub fn placeholders(template: &str) -> Vec<String> {
+    let mut names: Vec<String> = Vec::new();
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        let Some(end) = rest[start + 2..].find("}}") else {
+            break;
+        };
+        let name = rest[start + 2..start + 2 + end].trim();
+        if !name.is_empty() && !names.iter().any(|existing| existing == name) {
+            names.push(name.to_string());
+        }
+        rest = &rest[start + 2 + end + 2..];
+    }
+    names
+}
+
+/// Substitutes each placeholder with its value. Values go in verbatim, so
+/// anything the shell should see as one word must be quoted by the user.
+pub fn render(template: &str, value_of: impl Fn(&str) -> Option<String>) -> Result<String, String> {
+    let mut output = String::new();
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        let Some(end) = rest[start + 2..].find("}}") else {
+            break;
+        };
+        let name = rest[start + 2..start + 2 + end].trim();
+        output.push_str(&rest[..start]);
+        match value_of(name) {
+            Some(value) => output.push_str(&value),
+            None => return Err(format!("missing value for '{}'", name)),
+        }
+        rest = &rest[start + 2 + end + 2..];
+    }
+    output.push_str(rest);
+    Ok(output)
+}
+
+/// Workflow files in `dir`, sorted by name. Files that fail to parse are returned
+/// as errors alongside the ones that loaded.
+pub fn load_dir(dir: &Path, source: WorkflowSource) -> (Vec<Workflow>, Vec<String>) {
+    let mut workflows = Vec::new();
+    let mut errors = Vec::new();
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return (workflows, errors);
+    };
+    let mut paths: Vec<PathBuf> = entries.flatten().map(|entry| entry.path()).filter(|path| path.is_file()).collect();
+    paths.sort();
+    for path in paths {
+        if !matches!(path.extension().and_then(|ext| ext.to_str()), Some("toml" | "yaml" | "yml")) {
+            continue;
+        }
+        let parsed = std::fs::read_to_string(&path).map_err(|e| e.to_string()).and_then(|contents| Workflow::parse(&path, &contents));
+        match parsed {
+            Ok(workflow) => workflows.push(Workflow { source, ..workflow }),
+            Err(err) => errors.push(format!("{}: {}", path.display(), err)),
+        }
+    }
+    workflows.sort_by_key(|workflow| workflow.name.to_lowercase());
+    (workflows, errors)
+}
+
+/// The nearest `.tant/workflows` directory at or above `cwd`.
+pub fn team_dir(cwd: &Path) -> Option<PathBuf> {
+    cwd.ancestors().map(|dir| dir.join(TEAM_DIR)).find(|dir| dir.is_dir())
+}
+
+pub fn personal_dir() -> PathBuf {
+    paths::config_dir().join("workflows")
+}
+
+/// Team workflows for `cwd` followed by personal ones.
+pub fn load_workflows(cwd: &Path) -> (Vec<Workflow>, Vec<String>) {
+    let (mut workflows, mut errors) = match team_dir(cwd) {
+        Some(dir) => load_dir(&dir, WorkflowSource::Team),
+        None => (Vec::new(), Vec::new()),
+    };
+    let (personal, personal_errors) = load_dir(&personal_dir(), WorkflowSource::Personal);
+    workflows.extend(personal);
+    errors.extend(personal_errors);
+    (workflows, errors)
+}
+
+/// Argument values being filled in before a workflow runs.
+pub struct WorkflowForm {
+    pub workflow: Workflow,
+    pub arguments: Vec<WorkflowArg>,
+    pub values: Vec<String>,
+}
+
+impl WorkflowForm {
+    pub fn new(workflow: Workflow) -> Self {
+        let arguments = workflow.all_arguments();
+        let values = arguments
+            .iter()
+            .map(|arg| arg.default.clone().or_else(|| arg.choices.first().cloned()).unwrap_or_default())
+            .collect();
+        WorkflowForm { workflow, arguments, values }
+    }
+
+    /// The command with every placeholder filled; empty values are missing.
+    pub fn command(&self) -> Result<String, String> {
+        render(&self.workflow.command, |name| {
+            let index = self.arguments.iter().position(|arg| arg.name == name)?;
+            Some(self.values[index].clone()).filter(|value| !value.is_empty())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    include!(concat!(env!("CARGO_MANIFEST_DIR"), "/__tests__/workflows_tests.rs"));
+}